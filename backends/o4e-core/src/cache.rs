@@ -2,6 +2,8 @@
 
 //! Font caching infrastructure for efficient font management.
 
+use crate::types::{LineCap, LineJoin, Stroke};
+use crate::utils::quantize_size;
 use crate::{O4eError, Result, ShapingResult};
 use dashmap::DashMap;
 use lru::LruCache;
@@ -33,7 +35,28 @@ pub struct ShapeKey {
 pub struct GlyphKey {
     pub font_key: FontKey,
     pub glyph_id: u32,
-    pub size: u32, // Quantized size
+    pub size: u32,                 // Quantized size
+    pub stroke: Option<StrokeKey>, // None for filled masks
+}
+
+/// Stroke parameters that affect a rasterized glyph mask
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct StrokeKey {
+    pub width: u32, // Quantized like sizes
+    pub line_join: LineJoin,
+    pub line_cap: LineCap,
+    pub miter_limit: u32,
+}
+
+impl From<&Stroke> for StrokeKey {
+    fn from(stroke: &Stroke) -> Self {
+        Self {
+            width: quantize_size(stroke.width),
+            line_join: stroke.line_join,
+            line_cap: stroke.line_cap,
+            miter_limit: quantize_size(stroke.miter_limit),
+        }
+    }
 }
 
 /// Parsed font face (backend-specific)
@@ -221,6 +244,7 @@ mod tests {
             font_key: font_key.clone(),
             glyph_id: 42,
             size: 1200,
+            stroke: None,
         };
         cache.cache_glyph(
             glyph_key,
@@ -259,6 +283,7 @@ mod tests {
                 },
                 font: Some(Font::new("Test", 12.0)),
                 direction: Direction::LeftToRight,
+                lines: Vec::new(),
             },
         );
 
@@ -0,0 +1,549 @@
+// this_file: backends/o4e-core/src/color.rs
+
+//! CSS Color Level 4 parsing shared by every backend.
+//!
+//! Supports named colors, `#rgb[a]`/`#rrggbb[aa]`, `rgb()`/`rgba()`, `hsl()`/`hsla()`,
+//! `hwb()`, `transparent` and `currentColor`. Keywords and function names are
+//! case-insensitive; out-of-range components are clamped as CSS does.
+
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Straight-alpha RGBA color.
+pub type Rgba = (u8, u8, u8, u8);
+
+/// A parsed CSS color value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssColor {
+    /// A concrete color
+    Rgba(Rgba),
+    /// The `currentColor` keyword, resolved against the text color of the render
+    CurrentColor,
+}
+
+impl CssColor {
+    /// Parse a CSS color value.
+    pub fn parse(input: &str) -> Result<Self, ColorError> {
+        let error = |kind| ColorError {
+            input: input.to_string(),
+            kind,
+        };
+        let value = input.trim();
+        if value.is_empty() {
+            return Err(error(ColorErrorKind::Empty));
+        }
+
+        if let Some(hex) = value.strip_prefix('#') {
+            return parse_hex(hex)
+                .map(Self::Rgba)
+                .ok_or(error(ColorErrorKind::InvalidHex));
+        }
+
+        if let Some(open) = value.find('(') {
+            let name = value[..open].trim_end().to_ascii_lowercase();
+            let args = value[open + 1..]
+                .strip_suffix(')')
+                .ok_or(error(ColorErrorKind::InvalidArguments))?;
+            let rgba = match name.as_str() {
+                "rgb" | "rgba" => parse_rgb(args),
+                "hsl" | "hsla" => parse_hsl(args),
+                "hwb" => parse_hwb(args),
+                _ => return Err(error(ColorErrorKind::UnknownFunction)),
+            };
+            return rgba
+                .map(Self::Rgba)
+                .ok_or(error(ColorErrorKind::InvalidArguments));
+        }
+
+        let keyword = value.to_ascii_lowercase();
+        match keyword.as_str() {
+            "currentcolor" => Ok(Self::CurrentColor),
+            "transparent" => Ok(Self::Rgba((0, 0, 0, 0))),
+            _ => named_color(&keyword)
+                .map(|(r, g, b)| Self::Rgba((r, g, b, 255)))
+                .ok_or(error(ColorErrorKind::UnknownName)),
+        }
+    }
+
+    /// Concrete color, substituting `current` for `currentColor`.
+    pub fn resolve(self, current: Rgba) -> Rgba {
+        match self {
+            Self::Rgba(rgba) => rgba,
+            Self::CurrentColor => current,
+        }
+    }
+}
+
+impl FromStr for CssColor {
+    type Err = ColorError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::parse(input)
+    }
+}
+
+/// A color value that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Invalid color {input:?}: {kind}")]
+pub struct ColorError {
+    /// The offending input, as given
+    pub input: String,
+    /// What was wrong with it
+    pub kind: ColorErrorKind,
+}
+
+/// Why a color value was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorErrorKind {
+    /// Nothing but whitespace
+    Empty,
+    /// Not a CSS color keyword
+    UnknownName,
+    /// Hex notation without 3, 4, 6 or 8 hex digits
+    InvalidHex,
+    /// A function other than `rgb()`, `rgba()`, `hsl()`, `hsla()` or `hwb()`
+    UnknownFunction,
+    /// Wrong number or kind of function arguments
+    InvalidArguments,
+}
+
+impl fmt::Display for ColorErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Empty => "empty value",
+            Self::UnknownName => "unknown color name",
+            Self::InvalidHex => "expected 3, 4, 6 or 8 hex digits",
+            Self::UnknownFunction => "unknown color function",
+            Self::InvalidArguments => "invalid color function arguments",
+        })
+    }
+}
+
+/// SVG `fill`/`stroke` attributes (with their `-opacity` companion) for a color value.
+///
+/// `transparent` paints nothing; everything else goes through [`svg_color_attributes`].
+pub fn svg_paint_attributes(property: &str, color: &str) -> Result<String, ColorError> {
+    if color.trim().eq_ignore_ascii_case("transparent") {
+        CssColor::parse(color)?;
+        return Ok(format!(r#" {property}="none""#));
+    }
+    svg_color_attributes(property, &format!("{property}-opacity"), color)
+}
+
+/// SVG color attribute plus a separate opacity attribute for translucent colors.
+///
+/// Hex and functional notations are normalized to `#rrggbb`, since SVG 1.1 consumers do not
+/// all understand `hsl()`/`hwb()` or alpha hex; keywords pass through once they parse, so
+/// nothing unvalidated reaches the attribute.
+pub fn svg_color_attributes(
+    property: &str,
+    opacity_property: &str,
+    color: &str,
+) -> Result<String, ColorError> {
+    let (r, g, b, a) = CssColor::parse(color)?.resolve((0, 0, 0, 255));
+    let color = color.trim();
+    Ok(if color.chars().all(|c| c.is_ascii_alphabetic()) {
+        format!(r#" {property}="{color}""#)
+    } else if a == 255 {
+        format!(r##" {property}="#{r:02x}{g:02x}{b:02x}""##)
+    } else {
+        format!(
+            r##" {property}="#{r:02x}{g:02x}{b:02x}" {opacity_property}="{:.3}""##,
+            a as f32 / 255.0
+        )
+    })
+}
+
+fn parse_hex(hex: &str) -> Option<Rgba> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 => Some((digit(0)?, digit(1)?, digit(2)?, 255)),
+        4 => Some((digit(0)?, digit(1)?, digit(2)?, digit(3)?)),
+        6 => Some((pair(0)?, pair(2)?, pair(4)?, 255)),
+        8 => Some((pair(0)?, pair(2)?, pair(4)?, pair(6)?)),
+        _ => None,
+    }
+}
+
+/// Function arguments split into three components and an optional alpha.
+///
+/// Accepts the legacy comma syntax (`1, 2, 3, 0.5`) and the modern space syntax
+/// (`1 2 3 / 0.5`), where `none` stands for a zero component.
+fn split_arguments(args: &str, allow_legacy: bool) -> Option<([&str; 3], Option<&str>)> {
+    let parts: Vec<&str> = if args.contains(',') {
+        if !allow_legacy {
+            return None;
+        }
+        args.split(',').map(str::trim).collect()
+    } else {
+        let (components, alpha) = match args.split_once('/') {
+            Some((components, alpha)) => (components, Some(alpha.trim())),
+            None => (args, None),
+        };
+        let mut parts: Vec<&str> = components.split_whitespace().collect();
+        if parts.len() != 3 {
+            return None;
+        }
+        parts.extend(alpha);
+        parts
+    };
+    if parts.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+    match parts.as_slice() {
+        [a, b, c] => Some(([a, b, c], None)),
+        [a, b, c, alpha] => Some(([a, b, c], Some(alpha))),
+        _ => None,
+    }
+}
+
+fn number(value: &str) -> Option<f32> {
+    if value.eq_ignore_ascii_case("none") {
+        return Some(0.0);
+    }
+    value.parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+fn percentage(value: &str) -> Option<f32> {
+    number(value.strip_suffix('%')?).map(|v| v / 100.0)
+}
+
+/// Fraction in `0..=1` from a percentage, or from a number in `0..=scale`.
+fn fraction(value: &str, scale: f32) -> Option<f32> {
+    percentage(value)
+        .or_else(|| number(value).map(|v| v / scale))
+        .map(|v| v.clamp(0.0, 1.0))
+}
+
+/// Hue in degrees from a bare number or an angle with a unit.
+fn hue(value: &str) -> Option<f32> {
+    let lower = value.to_ascii_lowercase();
+    let (number_part, degrees_per_unit) = [
+        ("deg", 1.0),
+        ("grad", 0.9),
+        ("rad", 180.0 / std::f32::consts::PI),
+        ("turn", 360.0),
+    ]
+    .iter()
+    .find_map(|(unit, factor)| lower.strip_suffix(unit).map(|n| (n, *factor)))
+    .unwrap_or((lower.as_str(), 1.0));
+    number(number_part).map(|v| (v * degrees_per_unit).rem_euclid(360.0))
+}
+
+fn alpha(value: Option<&str>) -> Option<u8> {
+    value.map_or(Some(255), |value| fraction(value, 1.0).map(channel))
+}
+
+fn channel(fraction: f32) -> u8 {
+    (fraction.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn parse_rgb(args: &str) -> Option<Rgba> {
+    let ([r, g, b], a) = split_arguments(args, true)?;
+    Some((
+        channel(fraction(r, 255.0)?),
+        channel(fraction(g, 255.0)?),
+        channel(fraction(b, 255.0)?),
+        alpha(a)?,
+    ))
+}
+
+fn parse_hsl(args: &str) -> Option<Rgba> {
+    let ([h, s, l], a) = split_arguments(args, true)?;
+    let (r, g, b) = hsl_to_rgb(hue(h)?, fraction(s, 100.0)?, fraction(l, 100.0)?);
+    Some((channel(r), channel(g), channel(b), alpha(a)?))
+}
+
+fn parse_hwb(args: &str) -> Option<Rgba> {
+    let ([h, w, b], a) = split_arguments(args, false)?;
+    let (whiteness, blackness) = (fraction(w, 100.0)?, fraction(b, 100.0)?);
+    let (r, g, b) = if whiteness + blackness >= 1.0 {
+        let gray = whiteness / (whiteness + blackness);
+        (gray, gray, gray)
+    } else {
+        let (r, g, b) = hsl_to_rgb(hue(h)?, 1.0, 0.5);
+        let scale = 1.0 - whiteness - blackness;
+        (
+            r * scale + whiteness,
+            g * scale + whiteness,
+            b * scale + whiteness,
+        )
+    };
+    Some((channel(r), channel(g), channel(b), alpha(a)?))
+}
+
+/// CSS Color 4 `hslToRgb`; hue in degrees, the rest in `0..=1`.
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (f32, f32, f32) {
+    let f = |n: f32| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    (f(0.0), f(8.0), f(4.0))
+}
+
+/// Look up a lowercase CSS named color.
+fn named_color(name: &str) -> Option<(u8, u8, u8)> {
+    NAMED_COLORS
+        .binary_search_by(|(candidate, _)| candidate.cmp(&name))
+        .ok()
+        .map(|index| NAMED_COLORS[index].1)
+}
+
+/// CSS named colors, sorted by name.
+const NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("aliceblue", (240, 248, 255)),
+    ("antiquewhite", (250, 235, 215)),
+    ("aqua", (0, 255, 255)),
+    ("aquamarine", (127, 255, 212)),
+    ("azure", (240, 255, 255)),
+    ("beige", (245, 245, 220)),
+    ("bisque", (255, 228, 196)),
+    ("black", (0, 0, 0)),
+    ("blanchedalmond", (255, 235, 205)),
+    ("blue", (0, 0, 255)),
+    ("blueviolet", (138, 43, 226)),
+    ("brown", (165, 42, 42)),
+    ("burlywood", (222, 184, 135)),
+    ("cadetblue", (95, 158, 160)),
+    ("chartreuse", (127, 255, 0)),
+    ("chocolate", (210, 105, 30)),
+    ("coral", (255, 127, 80)),
+    ("cornflowerblue", (100, 149, 237)),
+    ("cornsilk", (255, 248, 220)),
+    ("crimson", (220, 20, 60)),
+    ("cyan", (0, 255, 255)),
+    ("darkblue", (0, 0, 139)),
+    ("darkcyan", (0, 139, 139)),
+    ("darkgoldenrod", (184, 134, 11)),
+    ("darkgray", (169, 169, 169)),
+    ("darkgreen", (0, 100, 0)),
+    ("darkgrey", (169, 169, 169)),
+    ("darkkhaki", (189, 183, 107)),
+    ("darkmagenta", (139, 0, 139)),
+    ("darkolivegreen", (85, 107, 47)),
+    ("darkorange", (255, 140, 0)),
+    ("darkorchid", (153, 50, 204)),
+    ("darkred", (139, 0, 0)),
+    ("darksalmon", (233, 150, 122)),
+    ("darkseagreen", (143, 188, 143)),
+    ("darkslateblue", (72, 61, 139)),
+    ("darkslategray", (47, 79, 79)),
+    ("darkslategrey", (47, 79, 79)),
+    ("darkturquoise", (0, 206, 209)),
+    ("darkviolet", (148, 0, 211)),
+    ("deeppink", (255, 20, 147)),
+    ("deepskyblue", (0, 191, 255)),
+    ("dimgray", (105, 105, 105)),
+    ("dimgrey", (105, 105, 105)),
+    ("dodgerblue", (30, 144, 255)),
+    ("firebrick", (178, 34, 34)),
+    ("floralwhite", (255, 250, 240)),
+    ("forestgreen", (34, 139, 34)),
+    ("fuchsia", (255, 0, 255)),
+    ("gainsboro", (220, 220, 220)),
+    ("ghostwhite", (248, 248, 255)),
+    ("gold", (255, 215, 0)),
+    ("goldenrod", (218, 165, 32)),
+    ("gray", (128, 128, 128)),
+    ("green", (0, 128, 0)),
+    ("greenyellow", (173, 255, 47)),
+    ("grey", (128, 128, 128)),
+    ("honeydew", (240, 255, 240)),
+    ("hotpink", (255, 105, 180)),
+    ("indianred", (205, 92, 92)),
+    ("indigo", (75, 0, 130)),
+    ("ivory", (255, 255, 240)),
+    ("khaki", (240, 230, 140)),
+    ("lavender", (230, 230, 250)),
+    ("lavenderblush", (255, 240, 245)),
+    ("lawngreen", (124, 252, 0)),
+    ("lemonchiffon", (255, 250, 205)),
+    ("lightblue", (173, 216, 230)),
+    ("lightcoral", (240, 128, 128)),
+    ("lightcyan", (224, 255, 255)),
+    ("lightgoldenrodyellow", (250, 250, 210)),
+    ("lightgray", (211, 211, 211)),
+    ("lightgreen", (144, 238, 144)),
+    ("lightgrey", (211, 211, 211)),
+    ("lightpink", (255, 182, 193)),
+    ("lightsalmon", (255, 160, 122)),
+    ("lightseagreen", (32, 178, 170)),
+    ("lightskyblue", (135, 206, 250)),
+    ("lightslategray", (119, 136, 153)),
+    ("lightslategrey", (119, 136, 153)),
+    ("lightsteelblue", (176, 196, 222)),
+    ("lightyellow", (255, 255, 224)),
+    ("lime", (0, 255, 0)),
+    ("limegreen", (50, 205, 50)),
+    ("linen", (250, 240, 230)),
+    ("magenta", (255, 0, 255)),
+    ("maroon", (128, 0, 0)),
+    ("mediumaquamarine", (102, 205, 170)),
+    ("mediumblue", (0, 0, 205)),
+    ("mediumorchid", (186, 85, 211)),
+    ("mediumpurple", (147, 112, 219)),
+    ("mediumseagreen", (60, 179, 113)),
+    ("mediumslateblue", (123, 104, 238)),
+    ("mediumspringgreen", (0, 250, 154)),
+    ("mediumturquoise", (72, 209, 204)),
+    ("mediumvioletred", (199, 21, 133)),
+    ("midnightblue", (25, 25, 112)),
+    ("mintcream", (245, 255, 250)),
+    ("mistyrose", (255, 228, 225)),
+    ("moccasin", (255, 228, 181)),
+    ("navajowhite", (255, 222, 173)),
+    ("navy", (0, 0, 128)),
+    ("oldlace", (253, 245, 230)),
+    ("olive", (128, 128, 0)),
+    ("olivedrab", (107, 142, 35)),
+    ("orange", (255, 165, 0)),
+    ("orangered", (255, 69, 0)),
+    ("orchid", (218, 112, 214)),
+    ("palegoldenrod", (238, 232, 170)),
+    ("palegreen", (152, 251, 152)),
+    ("paleturquoise", (175, 238, 238)),
+    ("palevioletred", (219, 112, 147)),
+    ("papayawhip", (255, 239, 213)),
+    ("peachpuff", (255, 218, 185)),
+    ("peru", (205, 133, 63)),
+    ("pink", (255, 192, 203)),
+    ("plum", (221, 160, 221)),
+    ("powderblue", (176, 224, 230)),
+    ("purple", (128, 0, 128)),
+    ("rebeccapurple", (102, 51, 153)),
+    ("red", (255, 0, 0)),
+    ("rosybrown", (188, 143, 143)),
+    ("royalblue", (65, 105, 225)),
+    ("saddlebrown", (139, 69, 19)),
+    ("salmon", (250, 128, 114)),
+    ("sandybrown", (244, 164, 96)),
+    ("seagreen", (46, 139, 87)),
+    ("seashell", (255, 245, 238)),
+    ("sienna", (160, 82, 45)),
+    ("silver", (192, 192, 192)),
+    ("skyblue", (135, 206, 235)),
+    ("slateblue", (106, 90, 205)),
+    ("slategray", (112, 128, 144)),
+    ("slategrey", (112, 128, 144)),
+    ("snow", (255, 250, 250)),
+    ("springgreen", (0, 255, 127)),
+    ("steelblue", (70, 130, 180)),
+    ("tan", (210, 180, 140)),
+    ("teal", (0, 128, 128)),
+    ("thistle", (216, 191, 216)),
+    ("tomato", (255, 99, 71)),
+    ("turquoise", (64, 224, 208)),
+    ("violet", (238, 130, 238)),
+    ("wheat", (245, 222, 179)),
+    ("white", (255, 255, 255)),
+    ("whitesmoke", (245, 245, 245)),
+    ("yellow", (255, 255, 0)),
+    ("yellowgreen", (154, 205, 50)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(input: &str) -> Rgba {
+        CssColor::parse(input).unwrap().resolve((1, 2, 3, 4))
+    }
+
+    fn kind(input: &str) -> ColorErrorKind {
+        CssColor::parse(input).unwrap_err().kind
+    }
+
+    #[test]
+    fn svg_attributes_normalize_and_validate() {
+        assert_eq!(
+            svg_paint_attributes("fill", "rebeccapurple").unwrap(),
+            r#" fill="rebeccapurple""#
+        );
+        assert_eq!(
+            svg_paint_attributes("fill", "transparent").unwrap(),
+            r#" fill="none""#
+        );
+        assert_eq!(
+            svg_paint_attributes("fill", "#0f0").unwrap(),
+            r##" fill="#00ff00""##
+        );
+        assert_eq!(
+            svg_paint_attributes("stroke", "currentColor").unwrap(),
+            r#" stroke="currentColor""#
+        );
+        assert_eq!(
+            svg_paint_attributes("fill", "hsl(120deg 100% 50% / 50%)").unwrap(),
+            r##" fill="#00ff00" fill-opacity="0.502""##
+        );
+        assert_eq!(
+            svg_color_attributes("stop-color", "stop-opacity", "#ff000080").unwrap(),
+            r##" stop-color="#ff0000" stop-opacity="0.502""##
+        );
+        // Nothing that fails to parse reaches an attribute
+        for color in ["blurple", r#"red" onload="alert(1)"#, "#12345"] {
+            assert!(svg_paint_attributes("fill", color).is_err());
+        }
+    }
+
+    #[test]
+    fn named_colors_are_sorted_for_lookup() {
+        assert!(NAMED_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(NAMED_COLORS.len(), 148);
+        assert_eq!(rgba("RebeccaPurple"), (102, 51, 153, 255));
+        assert_eq!(rgba(" transparent "), (0, 0, 0, 0));
+        assert_eq!(rgba("currentColor"), (1, 2, 3, 4));
+    }
+
+    #[test]
+    fn hex_notations() {
+        assert_eq!(rgba("#f80"), (255, 136, 0, 255));
+        assert_eq!(rgba("#f808"), (255, 136, 0, 136));
+        assert_eq!(rgba("#FF8800"), (255, 136, 0, 255));
+        assert_eq!(rgba("#ff880080"), (255, 136, 0, 128));
+        assert_eq!(kind("#ff888"), ColorErrorKind::InvalidHex);
+        assert_eq!(kind("#ggg"), ColorErrorKind::InvalidHex);
+        assert_eq!(kind("#+f+f+f"), ColorErrorKind::InvalidHex);
+    }
+
+    #[test]
+    fn rgb_functions_accept_legacy_and_modern_syntax() {
+        assert_eq!(rgba("rgb(255, 0, 128)"), (255, 0, 128, 255));
+        assert_eq!(rgba("rgba(255,0,128,0.5)"), (255, 0, 128, 128));
+        assert_eq!(rgba("rgb(100% 0% 50% / 25%)"), (255, 0, 128, 64));
+        assert_eq!(rgba("RGB(300 -5 none)"), (255, 0, 0, 255));
+        assert_eq!(kind("rgb(1 2)"), ColorErrorKind::InvalidArguments);
+        assert_eq!(kind("rgb(1, 2 3)"), ColorErrorKind::InvalidArguments);
+        assert_eq!(kind("rgb(1 2 3"), ColorErrorKind::InvalidArguments);
+    }
+
+    #[test]
+    fn hsl_and_hwb_convert_to_rgb() {
+        assert_eq!(rgba("hsl(120, 100%, 25%)"), (0, 128, 0, 255));
+        assert_eq!(rgba("hsl(0.5turn 100% 50% / 0.5)"), (0, 255, 255, 128));
+        assert_eq!(rgba("hsla(-120deg, 100%, 50%, 1)"), (0, 0, 255, 255));
+        assert_eq!(rgba("hwb(0 0% 0%)"), (255, 0, 0, 255));
+        assert_eq!(rgba("hwb(90 20% 80%)"), (51, 51, 51, 255));
+        assert_eq!(rgba("hwb(240 20% 40%)"), (51, 51, 153, 255));
+        assert_eq!(kind("hwb(0, 0%, 0%)"), ColorErrorKind::InvalidArguments);
+    }
+
+    #[test]
+    fn errors_keep_the_offending_input() {
+        let error = CssColor::parse("blurple").unwrap_err();
+        assert_eq!(error.input, "blurple");
+        assert_eq!(error.kind, ColorErrorKind::UnknownName);
+        assert_eq!(
+            error.to_string(),
+            "Invalid color \"blurple\": unknown color name"
+        );
+        assert_eq!(kind("  "), ColorErrorKind::Empty);
+        assert_eq!(kind("lab(50% 0 0)"), ColorErrorKind::UnknownFunction);
+        assert_eq!(kind("rgb(nan 0 0)"), ColorErrorKind::InvalidArguments);
+    }
+}
@@ -0,0 +1,692 @@
+// this_file: backends/o4e-core/src/description.rs
+
+//! Font description strings.
+//!
+//! Two syntaxes are read:
+//!
+//! - Pango style, families first and the size last:
+//!   `Noto Sans, Sans Condensed Bold Italic 14px @wght=650`
+//! - The CSS `font` shorthand: `italic 700 16px/1.4 "Noto Serif", serif`
+//!
+//! Sizes are in points; `px` and `pt` suffixes are both accepted, as they coincide at the
+//! default 72 DPI. A [`Font`] formats back to the Pango style with `Display` and to CSS
+//! with [`Font::to_css`], and both parse back to the same font.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::types::{Font, FontSource, FontStretch, FontStyle};
+
+/// CSS generic family names, written without quotes.
+const GENERIC_FAMILIES: &[&str] = &[
+    "serif",
+    "sans-serif",
+    "monospace",
+    "cursive",
+    "fantasy",
+    "system-ui",
+    "ui-serif",
+    "ui-sans-serif",
+    "ui-monospace",
+    "ui-rounded",
+    "math",
+    "emoji",
+    "fangsong",
+];
+
+/// The parts of a font a description sets; everything else is left to the font it is
+/// applied to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontDescription {
+    /// Family names in order of preference
+    pub families: Vec<String>,
+    pub style: Option<FontStyle>,
+    pub weight: Option<u16>,
+    pub stretch: Option<FontStretch>,
+    /// Size in points
+    pub size: Option<f32>,
+    /// Baseline-to-baseline distance as a multiple of the size
+    pub line_height: Option<f32>,
+    /// Variation axis values (`@wght=650` in the Pango style)
+    pub variations: HashMap<String, f32>,
+    /// OpenType features (`small-caps` in CSS turns on `smcp`)
+    pub features: HashMap<String, bool>,
+}
+
+impl FontDescription {
+    /// Parse a Pango-style description: comma-separated families, then stretch, weight and
+    /// style words, then a size with an optional `/line-height`, then `@axis=value,…`
+    /// variations. Every part may be left out.
+    ///
+    /// A family ending in a style word is followed by a comma: `Arial Black, 12`. Numeric
+    /// weights are read only before a size, where they cannot be the size themselves.
+    pub fn parse(input: &str) -> Result<Self, FontDescriptionError> {
+        let error = |kind| FontDescriptionError {
+            input: input.to_string(),
+            kind,
+        };
+        let mut description = Self::default();
+        let (body, variations) = match input.split_once('@') {
+            Some((body, variations)) => (body, Some(variations)),
+            None => (input, None),
+        };
+        if let Some(variations) = variations {
+            for variation in variations.split([',', ' ']).filter(|item| !item.is_empty()) {
+                let (axis, value) = variation
+                    .split_once('=')
+                    .and_then(|(axis, value)| Some((axis, value.parse::<f32>().ok()?)))
+                    .filter(|(axis, value)| axis.len() == 4 && value.is_finite())
+                    .ok_or(error(FontDescriptionErrorKind::InvalidVariation))?;
+                description.variations.insert(axis.to_string(), value);
+            }
+        }
+
+        // The last word may be the size; style words precede it, and the family the rest
+        let mut rest = body.trim_end();
+        if let Some((head, word)) = last_word(rest) {
+            if word.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+                let (size, line_height) = word.split_once('/').unwrap_or((word, ""));
+                description.size =
+                    Some(size_points(size).ok_or(error(FontDescriptionErrorKind::InvalidSize))?);
+                if !line_height.is_empty() {
+                    description.line_height = Some(
+                        line_height_multiple(line_height, description.size)
+                            .ok_or(error(FontDescriptionErrorKind::InvalidLineHeight))?,
+                    );
+                }
+                rest = head;
+            }
+        }
+        while let Some((head, word)) = last_word(rest) {
+            if word.ends_with(',') {
+                // A trailing comma ends the family list, as in "Arial Black, 12"
+                break;
+            }
+            let numeric_weight = word
+                .parse::<u16>()
+                .ok()
+                .filter(|weight| (1..=1000).contains(weight) && description.size.is_some());
+            let matched = if let (None, Some(style)) = (description.style, style_name(word)) {
+                description.style = Some(style);
+                true
+            } else if let (None, Some(weight)) = (description.weight, weight_name(word)) {
+                description.weight = Some(weight);
+                true
+            } else if let (None, Some(weight)) = (description.weight, numeric_weight) {
+                description.weight = Some(weight);
+                true
+            } else if let (None, Some(stretch)) = (description.stretch, stretch_name(word)) {
+                description.stretch = Some(stretch);
+                true
+            } else {
+                matches!(normalize(word).as_str(), "normal" | "regular" | "roman")
+            };
+            if !matched {
+                break;
+            }
+            rest = head;
+        }
+        description.families = rest
+            .split(',')
+            .map(|family| family.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|family| !family.is_empty())
+            .collect();
+        if description == Self::default() {
+            return Err(error(FontDescriptionErrorKind::Empty));
+        }
+        Ok(description)
+    }
+
+    /// Parse the CSS `font` shorthand: optional style, `small-caps`, weight and stretch
+    /// keywords, then a size with an optional `/line-height`, then the family list.
+    pub fn parse_css(input: &str) -> Result<Self, FontDescriptionError> {
+        let error = |kind| FontDescriptionError {
+            input: input.to_string(),
+            kind,
+        };
+        let mut description = Self::default();
+        let mut rest = input.trim_start();
+        loop {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let word = &rest[..end];
+            if word.is_empty() {
+                return Err(error(FontDescriptionErrorKind::MissingSize));
+            }
+            let lower = word.to_ascii_lowercase();
+            if let Some(size) = css_size(&lower) {
+                description.size = Some(size);
+                rest = &rest[end..];
+                break;
+            }
+            if let Some((size, _)) = lower.split_once('/') {
+                if let Some(size) = css_size(size) {
+                    description.size = Some(size);
+                    rest = &rest[lower.find('/').expect("split at /")..];
+                    break;
+                }
+            }
+            match lower.as_str() {
+                "normal" => {}
+                "italic" => description.style = Some(FontStyle::Italic),
+                "oblique" => description.style = Some(FontStyle::Oblique),
+                "small-caps" => {
+                    description.features.insert("smcp".to_string(), true);
+                }
+                "bold" => description.weight = Some(700),
+                _ => {
+                    if let Some(weight) = lower.parse().ok().filter(|w| (1..=1000).contains(w)) {
+                        description.weight = Some(weight);
+                    } else if let Some(stretch) = stretch_name(&lower) {
+                        description.stretch = Some(stretch);
+                    } else if lower.ends_with("deg")
+                        && description.style == Some(FontStyle::Oblique)
+                    {
+                        // `oblique 10deg`: the angle is not kept
+                    } else {
+                        return Err(error(FontDescriptionErrorKind::UnexpectedWord(
+                            word.to_string(),
+                        )));
+                    }
+                }
+            }
+            rest = rest[end..].trim_start();
+        }
+
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('/') {
+            let after = after.trim_start();
+            let end = after.find(char::is_whitespace).unwrap_or(after.len());
+            let value = after[..end].to_ascii_lowercase();
+            if value != "normal" {
+                description.line_height = Some(
+                    line_height_multiple(&value, description.size)
+                        .ok_or(error(FontDescriptionErrorKind::InvalidLineHeight))?,
+                );
+            }
+            rest = &after[end..];
+        }
+        description.families = css_families(rest).map_err(error)?;
+        if description.families.is_empty() {
+            return Err(error(FontDescriptionErrorKind::MissingFamily));
+        }
+        Ok(description)
+    }
+
+    /// Set the parts of `font` this description has.
+    ///
+    /// A family replaces the font's source with [`FontSource::Family`].
+    pub fn apply(&self, font: &mut Font) {
+        if let Some((family, fallbacks)) = self.families.split_first() {
+            font.family = family.clone();
+            font.source = FontSource::Family(family.clone());
+            font.fallbacks = fallbacks.to_vec();
+        }
+        font.style = self.style.unwrap_or(font.style);
+        font.weight = self.weight.unwrap_or(font.weight);
+        font.stretch = self.stretch.unwrap_or(font.stretch);
+        font.size = self.size.unwrap_or(font.size);
+        font.line_height = self.line_height.or(font.line_height);
+        font.variations.extend(self.variations.clone());
+        font.features.tags.extend(self.features.clone());
+    }
+
+    /// A font from this description; it must name a family and a size.
+    fn into_font(self, input: &str) -> Result<Font, FontDescriptionError> {
+        let error = |kind| FontDescriptionError {
+            input: input.to_string(),
+            kind,
+        };
+        let family = self
+            .families
+            .first()
+            .ok_or(error(FontDescriptionErrorKind::MissingFamily))?;
+        let size = self
+            .size
+            .ok_or(error(FontDescriptionErrorKind::MissingSize))?;
+        let mut font = Font::new(family.clone(), size);
+        self.apply(&mut font);
+        Ok(font)
+    }
+}
+
+impl From<&Font> for FontDescription {
+    fn from(font: &Font) -> Self {
+        Self {
+            families: std::iter::once(font.family.clone())
+                .chain(font.fallbacks.iter().cloned())
+                .collect(),
+            style: Some(font.style),
+            weight: Some(font.weight),
+            stretch: Some(font.stretch),
+            size: Some(font.size),
+            line_height: font.line_height,
+            variations: font.variations.clone(),
+            features: font.features.tags.clone(),
+        }
+    }
+}
+
+impl fmt::Display for FontDescription {
+    /// The Pango style; features are not part of it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut families = self.families.join(", ");
+        if last_word(&families).is_some_and(|(_, word)| {
+            style_name(word).is_some()
+                || weight_name(word).is_some()
+                || stretch_name(word).is_some()
+                || word.parse::<f32>().is_ok()
+                || matches!(normalize(word).as_str(), "normal" | "regular" | "roman")
+        }) {
+            families.push(',');
+        }
+        let mut words: Vec<String> = vec![families];
+        if let Some(stretch) = self.stretch.filter(|&s| s != FontStretch::Normal) {
+            words.push(stretch_label(stretch).to_string());
+        }
+        if let Some(weight) = self.weight.filter(|&w| w != 400) {
+            words.push(weight_label(weight));
+        }
+        match self.style {
+            Some(FontStyle::Italic) => words.push("Italic".to_string()),
+            Some(FontStyle::Oblique) => words.push("Oblique".to_string()),
+            _ => {}
+        }
+        if let Some(size) = self.size {
+            match self.line_height {
+                Some(line_height) => words.push(format!("{size}/{line_height}")),
+                None => words.push(size.to_string()),
+            }
+        }
+        if !self.variations.is_empty() {
+            let mut axes: Vec<_> = self.variations.iter().collect();
+            axes.sort_by(|a, b| a.0.cmp(b.0));
+            let axes: Vec<String> = axes
+                .into_iter()
+                .map(|(axis, value)| format!("{axis}={value}"))
+                .collect();
+            words.push(format!("@{}", axes.join(",")));
+        }
+        words.retain(|word| !word.is_empty());
+        f.write_str(&words.join(" "))
+    }
+}
+
+impl FromStr for FontDescription {
+    type Err = FontDescriptionError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::parse(input)
+    }
+}
+
+impl Font {
+    /// Parse a Pango-style description such as `"Noto Sans Condensed Bold Italic 14px"`;
+    /// see [`FontDescription::parse`]. The family and the size are required.
+    pub fn parse(description: &str) -> Result<Self, FontDescriptionError> {
+        FontDescription::parse(description)?.into_font(description)
+    }
+
+    /// Parse the CSS `font` shorthand, such as `italic 700 16px/1.4 "Noto Serif", serif`.
+    pub fn parse_css(shorthand: &str) -> Result<Self, FontDescriptionError> {
+        FontDescription::parse_css(shorthand)?.into_font(shorthand)
+    }
+
+    /// The CSS `font` shorthand for this font; variations and features other than `smcp`
+    /// have no place in it.
+    pub fn to_css(&self) -> String {
+        let mut words = Vec::new();
+        match self.style {
+            FontStyle::Italic => words.push("italic".to_string()),
+            FontStyle::Oblique => words.push("oblique".to_string()),
+            FontStyle::Normal => {}
+        }
+        if self.features.tags.get("smcp") == Some(&true) {
+            words.push("small-caps".to_string());
+        }
+        if self.weight != 400 {
+            words.push(self.weight.to_string());
+        }
+        if self.stretch != FontStretch::Normal {
+            words.push(self.stretch.css_name().to_string());
+        }
+        match self.line_height {
+            Some(line_height) => words.push(format!("{}px/{line_height}", self.size)),
+            None => words.push(format!("{}px", self.size)),
+        }
+        let families: Vec<String> = std::iter::once(&self.family)
+            .chain(&self.fallbacks)
+            .map(|family| {
+                if GENERIC_FAMILIES.contains(&family.as_str()) {
+                    family.clone()
+                } else {
+                    format!("\"{}\"", family.replace('\\', "\\\\").replace('"', "\\\""))
+                }
+            })
+            .collect();
+        words.push(families.join(", "));
+        words.join(" ")
+    }
+}
+
+impl FromStr for Font {
+    type Err = FontDescriptionError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::parse(input)
+    }
+}
+
+impl fmt::Display for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        FontDescription::from(self).fmt(f)
+    }
+}
+
+/// A font description that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Invalid font description {input:?}: {kind}")]
+pub struct FontDescriptionError {
+    /// The offending input, as given
+    pub input: String,
+    /// What was wrong with it
+    pub kind: FontDescriptionErrorKind,
+}
+
+/// Why a font description was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontDescriptionErrorKind {
+    /// Nothing to describe a font with
+    Empty,
+    /// No family name
+    MissingFamily,
+    /// No size, or in CSS nothing that reads as one before the families
+    MissingSize,
+    /// A size that is not a positive number of points
+    InvalidSize,
+    /// A line height that is not a positive number, length or percentage
+    InvalidLineHeight,
+    /// A variation other than `axis=value` with a four-letter axis
+    InvalidVariation,
+    /// A CSS family name with an unterminated quote
+    UnterminatedQuote,
+    /// A word that is not a CSS style, weight or stretch keyword
+    UnexpectedWord(String),
+}
+
+impl fmt::Display for FontDescriptionErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("empty description"),
+            Self::MissingFamily => f.write_str("no family name"),
+            Self::MissingSize => f.write_str("no font size"),
+            Self::InvalidSize => f.write_str("expected a positive size in points"),
+            Self::InvalidLineHeight => f.write_str("invalid line height"),
+            Self::InvalidVariation => f.write_str("expected variations as axis=value"),
+            Self::UnterminatedQuote => f.write_str("unterminated quoted family name"),
+            Self::UnexpectedWord(word) => write!(f, "unexpected {word:?}"),
+        }
+    }
+}
+
+/// `text` split before its last whitespace-separated word.
+fn last_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_end();
+    let start = text.rfind(char::is_whitespace).map_or(0, |index| index + 1);
+    (start < text.len()).then(|| (&text[..start], &text[start..]))
+}
+
+/// Lowercase without hyphens, so `Semi-Bold`, `semibold` and `SemiBold` compare equal.
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|&c| c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Size from a number with an optional `px` or `pt` suffix.
+fn size_points(value: &str) -> Option<f32> {
+    let number = value
+        .strip_suffix("px")
+        .or_else(|| value.strip_suffix("pt"))
+        .unwrap_or(value);
+    number
+        .parse::<f32>()
+        .ok()
+        .filter(|size| size.is_finite() && *size > 0.0)
+}
+
+/// A CSS size: a length with a unit, or an absolute-size keyword.
+fn css_size(value: &str) -> Option<f32> {
+    let keyword = match value {
+        "xx-small" => Some(9.0),
+        "x-small" => Some(10.0),
+        "small" => Some(13.0),
+        "medium" => Some(16.0),
+        "large" => Some(18.0),
+        "x-large" => Some(24.0),
+        "xx-large" => Some(32.0),
+        "xxx-large" => Some(48.0),
+        _ => None,
+    };
+    keyword.or_else(|| {
+        (value.ends_with("px") || value.ends_with("pt"))
+            .then(|| size_points(value))
+            .flatten()
+    })
+}
+
+/// A line height as a multiple of `size`: a number, a percentage, or a length in points.
+fn line_height_multiple(value: &str, size: Option<f32>) -> Option<f32> {
+    let positive = |value: f32| Some(value).filter(|v| v.is_finite() && *v > 0.0);
+    if let Some(percent) = value.strip_suffix('%') {
+        return positive(percent.parse::<f32>().ok()? / 100.0);
+    }
+    if value.ends_with("px") || value.ends_with("pt") {
+        return positive(size_points(value)? / size?);
+    }
+    positive(value.parse().ok()?)
+}
+
+/// Comma-separated CSS family names, quoted or not.
+fn css_families(list: &str) -> Result<Vec<String>, FontDescriptionErrorKind> {
+    let mut families = Vec::new();
+    let mut chars = list.trim().chars().peekable();
+    while chars.peek().is_some() {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let family = match chars.peek() {
+            Some(&quote @ ('"' | '\'')) => {
+                chars.next();
+                let mut family = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => family.extend(chars.next()),
+                        Some(c) if c == quote => break,
+                        Some(c) => family.push(c),
+                        None => return Err(FontDescriptionErrorKind::UnterminatedQuote),
+                    }
+                }
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                family
+            }
+            _ => {
+                let family: String = std::iter::from_fn(|| chars.next_if(|&c| c != ',')).collect();
+                family.split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+        };
+        match chars.next() {
+            None | Some(',') => {}
+            Some(c) => return Err(FontDescriptionErrorKind::UnexpectedWord(c.to_string())),
+        }
+        if !family.is_empty() {
+            families.push(family);
+        }
+    }
+    Ok(families)
+}
+
+fn style_name(word: &str) -> Option<FontStyle> {
+    match normalize(word).as_str() {
+        "italic" => Some(FontStyle::Italic),
+        "oblique" => Some(FontStyle::Oblique),
+        _ => None,
+    }
+}
+
+/// Pango and CSS weight names.
+fn weight_name(word: &str) -> Option<u16> {
+    Some(match normalize(word).as_str() {
+        "thin" | "hairline" => 100,
+        "ultralight" | "extralight" => 200,
+        "light" => 300,
+        "semilight" => 350,
+        "book" => 380,
+        "medium" => 500,
+        "semibold" | "demibold" => 600,
+        "bold" => 700,
+        "ultrabold" | "extrabold" => 800,
+        "heavy" | "black" => 900,
+        "ultraheavy" | "extrablack" => 1000,
+        _ => return None,
+    })
+}
+
+fn weight_label(weight: u16) -> String {
+    match weight {
+        100 => "Thin",
+        200 => "Ultra-Light",
+        300 => "Light",
+        350 => "Semi-Light",
+        380 => "Book",
+        500 => "Medium",
+        600 => "Semi-Bold",
+        700 => "Bold",
+        800 => "Ultra-Bold",
+        900 => "Heavy",
+        1000 => "Ultra-Heavy",
+        other => return other.to_string(),
+    }
+    .to_string()
+}
+
+fn stretch_name(word: &str) -> Option<FontStretch> {
+    Some(match normalize(word).as_str() {
+        "ultracondensed" => FontStretch::UltraCondensed,
+        "extracondensed" => FontStretch::ExtraCondensed,
+        "condensed" => FontStretch::Condensed,
+        "semicondensed" => FontStretch::SemiCondensed,
+        "semiexpanded" => FontStretch::SemiExpanded,
+        "expanded" => FontStretch::Expanded,
+        "extraexpanded" => FontStretch::ExtraExpanded,
+        "ultraexpanded" => FontStretch::UltraExpanded,
+        _ => return None,
+    })
+}
+
+fn stretch_label(stretch: FontStretch) -> &'static str {
+    match stretch {
+        FontStretch::UltraCondensed => "Ultra-Condensed",
+        FontStretch::ExtraCondensed => "Extra-Condensed",
+        FontStretch::Condensed => "Condensed",
+        FontStretch::SemiCondensed => "Semi-Condensed",
+        FontStretch::Normal => "Normal",
+        FontStretch::SemiExpanded => "Semi-Expanded",
+        FontStretch::Expanded => "Expanded",
+        FontStretch::ExtraExpanded => "Extra-Expanded",
+        FontStretch::UltraExpanded => "Ultra-Expanded",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same(a: &Font, b: &Font) {
+        assert_eq!(a.family, b.family);
+        assert_eq!(a.fallbacks, b.fallbacks);
+        assert_eq!(a.size, b.size);
+        assert_eq!(a.weight, b.weight);
+        assert_eq!(a.style, b.style);
+        assert_eq!(a.stretch, b.stretch);
+        assert_eq!(a.line_height, b.line_height);
+        assert_eq!(a.variations, b.variations);
+    }
+
+    #[test]
+    fn pango_descriptions() {
+        let font = Font::parse("Noto Sans Condensed Bold Italic 14px").unwrap();
+        assert_eq!(font.family, "Noto Sans");
+        assert!(matches!(&font.source, FontSource::Family(name) if name == "Noto Sans"));
+        assert_eq!(
+            (font.size, font.weight, font.style, font.stretch),
+            (14.0, 700, FontStyle::Italic, FontStretch::Condensed)
+        );
+        assert_eq!(font.to_string(), "Noto Sans Condensed Bold Italic 14");
+
+        let font = Font::parse("DejaVu Sans,Sans Semi-Bold 10.5/1.2 @wght=650,wdth=80").unwrap();
+        assert_eq!(font.family, "DejaVu Sans");
+        assert_eq!(font.fallbacks, vec!["Sans"]);
+        assert_eq!((font.weight, font.line_height), (600, Some(1.2)));
+        assert_eq!(font.variations.get("wdth"), Some(&80.0));
+        assert_eq!(
+            font.to_string(),
+            "DejaVu Sans, Sans Semi-Bold 10.5/1.2 @wdth=80,wght=650"
+        );
+        assert_same(&font, &font.to_string().parse().unwrap());
+
+        // Family names that end like a style keep a comma; odd weights stay numeric
+        let mut font = Font::parse("Arial Black, 12").unwrap();
+        assert_eq!((font.family.as_str(), font.weight), ("Arial Black", 400));
+        font.weight = 450;
+        assert_eq!(font.to_string(), "Arial Black, 450 12");
+        assert_same(&font, &font.to_string().parse().unwrap());
+
+        // Partial descriptions are fine for FontDescription, not for Font
+        let description = FontDescription::parse("Bold 12").unwrap();
+        assert!(description.families.is_empty());
+        assert_eq!(description.weight, Some(700));
+        let error = Font::parse("Bold 12").unwrap_err();
+        assert_eq!(error.kind, FontDescriptionErrorKind::MissingFamily);
+        let error = Font::parse("Noto Sans").unwrap_err();
+        assert_eq!(error.kind, FontDescriptionErrorKind::MissingSize);
+        assert!(FontDescription::parse("Sans 0").is_err());
+        assert!(FontDescription::parse("Sans 12 @wght").is_err());
+    }
+
+    #[test]
+    fn css_shorthand() {
+        let font = Font::parse_css("italic 700 16px/1.4 \"Noto Serif\", serif").unwrap();
+        assert_eq!(font.family, "Noto Serif");
+        assert_eq!(font.fallbacks, vec!["serif"]);
+        assert_eq!(
+            (font.size, font.weight, font.style, font.line_height),
+            (16.0, 700, FontStyle::Italic, Some(1.4))
+        );
+        assert_eq!(font.to_css(), "italic 700 16px/1.4 \"Noto Serif\", serif");
+        assert_same(&font, &Font::parse_css(&font.to_css()).unwrap());
+        assert_same(&font, &Font::parse(&font.to_string()).unwrap());
+
+        let font =
+            Font::parse_css("small-caps condensed large / 27px Helvetica Neue, 'Arial'").unwrap();
+        assert_eq!(font.family, "Helvetica Neue");
+        assert_eq!(font.fallbacks, vec!["Arial"]);
+        assert_eq!((font.size, font.line_height), (18.0, Some(1.5)));
+        assert_eq!(font.stretch, FontStretch::Condensed);
+        assert_eq!(font.features.tags.get("smcp"), Some(&true));
+        assert_same(&font, &Font::parse_css(&font.to_css()).unwrap());
+
+        let kind = |input: &str| Font::parse_css(input).unwrap_err().kind;
+        assert_eq!(kind("bold"), FontDescriptionErrorKind::MissingSize);
+        assert_eq!(kind("12px"), FontDescriptionErrorKind::MissingFamily);
+        assert_eq!(
+            kind("12px \"Noto"),
+            FontDescriptionErrorKind::UnterminatedQuote
+        );
+        assert_eq!(
+            kind("heavy 12px serif"),
+            FontDescriptionErrorKind::UnexpectedWord("heavy".to_string())
+        );
+    }
+}
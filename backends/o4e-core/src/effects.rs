@@ -0,0 +1,504 @@
+// this_file: backends/o4e-core/src/effects.rs
+
+//! Raster effect layers (shadows, glow, blur) composited around a rendered text layer.
+//!
+//! All buffers are premultiplied RGBA unless noted otherwise; masks are single-channel
+//! coverage planes.
+
+use crate::{types::Effect, utils::parse_color, O4eError, Result};
+
+/// Premultiplied RGBA image.
+pub(crate) struct Layer {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) data: Vec<u8>,
+}
+
+/// What is painted under the text and its effects.
+#[derive(Debug, Clone)]
+pub(crate) enum Background {
+    /// Straight-alpha RGBA color
+    Solid([u8; 4]),
+    /// Premultiplied RGBA image covering the output canvas
+    Image(Vec<u8>),
+}
+
+/// Extra canvas needed on each side so no effect is clipped.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Margins {
+    pub(crate) left: u32,
+    pub(crate) top: u32,
+    pub(crate) right: u32,
+    pub(crate) bottom: u32,
+}
+
+impl Margins {
+    fn union(self, other: Margins) -> Margins {
+        Margins {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    /// Margins for a blurred copy moved by `(dx, dy)`.
+    fn shifted(spread: u32, dx: i32, dy: i32) -> Margins {
+        let spread = spread as i32;
+        Margins {
+            left: (spread - dx).max(0) as u32,
+            top: (spread - dy).max(0) as u32,
+            right: (spread + dx).max(0) as u32,
+            bottom: (spread + dy).max(0) as u32,
+        }
+    }
+}
+
+/// Canvas growth required by `effects`.
+pub(crate) fn margins(effects: &[Effect]) -> Margins {
+    effects
+        .iter()
+        .map(|effect| match effect {
+            Effect::DropShadow {
+                offset_x,
+                offset_y,
+                blur_radius,
+                ..
+            } => Margins::shifted(
+                blur_spread(*blur_radius),
+                offset_x.round() as i32,
+                offset_y.round() as i32,
+            ),
+            Effect::OuterGlow { radius, .. } | Effect::Blur { radius } => {
+                Margins::shifted(blur_spread(*radius), 0, 0)
+            }
+            Effect::InnerShadow { .. } => Margins::default(),
+        })
+        .fold(Margins::default(), Margins::union)
+}
+
+/// Composite `text` with its effects over an optional `background`.
+///
+/// The canvas grows by [`margins`]; layers are painted background, then drop shadows and
+/// glows, then the (optionally blurred) text, then inner shadows.
+pub(crate) fn compose(
+    text: Layer,
+    effects: &[Effect],
+    background: Option<Background>,
+) -> Result<Layer> {
+    let margins = margins(effects);
+    let width = text.width + margins.left + margins.right;
+    let height = text.height + margins.top + margins.bottom;
+    let mut text = pad(&text, margins, width, height);
+    let mask: Vec<u8> = text.chunks_exact(4).map(|px| px[3]).collect();
+
+    let mut out = background_data(width, height, background)?;
+
+    for effect in effects {
+        match effect {
+            Effect::DropShadow {
+                offset_x,
+                offset_y,
+                blur_radius,
+                color,
+            } => {
+                let mut shadow = shift(
+                    &mask,
+                    width,
+                    height,
+                    offset_x.round() as i32,
+                    offset_y.round() as i32,
+                    0,
+                );
+                blur_plane(&mut shadow, width, height, *blur_radius);
+                fill_mask(&mut out, Some(&shadow), effect_color(color)?);
+            }
+            Effect::OuterGlow { radius, color } => {
+                let mut glow = mask.clone();
+                blur_plane(&mut glow, width, height, *radius);
+                // A plain blur fades to half strength at the glyph edge; boost it so the halo
+                // reads as a glow rather than a soft shadow.
+                for value in &mut glow {
+                    *value = value.saturating_mul(2);
+                }
+                fill_mask(&mut out, Some(&glow), effect_color(color)?);
+            }
+            Effect::InnerShadow { .. } | Effect::Blur { .. } => {}
+        }
+    }
+
+    for effect in effects {
+        if let Effect::Blur { radius } = effect {
+            blur_rgba(&mut text, width, height, *radius);
+        }
+    }
+    composite(&mut out, &text);
+
+    for effect in effects {
+        if let Effect::InnerShadow {
+            offset_x,
+            offset_y,
+            blur_radius,
+            color,
+        } = effect
+        {
+            // The shadow is cast by everything outside the glyphs, then clipped to them.
+            let outside: Vec<u8> = mask.iter().map(|a| 255 - a).collect();
+            let mut shadow = shift(
+                &outside,
+                width,
+                height,
+                offset_x.round() as i32,
+                offset_y.round() as i32,
+                255,
+            );
+            blur_plane(&mut shadow, width, height, *blur_radius);
+            for (value, coverage) in shadow.iter_mut().zip(&mask) {
+                *value = mul(*value, *coverage);
+            }
+            fill_mask(&mut out, Some(&shadow), effect_color(color)?);
+        }
+    }
+
+    Ok(Layer {
+        width,
+        height,
+        data: out,
+    })
+}
+
+/// Composite `layer` with its top-left corner at `(x, y)` over a `width`×`height` canvas
+/// filled with `background`, clipping whatever falls outside.
+pub(crate) fn place(
+    layer: &Layer,
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    background: Option<Background>,
+) -> Result<Layer> {
+    let mut out = background_data(width, height, background)?;
+    let (canvas_width, canvas_height) = (width as i32, height as i32);
+    for src_y in 0..layer.height as i32 {
+        let dst_y = src_y + y;
+        if !(0..canvas_height).contains(&dst_y) {
+            continue;
+        }
+        for src_x in 0..layer.width as i32 {
+            let dst_x = src_x + x;
+            if !(0..canvas_width).contains(&dst_x) {
+                continue;
+            }
+            let src = ((src_y * layer.width as i32 + src_x) * 4) as usize;
+            let dst = ((dst_y * canvas_width + dst_x) * 4) as usize;
+            if layer.data[src + 3] != 0 {
+                over(&mut out[dst..dst + 4], &layer.data[src..src + 4]);
+            }
+        }
+    }
+    Ok(Layer {
+        width,
+        height,
+        data: out,
+    })
+}
+
+fn background_data(width: u32, height: u32, background: Option<Background>) -> Result<Vec<u8>> {
+    let mut out = vec![0u8; (width * height * 4) as usize];
+    match background {
+        Some(Background::Solid(color)) => fill_mask(&mut out, None, color),
+        Some(Background::Image(image)) => {
+            if image.len() != out.len() {
+                return Err(O4eError::render(format!(
+                    "background image must cover the {width}x{height} canvas"
+                )));
+            }
+            out = image;
+        }
+        None => {}
+    }
+    Ok(out)
+}
+
+fn effect_color(color: &str) -> Result<[u8; 4]> {
+    let (r, g, b, a) = parse_color(color)?;
+    Ok([r, g, b, a])
+}
+
+fn pad(layer: &Layer, margins: Margins, width: u32, height: u32) -> Vec<u8> {
+    if width == layer.width && height == layer.height {
+        return layer.data.clone();
+    }
+
+    let mut data = vec![0u8; (width * height * 4) as usize];
+    let row = (layer.width * 4) as usize;
+    for y in 0..layer.height as usize {
+        let src = y * row;
+        let dst = ((y + margins.top as usize) * width as usize + margins.left as usize) * 4;
+        data[dst..dst + row].copy_from_slice(&layer.data[src..src + row]);
+    }
+    data
+}
+
+/// Move a mask by `(dx, dy)`, filling uncovered pixels with `fill`.
+fn shift(mask: &[u8], width: u32, height: u32, dx: i32, dy: i32, fill: u8) -> Vec<u8> {
+    let (width, height) = (width as i32, height as i32);
+    let mut shifted = vec![fill; mask.len()];
+    for y in 0..height {
+        let src_y = y - dy;
+        if !(0..height).contains(&src_y) {
+            continue;
+        }
+        for x in 0..width {
+            let src_x = x - dx;
+            if (0..width).contains(&src_x) {
+                shifted[(y * width + x) as usize] = mask[(src_y * width + src_x) as usize];
+            }
+        }
+    }
+    shifted
+}
+
+/// Paint straight-alpha `color` through `mask` (the whole canvas when `None`), source-over.
+fn fill_mask(out: &mut [u8], mask: Option<&[u8]>, color: [u8; 4]) {
+    let [r, g, b, a] = color;
+    for (index, pixel) in out.chunks_exact_mut(4).enumerate() {
+        let coverage = mask.map_or(255, |mask| mask[index]);
+        let alpha = mul(a, coverage);
+        if alpha == 0 {
+            continue;
+        }
+        let src = [mul(r, alpha), mul(g, alpha), mul(b, alpha), alpha];
+        over(pixel, &src);
+    }
+}
+
+/// Source-over composite of premultiplied `src` onto premultiplied `dst`.
+fn composite(dst: &mut [u8], src: &[u8]) {
+    for (pixel, src) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+        if src[3] != 0 {
+            over(pixel, src);
+        }
+    }
+}
+
+fn over(dst: &mut [u8], src: &[u8]) {
+    let inverse = 255 - src[3];
+    for channel in 0..4 {
+        dst[channel] = src[channel].saturating_add(mul(dst[channel], inverse));
+    }
+}
+
+fn mul(a: u8, b: u8) -> u8 {
+    ((u16::from(a) * u16::from(b) + 127) / 255) as u8
+}
+
+/// Box radii of three successive box blurs approximating a Gaussian with radius `radius`.
+fn box_radii(radius: f32) -> [usize; 3] {
+    let sigma = (radius / 2.0) as f64;
+    if sigma <= 0.0 {
+        return [0; 3];
+    }
+
+    let passes = 3.0;
+    let ideal = (12.0 * sigma * sigma / passes + 1.0).sqrt();
+    let mut lower = ideal.floor() as i64;
+    if lower % 2 == 0 {
+        lower -= 1;
+    }
+    let upper = lower + 2;
+    let lower_f = lower as f64;
+    let lower_count = ((12.0 * sigma * sigma
+        - passes * lower_f * lower_f
+        - 4.0 * passes * lower_f
+        - 3.0 * passes)
+        / (-4.0 * lower_f - 4.0))
+        .round() as i64;
+
+    let mut radii = [0; 3];
+    for (pass, radius) in radii.iter_mut().enumerate() {
+        let size = if (pass as i64) < lower_count {
+            lower
+        } else {
+            upper
+        };
+        *radius = ((size.max(1) - 1) / 2) as usize;
+    }
+    radii
+}
+
+/// How far a blur with `radius` spreads coverage, in whole pixels.
+fn blur_spread(radius: f32) -> u32 {
+    box_radii(radius).iter().sum::<usize>() as u32
+}
+
+fn blur_plane(plane: &mut [u8], width: u32, height: u32, radius: f32) {
+    let (width, height) = (width as usize, height as usize);
+    let mut scratch = vec![0u8; plane.len()];
+    for box_radius in box_radii(radius) {
+        if box_radius == 0 {
+            continue;
+        }
+        box_blur(plane, &mut scratch, width, height, box_radius, 1, width);
+        box_blur(&scratch, plane, height, width, box_radius, width, 1);
+    }
+}
+
+fn blur_rgba(data: &mut [u8], width: u32, height: u32, radius: f32) {
+    for channel in 0..4 {
+        let mut plane: Vec<u8> = data.iter().skip(channel).step_by(4).copied().collect();
+        blur_plane(&mut plane, width, height, radius);
+        for (pixel, value) in data.chunks_exact_mut(4).zip(plane) {
+            pixel[channel] = value;
+        }
+    }
+    // Keep premultiplied color within alpha after independent channel rounding.
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = pixel[3];
+        for channel in &mut pixel[..3] {
+            *channel = (*channel).min(alpha);
+        }
+    }
+}
+
+/// One box-blur pass along lines of `len` samples spaced `step` apart; `lines` lines start
+/// `line_step` apart. Samples outside the plane count as zero.
+fn box_blur(
+    src: &[u8],
+    dst: &mut [u8],
+    lines: usize,
+    len: usize,
+    radius: usize,
+    line_step: usize,
+    step: usize,
+) {
+    let window = (2 * radius + 1) as u32;
+    for line in 0..lines {
+        let start = line * line_step;
+        let at = |i: usize| u32::from(src[start + i * step]);
+        let mut sum: u32 = (0..radius.min(len)).map(at).sum();
+        for i in 0..len {
+            if i + radius < len {
+                sum += at(i + radius);
+            }
+            if i > radius {
+                sum -= at(i - radius - 1);
+            }
+            dst[start + i * step] = ((sum + window / 2) / window) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(size: u32, inset: u32) -> Layer {
+        let mut data = vec![0u8; (size * size * 4) as usize];
+        for y in inset..size - inset {
+            for x in inset..size - inset {
+                let i = ((y * size + x) * 4) as usize;
+                data[i..i + 4].copy_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+        Layer {
+            width: size,
+            height: size,
+            data,
+        }
+    }
+
+    fn alpha(layer: &Layer, x: u32, y: u32) -> u8 {
+        layer.data[((y * layer.width + x) * 4 + 3) as usize]
+    }
+
+    #[test]
+    fn blur_preserves_total_coverage() {
+        let mut plane = vec![0u8; 21 * 21];
+        plane[10 * 21 + 10] = 255;
+        plane[10 * 21 + 11] = 255;
+        blur_plane(&mut plane, 21, 21, 4.0);
+        let total: u32 = plane.iter().map(|&v| u32::from(v)).sum();
+        assert!((total as i32 - 510).abs() < 60, "total {total}");
+        assert!(plane[10 * 21 + 10] < 255);
+        assert!(plane[10 * 21 + 13] > 0);
+    }
+
+    #[test]
+    fn drop_shadow_grows_canvas_towards_offset() {
+        let effects = [Effect::DropShadow {
+            offset_x: 3.0,
+            offset_y: 2.0,
+            blur_radius: 0.0,
+            color: "#ff0000".to_string(),
+        }];
+        assert_eq!(
+            margins(&effects),
+            Margins {
+                left: 0,
+                top: 0,
+                right: 3,
+                bottom: 2
+            }
+        );
+
+        let out = compose(square(10, 2), &effects, None).unwrap();
+        assert_eq!((out.width, out.height), (13, 12));
+        // Text pixel stays black; the shadow shows only to the lower right.
+        let px = |x: u32, y: u32| {
+            let i = ((y * out.width + x) * 4) as usize;
+            out.data[i..i + 4].to_vec()
+        };
+        assert_eq!(px(3, 3), vec![0, 0, 0, 255]);
+        assert_eq!(px(10, 9), vec![255, 0, 0, 255]);
+        assert_eq!(px(1, 1), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn glow_surrounds_text_and_background_fills_grown_canvas() {
+        let effects = [Effect::OuterGlow {
+            radius: 4.0,
+            color: "#00ff00".to_string(),
+        }];
+        let spread = blur_spread(4.0);
+        assert!(spread > 0);
+        let out = compose(
+            square(10, 2),
+            &effects,
+            Some(Background::Solid([0, 0, 255, 255])),
+        )
+        .unwrap();
+        assert_eq!(out.width, 10 + 2 * spread);
+
+        let edge = spread + 1;
+        let i = ((edge * out.width + edge) * 4) as usize;
+        assert!(out.data[i + 1] > 0, "glow just outside the text");
+        assert!(out.data.chunks_exact(4).all(|px| px[3] == 255));
+    }
+
+    #[test]
+    fn inner_shadow_stays_inside_the_glyphs() {
+        let effects = [Effect::InnerShadow {
+            offset_x: 2.0,
+            offset_y: 2.0,
+            blur_radius: 0.0,
+            color: "#ffffff".to_string(),
+        }];
+        let out = compose(square(10, 2), &effects, None).unwrap();
+        assert_eq!((out.width, out.height), (10, 10));
+        // Top-left band of the square is lit, the rest of the square stays black.
+        let i = ((2 * 10 + 2) * 4) as usize;
+        assert_eq!(&out.data[i..i + 4], &[255, 255, 255, 255]);
+        let i = ((6 * 10 + 6) * 4) as usize;
+        assert_eq!(&out.data[i..i + 4], &[0, 0, 0, 255]);
+        assert_eq!(alpha(&out, 0, 0), 0);
+    }
+
+    #[test]
+    fn blur_softens_text_edges() {
+        let out = compose(square(10, 2), &[Effect::Blur { radius: 2.0 }], None).unwrap();
+        let spread = blur_spread(2.0);
+        assert!(alpha(&out, spread + 1, spread + 5) > 0);
+        assert!(alpha(&out, spread + 2, spread + 5) < 255);
+    }
+}
@@ -2,6 +2,8 @@
 
 //! Error types for the o4e rendering engine.
 
+use crate::color::ColorError;
+use crate::description::FontDescriptionError;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -40,6 +42,18 @@ pub enum O4eError {
     #[error("Invalid parameter: {name} = {value}")]
     InvalidParameter { name: String, value: String },
 
+    /// Malformed text markup
+    #[error("Invalid markup at byte {offset}: {reason}")]
+    InvalidMarkup { offset: usize, reason: String },
+
+    /// Unparseable color value
+    #[error(transparent)]
+    InvalidColor(#[from] ColorError),
+
+    /// Unparseable font description
+    #[error(transparent)]
+    InvalidFontDescription(#[from] FontDescriptionError),
+
     /// Backend not available
     #[error("Backend not available: {name}")]
     BackendNotAvailable { name: String },
@@ -86,6 +100,14 @@ impl O4eError {
         }
     }
 
+    /// Create a new markup error at a byte offset into the markup
+    pub fn markup(offset: usize, reason: impl Into<String>) -> Self {
+        Self::InvalidMarkup {
+            offset,
+            reason: reason.into(),
+        }
+    }
+
     /// Create a new generic error
     pub fn other(msg: impl Into<String>) -> Self {
         Self::Other(msg.into())
@@ -0,0 +1,291 @@
+// this_file: backends/o4e-core/src/frame.rs
+
+//! Caller-owned pixel buffers that renders composite onto.
+//!
+//! A [`Frame`] borrows an existing image (a video frame, a texture upload buffer, a numpy
+//! array) so text can be drawn over it without allocating an output bitmap.
+
+use crate::{
+    color::Rgba,
+    pixel::{self, mul},
+    traits::Backend,
+    types::{Bitmap, PixelFormat, RenderFormat, RenderOptions, RenderOutput, ShapingResult},
+    O4eError, Result,
+};
+
+/// Mutable view of a caller-provided 8-bit image.
+#[derive(Debug)]
+pub struct Frame<'a> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: PixelFormat,
+}
+
+impl<'a> Frame<'a> {
+    /// Wrap `data` as a `width`×`height` image with rows `stride` bytes apart.
+    ///
+    /// Fails when the stride is shorter than a row or the buffer does not hold every row.
+    pub fn new(
+        data: &'a mut [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+        format: PixelFormat,
+    ) -> Result<Self> {
+        let row = width as usize * format.bytes_per_pixel();
+        if stride < row {
+            return Err(O4eError::InvalidParameter {
+                name: "stride".to_string(),
+                value: format!("{stride} (a {width} pixel row needs {row} bytes)"),
+            });
+        }
+        let required = match height {
+            0 => 0,
+            height => stride * (height as usize - 1) + row,
+        };
+        if data.len() < required {
+            return Err(O4eError::InvalidParameter {
+                name: "buffer".to_string(),
+                value: format!(
+                    "{} bytes (a {width}x{height} frame with stride {stride} needs {required})",
+                    data.len()
+                ),
+            });
+        }
+        Ok(Self {
+            data,
+            width,
+            height,
+            stride,
+            format,
+        })
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Bytes between the starts of consecutive rows.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Pixel layout of the buffer.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Composite straight-alpha `color` through a coverage `mask` (`mask_width` pixels per
+    /// row) with its top-left corner at `(x, y)`, clipped to the frame.
+    pub fn fill_mask(&mut self, x: i32, y: i32, mask: &[u8], mask_width: u32, color: Rgba) {
+        if mask_width == 0 {
+            return;
+        }
+        let (r, g, b, a) = color;
+        let mask_height = (mask.len() / mask_width as usize) as u32;
+        self.for_each_covered(x, y, mask_width, mask_height, |frame, index, offset| {
+            let alpha = mul(a, mask[index]);
+            if alpha != 0 {
+                frame.blend(offset, [mul(r, alpha), mul(g, alpha), mul(b, alpha), alpha]);
+            }
+        });
+    }
+
+    /// Composite `bitmap` (any [`PixelFormat`]) with its top-left corner at `(x, y)`,
+    /// clipped to the frame.
+    pub fn draw_bitmap(&mut self, x: i32, y: i32, bitmap: &Bitmap) {
+        let source_format = bitmap.format;
+        let bytes_per_pixel = source_format.bytes_per_pixel();
+        let columns = bitmap.width as usize;
+        self.for_each_covered(x, y, bitmap.width, bitmap.height, |frame, index, offset| {
+            let start = index / columns * bitmap.stride + index % columns * bytes_per_pixel;
+            let src = pixel::load(source_format, &bitmap.data[start..start + bytes_per_pixel]);
+            if src[3] != 0 {
+                frame.blend(offset, src);
+            }
+        });
+    }
+
+    /// Visit the frame byte offset of every source pixel of a `width`×`height` image placed
+    /// at `(x, y)` that lands inside the frame; the source index counts pixels row by row.
+    fn for_each_covered(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        mut visit: impl FnMut(&mut Self, usize, usize),
+    ) {
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let columns = x.max(0)..(x + width as i32).min(self.width as i32);
+        for dst_y in y.max(0)..(y + height as i32).min(self.height as i32) {
+            let src_row = (dst_y - y) as usize * width as usize;
+            let dst_row = dst_y as usize * self.stride;
+            for dst_x in columns.clone() {
+                let index = src_row + (dst_x - x) as usize;
+                visit(self, index, dst_row + dst_x as usize * bytes_per_pixel);
+            }
+        }
+    }
+
+    /// Source-over a premultiplied RGBA pixel onto the frame pixel at `offset`.
+    ///
+    /// Frames without alpha are opaque, so the result needs no flattening.
+    fn blend(&mut self, offset: usize, src: [u8; 4]) {
+        let pixel = &mut self.data[offset..offset + self.format.bytes_per_pixel()];
+        let blended = pixel::over(pixel::load(self.format, pixel), src);
+        pixel::store(self.format, blended, pixel);
+    }
+}
+
+/// Render `shaped` to a bitmap and composite it onto `frame` with its top-left corner at
+/// `origin`; the default [`Backend::render_into`].
+///
+/// The intermediate bitmap is always premultiplied RGBA, whatever `options.pixel_format`
+/// asks for: formats without alpha would flatten it onto white, and `A8` would drop its color.
+pub fn render_via_bitmap<B: Backend + ?Sized>(
+    backend: &B,
+    shaped: &ShapingResult,
+    options: &RenderOptions,
+    frame: &mut Frame<'_>,
+    origin: (i32, i32),
+) -> Result<()> {
+    let options = RenderOptions {
+        format: RenderFormat::Raw,
+        pixel_format: PixelFormat::Rgba8Premultiplied,
+        ..options.clone()
+    };
+    match backend.render(shaped, &options)? {
+        RenderOutput::Bitmap(bitmap) => {
+            frame.draw_bitmap(origin.0, origin.1, &bitmap);
+            Ok(())
+        }
+        _ => Err(O4eError::render(format!(
+            "{} did not return a bitmap to composite",
+            backend.name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_short_strides_and_buffers() {
+        let mut data = vec![0u8; 16];
+        assert!(Frame::new(&mut data, 2, 2, 7, PixelFormat::Rgba8).is_err());
+        assert!(Frame::new(&mut data, 2, 3, 8, PixelFormat::Rgba8).is_err());
+        // The last row does not need its stride padding.
+        let mut padded = vec![0u8; 12 + 8];
+        assert!(Frame::new(&mut padded, 2, 2, 12, PixelFormat::Bgra8).is_ok());
+    }
+
+    #[test]
+    fn masks_blend_into_bgra_rows_and_clip() {
+        // 3x2 opaque blue BGRA frame with 4 bytes of row padding.
+        let mut data = [255, 0, 0, 255].repeat(3);
+        data.extend([9; 4]);
+        data.extend([255, 0, 0, 255].repeat(3));
+        let mut frame = Frame::new(&mut data, 3, 2, 16, PixelFormat::Bgra8).unwrap();
+
+        // A 2x2 mask hanging off the right edge: only its left column lands.
+        frame.fill_mask(2, 0, &[255, 255, 128, 128], 2, (255, 0, 0, 255));
+        assert_eq!(&data[8..12], &[0, 0, 255, 255]);
+        assert_eq!(&data[12..16], &[9; 4]);
+        assert_eq!(&data[24..28], &[127, 0, 128, 255]);
+        assert_eq!(&data[0..4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn straight_and_premultiplied_frames_keep_their_convention() {
+        let half_red = Bitmap::rgba(1, 1, vec![255, 0, 0, 128]);
+
+        let mut straight = vec![0u8; 4];
+        Frame::new(&mut straight, 1, 1, 4, PixelFormat::Rgba8)
+            .unwrap()
+            .draw_bitmap(0, 0, &half_red);
+        assert_eq!(straight, vec![255, 0, 0, 128]);
+
+        let mut premultiplied = vec![0u8; 4];
+        Frame::new(&mut premultiplied, 1, 1, 4, PixelFormat::Rgba8Premultiplied)
+            .unwrap()
+            .draw_bitmap(0, 0, &half_red);
+        assert_eq!(premultiplied, vec![128, 0, 0, 128]);
+    }
+
+    #[test]
+    fn single_channel_frames_blend_coverage_and_luminance() {
+        let mut mask = vec![64u8, 0];
+        Frame::new(&mut mask, 2, 1, 2, PixelFormat::A8)
+            .unwrap()
+            .fill_mask(0, 0, &[128, 255], 2, (255, 0, 0, 255));
+        assert_eq!(mask, vec![160, 255]);
+
+        // Black text at full coverage on white paper, half coverage mid-gray.
+        let mut paper = vec![255u8; 2];
+        Frame::new(&mut paper, 2, 1, 2, PixelFormat::Gray8)
+            .unwrap()
+            .fill_mask(0, 0, &[255, 128], 2, (0, 0, 0, 255));
+        assert_eq!(paper, vec![0, 127]);
+
+        let mut rgb = vec![0u8, 0, 255];
+        Frame::new(&mut rgb, 1, 1, 3, PixelFormat::Rgb8)
+            .unwrap()
+            .draw_bitmap(0, 0, &Bitmap::new(1, 1, PixelFormat::A8, vec![255]));
+        assert_eq!(rgb, vec![0, 0, 0]);
+    }
+
+    /// Renders one half-transparent red pixel in the requested pixel format.
+    struct PixelBackend;
+
+    impl Backend for PixelBackend {
+        fn segment(
+            &self,
+            _text: &str,
+            _options: &crate::SegmentOptions,
+        ) -> Result<Vec<crate::TextRun>> {
+            Ok(Vec::new())
+        }
+
+        fn shape(&self, _run: &crate::TextRun, font: &crate::Font) -> Result<ShapingResult> {
+            Ok(ShapingResult::empty(font))
+        }
+
+        fn render(&self, _shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput> {
+            crate::RenderSurface::from_rgba(1, 1, vec![255, 0, 0, 128], false)
+                .with_pixel_format(options.pixel_format)
+                .into_render_output(options.format)
+        }
+
+        fn name(&self) -> &str {
+            "pixel"
+        }
+
+        fn clear_cache(&self) {}
+    }
+
+    #[test]
+    fn render_into_ignores_the_output_pixel_format() {
+        let shaped = ShapingResult::empty(&crate::Font::new("Test", 16.0));
+        for pixel_format in [PixelFormat::Gray8, PixelFormat::Rgb8, PixelFormat::A8] {
+            let options = RenderOptions {
+                pixel_format,
+                ..RenderOptions::default()
+            };
+            // Opaque blue frame: the text blends in, with no white box and no lost color
+            let mut data = vec![0u8, 0, 255, 255];
+            let mut frame = Frame::new(&mut data, 1, 1, 4, PixelFormat::Rgba8).unwrap();
+            render_via_bitmap(&PixelBackend, &shaped, &options, &mut frame, (0, 0)).unwrap();
+            assert_eq!(data, vec![128, 0, 127, 255], "{pixel_format:?}");
+        }
+    }
+}
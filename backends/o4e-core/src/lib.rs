@@ -3,19 +3,31 @@
 //! Core traits and types for the o4e text rendering engine.
 
 pub mod cache;
+pub mod color;
+pub mod description;
+mod effects;
 pub mod error;
+pub mod frame;
+mod pixel;
 pub mod surface;
 pub mod traits;
 pub mod types;
+pub mod units;
 pub mod utils;
 
 pub use cache::FontCache;
+pub use color::{ColorError, ColorErrorKind, CssColor};
+pub use description::{FontDescription, FontDescriptionError, FontDescriptionErrorKind};
 pub use error::O4eError;
-pub use surface::{RenderSurface, SurfaceFormat};
+pub use frame::Frame;
+pub use surface::{render_metadata, RenderSurface, SurfaceFormat, TextAnchor};
 pub use traits::{Backend, FontShaper, GlyphRenderer, TextSegmenter};
 pub use types::{
-    Bitmap, Features, Font, Glyph, RenderFormat, RenderOptions, RenderOutput, SegmentOptions,
-    ShapingResult, SvgOptions, TextRun,
+    BaselinePosition, Bitmap, Canvas, CanvasOverflow, Decoration, DecorationLine, DecorationStyle,
+    Effect, Features, Font, Glyph, GradientStop, HorizontalAlign, Paint, PdfOptions, PixelFormat,
+    PngCompression, PngFilter, PngOptions, RenderFormat, RenderOptions, RenderOutput,
+    SegmentOptions, ShapedLine, ShapingResult, SizingMode, SpreadMode, Stroke, SvgOptions,
+    TextPath, TextRun, Transform2D, VerticalAlign,
 };
 
 /// Result type for o4e operations
@@ -0,0 +1,116 @@
+// this_file: backends/o4e-core/src/pixel.rs
+
+//! Per-pixel conversion between [`PixelFormat`]s and premultiplied RGBA.
+
+use crate::types::PixelFormat;
+
+/// Read one pixel of `format` as premultiplied RGBA.
+///
+/// `A8` reads as black with that coverage; `Gray8` and `Rgb8` read as opaque.
+pub(crate) fn load(format: PixelFormat, pixel: &[u8]) -> [u8; 4] {
+    match format {
+        PixelFormat::Rgba8 => premultiply([pixel[0], pixel[1], pixel[2], pixel[3]]),
+        PixelFormat::Bgra8 => premultiply([pixel[2], pixel[1], pixel[0], pixel[3]]),
+        PixelFormat::Rgba8Premultiplied => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        PixelFormat::Bgra8Premultiplied => [pixel[2], pixel[1], pixel[0], pixel[3]],
+        PixelFormat::Rgb8 => [pixel[0], pixel[1], pixel[2], 255],
+        PixelFormat::Gray8 => [pixel[0], pixel[0], pixel[0], 255],
+        PixelFormat::A8 => [0, 0, 0, pixel[0]],
+    }
+}
+
+/// Write premultiplied RGBA `value` as one pixel of `format`.
+///
+/// `Rgb8` and `Gray8` keep the color channels only, so flatten translucent values first;
+/// `A8` keeps the coverage only.
+pub(crate) fn store(format: PixelFormat, value: [u8; 4], pixel: &mut [u8]) {
+    match format {
+        PixelFormat::Rgba8 => pixel.copy_from_slice(&unpremultiply(value)),
+        PixelFormat::Bgra8 => {
+            let [r, g, b, a] = unpremultiply(value);
+            pixel.copy_from_slice(&[b, g, r, a]);
+        }
+        PixelFormat::Rgba8Premultiplied => pixel.copy_from_slice(&value),
+        PixelFormat::Bgra8Premultiplied => {
+            pixel.copy_from_slice(&[value[2], value[1], value[0], value[3]])
+        }
+        PixelFormat::Rgb8 => pixel.copy_from_slice(&value[..3]),
+        PixelFormat::Gray8 => pixel[0] = luminance(value),
+        PixelFormat::A8 => pixel[0] = value[3],
+    }
+}
+
+/// Source-over of premultiplied `src` onto premultiplied `dst`.
+pub(crate) fn over(dst: [u8; 4], src: [u8; 4]) -> [u8; 4] {
+    let inverse = 255 - src[3];
+    [0, 1, 2, 3].map(|channel| src[channel].saturating_add(mul(dst[channel], inverse)))
+}
+
+/// Rec. 709 luma of the color channels.
+pub(crate) fn luminance(value: [u8; 4]) -> u8 {
+    let [r, g, b, _] = value.map(u32::from);
+    ((54 * r + 183 * g + 19 * b + 128) >> 8) as u8
+}
+
+pub(crate) fn premultiply(value: [u8; 4]) -> [u8; 4] {
+    let alpha = value[3];
+    [
+        mul(value[0], alpha),
+        mul(value[1], alpha),
+        mul(value[2], alpha),
+        alpha,
+    ]
+}
+
+pub(crate) fn unpremultiply(value: [u8; 4]) -> [u8; 4] {
+    let alpha = u32::from(value[3]);
+    if alpha == 0 || alpha == 255 {
+        return value;
+    }
+    let channel = |c: u8| (u32::from(c) * 255 / alpha).min(255) as u8;
+    [
+        channel(value[0]),
+        channel(value[1]),
+        channel(value[2]),
+        value[3],
+    ]
+}
+
+pub(crate) fn mul(a: u8, b: u8) -> u8 {
+    ((u16::from(a) * u16::from(b) + 127) / 255) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_round_trip_through_premultiplied_rgba() {
+        let value = [100, 50, 0, 200];
+        for format in [
+            PixelFormat::Rgba8,
+            PixelFormat::Bgra8,
+            PixelFormat::Rgba8Premultiplied,
+            PixelFormat::Bgra8Premultiplied,
+        ] {
+            let mut pixel = [0u8; 4];
+            store(format, value, &mut pixel);
+            let loaded = load(format, &pixel);
+            assert!(
+                loaded.iter().zip(value).all(|(a, b)| a.abs_diff(b) <= 1),
+                "{format:?}: {loaded:?}"
+            );
+        }
+
+        let mut mask = [0u8];
+        store(PixelFormat::A8, value, &mut mask);
+        assert_eq!(mask, [200]);
+        assert_eq!(load(PixelFormat::A8, &mask), [0, 0, 0, 200]);
+
+        let mut gray = [0u8];
+        store(PixelFormat::Gray8, [255, 255, 255, 255], &mut gray);
+        assert_eq!(gray, [255]);
+        store(PixelFormat::Gray8, [0, 255, 0, 255], &mut gray);
+        assert_eq!(gray, [182]);
+    }
+}
@@ -1,9 +1,21 @@
 // this_file: backends/o4e-core/src/surface.rs
 
 //! Shared helpers for converting backend-specific buffers into [`RenderOutput`]s.
+//!
+//! Every bitmap encoder lives here, so all backends produce the same PNG, WebP, JPEG,
+//! PGM/PBM and BMP files.
+
+use std::collections::HashMap;
 
 use crate::{
-    types::{Bitmap, RenderFormat, RenderOutput},
+    color::Rgba,
+    effects::{self, Background, Layer},
+    pixel,
+    types::{
+        Bitmap, Canvas, CanvasOverflow, Effect, FontSource, Paint, PixelFormat, PngCompression,
+        PngFilter, PngOptions, RenderFormat, RenderOptions, RenderOutput, ShapingResult,
+    },
+    utils::parse_color_with_current,
     O4eError, Result,
 };
 
@@ -14,8 +26,24 @@ pub enum SurfaceFormat {
     Rgba,
     /// BGRA ordering.
     Bgra,
-    /// Grayscale alpha-less mask.
-    Gray,
+    /// Single-channel coverage mask.
+    Alpha,
+}
+
+/// Line geometry of the text on a surface, in surface pixels (y down), used to place it on a
+/// fixed [`Canvas`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextAnchor {
+    /// Pen origin x of the line
+    pub origin_x: f32,
+    /// Baseline y of the line
+    pub baseline_y: f32,
+    /// Pen advance of the line
+    pub advance: f32,
+    /// Font ascent above the baseline
+    pub ascent: f32,
+    /// Font descent below the baseline (positive)
+    pub descent: f32,
 }
 
 /// Render surface produced by a backend prior to format conversion/encoding.
@@ -26,6 +54,13 @@ pub struct RenderSurface {
     format: SurfaceFormat,
     premultiplied: bool,
     data: Vec<u8>,
+    background: Option<Background>,
+    effects: Vec<Effect>,
+    canvas: Option<(Canvas, TextAnchor)>,
+    pixel_format: PixelFormat,
+    jpeg_quality: u8,
+    png: PngOptions,
+    metadata: Vec<(String, String)>,
 }
 
 impl RenderSurface {
@@ -37,6 +72,13 @@ impl RenderSurface {
             format: SurfaceFormat::Rgba,
             premultiplied,
             data,
+            background: None,
+            effects: Vec::new(),
+            canvas: None,
+            pixel_format: PixelFormat::default(),
+            jpeg_quality: 90,
+            png: PngOptions::default(),
+            metadata: Vec::new(),
         }
     }
 
@@ -48,101 +90,435 @@ impl RenderSurface {
             format: SurfaceFormat::Bgra,
             premultiplied,
             data,
+            background: None,
+            effects: Vec::new(),
+            canvas: None,
+            pixel_format: PixelFormat::default(),
+            jpeg_quality: 90,
+            png: PngOptions::default(),
+            metadata: Vec::new(),
         }
     }
 
-    /// Create a grayscale surface (used for alpha-only glyph caches).
-    pub fn from_gray(width: u32, height: u32, data: Vec<u8>) -> Self {
+    /// Create a coverage mask surface (used for alpha-only glyph caches).
+    ///
+    /// The mask stays a mask: converted to color it is black text on transparency, not
+    /// an opaque gray image.
+    pub fn from_alpha(width: u32, height: u32, data: Vec<u8>) -> Self {
         Self {
             width,
             height,
-            format: SurfaceFormat::Gray,
+            format: SurfaceFormat::Alpha,
             premultiplied: false,
             data,
+            background: None,
+            effects: Vec::new(),
+            canvas: None,
+            pixel_format: PixelFormat::default(),
+            jpeg_quality: 90,
+            png: PngOptions::default(),
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Create a transparent 1×1 surface for text without glyphs, with the output settings
+    /// of `options` (see [`with_render_options`](Self::with_render_options)).
+    ///
+    /// On a fixed canvas it is placed like a line with no advance or font metrics, so the
+    /// output still has the canvas size.
+    pub fn empty(options: &RenderOptions, text_color: Rgba) -> Result<Self> {
+        let surface =
+            Self::from_rgba(1, 1, vec![0; 4], false).with_render_options(options, text_color)?;
+        Ok(match options.canvas {
+            Some(canvas) => surface.with_canvas(
+                canvas,
+                TextAnchor {
+                    origin_x: 0.0,
+                    baseline_y: 0.0,
+                    advance: 0.0,
+                    ascent: 0.0,
+                    descent: 0.0,
+                },
+            ),
+            None => surface,
+        })
+    }
+
+    /// Paint a straight-alpha RGBA background under the surface contents (and its effects).
+    pub fn with_background(mut self, color: [u8; 4]) -> Self {
+        self.background = Some(Background::Solid(color));
+        self
+    }
+
+    /// Paint a premultiplied RGBA image under the surface contents (and its effects).
+    ///
+    /// The image must cover the output canvas, see [`output_size`](Self::output_size); set
+    /// effects and the canvas first.
+    pub fn with_background_image(mut self, data: Vec<u8>) -> Self {
+        self.background = Some(Background::Image(data));
+        self
+    }
+
+    /// Apply effect layers to the surface contents when converting; the surface grows so
+    /// no effect is clipped.
+    pub fn with_effects(mut self, effects: Vec<Effect>) -> Self {
+        self.effects = effects;
+        self
+    }
+
+    /// Place the contents on a fixed canvas when converting, aligning `anchor` as the canvas
+    /// asks; the position is rounded to whole pixels.
+    pub fn with_canvas(mut self, canvas: Canvas, anchor: TextAnchor) -> Self {
+        self.canvas = Some((canvas, anchor));
+        self
+    }
+
+    /// Pixel layout of converted `RenderFormat::Raw` bitmaps (straight RGBA by default).
+    pub fn with_pixel_format(mut self, format: PixelFormat) -> Self {
+        self.pixel_format = format;
+        self
+    }
+
+    /// JPEG quality (1–100) for `RenderFormat::Jpeg`.
+    pub fn with_jpeg_quality(mut self, quality: u8) -> Self {
+        self.jpeg_quality = quality;
+        self
+    }
+
+    /// PNG encoder options for `RenderFormat::Png`.
+    pub fn with_png_options(mut self, options: PngOptions) -> Self {
+        self.png = options;
+        self
+    }
+
+    /// Output settings from `options`: effects, pixel format, JPEG quality, PNG options and a
+    /// solid background, `currentColor` resolving to `text_color`.
+    ///
+    /// The background goes under the effect layers, so the surface paints it rather than the
+    /// backend. Gradient and pattern backgrounds are left to backends that can draw them, with
+    /// [`with_background_image`](Self::with_background_image) once the canvas is set.
+    pub fn with_render_options(
+        mut self,
+        options: &RenderOptions,
+        text_color: Rgba,
+    ) -> Result<Self> {
+        self = self
+            .with_effects(options.effects.clone())
+            .with_pixel_format(options.pixel_format)
+            .with_jpeg_quality(options.jpeg_quality)
+            .with_png_options(options.png.clone().unwrap_or_default());
+        if let Some(Paint::Solid(color)) = options.resolved_background() {
+            let (r, g, b, a) = parse_color_with_current(&color, text_color)?;
+            self = self.with_background([r, g, b, a]);
         }
+        Ok(self)
+    }
+
+    /// Keyword/value pairs describing the render (see [`render_metadata`]); PNG output
+    /// records them when [`PngOptions::metadata`] is set.
+    pub fn with_metadata(mut self, metadata: Vec<(String, String)>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Size of the converted bitmap: the fixed canvas when set, otherwise the surface grown
+    /// by [`effect_margins`](Self::effect_margins).
+    pub fn output_size(&self) -> (u32, u32) {
+        if let Some((canvas, _)) = &self.canvas {
+            return (canvas.width, canvas.height);
+        }
+        let (left, top, right, bottom) = self.effect_margins();
+        (self.width + left + right, self.height + top + bottom)
+    }
+
+    /// Margins (left, top, right, bottom) the configured effects add around the surface.
+    pub fn effect_margins(&self) -> (u32, u32, u32, u32) {
+        let margins = effects::margins(&self.effects);
+        (margins.left, margins.top, margins.right, margins.bottom)
     }
 
     /// Convert the surface into a [`RenderOutput`].
-    pub fn into_render_output(self, format: RenderFormat) -> Result<RenderOutput> {
-        let width = self.width;
-        let height = self.height;
+    ///
+    /// Background and effects are composited first, so bitmap outputs may be larger than
+    /// the surface when effects are set. With a fixed canvas, fails if glyph ink overflows
+    /// a canvas set to [`CanvasOverflow::Error`]. JPEG has no alpha channel, so it fails
+    /// unless a background is set.
+    pub fn into_render_output(mut self, format: RenderFormat) -> Result<RenderOutput> {
+        let pixel_format = self.pixel_format;
+        let jpeg_quality = self.jpeg_quality;
+        let png_options = std::mem::take(&mut self.png);
+        let metadata = std::mem::take(&mut self.metadata);
+        if format == RenderFormat::Jpeg {
+            if !(1..=100).contains(&jpeg_quality) {
+                return Err(O4eError::InvalidParameter {
+                    name: "jpeg_quality".to_string(),
+                    value: format!("{jpeg_quality} (expected 1-100)"),
+                });
+            }
+            if self.background.is_none() {
+                return Err(O4eError::InvalidParameter {
+                    name: "background".to_string(),
+                    value: "transparent (JPEG output needs a background)".to_string(),
+                });
+            }
+        }
+        let surface = self.composited()?;
+        let (width, height) = (surface.width, surface.height);
+        // Single-channel outputs keep masks as coverage and convert color to luminance
+        let gray_format = match pixel_format {
+            PixelFormat::A8 => PixelFormat::A8,
+            _ => PixelFormat::Gray8,
+        };
         match format {
             RenderFormat::Svg => Err(O4eError::render(
                 "RenderSurface cannot be converted to SVG output",
             )),
-            RenderFormat::Raw => {
-                let rgba = self.into_rgba_data()?;
-                Ok(RenderOutput::Bitmap(Bitmap {
-                    width,
-                    height,
-                    data: rgba,
-                }))
-            }
+            RenderFormat::Pdf => Err(O4eError::render(
+                "RenderSurface cannot be converted to PDF output",
+            )),
+            RenderFormat::Raw => Ok(RenderOutput::Bitmap(surface.into_bitmap(pixel_format))),
             RenderFormat::Png => {
-                let rgba = self.into_rgba_data()?;
-                let png_data = encode_png(width, height, &rgba)?;
+                // PNG has no BGRA or premultiplied layouts
+                let png_format = match pixel_format {
+                    PixelFormat::A8 | PixelFormat::Gray8 | PixelFormat::Rgb8 => pixel_format,
+                    _ => PixelFormat::Rgba8,
+                };
+                let metadata = if png_options.metadata {
+                    metadata.as_slice()
+                } else {
+                    &[]
+                };
+                let png_data =
+                    encode_png(&surface.into_bitmap(png_format), &png_options, metadata)?;
                 Ok(RenderOutput::Png(png_data))
             }
-        }
-    }
-
-    fn into_rgba_data(mut self) -> Result<Vec<u8>> {
-        match self.format {
-            SurfaceFormat::Gray => Ok(expand_gray(&self.data)),
-            SurfaceFormat::Rgba => {
-                if self.premultiplied {
-                    unpremultiply(&mut self.data);
-                }
-                Ok(std::mem::take(&mut self.data))
+            RenderFormat::Webp => {
+                let webp_format = match pixel_format {
+                    PixelFormat::A8 | PixelFormat::Gray8 => gray_format,
+                    PixelFormat::Rgb8 => PixelFormat::Rgb8,
+                    _ => PixelFormat::Rgba8,
+                };
+                let webp_data = encode_webp(&surface.into_bitmap(webp_format))?;
+                Ok(RenderOutput::Webp(webp_data))
             }
-            SurfaceFormat::Bgra => {
-                bgra_to_rgba(&mut self.data);
-                if self.premultiplied {
-                    unpremultiply(&mut self.data);
+            RenderFormat::Jpeg => {
+                if width > u32::from(u16::MAX) || height > u32::from(u16::MAX) {
+                    return Err(O4eError::render(format!(
+                        "{width}x{height} exceeds the 65535 pixel JPEG limit"
+                    )));
                 }
-                Ok(std::mem::take(&mut self.data))
+                let jpeg_format = match pixel_format {
+                    PixelFormat::A8 | PixelFormat::Gray8 => PixelFormat::Gray8,
+                    _ => PixelFormat::Rgb8,
+                };
+                let bitmap = surface.into_bitmap(jpeg_format);
+                Ok(RenderOutput::Jpeg(encode_jpeg(&bitmap, jpeg_quality)?))
+            }
+            RenderFormat::Pgm => Ok(RenderOutput::Pgm(encode_pgm(
+                &surface.into_bitmap(gray_format),
+            ))),
+            RenderFormat::Pbm => Ok(RenderOutput::Pbm(encode_pbm(
+                &surface.into_bitmap(gray_format),
+            ))),
+            RenderFormat::Bmp => {
+                let bmp_format = match pixel_format {
+                    PixelFormat::A8 | PixelFormat::Gray8 => gray_format,
+                    PixelFormat::Rgb8 => PixelFormat::Rgb8,
+                    _ => PixelFormat::Bgra8,
+                };
+                Ok(RenderOutput::Bmp(encode_bmp(
+                    &surface.into_bitmap(bmp_format),
+                )))
             }
         }
     }
-}
 
-fn expand_gray(data: &[u8]) -> Vec<u8> {
-    let mut rgba = Vec::with_capacity(data.len() * 4);
-    for &value in data {
-        rgba.extend_from_slice(&[value, value, value, 255]);
+    /// Flatten background and effects into a premultiplied RGBA surface.
+    fn composited(mut self) -> Result<Self> {
+        if self.background.is_none() && self.effects.is_empty() && self.canvas.is_none() {
+            return Ok(self);
+        }
+
+        let effects = std::mem::take(&mut self.effects);
+        let background = self.background.take();
+        let canvas = self.canvas.take();
+        let (width, height) = (self.width, self.height);
+        let text = Layer {
+            width,
+            height,
+            data: self.into_bitmap(PixelFormat::Rgba8Premultiplied).data,
+        };
+
+        let Some((canvas, anchor)) = canvas else {
+            let layer = effects::compose(text, &effects, background)?;
+            return Ok(Self::from_rgba(layer.width, layer.height, layer.data, true));
+        };
+
+        if canvas.width == 0 || canvas.height == 0 {
+            return Err(O4eError::InvalidParameter {
+                name: "canvas".to_string(),
+                value: format!("{}x{}", canvas.width, canvas.height),
+            });
+        }
+        let x = (canvas.origin_x(anchor.advance) - anchor.origin_x).round() as i32;
+        let y =
+            (canvas.baseline_y(anchor.ascent, anchor.descent) - anchor.baseline_y).round() as i32;
+        if canvas.overflow == CanvasOverflow::Error && overflows(&text, &canvas, x, y) {
+            return Err(O4eError::render(format!(
+                "Text overflows the {}x{} canvas",
+                canvas.width, canvas.height
+            )));
+        }
+
+        // Effects keep their margins around the text, then everything is clipped to the canvas
+        let margins = effects::margins(&effects);
+        let composed = effects::compose(text, &effects, None)?;
+        let layer = effects::place(
+            &composed,
+            canvas.width,
+            canvas.height,
+            x - margins.left as i32,
+            y - margins.top as i32,
+            background,
+        )?;
+        Ok(Self::from_rgba(layer.width, layer.height, layer.data, true))
     }
-    rgba
-}
 
-fn bgra_to_rgba(data: &mut [u8]) {
-    for chunk in data.chunks_exact_mut(4) {
-        chunk.swap(0, 2);
+    /// Pixel layout of the surface data.
+    fn pixel_format(&self) -> PixelFormat {
+        match (self.format, self.premultiplied) {
+            (SurfaceFormat::Rgba, false) => PixelFormat::Rgba8,
+            (SurfaceFormat::Rgba, true) => PixelFormat::Rgba8Premultiplied,
+            (SurfaceFormat::Bgra, false) => PixelFormat::Bgra8,
+            (SurfaceFormat::Bgra, true) => PixelFormat::Bgra8Premultiplied,
+            (SurfaceFormat::Alpha, _) => PixelFormat::A8,
+        }
     }
+
+    /// Convert the surface data to `format`; formats without alpha are flattened onto white.
+    fn into_bitmap(self, format: PixelFormat) -> Bitmap {
+        let source = self.pixel_format();
+        if source == format {
+            return Bitmap::new(self.width, self.height, format, self.data);
+        }
+
+        let (source_size, target_size) = (source.bytes_per_pixel(), format.bytes_per_pixel());
+        let mut data = vec![0u8; self.data.len() / source_size * target_size];
+        for (src, dst) in self
+            .data
+            .chunks_exact(source_size)
+            .zip(data.chunks_exact_mut(target_size))
+        {
+            let mut value = pixel::load(source, src);
+            if !format.has_alpha() {
+                value = pixel::over([255; 4], value);
+            }
+            pixel::store(format, value, dst);
+        }
+        Bitmap::new(self.width, self.height, format, data)
+    }
+}
+
+/// Whether any covered pixel of `text`, placed at `(x, y)`, falls outside `canvas`.
+fn overflows(text: &Layer, canvas: &Canvas, x: i32, y: i32) -> bool {
+    let (width, height) = (canvas.width as i32, canvas.height as i32);
+    text.data
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(_, pixel)| pixel[3] != 0)
+        .any(|(index, _)| {
+            let px = (index as u32 % text.width) as i32 + x;
+            let py = (index as u32 / text.width) as i32 + y;
+            !(0..width).contains(&px) || !(0..height).contains(&py)
+        })
 }
 
-fn unpremultiply(data: &mut [u8]) {
-    for chunk in data.chunks_exact_mut(4) {
-        let alpha = chunk[3];
-        if alpha == 0 || alpha == 255 {
-            continue;
+/// Describe a render for image metadata: the text, font family, path (for fonts loaded from
+/// a file), size and variations, and the backend name.
+pub fn render_metadata(backend: &str, shaped: &ShapingResult) -> Vec<(String, String)> {
+    let mut metadata = vec![
+        (
+            "Software".to_string(),
+            format!("o4e {}", env!("CARGO_PKG_VERSION")),
+        ),
+        ("Title".to_string(), shaped.text.clone()),
+        ("o4e:backend".to_string(), backend.to_string()),
+    ];
+    if let Some(font) = &shaped.font {
+        metadata.push(("o4e:font-family".to_string(), font.family.clone()));
+        if let FontSource::Path(path) = &font.source {
+            metadata.push(("o4e:font-path".to_string(), path.clone()));
         }
-        let alpha_f = alpha as f32 / 255.0;
-        for channel in &mut chunk[..3] {
-            let unpremultiplied = ((*channel as f32) / alpha_f).clamp(0.0, 255.0);
-            *channel = unpremultiplied as u8;
+        metadata.push(("o4e:font-size".to_string(), font.size.to_string()));
+        if !font.variations.is_empty() {
+            let mut axes: Vec<String> = font
+                .variations
+                .iter()
+                .map(|(tag, value)| format!("{tag}={value}"))
+                .collect();
+            axes.sort();
+            metadata.push(("o4e:variations".to_string(), axes.join(",")));
         }
     }
+    metadata
 }
 
-fn encode_png(width: u32, height: u32, data: &[u8]) -> Result<Vec<u8>> {
+/// Encode a tightly packed `Rgba8`, `Rgb8`, `Gray8` or `A8` bitmap (masks as grayscale),
+/// writing `metadata` as text chunks.
+fn encode_png(
+    bitmap: &Bitmap,
+    options: &PngOptions,
+    metadata: &[(String, String)],
+) -> Result<Vec<u8>> {
+    let reduced = match bitmap.format {
+        PixelFormat::Rgba8 if options.reduce_colors => reduce_colors(&bitmap.data),
+        PixelFormat::A8 | PixelFormat::Gray8 => PngPixels::plain(png::ColorType::Grayscale),
+        PixelFormat::Rgb8 => PngPixels::plain(png::ColorType::Rgb),
+        _ => PngPixels::plain(png::ColorType::Rgba),
+    };
+    let data = reduced.data.as_deref().unwrap_or(&bitmap.data);
+    let png_err = |err: png::EncodingError| O4eError::render(format!("PNG encoder error: {err}"));
+
     let mut png_data = Vec::new();
     {
-        let mut encoder = png::Encoder::new(&mut png_data, width, height);
-        encoder.set_color(png::ColorType::Rgba);
+        let mut encoder = png::Encoder::new(&mut png_data, bitmap.width, bitmap.height);
+        encoder.set_color(reduced.color);
         encoder.set_depth(png::BitDepth::Eight);
-        let mut writer = encoder
-            .write_header()
-            .map_err(|err| O4eError::render(format!("PNG encoder error: {err}")))?;
+        if let Some((palette, transparency)) = reduced.palette {
+            encoder.set_palette(palette);
+            if let Some(transparency) = transparency {
+                encoder.set_trns(transparency);
+            }
+        }
+        encoder.set_compression(match options.compression {
+            PngCompression::Fast => png::Compression::Fast,
+            PngCompression::Default => png::Compression::Default,
+            PngCompression::Best => png::Compression::Best,
+        });
+        let filter = match options.filter {
+            PngFilter::Adaptive => None,
+            PngFilter::None => Some(png::FilterType::NoFilter),
+            PngFilter::Sub => Some(png::FilterType::Sub),
+            PngFilter::Up => Some(png::FilterType::Up),
+            PngFilter::Average => Some(png::FilterType::Avg),
+            PngFilter::Paeth => Some(png::FilterType::Paeth),
+        };
+        match filter {
+            Some(filter) => encoder.set_filter(filter),
+            None => encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive),
+        }
+        if options.srgb {
+            encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+        for (keyword, text) in metadata {
+            if text.is_ascii() {
+                encoder.add_text_chunk(keyword.clone(), text.clone())
+            } else {
+                encoder.add_itxt_chunk(keyword.clone(), text.clone())
+            }
+            .map_err(png_err)?;
+        }
+        let mut writer = encoder.write_header().map_err(png_err)?;
         writer
             .write_image_data(data)
             .map_err(|err| O4eError::render(format!("PNG write error: {err}")))?;
@@ -150,10 +526,235 @@ fn encode_png(width: u32, height: u32, data: &[u8]) -> Result<Vec<u8>> {
     Ok(png_data)
 }
 
+/// PNG color type for a bitmap, with repacked pixels and palette when reduced.
+struct PngPixels {
+    color: png::ColorType,
+    data: Option<Vec<u8>>,
+    /// RGB palette and per-entry alpha (omitted when opaque)
+    palette: Option<(Vec<u8>, Option<Vec<u8>>)>,
+}
+
+impl PngPixels {
+    fn plain(color: png::ColorType) -> Self {
+        Self {
+            color,
+            data: None,
+            palette: None,
+        }
+    }
+}
+
+/// Smallest lossless layout for straight RGBA pixels: grayscale (+alpha) when every visible
+/// pixel is gray, an indexed palette for up to 256 colors, otherwise RGB(A).
+fn reduce_colors(rgba: &[u8]) -> PngPixels {
+    let pixels = || rgba.chunks_exact(4);
+    let opaque = pixels().all(|pixel| pixel[3] == 255);
+    // Invisible pixels keep no color
+    let visible = |pixel: &[u8]| -> [u8; 4] {
+        match pixel[3] {
+            0 => [0; 4],
+            _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        }
+    };
+
+    if pixels().all(|pixel| {
+        let [r, g, b, _] = visible(pixel);
+        r == g && g == b
+    }) {
+        let (color, data) = if opaque {
+            (
+                png::ColorType::Grayscale,
+                pixels().map(|pixel| pixel[0]).collect(),
+            )
+        } else {
+            (
+                png::ColorType::GrayscaleAlpha,
+                pixels()
+                    .flat_map(|pixel| {
+                        let [gray, _, _, alpha] = visible(pixel);
+                        [gray, alpha]
+                    })
+                    .collect(),
+            )
+        };
+        return PngPixels {
+            color,
+            data: Some(data),
+            palette: None,
+        };
+    }
+
+    let mut entries: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+    for pixel in pixels() {
+        let value = visible(pixel);
+        let next = entries.len();
+        let index = *entries.entry(value).or_insert(next.min(255) as u8);
+        if entries.len() > 256 {
+            let color = if opaque {
+                png::ColorType::Rgb
+            } else {
+                png::ColorType::Rgba
+            };
+            let data = opaque.then(|| pixels().flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]));
+            return PngPixels {
+                color,
+                data: data.map(Iterator::collect),
+                palette: None,
+            };
+        }
+        indices.push(index);
+    }
+
+    let mut palette = vec![[0u8; 4]; entries.len()];
+    for (value, index) in entries {
+        palette[index as usize] = value;
+    }
+    let transparency = (!opaque).then(|| palette.iter().map(|entry| entry[3]).collect());
+    PngPixels {
+        color: png::ColorType::Indexed,
+        data: Some(indices),
+        palette: Some((
+            palette
+                .iter()
+                .flat_map(|entry| [entry[0], entry[1], entry[2]])
+                .collect(),
+            transparency,
+        )),
+    }
+}
+
+/// Encode a tightly packed `Rgba8`, `Rgb8`, `Gray8` or `A8` bitmap as lossless WebP.
+fn encode_webp(bitmap: &Bitmap) -> Result<Vec<u8>> {
+    let color = match bitmap.format {
+        PixelFormat::A8 | PixelFormat::Gray8 => image_webp::ColorType::L8,
+        PixelFormat::Rgb8 => image_webp::ColorType::Rgb8,
+        _ => image_webp::ColorType::Rgba8,
+    };
+    let mut webp_data = Vec::new();
+    image_webp::WebPEncoder::new(&mut webp_data)
+        .encode(&bitmap.data, bitmap.width, bitmap.height, color)
+        .map_err(|err| O4eError::render(format!("WebP encoder error: {err}")))?;
+    Ok(webp_data)
+}
+
+/// Encode an `Rgb8` or `Gray8` bitmap as baseline JPEG.
+///
+/// Chroma stays at full resolution: subsampling would smear the colored edges of small text.
+fn encode_jpeg(bitmap: &Bitmap, quality: u8) -> Result<Vec<u8>> {
+    let color = match bitmap.format {
+        PixelFormat::Gray8 => jpeg_encoder::ColorType::Luma,
+        _ => jpeg_encoder::ColorType::Rgb,
+    };
+    let mut jpeg_data = Vec::new();
+    let mut encoder = jpeg_encoder::Encoder::new(&mut jpeg_data, quality);
+    encoder.set_sampling_factor(jpeg_encoder::SamplingFactor::R_4_4_4);
+    encoder
+        .encode(
+            &bitmap.data,
+            bitmap.width as u16,
+            bitmap.height as u16,
+            color,
+        )
+        .map_err(|err| O4eError::render(format!("JPEG encoder error: {err}")))?;
+    Ok(jpeg_data)
+}
+
+/// Encode a single-channel bitmap as binary PGM (`P5`, maxval 255).
+fn encode_pgm(bitmap: &Bitmap) -> Vec<u8> {
+    let mut out = format!("P5\n{} {}\n255\n", bitmap.width, bitmap.height).into_bytes();
+    out.extend_from_slice(&bitmap.data);
+    out
+}
+
+/// Encode a single-channel bitmap as binary PBM (`P4`, 1 = black), thresholding at half
+/// intensity: `A8` coverage marks ink, `Gray8` luminance marks paper.
+fn encode_pbm(bitmap: &Bitmap) -> Vec<u8> {
+    let ink = |value: u8| match bitmap.format {
+        PixelFormat::A8 => value >= 128,
+        _ => value < 128,
+    };
+    let mut out = format!("P4\n{} {}\n", bitmap.width, bitmap.height).into_bytes();
+    for y in 0..bitmap.height {
+        let row = bitmap.row(y);
+        out.extend(row.chunks(8).map(|pixels| {
+            pixels
+                .iter()
+                .enumerate()
+                .filter(|(_, &value)| ink(value))
+                .fold(0u8, |byte, (bit, _)| byte | 0x80 >> bit)
+        }));
+    }
+    out
+}
+
+/// Encode a `Bgra8`, `Rgb8`, `Gray8` or `A8` bitmap as a bottom-up BMP: 32-bit with an
+/// alpha mask, 24-bit, or 8-bit with a gray palette.
+fn encode_bmp(bitmap: &Bitmap) -> Vec<u8> {
+    const FILE_HEADER: u32 = 14;
+    // BITMAPV4HEADER declares the BGRA channel masks, plain BITMAPINFOHEADER otherwise
+    let (bits, info_header, palette): (u16, u32, u32) = match bitmap.format {
+        PixelFormat::Bgra8 => (32, 108, 0),
+        PixelFormat::Rgb8 => (24, 40, 0),
+        _ => (8, 40, 256 * 4),
+    };
+    let row_size = (bitmap.width as usize * usize::from(bits) / 8).next_multiple_of(4);
+    let image_size = (row_size * bitmap.height as usize) as u32;
+    let offset = FILE_HEADER + info_header + palette;
+
+    let mut out = Vec::with_capacity((offset + image_size) as usize);
+    out.extend(b"BM");
+    out.extend((offset + image_size).to_le_bytes());
+    out.extend([0; 4]);
+    out.extend(offset.to_le_bytes());
+
+    out.extend(info_header.to_le_bytes());
+    out.extend((bitmap.width as i32).to_le_bytes());
+    out.extend((bitmap.height as i32).to_le_bytes());
+    out.extend(1u16.to_le_bytes());
+    out.extend(bits.to_le_bytes());
+    // BI_BITFIELDS for the V4 header, BI_RGB otherwise
+    let compression: u32 = if bits == 32 { 3 } else { 0 };
+    out.extend(compression.to_le_bytes());
+    out.extend(image_size.to_le_bytes());
+    // 72 DPI in pixels per metre
+    out.extend(2835u32.to_le_bytes());
+    out.extend(2835u32.to_le_bytes());
+    let colors: u32 = if bits == 8 { 256 } else { 0 };
+    out.extend(colors.to_le_bytes());
+    out.extend(0u32.to_le_bytes());
+    if bits == 32 {
+        for mask in [0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] {
+            out.extend(mask.to_le_bytes());
+        }
+        // LCS_sRGB, then unused endpoints and gamma
+        out.extend(b"BGRs");
+        out.extend([0; 48]);
+    }
+    if bits == 8 {
+        out.extend((0..=255u8).flat_map(|level| [level, level, level, 0]));
+    }
+
+    for y in (0..bitmap.height).rev() {
+        let start = out.len();
+        match bitmap.format {
+            PixelFormat::Rgb8 => out.extend(
+                bitmap
+                    .row(y)
+                    .chunks_exact(3)
+                    .flat_map(|pixel| [pixel[2], pixel[1], pixel[0]]),
+            ),
+            _ => out.extend_from_slice(bitmap.row(y)),
+        }
+        out.resize(start + row_size, 0);
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{RenderFormat, RenderOutput};
+    use crate::types::{PixelFormat, RenderFormat, RenderOutput};
 
     fn bitmap_data(output: RenderOutput) -> Vec<u8> {
         match output {
@@ -170,13 +771,68 @@ mod tests {
     }
 
     #[test]
-    fn gray_surface_expands_to_rgba() {
-        let surface = RenderSurface::from_gray(3, 1, vec![0, 128, 255]);
+    fn alpha_surface_stays_a_mask() {
+        let surface = RenderSurface::from_alpha(3, 1, vec![0, 128, 255]);
         let data = bitmap_data(surface.into_render_output(RenderFormat::Raw).unwrap());
+        assert_eq!(data, vec![0, 0, 0, 0, 0, 0, 0, 128, 0, 0, 0, 255]);
+
+        let mask = RenderSurface::from_alpha(3, 1, vec![0, 128, 255])
+            .with_pixel_format(PixelFormat::A8)
+            .into_render_output(RenderFormat::Raw)
+            .unwrap();
+        let RenderOutput::Bitmap(mask) = mask else {
+            panic!("expected bitmap output");
+        };
+        assert_eq!((mask.format, mask.stride), (PixelFormat::A8, 3));
+        assert_eq!(mask.data, vec![0, 128, 255]);
+    }
+
+    #[test]
+    fn pixel_formats_convert_channels_and_flatten() {
+        // Opaque red, then half-covered green.
+        let convert = |format: PixelFormat| {
+            let surface =
+                RenderSurface::from_rgba(2, 1, vec![255, 0, 0, 255, 0, 255, 0, 128], false)
+                    .with_pixel_format(format);
+            match surface.into_render_output(RenderFormat::Raw).unwrap() {
+                RenderOutput::Bitmap(bitmap) => {
+                    assert_eq!(bitmap.format, format);
+                    assert_eq!(bitmap.stride, 2 * format.bytes_per_pixel());
+                    bitmap.data
+                }
+                other => panic!("expected bitmap output, got {other:?}"),
+            }
+        };
+
         assert_eq!(
-            data,
-            vec![0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255, 255,]
+            convert(PixelFormat::Bgra8),
+            vec![0, 0, 255, 255, 0, 255, 0, 128]
+        );
+        assert_eq!(
+            convert(PixelFormat::Rgba8Premultiplied),
+            vec![255, 0, 0, 255, 0, 128, 0, 128]
+        );
+        assert_eq!(
+            convert(PixelFormat::Bgra8Premultiplied),
+            vec![0, 0, 255, 255, 0, 128, 0, 128]
         );
+        assert_eq!(convert(PixelFormat::A8), vec![255, 128]);
+        // Without alpha, translucent pixels are flattened onto white.
+        assert_eq!(convert(PixelFormat::Rgb8), vec![255, 0, 0, 127, 255, 127]);
+        assert_eq!(convert(PixelFormat::Gray8), vec![54, 219]);
+
+        let png = RenderSurface::from_alpha(2, 1, vec![0, 200])
+            .with_pixel_format(PixelFormat::A8)
+            .into_render_output(RenderFormat::Png)
+            .unwrap();
+        let RenderOutput::Png(png) = png else {
+            panic!("expected png output");
+        };
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        assert_eq!(reader.info().color_type, png::ColorType::Grayscale);
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        assert_eq!(&buf[..2], &[0, 200]);
     }
 
     #[test]
@@ -195,7 +851,9 @@ mod tests {
             other => panic!("expected png output, got {other:?}"),
         };
 
-        let decoder = png::Decoder::new(png_bytes.as_slice());
+        // A single color is stored as a palette; expanding restores RGBA
+        let mut decoder = png::Decoder::new(png_bytes.as_slice());
+        decoder.set_transformations(png::Transformations::EXPAND);
         let mut reader = decoder.read_info().unwrap();
         let mut buf = vec![0; reader.output_buffer_size()];
         let info = reader.next_frame(&mut buf).unwrap();
@@ -204,6 +862,415 @@ mod tests {
         assert_eq!(&buf[..4], &[5, 6, 7, 8]);
     }
 
+    /// Decode a PNG, returning its stored color type, expanded pixels and text chunks.
+    fn decode_png(bytes: &[u8]) -> (png::ColorType, Vec<u8>, Vec<(String, String)>) {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        reader.finish().unwrap();
+        let chunks = reader.info();
+        let mut text: Vec<(String, String)> = chunks
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+            .collect();
+        for chunk in &chunks.utf8_text {
+            text.push((chunk.keyword.clone(), chunk.get_text().unwrap()));
+        }
+        assert!(chunks.srgb.is_some());
+        buf.truncate(info.buffer_size());
+        (chunks.color_type, buf, text)
+    }
+
+    fn png_bytes(surface: RenderSurface) -> Vec<u8> {
+        match surface.into_render_output(RenderFormat::Png).unwrap() {
+            RenderOutput::Png(bytes) => bytes,
+            other => panic!("expected png output, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn png_reduces_single_color_renders_losslessly() {
+        // Black text: gray with alpha
+        let black: Vec<u8> = (0..=255).flat_map(|alpha| [0, 0, 0, alpha]).collect();
+        let (color, pixels, _) =
+            decode_png(&png_bytes(RenderSurface::from_rgba(16, 16, black, false)));
+        assert_eq!(color, png::ColorType::GrayscaleAlpha);
+        assert_eq!(
+            pixels,
+            (0..=255).flat_map(|alpha| [0, alpha]).collect::<Vec<u8>>()
+        );
+
+        // Red text: a palette of coverage levels with transparency
+        let red: Vec<u8> = (0..=255).flat_map(|alpha| [255, 0, 0, alpha]).collect();
+        let (color, pixels, _) = decode_png(&png_bytes(RenderSurface::from_rgba(
+            16,
+            16,
+            red.clone(),
+            false,
+        )));
+        assert_eq!(color, png::ColorType::Indexed);
+        assert_eq!(pixels[4..], red[4..]);
+        assert_eq!(pixels[..4], [0, 0, 0, 0]);
+
+        let (color, pixels, _) = decode_png(&png_bytes(
+            RenderSurface::from_rgba(16, 16, red.clone(), false).with_png_options(PngOptions {
+                reduce_colors: false,
+                ..Default::default()
+            }),
+        ));
+        assert_eq!(color, png::ColorType::Rgba);
+        assert_eq!(pixels, red);
+
+        // More than 256 colors stay RGB
+        let ramp: Vec<u8> = (0..300u32)
+            .flat_map(|index| [(index % 256) as u8, (index / 256) as u8, 9, 255])
+            .collect();
+        let (color, pixels, _) = decode_png(&png_bytes(RenderSurface::from_rgba(
+            300,
+            1,
+            ramp.clone(),
+            false,
+        )));
+        assert_eq!(color, png::ColorType::Rgb);
+        assert_eq!(
+            pixels,
+            ramp.chunks_exact(4)
+                .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+                .collect::<Vec<u8>>()
+        );
+    }
+
+    #[test]
+    fn png_records_render_metadata_when_asked() {
+        let mut font = crate::types::Font::new("Noto Sans", 24.0);
+        font.source = FontSource::Path("/fonts/NotoSans.ttf".to_string());
+        font.variations.insert("wght".to_string(), 700.0);
+        font.variations.insert("wdth".to_string(), 87.5);
+        let shaped = ShapingResult {
+            text: "Grüße".to_string(),
+            glyphs: Vec::new(),
+            advance: 0.0,
+            bbox: crate::types::BoundingBox {
+                x: 0.0,
+                y: 0.0,
+                width: 0.0,
+                height: 0.0,
+            },
+            font: Some(font),
+            direction: crate::types::Direction::LeftToRight,
+            lines: Vec::new(),
+        };
+        let surface = || {
+            RenderSurface::from_rgba(1, 1, vec![0, 0, 0, 255], false)
+                .with_metadata(render_metadata("harfbuzz", &shaped))
+        };
+
+        let (_, _, text) = decode_png(&png_bytes(surface()));
+        assert!(text.is_empty());
+
+        let options = PngOptions {
+            metadata: true,
+            compression: PngCompression::Best,
+            filter: PngFilter::Paeth,
+            ..Default::default()
+        };
+        let (_, _, text) = decode_png(&png_bytes(surface().with_png_options(options)));
+        let value = |keyword: &str| {
+            text.iter()
+                .find(|(key, _)| key == keyword)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(value("Title"), Some("Grüße"));
+        assert_eq!(value("o4e:backend"), Some("harfbuzz"));
+        assert_eq!(value("o4e:font-family"), Some("Noto Sans"));
+        assert_eq!(value("o4e:font-path"), Some("/fonts/NotoSans.ttf"));
+        assert_eq!(value("o4e:font-size"), Some("24"));
+        assert_eq!(value("o4e:variations"), Some("wdth=87.5,wght=700"));
+        assert!(value("Software").unwrap().starts_with("o4e "));
+    }
+
+    #[test]
+    fn background_is_painted_under_contents() {
+        let surface = RenderSurface::from_rgba(2, 1, vec![0, 0, 0, 0, 255, 0, 0, 128], false)
+            .with_background([0, 0, 255, 255]);
+        let data = bitmap_data(surface.into_render_output(RenderFormat::Raw).unwrap());
+        assert_eq!(data, vec![0, 0, 255, 255, 128, 0, 127, 255]);
+    }
+
+    #[test]
+    fn render_options_set_the_background_and_output() {
+        let options = RenderOptions {
+            background: "currentColor".to_string(),
+            pixel_format: PixelFormat::Rgb8,
+            ..RenderOptions::default()
+        };
+        let surface = RenderSurface::from_rgba(1, 1, vec![0; 4], false)
+            .with_render_options(&options, (0, 255, 0, 255))
+            .unwrap();
+        let data = bitmap_data(surface.into_render_output(RenderFormat::Raw).unwrap());
+        assert_eq!(data, vec![0, 255, 0]);
+
+        let options = RenderOptions {
+            background: "blurple".to_string(),
+            ..RenderOptions::default()
+        };
+        assert!(RenderSurface::from_rgba(1, 1, vec![0; 4], false)
+            .with_render_options(&options, (0, 0, 0, 255))
+            .is_err());
+    }
+
+    #[test]
+    fn background_image_is_painted_under_contents() {
+        let surface = RenderSurface::from_rgba(2, 1, vec![0, 0, 0, 0, 255, 0, 0, 255], false)
+            .with_background_image(vec![0, 128, 0, 255, 0, 128, 0, 255]);
+        let data = bitmap_data(surface.into_render_output(RenderFormat::Raw).unwrap());
+        assert_eq!(data, vec![0, 128, 0, 255, 255, 0, 0, 255]);
+
+        let mismatched =
+            RenderSurface::from_rgba(2, 1, vec![0; 8], false).with_background_image(vec![0; 4]);
+        assert!(mismatched.into_render_output(RenderFormat::Raw).is_err());
+    }
+
+    #[test]
+    fn effects_grow_the_output() {
+        let surface = RenderSurface::from_rgba(4, 4, vec![255; 64], false).with_effects(vec![
+            Effect::DropShadow {
+                offset_x: 2.0,
+                offset_y: 1.0,
+                blur_radius: 0.0,
+                color: "#000000".to_string(),
+            },
+        ]);
+        assert_eq!(surface.effect_margins(), (0, 0, 2, 1));
+        let output = surface.into_render_output(RenderFormat::Raw).unwrap();
+        let RenderOutput::Bitmap(bitmap) = output else {
+            panic!("expected bitmap output");
+        };
+        assert_eq!((bitmap.width, bitmap.height), (6, 5));
+        let corner = ((4 * 6 + 5) * 4) as usize;
+        assert_eq!(&bitmap.data[corner..corner + 4], &[0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn canvas_places_baseline_and_clips_or_errors() {
+        // A 2x2 opaque block whose baseline is its bottom edge.
+        let anchor = TextAnchor {
+            origin_x: 0.0,
+            baseline_y: 2.0,
+            advance: 2.0,
+            ascent: 2.0,
+            descent: 0.0,
+        };
+        let canvas = Canvas::new(6, 8);
+        let surface = RenderSurface::from_rgba(2, 2, vec![255; 16], false)
+            .with_background([0, 0, 255, 255])
+            .with_canvas(canvas, anchor);
+        assert_eq!(surface.output_size(), (6, 8));
+        let output = surface.into_render_output(RenderFormat::Raw).unwrap();
+        let RenderOutput::Bitmap(bitmap) = output else {
+            panic!("expected bitmap output");
+        };
+        assert_eq!((bitmap.width, bitmap.height), (6, 8));
+        let covered: Vec<(u32, u32)> = (0..48)
+            .filter(|index| bitmap.data[index * 4] == 255)
+            .map(|index| (index as u32 % 6, index as u32 / 6))
+            .collect();
+        // Baseline at 75% of the height: rows 4 and 5.
+        assert_eq!(covered, vec![(0, 4), (1, 4), (0, 5), (1, 5)]);
+        assert!(bitmap.data.chunks_exact(4).all(|px| px[3] == 255));
+
+        let centered = Canvas {
+            horizontal_align: crate::types::HorizontalAlign::Center,
+            vertical_align: crate::types::VerticalAlign::Bottom,
+            ..canvas
+        };
+        let output = RenderSurface::from_rgba(2, 2, vec![255; 16], false)
+            .with_canvas(centered, anchor)
+            .into_render_output(RenderFormat::Raw)
+            .unwrap();
+        let RenderOutput::Bitmap(bitmap) = output else {
+            panic!("expected bitmap output");
+        };
+        let first = bitmap.data.chunks_exact(4).position(|px| px[3] != 0);
+        assert_eq!(first, Some(6 * 6 + 2));
+
+        let tiny = Canvas::new(1, 1);
+        let clipped = RenderSurface::from_rgba(2, 2, vec![255; 16], false)
+            .with_canvas(tiny, anchor)
+            .into_render_output(RenderFormat::Raw);
+        assert!(clipped.is_ok());
+        let strict = Canvas {
+            overflow: CanvasOverflow::Error,
+            ..tiny
+        };
+        let err = RenderSurface::from_rgba(2, 2, vec![255; 16], false)
+            .with_canvas(strict, anchor)
+            .into_render_output(RenderFormat::Raw)
+            .expect_err("overflowing text should fail");
+        assert!(err.to_string().contains("overflows the 1x1 canvas"));
+    }
+
+    /// 19x11 straight RGBA ramp from red to blue with a translucent black band; the odd size
+    /// leaves partial JPEG blocks and padded BMP rows.
+    fn test_card() -> RenderSurface {
+        let data = (0..11u32)
+            .flat_map(|y| {
+                (0..19u32).flat_map(move |x| {
+                    let ramp = (x * 255 / 18) as u8;
+                    match y {
+                        4..=6 => [0, 0, 0, 128],
+                        _ => [255 - ramp, 0, ramp, 255],
+                    }
+                })
+            })
+            .collect();
+        RenderSurface::from_rgba(19, 11, data, false)
+    }
+
+    fn encoded(surface: RenderSurface, format: RenderFormat) -> Vec<u8> {
+        match surface.into_render_output(format).unwrap() {
+            RenderOutput::Webp(data)
+            | RenderOutput::Jpeg(data)
+            | RenderOutput::Pgm(data)
+            | RenderOutput::Pbm(data)
+            | RenderOutput::Bmp(data) => data,
+            other => panic!("expected encoded image, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn jpeg_round_trips_and_needs_a_background() {
+        let err = test_card()
+            .into_render_output(RenderFormat::Jpeg)
+            .expect_err("JPEG without a background should fail");
+        assert!(err.to_string().contains("background"));
+        let err = test_card()
+            .with_background([255; 4])
+            .with_jpeg_quality(0)
+            .into_render_output(RenderFormat::Jpeg)
+            .expect_err("quality 0 should fail");
+        assert!(err.to_string().contains("jpeg_quality"));
+
+        let expected = bitmap_data(
+            test_card()
+                .with_background([255; 4])
+                .with_pixel_format(PixelFormat::Rgb8)
+                .into_render_output(RenderFormat::Raw)
+                .unwrap(),
+        );
+        for (pixel_format, channels) in [(PixelFormat::Rgba8, 3), (PixelFormat::Gray8, 1)] {
+            let jpeg = encoded(
+                test_card()
+                    .with_background([255; 4])
+                    .with_pixel_format(pixel_format)
+                    .with_jpeg_quality(95),
+                RenderFormat::Jpeg,
+            );
+            assert_eq!(&jpeg[..2], &[0xff, 0xd8]);
+            let mut decoder = zune_jpeg::JpegDecoder::new(&jpeg);
+            let pixels = decoder.decode().unwrap();
+            assert_eq!(decoder.dimensions(), Some((19, 11)));
+
+            let reference: Vec<u8> = match channels {
+                3 => expected.clone(),
+                _ => expected
+                    .chunks_exact(3)
+                    .map(|rgb| pixel::luminance([rgb[0], rgb[1], rgb[2], 255]))
+                    .collect(),
+            };
+            // Decoders may expand grayscale to RGB
+            let decoded_channels = pixels.len() / (19 * 11);
+            let error: Vec<u32> = reference
+                .chunks_exact(channels)
+                .zip(pixels.chunks_exact(decoded_channels))
+                .flat_map(|(a, b)| a.iter().zip(b).map(|(a, b)| u32::from(a.abs_diff(*b))))
+                .collect();
+            let mean = error.iter().sum::<u32>() as f32 / error.len() as f32;
+            assert!(mean < 3.0, "{pixel_format:?}: mean error {mean}");
+            assert!(error.iter().all(|&e| e < 48), "{pixel_format:?}: {error:?}");
+        }
+
+        let small = encoded(
+            test_card().with_background([255; 4]).with_jpeg_quality(10),
+            RenderFormat::Jpeg,
+        );
+        let large = encoded(
+            test_card().with_background([255; 4]).with_jpeg_quality(100),
+            RenderFormat::Jpeg,
+        );
+        assert!(small.len() < large.len());
+    }
+
+    #[test]
+    fn webp_is_lossless() {
+        let expected = bitmap_data(test_card().into_render_output(RenderFormat::Raw).unwrap());
+        let webp = encoded(test_card(), RenderFormat::Webp);
+        let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(&webp)).unwrap();
+        assert_eq!(decoder.dimensions(), (19, 11));
+        assert!(decoder.has_alpha());
+        let mut pixels = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut pixels).unwrap();
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn netpbm_stores_coverage_luminance_and_ink() {
+        let mask = || RenderSurface::from_alpha(10, 2, (0..20).map(|v| v * 13).collect());
+        let pgm = encoded(mask().with_pixel_format(PixelFormat::A8), RenderFormat::Pgm);
+        let header = b"P5\n10 2\n255\n";
+        assert_eq!(&pgm[..header.len()], header);
+        assert_eq!(
+            pgm[header.len()..],
+            (0..20).map(|v| v * 13).collect::<Vec<u8>>()
+        );
+
+        // Without A8, black text on transparency flattens onto white paper
+        let pgm = encoded(mask(), RenderFormat::Pgm);
+        assert_eq!(pgm[header.len()..][..2], [255, 242]);
+
+        // Ten pixels need two bytes per row; ink starts at coverage 130 (index 10)
+        let pbm = encoded(mask(), RenderFormat::Pbm);
+        let header = b"P4\n10 2\n";
+        assert_eq!(&pbm[..header.len()], header);
+        assert_eq!(pbm[header.len()..], [0b0000_0000, 0b0000_0000, 0xff, 0xc0]);
+        let pbm = encoded(mask().with_pixel_format(PixelFormat::A8), RenderFormat::Pbm);
+        assert_eq!(pbm[header.len()..], [0b0000_0000, 0b0000_0000, 0xff, 0xc0]);
+    }
+
+    #[test]
+    fn bmp_rows_run_bottom_up_and_pad() {
+        let bmp = encoded(test_card(), RenderFormat::Bmp);
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bmp[offset..offset + 4].try_into().unwrap());
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(u32_at(2) as usize, bmp.len());
+        // 32-bit BGRA behind a V4 header
+        assert_eq!(u32_at(14), 108);
+        assert_eq!(u32_at(10), 14 + 108);
+        assert_eq!(bmp[28], 32);
+        // The first stored row is the bottom one; its first pixel is opaque red
+        assert_eq!(&bmp[122..126], &[0, 0, 255, 255]);
+
+        let rgb = encoded(
+            test_card().with_pixel_format(PixelFormat::Rgb8),
+            RenderFormat::Bmp,
+        );
+        // 19 * 3 = 57 bytes of pixels padded to 60 per row
+        assert_eq!(rgb.len(), 14 + 40 + 60 * 11);
+        assert_eq!(&rgb[54..57], &[0, 0, 255]);
+        assert_eq!(&rgb[54 + 57..54 + 60], &[0, 0, 0]);
+
+        let gray = encoded(
+            test_card().with_pixel_format(PixelFormat::Gray8),
+            RenderFormat::Bmp,
+        );
+        assert_eq!(gray[28], 8);
+        assert_eq!(gray.len(), 14 + 40 + 1024 + 20 * 11);
+    }
+
     #[test]
     fn svg_conversion_returns_error() {
         let surface = RenderSurface::from_rgba(1, 1, vec![0, 0, 0, 0], false);
@@ -2,6 +2,7 @@
 
 //! Core traits that all backends must implement.
 
+use crate::frame::{self, Frame};
 use crate::types::*;
 use crate::Result;
 
@@ -10,12 +11,44 @@ pub trait Backend: Send + Sync {
     /// Segment text into runs for rendering
     fn segment(&self, text: &str, options: &SegmentOptions) -> Result<Vec<TextRun>>;
 
+    /// Segment text into runs that also end at the byte offsets in `boundaries`
+    ///
+    /// Attributed text passes its span edges here, so every run has one set of attributes.
+    /// The default splits the runs of [`segment`](Self::segment) at the boundaries.
+    fn segment_with_boundaries(
+        &self,
+        text: &str,
+        options: &SegmentOptions,
+        boundaries: &[usize],
+    ) -> Result<Vec<TextRun>> {
+        Ok(crate::utils::split_runs(
+            self.segment(text, options)?,
+            boundaries,
+        ))
+    }
+
     /// Shape a text run into glyphs
     fn shape(&self, run: &TextRun, font: &Font) -> Result<ShapingResult>;
 
     /// Render shaped glyphs to output
     fn render(&self, shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput>;
 
+    /// Composite rendered glyphs onto a caller-owned frame
+    ///
+    /// `origin` is where the top-left corner of the bitmap [`render`](Self::render) returns
+    /// for the same options lands in the frame; anything outside the frame is clipped and
+    /// `options.format` is ignored. The default renders that bitmap and composites it;
+    /// backends may draw straight into the frame instead.
+    fn render_into(
+        &self,
+        shaped: &ShapingResult,
+        options: &RenderOptions,
+        frame: &mut Frame<'_>,
+        origin: (i32, i32),
+    ) -> Result<()> {
+        frame::render_via_bitmap(self, shaped, options, frame, origin)
+    }
+
     /// Backend name for identification
     fn name(&self) -> &str;
 
@@ -51,12 +51,22 @@ impl FontSource {
 pub struct Font {
     /// Font family name (display / fallback only)
     pub family: String,
-    /// Font size in pixels
+    /// Font size in points (pixels at the default 72 DPI, see [`crate::units`])
     pub size: f32,
     /// Font weight (100-900)
     pub weight: u16,
     /// Font style (normal, italic, oblique)
     pub style: FontStyle,
+    /// Width (condensed to expanded)
+    #[serde(default)]
+    pub stretch: FontStretch,
+    /// Families tried in order when `family` is not installed
+    #[serde(default)]
+    pub fallbacks: Vec<String>,
+    /// Baseline-to-baseline distance as a multiple of `size`; `None` uses the font's
+    /// ascender, descender and line gap
+    #[serde(default)]
+    pub line_height: Option<f32>,
     /// Variable font axes
     pub variations: HashMap<String, f32>,
     /// OpenType features
@@ -73,6 +83,9 @@ impl Font {
             size,
             weight: 400,
             style: FontStyle::Normal,
+            stretch: FontStretch::Normal,
+            fallbacks: Vec::new(),
+            line_height: None,
             variations: HashMap::new(),
             features: Features::default(),
             source: FontSource::Family(family),
@@ -87,6 +100,9 @@ impl Font {
             size,
             weight: 400,
             style: FontStyle::Normal,
+            stretch: FontStretch::Normal,
+            fallbacks: Vec::new(),
+            line_height: None,
             variations: HashMap::new(),
             features: Features::default(),
             source: FontSource::Path(path),
@@ -101,6 +117,9 @@ impl Font {
             size,
             weight: 400,
             style: FontStyle::Normal,
+            stretch: FontStretch::Normal,
+            fallbacks: Vec::new(),
+            line_height: None,
             variations: HashMap::new(),
             features: Features::default(),
             source: FontSource::Bytes {
@@ -146,6 +165,53 @@ pub enum FontStyle {
     Oblique,
 }
 
+/// Font width, as the CSS `font-stretch` keywords.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FontStretch {
+    UltraCondensed,
+    ExtraCondensed,
+    Condensed,
+    SemiCondensed,
+    #[default]
+    Normal,
+    SemiExpanded,
+    Expanded,
+    ExtraExpanded,
+    UltraExpanded,
+}
+
+impl FontStretch {
+    /// Width as a percentage of normal, the `wdth` axis value.
+    pub fn percentage(self) -> f32 {
+        match self {
+            Self::UltraCondensed => 50.0,
+            Self::ExtraCondensed => 62.5,
+            Self::Condensed => 75.0,
+            Self::SemiCondensed => 87.5,
+            Self::Normal => 100.0,
+            Self::SemiExpanded => 112.5,
+            Self::Expanded => 125.0,
+            Self::ExtraExpanded => 150.0,
+            Self::UltraExpanded => 200.0,
+        }
+    }
+
+    /// The CSS keyword, such as `semi-condensed`.
+    pub fn css_name(self) -> &'static str {
+        match self {
+            Self::UltraCondensed => "ultra-condensed",
+            Self::ExtraCondensed => "extra-condensed",
+            Self::Condensed => "condensed",
+            Self::SemiCondensed => "semi-condensed",
+            Self::Normal => "normal",
+            Self::SemiExpanded => "semi-expanded",
+            Self::Expanded => "expanded",
+            Self::ExtraExpanded => "extra-expanded",
+            Self::UltraExpanded => "ultra-expanded",
+        }
+    }
+}
+
 /// Text run - a contiguous segment of text with uniform properties
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextRun {
@@ -186,6 +252,38 @@ pub struct ShapingResult {
     pub font: Option<Font>,
     /// Direction resolved during shaping
     pub direction: Direction,
+    /// Stacked lines of multi-line text, in order; empty for a single line at y = 0
+    #[serde(default)]
+    pub lines: Vec<ShapedLine>,
+}
+
+impl ShapingResult {
+    /// A result without text or glyphs, set in `font`.
+    pub fn empty(font: &Font) -> Self {
+        Self {
+            text: String::new(),
+            glyphs: Vec::new(),
+            advance: 0.0,
+            bbox: BoundingBox {
+                x: 0.0,
+                y: 0.0,
+                width: 0.0,
+                height: 0.0,
+            },
+            font: Some(font.clone()),
+            direction: Direction::LeftToRight,
+            lines: Vec::new(),
+        }
+    }
+}
+
+/// Where one line of a multi-line [`ShapingResult`] starts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ShapedLine {
+    /// Index of the line's first glyph; the line runs to the next line's first glyph
+    pub first_glyph: usize,
+    /// Baseline offset from the first line's baseline (y up, like [`Glyph::y`])
+    pub baseline: f32,
 }
 
 /// Individual glyph information
@@ -197,7 +295,7 @@ pub struct Glyph {
     pub cluster: u32,
     /// X position
     pub x: f32,
-    /// Y position
+    /// Vertical offset from the baseline (font space, positive is up)
     pub y: f32,
     /// Horizontal advance
     pub advance: f32,
@@ -215,12 +313,24 @@ pub struct BoundingBox {
 /// Render output
 #[derive(Debug, Clone)]
 pub enum RenderOutput {
-    /// Bitmap image data (raw RGBA)
+    /// Bitmap image data in `RenderOptions::pixel_format`
     Bitmap(Bitmap),
     /// SVG string
     Svg(String),
     /// PNG encoded image
     Png(Vec<u8>),
+    /// Lossless WebP encoded image
+    Webp(Vec<u8>),
+    /// Baseline JPEG encoded image
+    Jpeg(Vec<u8>),
+    /// Binary PGM (`P5`) grayscale image
+    Pgm(Vec<u8>),
+    /// Binary PBM (`P4`) 1-bit image
+    Pbm(Vec<u8>),
+    /// BMP encoded image
+    Bmp(Vec<u8>),
+    /// PDF document
+    Pdf(Vec<u8>),
     /// Raw pixel data
     Raw(Vec<u8>),
 }
@@ -232,10 +342,79 @@ pub struct Bitmap {
     pub width: u32,
     /// Height in pixels
     pub height: u32,
-    /// Pixel data (RGBA)
+    /// Bytes between the starts of consecutive rows
+    pub stride: usize,
+    /// Layout of each pixel in `data`
+    pub format: PixelFormat,
+    /// Pixel data, `height` rows of `stride` bytes
     pub data: Vec<u8>,
 }
 
+impl Bitmap {
+    /// Tightly packed bitmap of `format`.
+    pub fn new(width: u32, height: u32, format: PixelFormat, data: Vec<u8>) -> Self {
+        Self {
+            width,
+            height,
+            stride: width as usize * format.bytes_per_pixel(),
+            format,
+            data,
+        }
+    }
+
+    /// Tightly packed straight-alpha RGBA bitmap.
+    pub fn rgba(width: u32, height: u32, data: Vec<u8>) -> Self {
+        Self::new(width, height, PixelFormat::Rgba8, data)
+    }
+
+    /// Bytes of row `y`, without stride padding.
+    pub fn row(&self, y: u32) -> &[u8] {
+        let start = y as usize * self.stride;
+        &self.data[start..start + self.width as usize * self.format.bytes_per_pixel()]
+    }
+}
+
+/// Channel layout and alpha convention of an 8-bit-per-channel pixel buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PixelFormat {
+    /// RGBA, straight alpha
+    #[default]
+    Rgba8,
+    /// BGRA, straight alpha
+    Bgra8,
+    /// RGBA, color premultiplied by alpha
+    Rgba8Premultiplied,
+    /// BGRA, color premultiplied by alpha (the usual video frame layout)
+    Bgra8Premultiplied,
+    /// RGB888 without alpha; rendered output is flattened onto white
+    Rgb8,
+    /// Luminance; rendered output is flattened onto white
+    Gray8,
+    /// Coverage (alpha) only
+    A8,
+}
+
+impl PixelFormat {
+    /// Bytes per pixel.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba8 | Self::Bgra8 | Self::Rgba8Premultiplied | Self::Bgra8Premultiplied => 4,
+            Self::Rgb8 => 3,
+            Self::Gray8 | Self::A8 => 1,
+        }
+    }
+
+    /// Whether the format carries alpha.
+    pub fn has_alpha(self) -> bool {
+        !matches!(self, Self::Rgb8 | Self::Gray8)
+    }
+
+    /// Whether color channels are premultiplied by alpha.
+    pub fn is_premultiplied(self) -> bool {
+        matches!(self, Self::Rgba8Premultiplied | Self::Bgra8Premultiplied)
+    }
+}
+
 /// Options for text segmentation
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SegmentOptions {
@@ -254,7 +433,7 @@ pub struct SegmentOptions {
 pub struct RenderOptions {
     /// Output format
     pub format: RenderFormat,
-    /// Text color (hex or rgb)
+    /// Text color (CSS color syntax, see [`crate::color`])
     pub color: String,
     /// Background color
     pub background: String,
@@ -262,10 +441,66 @@ pub struct RenderOptions {
     pub antialias: AntialiasMode,
     /// Hinting mode
     pub hinting: HintingMode,
-    /// DPI for scaling
+    /// Output resolution: raster output has `dpi / 72` pixels per point, so 144 renders a
+    /// @2x bitmap; SVG output keeps a viewBox in points and scales its width and height
     pub dpi: f32,
-    /// Padding around text
+    /// Padding around text, in points
     pub padding: u32,
+    /// Full SVG options for `RenderFormat::Svg`; derived from the fields above when unset
+    #[serde(default)]
+    pub svg: Option<SvgOptions>,
+    /// Full PDF options for `RenderFormat::Pdf`; derived from the fields above when unset
+    #[serde(default)]
+    pub pdf: Option<PdfOptions>,
+    /// Glyph outline stroke (none when unset)
+    #[serde(default)]
+    pub stroke: Option<Stroke>,
+    /// Fill glyph interiors; disable together with `stroke` for outlined text
+    #[serde(default = "default_fill")]
+    pub fill: bool,
+    /// Raster effect layers, applied in order (bitmap formats only)
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+    /// Underlines, overlines and line-throughs (bitmap and SVG output)
+    #[serde(default)]
+    pub decorations: Vec<Decoration>,
+    /// Text fill paint; overrides `color` when set
+    #[serde(default)]
+    pub paint: Option<Paint>,
+    /// Background paint; overrides `background` when set
+    #[serde(default)]
+    pub background_paint: Option<Paint>,
+    /// Affine transform applied to the laid-out text (bitmap and SVG output); the output is
+    /// sized to fit the transformed text
+    #[serde(default)]
+    pub transform: Option<Transform2D>,
+    /// Lay the glyphs along a path instead of a straight baseline (bitmap and SVG output)
+    #[serde(default)]
+    pub text_path: Option<TextPath>,
+    /// What the output is fitted to when no `canvas` is set (bitmap and SVG output)
+    #[serde(default)]
+    pub sizing: SizingMode,
+    /// Render onto a fixed-size canvas instead of sizing the bitmap to the text
+    #[serde(default)]
+    pub canvas: Option<Canvas>,
+    /// Pixel layout of `RenderFormat::Raw` bitmaps; PNG output keeps the same channels
+    /// (grayscale for `A8` and `Gray8`, RGB for `Rgb8`, straight RGBA otherwise)
+    #[serde(default)]
+    pub pixel_format: PixelFormat,
+    /// JPEG quality for `RenderFormat::Jpeg`, 1 (smallest) to 100 (best)
+    #[serde(default = "default_jpeg_quality")]
+    pub jpeg_quality: u8,
+    /// PNG encoder options for `RenderFormat::Png`; defaults when unset
+    #[serde(default)]
+    pub png: Option<PngOptions>,
+}
+
+fn default_fill() -> bool {
+    true
+}
+
+fn default_jpeg_quality() -> u8 {
+    90
 }
 
 /// Output format for rendering
@@ -275,8 +510,20 @@ pub enum RenderFormat {
     Raw,
     /// PNG encoded image
     Png,
+    /// Lossless WebP encoded image
+    Webp,
+    /// Baseline JPEG at `RenderOptions::jpeg_quality`; needs an opaque background
+    Jpeg,
+    /// Binary PGM (`P5`): coverage for `A8` masks, luminance otherwise
+    Pgm,
+    /// Binary PBM (`P4`): ink thresholded at half coverage
+    Pbm,
+    /// Uncompressed BMP
+    Bmp,
     /// SVG vector graphics
     Svg,
+    /// Single-page PDF with vector glyphs
+    Pdf,
 }
 
 impl Default for RenderOptions {
@@ -289,10 +536,99 @@ impl Default for RenderOptions {
             hinting: HintingMode::Slight,
             dpi: 72.0,
             padding: 10,
+            svg: None,
+            pdf: None,
+            stroke: None,
+            fill: default_fill(),
+            effects: Vec::new(),
+            decorations: Vec::new(),
+            paint: None,
+            background_paint: None,
+            transform: None,
+            text_path: None,
+            sizing: SizingMode::default(),
+            canvas: None,
+            pixel_format: PixelFormat::default(),
+            jpeg_quality: default_jpeg_quality(),
+            png: None,
         }
     }
 }
 
+impl RenderOptions {
+    /// SVG options for this render.
+    ///
+    /// Returns the caller-provided [`SvgOptions`] unchanged when set. Otherwise the text color,
+    /// background, padding, DPI scale, stroke, fill, paints and layout of these options are
+    /// carried over to the defaults.
+    pub fn svg_options(&self) -> SvgOptions {
+        if let Some(svg) = &self.svg {
+            return svg.clone();
+        }
+
+        SvgOptions {
+            foreground_color: Some(self.color.clone()),
+            background: (self.background != "transparent").then(|| self.background.clone()),
+            padding: self.padding as f32,
+            scale: self.dpi / crate::units::POINTS_PER_INCH,
+            stroke: self.stroke.clone(),
+            fill: self.fill,
+            paint: self.paint.clone(),
+            background_paint: self.background_paint.clone(),
+            transform: self.transform,
+            text_path: self.text_path.clone(),
+            sizing: self.sizing,
+            decorations: self.decorations.clone(),
+            ..SvgOptions::default()
+        }
+    }
+
+    /// PDF options for this render.
+    ///
+    /// Returns the caller-provided [`PdfOptions`] unchanged when set. Otherwise the text color,
+    /// background, padding, stroke and fill of these options are carried over to the defaults.
+    /// Solid paints replace the plain colors; other paints fall back to them.
+    pub fn pdf_options(&self) -> PdfOptions {
+        if let Some(pdf) = &self.pdf {
+            return pdf.clone();
+        }
+
+        // PDF output only draws solid paints
+        let solid = |paint: &Option<Paint>| match paint {
+            Some(Paint::Solid(color)) => Some(color.clone()),
+            _ => None,
+        };
+        PdfOptions {
+            foreground_color: solid(&self.paint).unwrap_or_else(|| self.color.clone()),
+            background: solid(&self.background_paint)
+                .or_else(|| (self.background != "transparent").then(|| self.background.clone())),
+            padding: self.padding as f32,
+            stroke: self.stroke.clone().map(|mut stroke| {
+                if let Some(color) = solid(&stroke.paint) {
+                    stroke.color = color;
+                }
+                stroke
+            }),
+            fill: self.fill,
+            ..PdfOptions::default()
+        }
+    }
+
+    /// Paint the text fill is drawn with.
+    pub fn resolved_paint(&self) -> Paint {
+        self.paint
+            .clone()
+            .unwrap_or_else(|| Paint::Solid(self.color.clone()))
+    }
+
+    /// Paint the background is drawn with (none for a transparent background).
+    pub fn resolved_background(&self) -> Option<Paint> {
+        self.background_paint.clone().or_else(|| {
+            (self.background != "transparent").then(|| Paint::Solid(self.background.clone()))
+        })
+    }
+}
+
 /// Antialiasing mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AntialiasMode {
@@ -309,6 +645,435 @@ pub enum HintingMode {
     Full,
 }
 
+/// Raster effect layered around or inside the text
+///
+/// Offsets and radii are in points with y pointing down; blur radii follow CSS (standard
+/// deviation is half the radius).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    /// Offset, blurred copy of the text painted behind it
+    DropShadow {
+        offset_x: f32,
+        offset_y: f32,
+        blur_radius: f32,
+        color: String,
+    },
+    /// Blurred halo painted behind the text
+    OuterGlow { radius: f32, color: String },
+    /// Offset, blurred shadow painted inside the glyphs
+    InnerShadow {
+        offset_x: f32,
+        offset_y: f32,
+        blur_radius: f32,
+        color: String,
+    },
+    /// Gaussian blur of the text itself
+    Blur { radius: f32 },
+}
+
+/// Shape drawn where stroke segments meet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// Shape drawn at the ends of open stroke segments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// Glyph outline stroke
+///
+/// The stroke is painted under the fill, so it never covers the glyph interiors: while `fill`
+/// is on, only the outer half of `width` shows outside each glyph.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stroke {
+    /// Stroke width in points, centered on the outline
+    pub width: f32,
+    /// Stroke color (CSS color; `currentColor` is the text color)
+    pub color: String,
+    /// Join style
+    pub line_join: LineJoin,
+    /// Cap style
+    pub line_cap: LineCap,
+    /// Miter length limit as a multiple of the width (miter joins only)
+    pub miter_limit: f32,
+    /// Stroke paint; overrides `color` when set
+    #[serde(default)]
+    pub paint: Option<Paint>,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            color: "#000000".to_string(),
+            line_join: LineJoin::Miter,
+            line_cap: LineCap::Butt,
+            miter_limit: 4.0,
+            paint: None,
+        }
+    }
+}
+
+impl Stroke {
+    /// How far the stroke can reach outside the outline it is applied to.
+    pub fn outset(&self) -> f32 {
+        let half = self.width.max(0.0) / 2.0;
+        match self.line_join {
+            LineJoin::Miter => half * self.miter_limit.max(1.0),
+            LineJoin::Round | LineJoin::Bevel => half,
+        }
+    }
+
+    /// Paint the stroke is drawn with.
+    pub fn resolved_paint(&self) -> Paint {
+        self.paint
+            .clone()
+            .unwrap_or_else(|| Paint::Solid(self.color.clone()))
+    }
+}
+
+/// How text, strokes and backgrounds are painted
+///
+/// Gradient geometry is relative to the painted box: `[0, 0]` is its top-left corner and
+/// `[1, 1]` its bottom-right, like SVG's `objectBoundingBox` units. Text and stroke paints
+/// use the text box (without padding); background paints use the whole canvas.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Paint {
+    /// Single CSS color
+    Solid(String),
+    /// Color ramp along the line from `start` to `end`
+    LinearGradient {
+        start: [f32; 2],
+        end: [f32; 2],
+        stops: Vec<GradientStop>,
+        #[serde(default)]
+        spread: SpreadMode,
+    },
+    /// Color ramp outwards from `center`; `radius` is relative to the box size, so the
+    /// gradient is elliptical on non-square boxes
+    RadialGradient {
+        center: [f32; 2],
+        radius: f32,
+        stops: Vec<GradientStop>,
+        #[serde(default)]
+        spread: SpreadMode,
+    },
+    /// PNG image tiled from the top-left of the box, at `scale` points per image pixel
+    Pattern { png: Vec<u8>, scale: f32 },
+}
+
+/// Color at a position (0..=1) along a gradient
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: String,
+}
+
+impl GradientStop {
+    pub fn new(offset: f32, color: impl Into<String>) -> Self {
+        Self {
+            offset,
+            color: color.into(),
+        }
+    }
+}
+
+/// How a gradient continues past its first and last stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SpreadMode {
+    /// Extend the end colors
+    #[default]
+    Pad,
+    /// Restart the ramp
+    Repeat,
+    /// Run the ramp back and forth
+    Reflect,
+}
+
+/// 2D affine transform `[a, b, c, d, e, f]` in output space (y down), as in SVG's
+/// `matrix(a b c d e f)`: a point `(x, y)` maps to `(a·x + c·y + e, b·x + d·y + f)`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Transform2D(pub [f32; 6]);
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform2D {
+    pub const IDENTITY: Self = Self([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    pub fn translate(x: f32, y: f32) -> Self {
+        Self([1.0, 0.0, 0.0, 1.0, x, y])
+    }
+
+    pub fn scale(x: f32, y: f32) -> Self {
+        Self([x, 0.0, 0.0, y, 0.0, 0.0])
+    }
+
+    /// Rotation by `degrees`; positive angles turn clockwise on screen since y points down.
+    pub fn rotate(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    /// Skew along x and y by the given angles in degrees, like CSS `skew()`.
+    pub fn skew(x_degrees: f32, y_degrees: f32) -> Self {
+        Self([
+            1.0,
+            y_degrees.to_radians().tan(),
+            x_degrees.to_radians().tan(),
+            1.0,
+            0.0,
+            0.0,
+        ])
+    }
+
+    /// This transform followed by `next`.
+    pub fn then(self, next: Self) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let [na, nb, nc, nd, ne, nf] = next.0;
+        Self([
+            na * a + nc * b,
+            nb * a + nd * b,
+            na * c + nc * d,
+            nb * c + nd * d,
+            na * e + nc * f + ne,
+            nb * e + nd * f + nf,
+        ])
+    }
+
+    /// Map a point.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+}
+
+/// Path the text is laid along
+///
+/// Each glyph is centered on the path at the arc length of its advance midpoint and rotated to
+/// the path tangent, like SVG `<textPath>`. Glyphs that fall past either end are not drawn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextPath {
+    /// SVG path data in output units (y down); the text starts at the first point
+    pub data: String,
+    /// Distance along the path to the start of the text
+    #[serde(default)]
+    pub start_offset: f32,
+}
+
+impl TextPath {
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            start_offset: 0.0,
+        }
+    }
+}
+
+/// Line drawn along the text
+///
+/// Positions and default thicknesses come from the font: the `post` table's underline metrics
+/// for underlines and overlines (which sit on the ascender), and the OS/2 strikeout metrics
+/// for line-throughs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decoration {
+    /// Which line to draw
+    pub line: DecorationLine,
+    /// Line style
+    #[serde(default)]
+    pub style: DecorationStyle,
+    /// Line color (CSS color); the text color when unset
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Line thickness in points; the font's thickness when unset
+    #[serde(default)]
+    pub thickness: Option<f32>,
+    /// Break underlines and overlines where glyph ink crosses them, like CSS
+    /// `text-decoration-skip-ink: auto`; line-throughs are never broken
+    #[serde(default = "default_true")]
+    pub skip_ink: bool,
+}
+
+impl Decoration {
+    /// A solid `line` in the text color with the font's thickness, skipping ink.
+    pub fn new(line: DecorationLine) -> Self {
+        Self {
+            line,
+            style: DecorationStyle::default(),
+            color: None,
+            thickness: None,
+            skip_ink: default_true(),
+        }
+    }
+}
+
+/// Decoration line position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecorationLine {
+    /// Below the baseline, drawn under the text
+    Underline,
+    /// Along the ascender, drawn under the text
+    Overline,
+    /// Through the lowercase letters, drawn over the text
+    LineThrough,
+}
+
+/// Decoration line style
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DecorationStyle {
+    #[default]
+    Solid,
+    /// Two lines, one thickness apart
+    Double,
+    /// Round dots one thickness wide
+    Dotted,
+    /// Dashes three thicknesses long
+    Dashed,
+    /// Sine wave one thickness high
+    Wavy,
+}
+
+/// Fixed-size output canvas for bitmap renders
+///
+/// The text is positioned by its line box (pen advance horizontally, font ascent to descent
+/// vertically) rather than its ink, so every glyph of a font lands on the same baseline.
+/// `RenderOptions::padding` does not apply; effects and the background are clipped to the
+/// canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Canvas {
+    /// Canvas width in points
+    pub width: u32,
+    /// Canvas height in points
+    pub height: u32,
+    /// Baseline position, used with `VerticalAlign::Baseline`
+    #[serde(default)]
+    pub baseline: BaselinePosition,
+    /// Horizontal placement of the pen advance
+    #[serde(default)]
+    pub horizontal_align: HorizontalAlign,
+    /// Vertical placement of the line box
+    #[serde(default)]
+    pub vertical_align: VerticalAlign,
+    /// What to do when glyph ink falls outside the canvas
+    #[serde(default)]
+    pub overflow: CanvasOverflow,
+}
+
+impl Canvas {
+    /// A `width`×`height` canvas with the baseline at 75% of the height, text left-aligned.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            baseline: BaselinePosition::default(),
+            horizontal_align: HorizontalAlign::default(),
+            vertical_align: VerticalAlign::default(),
+            overflow: CanvasOverflow::default(),
+        }
+    }
+
+    /// Baseline y from the top edge for a line `ascent` above and `descent` below its
+    /// baseline, in the canvas's units (points, or device pixels once scaled for output).
+    pub fn baseline_y(&self, ascent: f32, descent: f32) -> f32 {
+        let height = self.height as f32;
+        match self.vertical_align {
+            VerticalAlign::Baseline => match self.baseline {
+                BaselinePosition::Absolute(y) => y,
+                BaselinePosition::Fraction(fraction) => height * fraction,
+            },
+            VerticalAlign::Top => ascent,
+            VerticalAlign::Middle => (height - ascent - descent) / 2.0 + ascent,
+            VerticalAlign::Bottom => height - descent,
+        }
+    }
+
+    /// Pen origin x from the left edge for a line `advance` wide, in the canvas's units.
+    pub fn origin_x(&self, advance: f32) -> f32 {
+        let width = self.width as f32;
+        match self.horizontal_align {
+            HorizontalAlign::Left => 0.0,
+            HorizontalAlign::Center => (width - advance) / 2.0,
+            HorizontalAlign::Right => width - advance,
+        }
+    }
+}
+
+/// Baseline position on a fixed canvas
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BaselinePosition {
+    /// Points from the top edge
+    Absolute(f32),
+    /// Fraction of the canvas height from the top edge
+    Fraction(f32),
+}
+
+impl Default for BaselinePosition {
+    fn default() -> Self {
+        Self::Fraction(0.75)
+    }
+}
+
+/// Area a text-sized output is fitted to, before padding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SizingMode {
+    /// The font's ascender-to-descender line box across the pen advance, grown to any ink
+    /// outside it; heights stay the same for every string at one size
+    #[default]
+    Logical,
+    /// The union of the glyph ink (and stroke) bounds only
+    Ink,
+}
+
+/// Horizontal text alignment on a fixed canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HorizontalAlign {
+    /// Pen origin on the left edge
+    #[default]
+    Left,
+    /// Advance centered
+    Center,
+    /// Advance ending on the right edge
+    Right,
+}
+
+/// Vertical text alignment on a fixed canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VerticalAlign {
+    /// Baseline at `Canvas::baseline`
+    #[default]
+    Baseline,
+    /// Ascent on the top edge
+    Top,
+    /// Line box centered
+    Middle,
+    /// Descent on the bottom edge
+    Bottom,
+}
+
+/// Handling of glyph ink outside a fixed canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CanvasOverflow {
+    /// Cut the ink at the canvas edges
+    #[default]
+    Clip,
+    /// Fail the render
+    Error,
+}
+
 /// SVG rendering options
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SvgOptions {
@@ -322,8 +1087,161 @@ pub struct SvgOptions {
     pub color_fonts: bool,
     /// Palette index to use when rendering color fonts
     pub palette_index: u16,
-    /// Optional color override used for COLR foreground references
+    /// Text fill color, also used for COLR foreground references (SVG default black if unset)
     pub foreground_color: Option<String>,
+    /// Background color painted behind the text (none when unset)
+    #[serde(default)]
+    pub background: Option<String>,
+    /// Extra space added around the glyph bounds in the viewBox
+    #[serde(default)]
+    pub padding: f32,
+    /// Write each distinct glyph outline once into `<defs>` and reference it with `<use>`
+    #[serde(default)]
+    pub deduplicate_glyphs: bool,
+    /// Ratio between output size (`width`/`height`) and viewBox units
+    #[serde(default = "default_svg_scale")]
+    pub scale: f32,
+    /// Expose the source text to assistive technology via `<title>` and `aria-label`
+    #[serde(default)]
+    pub accessible_text: bool,
+    /// Overlay invisible `<text>` positioned per cluster so the text can be selected and copied
+    #[serde(default)]
+    pub selectable_text: bool,
+    /// Glyph outline stroke (none when unset)
+    #[serde(default)]
+    pub stroke: Option<Stroke>,
+    /// Fill glyph interiors
+    #[serde(default = "default_fill")]
+    pub fill: bool,
+    /// Glyph fill paint; overrides `foreground_color` for the glyph fill when set
+    #[serde(default)]
+    pub paint: Option<Paint>,
+    /// Background paint; overrides `background` when set
+    #[serde(default)]
+    pub background_paint: Option<Paint>,
+    /// Affine transform applied to the laid-out text; the viewBox fits the transformed text
+    #[serde(default)]
+    pub transform: Option<Transform2D>,
+    /// Lay the glyphs along a path instead of a straight baseline
+    #[serde(default)]
+    pub text_path: Option<TextPath>,
+    /// What the viewBox is fitted to
+    #[serde(default)]
+    pub sizing: SizingMode,
+    /// Underlines, overlines and line-throughs
+    #[serde(default)]
+    pub decorations: Vec<Decoration>,
+}
+
+/// PDF rendering options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdfOptions {
+    /// Text fill color
+    pub foreground_color: String,
+    /// Background color painted behind the text (none when unset)
+    #[serde(default)]
+    pub background: Option<String>,
+    /// Extra space added around the glyph bounds, in points
+    #[serde(default)]
+    pub padding: f32,
+    /// Embed an invisible text layer (subset font with ToUnicode map) so text can be searched
+    #[serde(default = "default_pdf_embed_text")]
+    pub embed_text: bool,
+    /// Glyph outline stroke (none when unset)
+    #[serde(default)]
+    pub stroke: Option<Stroke>,
+    /// Fill glyph interiors
+    #[serde(default = "default_fill")]
+    pub fill: bool,
+}
+
+fn default_pdf_embed_text() -> bool {
+    true
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            foreground_color: "#000000".to_string(),
+            background: None,
+            padding: 0.0,
+            embed_text: default_pdf_embed_text(),
+            stroke: None,
+            fill: default_fill(),
+        }
+    }
+}
+
+/// PNG encoder options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PngOptions {
+    /// Deflate effort
+    #[serde(default)]
+    pub compression: PngCompression,
+    /// Row filter
+    #[serde(default)]
+    pub filter: PngFilter,
+    /// Store RGBA renders losslessly as grayscale (+alpha) when every pixel is gray, or as an
+    /// indexed palette when they use at most 256 colors (single-color text)
+    #[serde(default = "default_true")]
+    pub reduce_colors: bool,
+    /// Tag the pixels as sRGB with an `sRGB` chunk
+    #[serde(default = "default_true")]
+    pub srgb: bool,
+    /// Record the text, font family/path, size, variations and backend name in `tEXt`
+    /// chunks (`iTXt` for non-ASCII values)
+    #[serde(default)]
+    pub metadata: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self {
+            compression: PngCompression::default(),
+            filter: PngFilter::default(),
+            reduce_colors: default_true(),
+            srgb: default_true(),
+            metadata: false,
+        }
+    }
+}
+
+/// PNG deflate effort
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PngCompression {
+    /// Fastest encoding, larger files
+    Fast,
+    /// Balanced
+    #[default]
+    Default,
+    /// Smallest files, slower encoding
+    Best,
+}
+
+/// PNG row filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PngFilter {
+    /// Pick the best filter per row
+    #[default]
+    Adaptive,
+    /// No filtering
+    None,
+    /// Difference to the pixel on the left
+    Sub,
+    /// Difference to the pixel above
+    Up,
+    /// Difference to the average of left and above
+    Average,
+    /// Paeth predictor
+    Paeth,
+}
+
+fn default_svg_scale() -> f32 {
+    1.0
 }
 
 impl Default for SvgOptions {
@@ -335,6 +1253,20 @@ impl Default for SvgOptions {
             color_fonts: true,
             palette_index: 0,
             foreground_color: None,
+            background: None,
+            padding: 0.0,
+            deduplicate_glyphs: false,
+            scale: default_svg_scale(),
+            accessible_text: false,
+            selectable_text: false,
+            stroke: None,
+            fill: default_fill(),
+            paint: None,
+            background_paint: None,
+            transform: None,
+            text_path: None,
+            sizing: SizingMode::default(),
+            decorations: Vec::new(),
         }
     }
 }
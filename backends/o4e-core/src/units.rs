@@ -0,0 +1,283 @@
+// this_file: backends/o4e-core/src/units.rs
+
+//! Points, pixels and the device scale.
+//!
+//! Font sizes, shaping positions and every length in [`RenderOptions`] are in points
+//! (1/72 inch). Vector output (SVG viewBox, PDF) stays in points. Raster output has
+//! `dpi / 72` pixels per point, so at the default 72 DPI a point is a pixel and at 144 DPI
+//! the same options produce a @2x bitmap. Raster backends convert the shaped run and the
+//! options with [`to_device_pixels`] before drawing.
+
+use std::borrow::Cow;
+
+use kurbo::{Affine, BezPath};
+
+use crate::error::O4eError;
+use crate::types::{
+    BaselinePosition, BoundingBox, Effect, Paint, RenderOptions, ShapingResult, Transform2D,
+};
+use crate::Result;
+
+/// Points per inch; the DPI at which one point is one pixel
+pub const POINTS_PER_INCH: f32 = 72.0;
+
+/// DPI for a device scale factor (2.0 for @2x displays).
+pub fn dpi_for_scale(scale: f32) -> f32 {
+    scale * POINTS_PER_INCH
+}
+
+/// Pixels per point at `dpi`.
+pub fn device_scale(dpi: f32) -> Result<f32> {
+    if !(dpi.is_finite() && dpi > 0.0) {
+        return Err(O4eError::InvalidParameter {
+            name: "dpi".to_string(),
+            value: dpi.to_string(),
+        });
+    }
+    Ok(dpi / POINTS_PER_INCH)
+}
+
+/// The shaped run and options in device pixels for `options.dpi`.
+///
+/// Both are borrowed unchanged at 72 DPI. Otherwise glyph positions, the font size (and with
+/// it glyph cache keys) and the option lengths are scaled, and the returned options have a
+/// DPI of 72 so converting them again is a no-op.
+pub fn to_device_pixels<'a>(
+    shaped: &'a ShapingResult,
+    options: &'a RenderOptions,
+) -> Result<(Cow<'a, ShapingResult>, Cow<'a, RenderOptions>)> {
+    let scale = device_scale(options.dpi)?;
+    if scale == 1.0 {
+        return Ok((Cow::Borrowed(shaped), Cow::Borrowed(options)));
+    }
+    Ok((
+        Cow::Owned(scale_shaped(shaped, scale)),
+        Cow::Owned(scale_options(options, scale)?),
+    ))
+}
+
+/// Copy of `shaped` with every position and the font size multiplied by `scale`.
+pub fn scale_shaped(shaped: &ShapingResult, scale: f32) -> ShapingResult {
+    let mut scaled = shaped.clone();
+    for glyph in &mut scaled.glyphs {
+        glyph.x *= scale;
+        glyph.y *= scale;
+        glyph.advance *= scale;
+    }
+    for line in &mut scaled.lines {
+        line.baseline *= scale;
+    }
+    scaled.advance *= scale;
+    scaled.bbox = BoundingBox {
+        x: shaped.bbox.x * scale,
+        y: shaped.bbox.y * scale,
+        width: shaped.bbox.width * scale,
+        height: shaped.bbox.height * scale,
+    };
+    if let Some(font) = &mut scaled.font {
+        font.size *= scale;
+    }
+    scaled
+}
+
+/// Copy of `options` with every length multiplied by `scale` and the DPI reset to 72.
+///
+/// Box-relative gradient geometry, colors and the vector `svg`/`pdf` options are unchanged.
+pub fn scale_options(options: &RenderOptions, scale: f32) -> Result<RenderOptions> {
+    let mut scaled = options.clone();
+    scaled.dpi = POINTS_PER_INCH;
+    scaled.padding = (options.padding as f32 * scale).round() as u32;
+    if let Some(stroke) = &mut scaled.stroke {
+        stroke.width *= scale;
+        scale_paint(&mut stroke.paint, scale);
+    }
+    scale_paint(&mut scaled.paint, scale);
+    scale_paint(&mut scaled.background_paint, scale);
+    for effect in &mut scaled.effects {
+        match effect {
+            Effect::DropShadow {
+                offset_x,
+                offset_y,
+                blur_radius,
+                ..
+            }
+            | Effect::InnerShadow {
+                offset_x,
+                offset_y,
+                blur_radius,
+                ..
+            } => {
+                *offset_x *= scale;
+                *offset_y *= scale;
+                *blur_radius *= scale;
+            }
+            Effect::OuterGlow { radius, .. } | Effect::Blur { radius } => *radius *= scale,
+        }
+    }
+    for decoration in &mut scaled.decorations {
+        if let Some(thickness) = &mut decoration.thickness {
+            *thickness *= scale;
+        }
+    }
+    // Same linear part; only the translation is a length
+    if let Some(Transform2D(matrix)) = &mut scaled.transform {
+        matrix[4] *= scale;
+        matrix[5] *= scale;
+    }
+    if let Some(path) = &mut scaled.text_path {
+        path.data = scale_path_data(&path.data, scale)?;
+        path.start_offset *= scale;
+    }
+    if let Some(canvas) = &mut scaled.canvas {
+        canvas.width = (canvas.width as f32 * scale).round() as u32;
+        canvas.height = (canvas.height as f32 * scale).round() as u32;
+        if let BaselinePosition::Absolute(y) = &mut canvas.baseline {
+            *y *= scale;
+        }
+    }
+    Ok(scaled)
+}
+
+fn scale_paint(paint: &mut Option<Paint>, scale: f32) {
+    if let Some(Paint::Pattern { scale: pattern, .. }) = paint {
+        *pattern *= scale;
+    }
+}
+
+/// SVG path data with every coordinate multiplied by `scale`.
+///
+/// The output is kurbo's normalized absolute form, with arcs converted to cubic curves.
+pub fn scale_path_data(data: &str, scale: f32) -> Result<String> {
+    let invalid = || O4eError::InvalidParameter {
+        name: "text_path".to_string(),
+        value: data.to_string(),
+    };
+    let path = BezPath::from_svg(data).map_err(|_| invalid())?;
+    // kurbo skips data that does not start with a command
+    if path.elements().is_empty() && !data.trim().is_empty() {
+        return Err(invalid());
+    }
+    Ok((Affine::scale(f64::from(scale)) * path).to_svg())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Canvas, Font, Glyph, Stroke, TextPath};
+
+    fn shaped() -> ShapingResult {
+        let mut shaped = ShapingResult::empty(&Font::new("Test", 12.0));
+        shaped.text = "A".to_string();
+        shaped.glyphs = vec![Glyph {
+            id: 1,
+            cluster: 0,
+            x: 3.0,
+            y: -1.5,
+            advance: 10.0,
+        }];
+        shaped.advance = 10.0;
+        shaped.bbox = BoundingBox {
+            x: 0.0,
+            y: -4.0,
+            width: 10.0,
+            height: 16.0,
+        };
+        shaped
+    }
+
+    #[test]
+    fn default_dpi_borrows_both() {
+        let shaped = shaped();
+        let options = RenderOptions::default();
+        let (scaled_shaped, scaled_options) = to_device_pixels(&shaped, &options).unwrap();
+        assert!(matches!(scaled_shaped, Cow::Borrowed(_)));
+        assert!(matches!(scaled_options, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn double_dpi_scales_positions_sizes_and_lengths() {
+        let shaped = shaped();
+        let options = RenderOptions {
+            dpi: dpi_for_scale(2.0),
+            padding: 5,
+            stroke: Some(Stroke {
+                width: 1.5,
+                ..Stroke::default()
+            }),
+            effects: vec![Effect::DropShadow {
+                offset_x: 2.0,
+                offset_y: -1.0,
+                blur_radius: 3.0,
+                color: "black".to_string(),
+            }],
+            transform: Some(Transform2D([0.0, 1.0, -1.0, 0.0, 4.0, 6.0])),
+            text_path: Some(TextPath {
+                data: "M0,10 L20,10".to_string(),
+                start_offset: 2.5,
+            }),
+            canvas: Some(Canvas {
+                baseline: BaselinePosition::Absolute(30.0),
+                ..Canvas::new(64, 48)
+            }),
+            ..Default::default()
+        };
+        let (scaled_shaped, scaled_options) = to_device_pixels(&shaped, &options).unwrap();
+
+        let glyph = &scaled_shaped.glyphs[0];
+        assert_eq!((glyph.x, glyph.y, glyph.advance), (6.0, -3.0, 20.0));
+        assert_eq!(scaled_shaped.advance, 20.0);
+        assert_eq!(scaled_shaped.bbox.height, 32.0);
+        assert_eq!(scaled_shaped.font.as_ref().unwrap().size, 24.0);
+
+        assert_eq!(scaled_options.dpi, POINTS_PER_INCH);
+        assert_eq!(scaled_options.padding, 10);
+        assert_eq!(scaled_options.stroke.as_ref().unwrap().width, 3.0);
+        assert!(matches!(
+            scaled_options.effects[0],
+            Effect::DropShadow {
+                offset_x: 4.0,
+                offset_y: -2.0,
+                blur_radius: 6.0,
+                ..
+            }
+        ));
+        assert_eq!(
+            scaled_options.transform,
+            Some(Transform2D([0.0, 1.0, -1.0, 0.0, 8.0, 12.0]))
+        );
+        let path = scaled_options.text_path.as_ref().unwrap();
+        assert_eq!(path.data, "M0,20 L40,20");
+        assert_eq!(path.start_offset, 5.0);
+        let canvas = scaled_options.canvas.unwrap();
+        assert_eq!((canvas.width, canvas.height), (128, 96));
+        assert_eq!(canvas.baseline, BaselinePosition::Absolute(60.0));
+
+        // Already in device pixels
+        let (_, again) = to_device_pixels(&scaled_shaped, &scaled_options).unwrap();
+        assert!(matches!(again, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn path_data_scales_every_coordinate() {
+        assert_eq!(
+            scale_path_data("M0 0 C1 2 3 4 5 6 7 8 9 10 11 12", 3.0).unwrap(),
+            "M0,0 C3,6 9,12 15,18 C21,24 27,30 33,36"
+        );
+
+        // Relative commands come out absolute, arcs as curves ending where the arc did
+        let scaled = scale_path_data("m1.5-2e1h3v.5a10 5 30 0110 10z", 2.0).unwrap();
+        assert!(scaled.starts_with("M3,-40 L9,-40 L9,-39 C"));
+        assert!(scaled.ends_with(" 29,-19 Z"));
+
+        assert!(scale_path_data("0 0 L 1 1", 2.0).is_err());
+        assert!(scale_path_data("M 0 0 X 1", 2.0).is_err());
+        assert!(scale_path_data("M 0 0 A 1 1 0 2 0 1 1", 2.0).is_err());
+    }
+
+    #[test]
+    fn dpi_must_be_positive() {
+        assert_eq!(device_scale(144.0).unwrap(), 2.0);
+        assert!(device_scale(0.0).is_err());
+        assert!(device_scale(f32::NAN).is_err());
+    }
+}
@@ -2,7 +2,8 @@
 
 //! Utility functions for the o4e rendering engine.
 
-use crate::types::{BoundingBox, Direction, Font, Glyph, ShapingResult};
+use crate::color::{ColorError, CssColor, Rgba};
+use crate::types::{BoundingBox, Direction, Font, Glyph, ShapingResult, TextRun};
 
 /// Calculate bounding box for a set of glyphs
 pub fn calculate_bbox(glyphs: &[Glyph]) -> BoundingBox {
@@ -40,17 +41,87 @@ pub fn calculate_bbox(glyphs: &[Glyph]) -> BoundingBox {
     }
 }
 
+/// Split `runs` at the byte offsets (into the segmented text) in `boundaries`.
+///
+/// Offsets that are not character boundaries of a run are skipped. The pieces of a
+/// right-to-left run are in visual order, like the runs themselves.
+pub fn split_runs(runs: Vec<TextRun>, boundaries: &[usize]) -> Vec<TextRun> {
+    let mut split = Vec::with_capacity(runs.len());
+    for run in runs {
+        let (start, end) = run.range;
+        let mut cuts: Vec<usize> = boundaries
+            .iter()
+            .copied()
+            .filter(|&offset| offset > start && offset < end)
+            .filter(|&offset| run.text.is_char_boundary(offset - start))
+            .collect();
+        if cuts.is_empty() {
+            split.push(run);
+            continue;
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+        cuts.push(end);
+        let first = split.len();
+        let mut piece_start = start;
+        for cut in cuts {
+            split.push(TextRun {
+                text: run.text[piece_start - start..cut - start].to_string(),
+                range: (piece_start, cut),
+                ..run.clone()
+            });
+            piece_start = cut;
+        }
+        if run.direction == Direction::RightToLeft {
+            split[first..].reverse();
+        }
+    }
+    split
+}
+
 /// Combine multiple shaping results into one
+///
+/// Texts are concatenated in order and each result's clusters are offset by the length of
+/// the text before it, so clusters stay byte offsets into the combined text.
 pub fn combine_shaped_results(results: Vec<ShapingResult>) -> ShapingResult {
+    let mut text = String::new();
+    let mut cluster_offsets = Vec::with_capacity(results.len());
+    for result in &results {
+        cluster_offsets.push(text.len());
+        text.push_str(&result.text);
+    }
+    place_in_order(results, &cluster_offsets, text)
+}
+
+/// Combine the shaping results of `runs`, segmented from one text, into one result.
+///
+/// Glyphs are placed one run after the other in the order the runs come in, which is
+/// visual order for segmented runs. The text stays in logical order: run texts are joined
+/// by their ranges and clusters are byte offsets into the joined text.
+pub fn combine_shaped_runs(runs: &[TextRun], results: Vec<ShapingResult>) -> ShapingResult {
+    let start = runs.iter().map(|run| run.range.0).min().unwrap_or(0);
+    let mut logical: Vec<&TextRun> = runs.iter().collect();
+    logical.sort_by_key(|run| run.range.0);
+    let text = logical.iter().map(|run| run.text.as_str()).collect();
+    let cluster_offsets: Vec<usize> = runs.iter().map(|run| run.range.0 - start).collect();
+    place_in_order(results, &cluster_offsets, text)
+}
+
+/// `results` placed one after the other as `text`, each result's clusters offset by its
+/// entry in `cluster_offsets`.
+fn place_in_order(
+    results: Vec<ShapingResult>,
+    cluster_offsets: &[usize],
+    text: String,
+) -> ShapingResult {
     let mut all_glyphs = Vec::new();
     let mut total_advance = 0.0;
     let mut x_offset = 0.0;
-    let mut combined_text = String::new();
     let mut combined_font: Option<Font> = None;
     let mut combined_direction = Direction::LeftToRight;
     let mut direction_set = false;
 
-    for mut result in results {
+    for (mut result, &cluster_offset) in results.into_iter().zip(cluster_offsets) {
         if !direction_set {
             combined_direction = result.direction;
             direction_set = true;
@@ -60,12 +131,10 @@ pub fn combine_shaped_results(results: Vec<ShapingResult>) -> ShapingResult {
                 combined_font = Some(font);
             }
         }
-        if !result.text.is_empty() {
-            combined_text.push_str(&result.text);
-        }
-        // Offset glyphs by accumulated advance
+        // Offset glyphs by accumulated advance and text
         for glyph in &mut result.glyphs {
             glyph.x += x_offset;
+            glyph.cluster += cluster_offset as u32;
         }
         all_glyphs.extend(result.glyphs);
         total_advance += result.advance;
@@ -75,12 +144,13 @@ pub fn combine_shaped_results(results: Vec<ShapingResult>) -> ShapingResult {
     let bbox = calculate_bbox(&all_glyphs);
 
     ShapingResult {
-        text: combined_text,
+        text,
         glyphs: all_glyphs,
         advance: total_advance,
         bbox,
         font: combined_font,
         direction: combined_direction,
+        lines: Vec::new(),
     }
 }
 
@@ -89,29 +159,16 @@ pub fn quantize_size(size: f32) -> u32 {
     (size * 100.0) as u32
 }
 
-/// Parse hex color string to RGBA
-pub fn parse_color(color: &str) -> Result<(u8, u8, u8, u8), String> {
-    if let Some(hex) = color.strip_prefix('#') {
-        if hex.len() == 6 {
-            let r = u8::from_str_radix(&hex[0..2], 16).map_err(|e| e.to_string())?;
-            let g = u8::from_str_radix(&hex[2..4], 16).map_err(|e| e.to_string())?;
-            let b = u8::from_str_radix(&hex[4..6], 16).map_err(|e| e.to_string())?;
-            return Ok((r, g, b, 255));
-        } else if hex.len() == 8 {
-            let r = u8::from_str_radix(&hex[0..2], 16).map_err(|e| e.to_string())?;
-            let g = u8::from_str_radix(&hex[2..4], 16).map_err(|e| e.to_string())?;
-            let b = u8::from_str_radix(&hex[4..6], 16).map_err(|e| e.to_string())?;
-            let a = u8::from_str_radix(&hex[6..8], 16).map_err(|e| e.to_string())?;
-            return Ok((r, g, b, a));
-        }
-    }
-
-    if color == "transparent" {
-        return Ok((0, 0, 0, 0));
-    }
+/// Parse a CSS color to straight-alpha RGBA; `currentColor` resolves to opaque black.
+///
+/// See [`CssColor`] for the accepted syntax.
+pub fn parse_color(color: &str) -> Result<Rgba, ColorError> {
+    parse_color_with_current(color, (0, 0, 0, 255))
+}
 
-    // Default to black
-    Ok((0, 0, 0, 255))
+/// Parse a CSS color to straight-alpha RGBA, resolving `currentColor` to `current`.
+pub fn parse_color_with_current(color: &str, current: Rgba) -> Result<Rgba, ColorError> {
+    CssColor::parse(color).map(|parsed| parsed.resolve(current))
 }
 
 /// System font directories for different platforms
@@ -179,6 +236,12 @@ mod tests {
         assert_eq!(parse_color("#FF0000").unwrap(), (255, 0, 0, 255));
         assert_eq!(parse_color("#00FF00FF").unwrap(), (0, 255, 0, 255));
         assert_eq!(parse_color("transparent").unwrap(), (0, 0, 0, 0));
+        assert_eq!(parse_color("currentColor").unwrap(), (0, 0, 0, 255));
+        assert_eq!(
+            parse_color_with_current("currentcolor", (1, 2, 3, 4)).unwrap(),
+            (1, 2, 3, 4)
+        );
+        assert!(parse_color("not-a-color").is_err());
     }
 
     #[test]
@@ -186,4 +249,92 @@ mod tests {
         assert_eq!(quantize_size(12.5), 1250);
         assert_eq!(quantize_size(24.0), 2400);
     }
+
+    #[test]
+    fn test_split_runs() {
+        let run = TextRun {
+            text: "héllo".to_string(),
+            range: (4, 10),
+            script: "Latin".to_string(),
+            language: "en".to_string(),
+            direction: Direction::LeftToRight,
+            font: None,
+        };
+        // 6 falls inside "é" and 4 is the run start; both are skipped
+        let runs = split_runs(vec![run], &[8, 4, 6, 8]);
+        let pieces: Vec<(&str, (usize, usize))> = runs
+            .iter()
+            .map(|run| (run.text.as_str(), run.range))
+            .collect();
+        assert_eq!(pieces, vec![("hél", (4, 8)), ("lo", (8, 10))]);
+
+        // Right-to-left pieces come out in visual order
+        let run = TextRun {
+            text: "אב גד".to_string(),
+            range: (0, 9),
+            script: "Hebrew".to_string(),
+            language: "he".to_string(),
+            direction: Direction::RightToLeft,
+            font: None,
+        };
+        let ranges: Vec<(usize, usize)> = split_runs(vec![run], &[5])
+            .iter()
+            .map(|run| run.range)
+            .collect();
+        assert_eq!(ranges, vec![(5, 9), (0, 5)]);
+    }
+
+    #[test]
+    fn test_combine_shaped_runs_keeps_rtl_text_logical() {
+        // "שלום עולם" split at the word, runs in visual order: the last word first
+        let text = "שלום עולם";
+        let runs: Vec<TextRun> = [(9, 17), (0, 9)]
+            .into_iter()
+            .map(|(start, end)| TextRun {
+                text: text[start..end].to_string(),
+                range: (start, end),
+                script: "Hebrew".to_string(),
+                language: "he".to_string(),
+                direction: Direction::RightToLeft,
+                font: None,
+            })
+            .collect();
+        // One-unit glyphs, right to left within each run
+        let results: Vec<ShapingResult> = runs
+            .iter()
+            .map(|run| {
+                let clusters: Vec<usize> = run.text.char_indices().map(|(i, _)| i).collect();
+                let glyphs: Vec<Glyph> = clusters
+                    .iter()
+                    .rev()
+                    .enumerate()
+                    .map(|(index, &cluster)| Glyph {
+                        id: 1,
+                        cluster: cluster as u32,
+                        x: index as f32,
+                        y: 0.0,
+                        advance: 1.0,
+                    })
+                    .collect();
+                ShapingResult {
+                    text: run.text.clone(),
+                    advance: glyphs.len() as f32,
+                    bbox: calculate_bbox(&glyphs),
+                    glyphs,
+                    font: None,
+                    direction: run.direction,
+                    lines: Vec::new(),
+                }
+            })
+            .collect();
+
+        let combined = combine_shaped_runs(&runs, results);
+        assert_eq!(combined.text, text);
+        assert_eq!(combined.advance, 9.0);
+        // Left to right on screen: "עולם" reversed, then "שלום " reversed
+        let clusters: Vec<u32> = combined.glyphs.iter().map(|glyph| glyph.cluster).collect();
+        assert_eq!(clusters, vec![15, 13, 11, 9, 8, 6, 4, 2, 0]);
+        let xs: Vec<f32> = combined.glyphs.iter().map(|glyph| glyph.x).collect();
+        assert_eq!(xs, (0..9).map(|x| x as f32).collect::<Vec<_>>());
+    }
 }
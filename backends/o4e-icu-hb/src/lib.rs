@@ -3,17 +3,19 @@
 //! ICU+HarfBuzz backend for cross-platform text rendering.
 
 use harfbuzz_rs::{Face as HbFace, Font as HbFont, Language, Owned, Tag, UnicodeBuffer};
-use kurbo::{BezPath, PathEl};
+use kurbo::{Affine, BezPath, PathEl, Rect as KurboRect, Shape};
 use lru::LruCache;
 use o4e_core::{
-    cache::{FontKey, GlyphKey, RenderedGlyph},
-    types::{Direction, FontSource, RenderFormat},
-    utils::{calculate_bbox, quantize_size},
-    Backend, Bitmap, Font, FontCache, Glyph, O4eError, RenderOptions, RenderOutput, RenderSurface,
-    Result, SegmentOptions, ShapingResult, TextRun,
+    cache::{FontKey, GlyphKey, RenderedGlyph, StrokeKey},
+    color::Rgba,
+    render_metadata,
+    types::{DecorationLine, Direction, FontSource, RenderFormat, SizingMode, Stroke},
+    utils::{calculate_bbox, parse_color_with_current, quantize_size},
+    Backend, Font, FontCache, Frame, Glyph, O4eError, RenderOptions, RenderOutput, RenderSurface,
+    Result, SegmentOptions, ShapingResult, TextAnchor, TextRun, Transform2D,
 };
 use o4e_fontdb::{script_fallbacks, FontDatabase, FontHandle};
-use o4e_render::outlines::glyph_bez_path as recorded_glyph_path;
+use o4e_render::outlines::{glyph_bez_path as recorded_glyph_path, glyph_stroke_path};
 use o4e_unicode::TextSegmenter;
 use parking_lot::RwLock;
 use std::collections::HashMap;
@@ -22,7 +24,9 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use tiny_skia::{
-    Color, FillRule, Paint, Path as SkiaPath, PathBuilder, Pixmap, PixmapPaint, PixmapRef,
+    Color, FillRule, FilterQuality, GradientStop as SkiaGradientStop, LinearGradient, Mask,
+    MaskType, Paint, Path as SkiaPath, PathBuilder, Pattern, Pixmap, PixmapPaint, PixmapRef,
+    Point as SkiaPoint, RadialGradient, Rect as SkiaRect, Shader, SpreadMode as SkiaSpreadMode,
     Transform,
 };
 use ttf_parser::{Face as TtfFace, GlyphId};
@@ -219,8 +223,9 @@ impl HarfBuzzBackend {
         glyph: &Glyph,
         scale: f32,
         antialias: bool,
+        stroke: Option<&Stroke>,
     ) -> Option<RenderedGlyph> {
-        let path = match glyph_path(ttf_face, glyph, scale) {
+        let path = match glyph_path(ttf_face, glyph, scale, stroke) {
             Some(path) => path,
             None => return Some(blank_rendered_glyph()),
         };
@@ -255,14 +260,31 @@ impl HarfBuzzBackend {
         })
     }
 
+    /// Coverage mask for `key`, rasterized and cached on a miss.
+    fn cached_glyph(
+        &self,
+        key: GlyphKey,
+        ttf_face: &TtfFace<'static>,
+        glyph: &Glyph,
+        scale: f32,
+        antialias: bool,
+        stroke: Option<&Stroke>,
+    ) -> Option<Arc<RenderedGlyph>> {
+        if let Some(entry) = self.cache.get_glyph(&key) {
+            return Some(entry);
+        }
+        let rendered = self.rasterize_glyph(ttf_face, glyph, scale, antialias, stroke)?;
+        Some(self.cache.cache_glyph(key, rendered))
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn draw_cached_glyph(
         &self,
         target: &mut Pixmap,
         glyph: &Glyph,
         cached: &RenderedGlyph,
+        origin_x: f32,
         baseline_y: f32,
-        padding: f32,
         scratch: &mut Vec<u8>,
         base_r: u16,
         base_g: u16,
@@ -294,13 +316,16 @@ impl HarfBuzzBackend {
             return;
         };
 
-        let dest_x = glyph.x + padding + cached.left;
-        let dest_y = baseline_y + cached.top;
+        // Glyph offsets are y-up while the pixmap is y-down.
+        let dest_x = origin_x + glyph.x + cached.left;
+        let dest_y = baseline_y - glyph.y + cached.top;
         let base_x = dest_x.floor() as i32;
         let base_y = dest_y.floor() as i32;
         let frac_x = dest_x - base_x as f32;
         let frac_y = dest_y - base_y as f32;
 
+        // Nearest-neighbour sampling: the fractional translation snaps the mask to the
+        // nearest whole pixel, which `render_into` relies on to match
         let paint = PixmapPaint::default();
         target.draw_pixmap(
             base_x,
@@ -335,6 +360,16 @@ impl Backend for HarfBuzzBackend {
         self.segmenter.segment(text, options)
     }
 
+    fn segment_with_boundaries(
+        &self,
+        text: &str,
+        options: &SegmentOptions,
+        boundaries: &[usize],
+    ) -> Result<Vec<TextRun>> {
+        self.segmenter
+            .segment_with_boundaries(text, options, boundaries)
+    }
+
     fn shape(&self, run: &TextRun, font: &Font) -> Result<ShapingResult> {
         let resolved_font = self.resolve_run_font(run, font);
         let hb_entry = self.get_or_create_hb_font(&resolved_font)?;
@@ -360,7 +395,8 @@ impl Backend for HarfBuzzBackend {
         // Extract glyph information
         let mut glyphs = Vec::new();
         let mut x_pos = 0.0;
-        let scale = font.size / hb_font.face().upem() as f32;
+        // The HarfBuzz font is scaled to the font size in 26.6 fixed point
+        let scale = 1.0 / 64.0;
 
         let positions = output.get_glyph_positions();
         let infos = output.get_glyph_infos();
@@ -385,18 +421,34 @@ impl Backend for HarfBuzzBackend {
             bbox,
             font: Some(resolved_font),
             direction: run.direction,
+            lines: Vec::new(),
         })
     }
 
     fn render(&self, shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput> {
-        // Diagnostics removed for simplicity
-        // Check if we have glyphs to render
+        // Vector output never needs the raster pass
+        if options.format == RenderFormat::Svg {
+            let svg_options = options.svg_options();
+            let renderer = o4e_render::SvgRenderer::new(&svg_options);
+            return Ok(RenderOutput::Svg(renderer.render(shaped, &svg_options)?));
+        }
+        if options.format == RenderFormat::Pdf {
+            let renderer = o4e_render::PdfRenderer::new();
+            return Ok(RenderOutput::Pdf(
+                renderer.render(shaped, &options.pdf_options())?,
+            ));
+        }
+
+        // Rasterize in device pixels; the metadata keeps the requested point size
+        let metadata = render_metadata(self.name(), shaped);
+        let (shaped, options) = o4e_core::units::to_device_pixels(shaped, options)?;
+        let (shaped, options) = (shaped.as_ref(), options.as_ref());
+
+        // Text without glyphs still gets the requested canvas, background and format
         if shaped.glyphs.is_empty() {
-            return Ok(RenderOutput::Bitmap(Bitmap {
-                width: 1,
-                height: 1,
-                data: vec![0, 0, 0, 0],
-            }));
+            let text_color = o4e_core::utils::parse_color(&options.color)?;
+            let surface = RenderSurface::empty(options, text_color)?.with_metadata(metadata);
+            return finish_surface(surface, options, text_color);
         }
 
         // Get the font from ShapingResult
@@ -409,86 +461,258 @@ impl Backend for HarfBuzzBackend {
         let face_entry = self.get_or_create_ttf_face(font)?;
         let ttf_face = face_entry.face();
 
-        // Calculate image dimensions
-        let padding = options.padding as f32;
-        let width = (shaped.bbox.width + padding * 2.0).ceil() as u32;
-        let height = (shaped.bbox.height + padding * 2.0).ceil() as u32;
-
-        // Create pixmap
-        let mut pixmap = Pixmap::new(width, height)
-            .ok_or_else(|| O4eError::render("Failed to create pixmap"))?;
-
-        // Parse colors
-        let (text_r, text_g, text_b, text_a) =
-            o4e_core::utils::parse_color(&options.color).map_err(O4eError::render)?;
-
-        // Fill background if not transparent
-        if options.background != "transparent" {
-            let (bg_r, bg_g, bg_b, bg_a) =
-                o4e_core::utils::parse_color(&options.background).map_err(O4eError::render)?;
-            pixmap.fill(Color::from_rgba8(bg_r, bg_g, bg_b, bg_a));
-        }
+        // Other colors may refer to the text color through `currentColor`
+        let text_color = o4e_core::utils::parse_color(&options.color)?;
 
         // Calculate scale factor
         let units_per_em = ttf_face.units_per_em();
         let scale = font.size / units_per_em as f32;
 
-        // Calculate baseline position
+        // Transformed and path-following text is drawn from outlines, glyph by glyph
+        let outset = options.stroke.as_ref().map_or(0.0, Stroke::outset);
+        let placements = o4e_render::glyph_placements(
+            shaped,
+            options.transform.as_ref(),
+            options.text_path.as_ref(),
+        )?;
+
+        let decorations = o4e_render::decoration_paths(
+            ttf_face,
+            scale,
+            shaped,
+            &options.decorations,
+            placements.as_deref(),
+        );
+
+        // Fit the canvas to the ink, line box and decorations; strokes reach past the glyph
+        // outlines
+        let padding = options.padding as f32 + outset.ceil();
+        let content = decorations
+            .iter()
+            .filter(|path| !path.elements().is_empty())
+            .fold(
+                content_bounds(
+                    ttf_face,
+                    shaped,
+                    placements.as_deref(),
+                    scale,
+                    outset,
+                    options.sizing,
+                ),
+                |content, path| content.union(path.bounding_box()),
+            );
+        let (content_width, content_height) = (content.width() as f32, content.height() as f32);
+        let width = (content_width + padding * 2.0).ceil().max(1.0) as u32;
+        let height = (content_height + padding * 2.0).ceil().max(1.0) as u32;
+        let origin = Affine::translate((padding as f64 - content.x0, padding as f64 - content.y0));
+
+        // Create pixmap
+        let mut pixmap = Pixmap::new(width, height)
+            .ok_or_else(|| O4eError::render("Failed to create pixmap"))?;
+
+        // Pen origin of the straight layout
+        let origin_x = (padding as f64 - content.x0) as f32;
+        let baseline_y = (padding as f64 - content.y0) as f32;
         let ascender = ttf_face.ascender() as f32 * scale;
-        let baseline_y = padding + ascender;
 
         let font_key = face_entry.font_key();
         let glyph_size = quantize_size(font.size);
+        let antialias = options.antialias != o4e_core::types::AntialiasMode::None;
         let mut scratch_rgba = Vec::new();
-        let base_r = (u16::from(text_r) * u16::from(text_a) + 127) / 255;
-        let base_g = (u16::from(text_g) * u16::from(text_a) + 127) / 255;
-        let base_b = (u16::from(text_b) * u16::from(text_a) + 127) / 255;
 
-        // Render each glyph using the shared glyph cache
-        for glyph in &shaped.glyphs {
-            let glyph_key = GlyphKey {
-                font_key: font_key.clone(),
-                glyph_id: glyph.id,
-                size: glyph_size,
+        // Render each glyph layer using the shared glyph cache
+        let mut draw_layer =
+            |pixmap: &mut Pixmap, stroke: Option<&Stroke>, color: (u8, u8, u8, u8)| {
+                if let Some(placements) = &placements {
+                    let layout = PlacedLayout {
+                        placements,
+                        origin,
+                        scale,
+                        antialias,
+                    };
+                    draw_placed_glyphs(pixmap, ttf_face, shaped, &layout, stroke, color);
+                    return;
+                }
+
+                let (r, g, b, a) = color;
+                let base_r = (u16::from(r) * u16::from(a) + 127) / 255;
+                let base_g = (u16::from(g) * u16::from(a) + 127) / 255;
+                let base_b = (u16::from(b) * u16::from(a) + 127) / 255;
+
+                for glyph in &shaped.glyphs {
+                    let glyph_key = GlyphKey {
+                        font_key: font_key.clone(),
+                        glyph_id: glyph.id,
+                        size: glyph_size,
+                        stroke: stroke.map(StrokeKey::from),
+                    };
+
+                    let Some(cached) =
+                        self.cached_glyph(glyph_key, ttf_face, glyph, scale, antialias, stroke)
+                    else {
+                        continue;
+                    };
+
+                    self.draw_cached_glyph(
+                        pixmap,
+                        glyph,
+                        cached.as_ref(),
+                        origin_x,
+                        baseline_y,
+                        &mut scratch_rgba,
+                        base_r,
+                        base_g,
+                        base_b,
+                        a,
+                    );
+                }
             };
 
-            let cached = if let Some(entry) = self.cache.get_glyph(&glyph_key) {
-                entry
-            } else {
-                match self.rasterize_glyph(
-                    ttf_face,
-                    glyph,
-                    scale,
-                    options.antialias != o4e_core::types::AntialiasMode::None,
-                ) {
-                    Some(rendered) => self.cache.cache_glyph(glyph_key, rendered),
-                    None => continue,
+        // Solid paints tint the glyph masks directly; other paints fill the canvas through a
+        // white coverage layer used as a mask.
+        let text_box = SkiaRect::from_xywh(
+            padding,
+            padding,
+            content_width.max(1.0),
+            content_height.max(1.0),
+        )
+        .ok_or_else(|| O4eError::render("Invalid text bounds"))?;
+        let mut paint_layer =
+            |pixmap: &mut Pixmap, stroke: Option<&Stroke>, paint: &o4e_core::Paint| -> Result<()> {
+                if let o4e_core::Paint::Solid(color) = paint {
+                    let color = parse_color_with_current(color, text_color)?;
+                    draw_layer(pixmap, stroke, color);
+                    return Ok(());
                 }
+
+                let mut coverage = Pixmap::new(width, height)
+                    .ok_or_else(|| O4eError::render("Failed to create pixmap"))?;
+                draw_layer(&mut coverage, stroke, (255, 255, 255, 255));
+                let mask = Mask::from_pixmap(coverage.as_ref(), MaskType::Alpha);
+                fill_with_paint(pixmap, paint, text_color, text_box, Some(&mask))
             };
 
-            self.draw_cached_glyph(
-                &mut pixmap,
-                glyph,
-                cached.as_ref(),
-                baseline_y,
-                padding,
-                &mut scratch_rgba,
-                base_r,
-                base_g,
-                base_b,
-                text_a,
+        // Underlines and overlines go under the glyphs, line-throughs over them
+        let draw_decorations = |pixmap: &mut Pixmap, over: bool| -> Result<()> {
+            let [sx, ky, kx, sy, tx, ty] = origin.as_coeffs().map(|c| c as f32);
+            let transform = Transform::from_row(sx, ky, kx, sy, tx, ty);
+            for (decoration, path) in options.decorations.iter().zip(&decorations) {
+                if (decoration.line == DecorationLine::LineThrough) != over {
+                    continue;
+                }
+                let Some(path) = bez_path_to_skia(path) else {
+                    continue;
+                };
+                let color = decoration.color.as_deref().unwrap_or(&options.color);
+                let (r, g, b, a) = parse_color_with_current(color, text_color)?;
+                let mut paint = Paint::default();
+                paint.set_color(Color::from_rgba8(r, g, b, a));
+                paint.anti_alias = antialias;
+                pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+            }
+            Ok(())
+        };
+
+        // Stroke under the fill, see `Stroke`
+        if let Some(stroke) = options.stroke.as_ref().filter(|s| s.width > 0.0) {
+            paint_layer(&mut pixmap, Some(stroke), &stroke.resolved_paint())?;
+        }
+        draw_decorations(&mut pixmap, false)?;
+        if options.fill {
+            paint_layer(&mut pixmap, None, &options.resolved_paint())?;
+        }
+        draw_decorations(&mut pixmap, true)?;
+
+        let mut surface = RenderSurface::from_rgba(width, height, pixmap.take(), true)
+            .with_render_options(options, text_color)?
+            .with_metadata(metadata);
+        if let Some(canvas) = options.canvas {
+            // The output coordinate origin is the pen origin unless a transform moves it
+            surface = surface.with_canvas(
+                canvas,
+                TextAnchor {
+                    origin_x,
+                    baseline_y,
+                    advance: shaped.advance,
+                    ascent: ascender,
+                    descent: -(ttf_face.descender() as f32) * scale,
+                },
             );
         }
+        finish_surface(surface, options, text_color)
+    }
 
-        if options.format == RenderFormat::Svg {
-            let svg_options = o4e_core::types::SvgOptions::default();
-            let renderer = o4e_render::SvgRenderer::new(&svg_options);
-            let svg = renderer.render(shaped, &svg_options);
-            return Ok(RenderOutput::Svg(svg));
+    fn render_into(
+        &self,
+        shaped: &ShapingResult,
+        options: &RenderOptions,
+        frame: &mut Frame<'_>,
+        origin: (i32, i32),
+    ) -> Result<()> {
+        let (shaped, options) = o4e_core::units::to_device_pixels(shaped, options)?;
+        let (shaped, options) = (shaped.as_ref(), options.as_ref());
+
+        // Plain solid text is blended straight from the cached glyph masks; anything that
+        // needs layers or a canvas goes through a bitmap
+        let plain = options.fill
+            && options
+                .stroke
+                .as_ref()
+                .is_none_or(|stroke| stroke.width <= 0.0)
+            && options.effects.is_empty()
+            && options.decorations.is_empty()
+            && options.resolved_background().is_none()
+            && options
+                .transform
+                .as_ref()
+                .is_none_or(Transform2D::is_identity)
+            && options.text_path.is_none()
+            && options.canvas.is_none();
+        let color = match options.resolved_paint() {
+            o4e_core::Paint::Solid(color) if plain => color,
+            _ => return o4e_core::frame::render_via_bitmap(self, shaped, options, frame, origin),
+        };
+        if shaped.glyphs.is_empty() {
+            return Ok(());
         }
 
-        let surface = RenderSurface::from_rgba(width, height, pixmap.take(), true);
-        surface.into_render_output(options.format)
+        let font = shaped
+            .font
+            .as_ref()
+            .ok_or_else(|| O4eError::render("Font information missing from shaped result"))?;
+        let face_entry = self.get_or_create_ttf_face(font)?;
+        let ttf_face = face_entry.face();
+        let text_color = o4e_core::utils::parse_color(&options.color)?;
+        let color = parse_color_with_current(&color, text_color)?;
+
+        // Same placement as `render`, offset so its top-left corner sits at `origin`
+        let scale = font.size / ttf_face.units_per_em() as f32;
+        let padding = f64::from(options.padding);
+        let content = content_bounds(ttf_face, shaped, None, scale, 0.0, options.sizing);
+        let left = (f64::from(origin.0) + padding - content.x0) as f32;
+        let baseline_y = (f64::from(origin.1) + padding - content.y0) as f32;
+        let font_key = face_entry.font_key();
+        let glyph_size = quantize_size(font.size);
+        let antialias = options.antialias != o4e_core::types::AntialiasMode::None;
+
+        for glyph in &shaped.glyphs {
+            let glyph_key = GlyphKey {
+                font_key: font_key.clone(),
+                glyph_id: glyph.id,
+                size: glyph_size,
+                stroke: None,
+            };
+            let Some(cached) =
+                self.cached_glyph(glyph_key, ttf_face, glyph, scale, antialias, None)
+            else {
+                continue;
+            };
+            // Masks land on the nearest whole pixel, as `draw_cached_glyph` places them
+            let x = (left + glyph.x + cached.left).round() as i32;
+            let y = (baseline_y - glyph.y + cached.top).round() as i32;
+            frame.fill_mask(x, y, &cached.bitmap, cached.width, color);
+        }
+        Ok(())
     }
 
     fn name(&self) -> &str {
@@ -509,12 +733,110 @@ impl Default for HarfBuzzBackend {
     }
 }
 
-fn glyph_path(ttf_face: &TtfFace<'static>, glyph: &Glyph, scale: f32) -> Option<SkiaPath> {
-    let gid = u16::try_from(glyph.id).ok()?;
-    let outline = recorded_glyph_path(ttf_face, GlyphId(gid), scale)?;
+/// Fillable glyph path: the outline itself, or the area covered by `stroke` around it.
+fn glyph_path(
+    ttf_face: &TtfFace<'static>,
+    glyph: &Glyph,
+    scale: f32,
+    stroke: Option<&Stroke>,
+) -> Option<SkiaPath> {
+    let gid = GlyphId(u16::try_from(glyph.id).ok()?);
+    let outline = match stroke {
+        Some(stroke) => glyph_stroke_path(ttf_face, gid, scale, stroke)?,
+        None => recorded_glyph_path(ttf_face, gid, scale)?,
+    };
     bez_path_to_skia(&outline)
 }
 
+/// Glyph placements of a transformed or path-following render, mapped onto the canvas.
+struct PlacedLayout<'a> {
+    placements: &'a [Option<Affine>],
+    /// Maps placed output space onto the padded canvas.
+    origin: Affine,
+    scale: f32,
+    antialias: bool,
+}
+
+/// Output-space area (y down, pen origin at zero) a text-sized canvas is fitted to: the glyph
+/// ink, grown by `outset` for strokes, and for `SizingMode::Logical` the line box from
+/// ascender to descender (per glyph cell when `placements` are set).
+fn content_bounds(
+    ttf_face: &TtfFace<'static>,
+    shaped: &ShapingResult,
+    placements: Option<&[Option<Affine>]>,
+    scale: f32,
+    outset: f32,
+    sizing: SizingMode,
+) -> KurboRect {
+    let scale_f64 = f64::from(scale);
+    let top = -f64::from(ttf_face.ascender()) * scale_f64;
+    let bottom = -f64::from(ttf_face.descender()) * scale_f64;
+    let outset = f64::from(outset);
+    let line = match (sizing, placements) {
+        (SizingMode::Ink, _) => None,
+        (SizingMode::Logical, Some(placements)) => {
+            o4e_render::layout::placed_line_bounds(shaped, placements, top, bottom)
+        }
+        // Each cell sits on its glyph's line, so stacked paragraph lines are all covered
+        (SizingMode::Logical, None) => shaped
+            .glyphs
+            .iter()
+            .zip(o4e_render::layout::line_baselines(shaped))
+            .map(|(glyph, baseline)| {
+                let (x, y) = (f64::from(glyph.x), -f64::from(baseline));
+                KurboRect::new(x, top + y, x + f64::from(glyph.advance), bottom + y)
+            })
+            .reduce(|a, b| a.union(b)),
+    };
+
+    shaped
+        .glyphs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, glyph)| {
+            let placement = match placements {
+                Some(placements) => placements[index]?,
+                // Glyph offsets are y-up while the output is y-down
+                None => Affine::translate((f64::from(glyph.x), -f64::from(glyph.y))),
+            };
+            let gid = GlyphId(u16::try_from(glyph.id).ok()?);
+            let ink = recorded_glyph_path(ttf_face, gid, scale)?
+                .bounding_box()
+                .inflate(outset, outset);
+            Some(placement.transform_rect_bbox(ink))
+        })
+        .chain(line)
+        .reduce(|a, b| a.union(b))
+        .unwrap_or(KurboRect::ZERO)
+}
+
+/// Fill each placed glyph (or its stroke) straight from its outline.
+fn draw_placed_glyphs(
+    pixmap: &mut Pixmap,
+    ttf_face: &TtfFace<'static>,
+    shaped: &ShapingResult,
+    layout: &PlacedLayout<'_>,
+    stroke: Option<&Stroke>,
+    color: Rgba,
+) {
+    let (r, g, b, a) = color;
+    let mut paint = Paint::default();
+    paint.set_color(Color::from_rgba8(r, g, b, a));
+    paint.anti_alias = layout.antialias;
+
+    for (glyph, placement) in shaped.glyphs.iter().zip(layout.placements) {
+        let Some(placement) = placement else {
+            continue;
+        };
+        let Some(path) = glyph_path(ttf_face, glyph, layout.scale, stroke) else {
+            continue;
+        };
+        let [sx, ky, kx, sy, tx, ty] = (layout.origin * *placement).as_coeffs().map(|c| c as f32);
+        let transform = Transform::from_row(sx, ky, kx, sy, tx, ty);
+        pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+    }
+}
+
 fn bez_path_to_skia(path: &BezPath) -> Option<SkiaPath> {
     if path.elements().is_empty() {
         return None;
@@ -542,6 +864,125 @@ fn bez_path_to_skia(path: &BezPath) -> Option<SkiaPath> {
     builder.finish()
 }
 
+/// Paint a gradient or pattern background under `surface` and encode it as `options` ask.
+///
+/// Solid backgrounds come with the render options; gradients and patterns are drawn over the
+/// final canvas, so the surface's effects and canvas must already be set.
+fn finish_surface(
+    mut surface: RenderSurface,
+    options: &RenderOptions,
+    text_color: Rgba,
+) -> Result<RenderOutput> {
+    if let Some(paint) = options
+        .resolved_background()
+        .filter(|paint| !matches!(paint, o4e_core::Paint::Solid(_)))
+    {
+        let (canvas_width, canvas_height) = surface.output_size();
+        let mut background = Pixmap::new(canvas_width, canvas_height)
+            .ok_or_else(|| O4eError::render("Failed to create pixmap"))?;
+        let canvas = SkiaRect::from_xywh(0.0, 0.0, canvas_width as f32, canvas_height as f32)
+            .ok_or_else(|| O4eError::render("Invalid canvas bounds"))?;
+        fill_with_paint(&mut background, &paint, text_color, canvas, None)?;
+        surface = surface.with_background_image(background.take());
+    }
+    surface.into_render_output(options.format)
+}
+
+/// Fill `target` with `paint` laid out over `area`, optionally through a coverage mask.
+///
+/// `currentColor` in the paint resolves to `current_color`.
+fn fill_with_paint(
+    target: &mut Pixmap,
+    paint: &o4e_core::Paint,
+    current_color: Rgba,
+    area: SkiaRect,
+    mask: Option<&Mask>,
+) -> Result<()> {
+    // Gradient geometry is in unit-box coordinates, mapped onto `area`.
+    let unit_box = Transform::from_row(area.width(), 0.0, 0.0, area.height(), area.x(), area.y());
+    let pattern_pixmap;
+    let shader = match paint {
+        o4e_core::Paint::Solid(color) => {
+            let (r, g, b, a) = parse_color_with_current(color, current_color)?;
+            Shader::SolidColor(Color::from_rgba8(r, g, b, a))
+        }
+        o4e_core::Paint::LinearGradient {
+            start,
+            end,
+            stops,
+            spread,
+        } => LinearGradient::new(
+            SkiaPoint::from_xy(start[0], start[1]),
+            SkiaPoint::from_xy(end[0], end[1]),
+            skia_stops(stops, current_color)?,
+            skia_spread(*spread),
+            unit_box,
+        )
+        .ok_or_else(|| O4eError::render("Invalid linear gradient"))?,
+        o4e_core::Paint::RadialGradient {
+            center,
+            radius,
+            stops,
+            spread,
+        } => {
+            let center = SkiaPoint::from_xy(center[0], center[1]);
+            RadialGradient::new(
+                center,
+                center,
+                *radius,
+                skia_stops(stops, current_color)?,
+                skia_spread(*spread),
+                unit_box,
+            )
+            .ok_or_else(|| O4eError::render("Invalid radial gradient"))?
+        }
+        o4e_core::Paint::Pattern { png, scale } => {
+            pattern_pixmap = Pixmap::decode_png(png)
+                .map_err(|err| O4eError::render(format!("Invalid pattern image: {err}")))?;
+            Pattern::new(
+                pattern_pixmap.as_ref(),
+                SkiaSpreadMode::Repeat,
+                FilterQuality::Bilinear,
+                1.0,
+                Transform::from_row(*scale, 0.0, 0.0, *scale, area.x(), area.y()),
+            )
+        }
+    };
+
+    let skia_paint = Paint {
+        shader,
+        ..Paint::default()
+    };
+    let canvas = SkiaRect::from_xywh(0.0, 0.0, target.width() as f32, target.height() as f32)
+        .ok_or_else(|| O4eError::render("Invalid canvas bounds"))?;
+    target.fill_rect(canvas, &skia_paint, Transform::identity(), mask);
+    Ok(())
+}
+
+fn skia_stops(
+    stops: &[o4e_core::GradientStop],
+    current_color: Rgba,
+) -> Result<Vec<SkiaGradientStop>> {
+    stops
+        .iter()
+        .map(|stop| {
+            let (r, g, b, a) = parse_color_with_current(&stop.color, current_color)?;
+            Ok(SkiaGradientStop::new(
+                stop.offset,
+                Color::from_rgba8(r, g, b, a),
+            ))
+        })
+        .collect()
+}
+
+fn skia_spread(spread: o4e_core::SpreadMode) -> SkiaSpreadMode {
+    match spread {
+        o4e_core::SpreadMode::Pad => SkiaSpreadMode::Pad,
+        o4e_core::SpreadMode::Repeat => SkiaSpreadMode::Repeat,
+        o4e_core::SpreadMode::Reflect => SkiaSpreadMode::Reflect,
+    }
+}
+
 fn blank_rendered_glyph() -> RenderedGlyph {
     RenderedGlyph {
         bitmap: Vec::new(),
@@ -555,6 +996,7 @@ fn blank_rendered_glyph() -> RenderedGlyph {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use o4e_core::PixelFormat;
     use serde::Deserialize;
     use std::collections::HashSet;
     use std::{fs, path::PathBuf, sync::Once};
@@ -848,6 +1290,658 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_svg_output_matches_raster_placement() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("Agé", &SegmentOptions::default()).unwrap();
+        let mut shaped = backend.shape(&runs[0], &font).unwrap();
+        // Exercise baseline offsets (e.g. mark positioning) in both pipelines.
+        shaped.glyphs[1].y = 6.0;
+
+        let options = RenderOptions {
+            format: o4e_core::types::RenderFormat::Raw,
+            ..Default::default()
+        };
+        let bitmap = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+
+        let svg = o4e_render::SvgRenderer::default()
+            .render(&shaped, &o4e_core::types::SvgOptions::default())
+            .unwrap();
+        let tree = usvg::Tree::from_str(&svg, &usvg::Options::default()).expect("valid SVG");
+        let view_box = svg_view_box(&svg);
+
+        // resvg maps the viewBox origin to (0, 0); shift so the SVG baseline origin lands on
+        // the raster baseline origin.
+        let face_entry = backend.get_or_create_ttf_face(&font).unwrap();
+        let face = face_entry.face();
+        let scale = font.size / face.units_per_em() as f32;
+        let padding = options.padding as f32;
+        let baseline_y = padding + face.ascender() as f32 * scale;
+        let mut svg_pixmap = Pixmap::new(bitmap.width, bitmap.height).unwrap();
+        resvg::render(
+            &tree,
+            Transform::from_translate(padding + view_box.0, baseline_y + view_box.1),
+            &mut svg_pixmap.as_mut(),
+        );
+
+        let raster_alpha: Vec<u8> = bitmap.data.chunks_exact(4).map(|px| px[3]).collect();
+        let svg_alpha: Vec<u8> = svg_pixmap.data().chunks_exact(4).map(|px| px[3]).collect();
+        let raster_ink = ink_bounds(&raster_alpha, bitmap.width).expect("raster ink");
+        let svg_ink = ink_bounds(&svg_alpha, bitmap.width).expect("svg ink");
+        for (a, b) in [
+            (raster_ink.0, svg_ink.0),
+            (raster_ink.1, svg_ink.1),
+            (raster_ink.2, svg_ink.2),
+            (raster_ink.3, svg_ink.3),
+        ] {
+            assert!(
+                a.abs_diff(b) <= 1,
+                "raster ink {raster_ink:?} vs SVG ink {svg_ink:?}"
+            );
+        }
+
+        let (mut both, mut either) = (0u32, 0u32);
+        for (a, b) in raster_alpha.iter().zip(&svg_alpha) {
+            let (a, b) = (*a > 127, *b > 127);
+            both += u32::from(a && b);
+            either += u32::from(a || b);
+        }
+        assert!(
+            both as f32 / either as f32 > 0.9,
+            "SVG and raster coverage diverge ({both}/{either})"
+        );
+    }
+
+    #[test]
+    fn test_svg_render_honors_render_options() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("oo", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+
+        let mut options = RenderOptions {
+            format: o4e_core::types::RenderFormat::Svg,
+            color: "#33669980".to_string(),
+            background: "#ffffff".to_string(),
+            padding: 4,
+            ..Default::default()
+        };
+        let svg = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Svg(svg) => svg,
+            other => panic!("expected SVG output, got {other:?}"),
+        };
+        assert!(svg.contains(r##"fill="#336699""##), "{svg}");
+        assert!(svg.contains("fill-opacity="), "{svg}");
+        assert!(svg.contains(r#"<rect id="background""#), "{svg}");
+        assert!(!svg.contains("<use"), "{svg}");
+
+        options.svg = Some(o4e_core::types::SvgOptions {
+            deduplicate_glyphs: true,
+            ..Default::default()
+        });
+        let svg = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Svg(svg) => svg,
+            other => panic!("expected SVG output, got {other:?}"),
+        };
+        assert!(svg.contains("<use"), "{svg}");
+        assert!(!svg.contains(r#"<rect id="background""#), "{svg}");
+    }
+
+    #[test]
+    fn test_pdf_render_returns_document() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend
+            .segment("Hello", &SegmentOptions::default())
+            .unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+
+        let options = RenderOptions {
+            format: RenderFormat::Pdf,
+            ..Default::default()
+        };
+        let pdf = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Pdf(pdf) => pdf,
+            other => panic!("expected PDF output, got {other:?}"),
+        };
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+    }
+
+    #[test]
+    fn test_stroked_masks_are_cached_separately() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("o", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let render = |options: &RenderOptions| match backend.render(&shaped, options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+
+        let filled_options = RenderOptions::default();
+        let filled = render(&filled_options);
+
+        let stroke = Stroke {
+            width: 4.0,
+            color: "#ff0000".to_string(),
+            line_join: o4e_core::types::LineJoin::Round,
+            ..Stroke::default()
+        };
+        let outlined_options = RenderOptions {
+            stroke: Some(stroke.clone()),
+            fill: false,
+            ..Default::default()
+        };
+        let outlined = render(&outlined_options);
+
+        // The canvas grows so the stroke is not clipped, and only stroke color is painted.
+        assert_eq!(outlined.width, filled.width + 4);
+        assert!(outlined.data.chunks_exact(4).any(|px| px[3] > 0));
+        assert!(outlined
+            .data
+            .chunks_exact(4)
+            .all(|px| px[3] == 0 || (px[1] == 0 && px[2] == 0)));
+
+        let key = |stroke: Option<&Stroke>| GlyphKey {
+            font_key: backend.get_or_create_ttf_face(&font).unwrap().font_key(),
+            glyph_id: shaped.glyphs[0].id,
+            size: quantize_size(font.size),
+            stroke: stroke.map(StrokeKey::from),
+        };
+        let fill_mask = backend
+            .cache
+            .get_glyph(&key(None))
+            .expect("fill mask cached");
+        let stroke_mask = backend
+            .cache
+            .get_glyph(&key(Some(&stroke)))
+            .expect("stroke mask cached");
+        assert_eq!(stroke_mask.width, fill_mask.width + 4);
+
+        // A filled render after the stroked one still uses the fill mask.
+        let again = render(&filled_options);
+        assert_eq!(again.data, filled.data);
+    }
+
+    #[test]
+    fn test_gradient_paints_fill_text_and_background() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("HHHH", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let horizontal = |from: &str, to: &str| o4e_core::Paint::LinearGradient {
+            start: [0.0, 0.0],
+            end: [1.0, 0.0],
+            stops: vec![
+                o4e_core::GradientStop::new(0.0, from),
+                o4e_core::GradientStop::new(1.0, to),
+            ],
+            spread: o4e_core::SpreadMode::Pad,
+        };
+
+        let mut options = RenderOptions {
+            paint: Some(horizontal("#ff0000", "#0000ff")),
+            ..Default::default()
+        };
+        let bitmap = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+        // Opaque text pixels shift from red on the left to blue on the right.
+        let opaque: Vec<(usize, &[u8])> = bitmap
+            .data
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, px)| px[3] == 255)
+            .map(|(index, px)| (index % bitmap.width as usize, px))
+            .collect();
+        let leftmost = opaque.iter().min_by_key(|(x, _)| *x).unwrap().1;
+        let rightmost = opaque.iter().max_by_key(|(x, _)| *x).unwrap().1;
+        assert!(leftmost[0] > 200 && leftmost[2] < 55, "{leftmost:?}");
+        assert!(rightmost[2] > 200 && rightmost[0] < 55, "{rightmost:?}");
+        assert!(bitmap.data.chunks_exact(4).all(|px| px[1] == 0));
+
+        // A gradient background covers the canvas grown by effects.
+        options.paint = None;
+        options.background_paint = Some(horizontal("#00ff00", "#00ff00"));
+        options.effects = vec![o4e_core::Effect::OuterGlow {
+            radius: 4.0,
+            color: "#000000".to_string(),
+        }];
+        let glowing = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+        assert!(glowing.width > bitmap.width);
+        assert_eq!(&glowing.data[..4], &[0, 255, 0, 255]);
+        assert_eq!(&glowing.data[glowing.data.len() - 4..], &[0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_drop_shadow_grows_bitmap_under_background() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("l", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let render = |options: &RenderOptions| match backend.render(&shaped, options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+
+        let plain_options = RenderOptions {
+            background: "#ffffff".to_string(),
+            ..Default::default()
+        };
+        let plain = render(&plain_options);
+
+        let mut shadow_options = plain_options.clone();
+        shadow_options.effects = vec![o4e_core::Effect::DropShadow {
+            offset_x: 6.0,
+            offset_y: 4.0,
+            blur_radius: 0.0,
+            color: "#0000ff".to_string(),
+        }];
+        let shadowed = render(&shadow_options);
+
+        assert_eq!(shadowed.width, plain.width + 6);
+        assert_eq!(shadowed.height, plain.height + 4);
+        // The background covers the grown canvas, and the shadow shows through next to the text.
+        assert!(shadowed.data.chunks_exact(4).all(|px| px[3] == 255));
+        assert!(shadowed
+            .data
+            .chunks_exact(4)
+            .any(|px| px[0] == 0 && px[1] == 0 && px[2] == 255));
+    }
+
+    #[test]
+    fn test_transform_and_text_path_place_raster_glyphs() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend
+            .segment("Hello world", &SegmentOptions::default())
+            .unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let render = |options: &RenderOptions| match backend.render(&shaped, options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+
+        let plain = render(&RenderOptions::default());
+        assert!(plain.width > plain.height);
+
+        let rotated_options = RenderOptions {
+            transform: Some(o4e_core::Transform2D::rotate(90.0)),
+            ..Default::default()
+        };
+        let rotated = render(&rotated_options);
+        assert!(rotated.height > rotated.width, "{rotated:?}");
+        assert!(rotated.height.abs_diff(plain.width) <= 2);
+        assert!(rotated.data.chunks_exact(4).any(|px| px[3] > 0));
+
+        let mut arc_options = RenderOptions {
+            text_path: Some(o4e_core::TextPath::new("M0 100 A100 100 0 0 1 200 100")),
+            ..Default::default()
+        };
+        let arc = render(&arc_options);
+        assert!(arc.data.chunks_exact(4).any(|px| px[3] > 0));
+        // The glyphs climb the half circle from its left end.
+        assert!(arc.height > 100, "{} vs {}", arc.height, plain.height);
+
+        arc_options.text_path = Some(o4e_core::TextPath::new("not a path"));
+        assert!(backend.render(&shaped, &arc_options).is_err());
+    }
+
+    #[test]
+    fn test_fixed_canvas_shares_size_and_baseline() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let mut options = RenderOptions {
+            canvas: Some(o4e_core::Canvas::new(64, 48)),
+            ..Default::default()
+        };
+
+        let mut bottoms = Vec::new();
+        for text in ["H", "x", "W"] {
+            let runs = backend.segment(text, &SegmentOptions::default()).unwrap();
+            let shaped = backend.shape(&runs[0], &font).unwrap();
+            let bitmap = match backend.render(&shaped, &options).unwrap() {
+                RenderOutput::Bitmap(bitmap) => bitmap,
+                other => panic!("expected bitmap output, got {other:?}"),
+            };
+            assert_eq!((bitmap.width, bitmap.height), (64, 48));
+            let alpha: Vec<u8> = bitmap.data.chunks_exact(4).map(|px| px[3]).collect();
+            let (_, _, _, bottom) = ink_bounds(&alpha, bitmap.width).unwrap();
+            bottoms.push(bottom);
+        }
+        // Glyphs without descenders all sit on the baseline at 75% of the height.
+        assert!(
+            bottoms.iter().all(|bottom| bottom.abs_diff(36) <= 1),
+            "{bottoms:?}"
+        );
+
+        let runs = backend
+            .segment("Hello world", &SegmentOptions::default())
+            .unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        options.canvas = Some(o4e_core::Canvas {
+            overflow: o4e_core::CanvasOverflow::Error,
+            ..o4e_core::Canvas::new(16, 16)
+        });
+        let err = backend.render(&shaped, &options).unwrap_err();
+        assert!(err.to_string().contains("overflows"), "{err}");
+    }
+
+    #[test]
+    fn test_empty_text_keeps_canvas_and_format() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let shaped = ShapingResult::empty(&font);
+        let options = RenderOptions {
+            format: RenderFormat::Png,
+            canvas: Some(o4e_core::Canvas::new(40, 24)),
+            background: "#ff0000".to_string(),
+            ..Default::default()
+        };
+        let png_bytes = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Png(bytes) => bytes,
+            other => panic!("expected PNG output, got {other:?}"),
+        };
+
+        let mut decoder = png::Decoder::new(png_bytes.as_slice());
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((frame.width, frame.height), (40, 24));
+        // The background still covers the whole canvas
+        assert_eq!(frame.color_type, png::ColorType::Rgb);
+        assert!(pixels[..frame.buffer_size()]
+            .chunks_exact(3)
+            .all(|px| px == [255, 0, 0]));
+    }
+
+    #[test]
+    fn test_render_into_places_subpixel_glyphs_like_render() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("Hil", &SegmentOptions::default()).unwrap();
+        let (width, height) = (160u32, 90u32);
+        for shift in [0.125f32, 0.25, 0.49, 0.5, 0.51, 0.75] {
+            let mut shaped = backend.shape(&runs[0], &font).unwrap();
+            shaped.glyphs[1].x += shift;
+            shaped.glyphs[1].y += shift;
+            shaped.glyphs[2].x += shift / 3.0;
+            let options = RenderOptions::default();
+            let mut direct = vec![0u8; (width * height * 4) as usize];
+            let mut composited = direct.clone();
+            for (data, via_bitmap) in [(&mut direct, false), (&mut composited, true)] {
+                let mut frame = Frame::new(
+                    data,
+                    width,
+                    height,
+                    width as usize * 4,
+                    PixelFormat::Rgba8Premultiplied,
+                )
+                .unwrap();
+                if via_bitmap {
+                    o4e_core::frame::render_via_bitmap(
+                        &backend,
+                        &shaped,
+                        &options,
+                        &mut frame,
+                        (3, 2),
+                    )
+                    .unwrap();
+                } else {
+                    backend
+                        .render_into(&shaped, &options, &mut frame, (3, 2))
+                        .unwrap();
+                }
+            }
+            assert!(direct.iter().any(|&byte| byte > 0));
+            assert!(direct == composited, "glyphs shifted by {shift} px differ");
+        }
+    }
+
+    #[test]
+    fn test_render_into_matches_bitmap_composite() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("Hi", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let mut options = RenderOptions {
+            color: "red".to_string(),
+            ..Default::default()
+        };
+
+        // Opaque white BGRA video frame with padded rows.
+        let (width, height, stride) = (160u32, 90u32, 160 * 4 + 16);
+        let blank = [255u8; 160 * 4 + 16].repeat(height as usize);
+        let red_bounds = |data: &[u8]| {
+            let alpha: Vec<u8> = (0..width * height)
+                .map(|index| {
+                    let offset = (index / width) as usize * stride + (index % width) as usize * 4;
+                    255 - data[offset]
+                })
+                .collect();
+            ink_bounds(&alpha, width)
+        };
+
+        let mut direct = blank.clone();
+        let mut frame = Frame::new(
+            &mut direct,
+            width,
+            height,
+            stride,
+            PixelFormat::Bgra8Premultiplied,
+        )
+        .unwrap();
+        backend
+            .render_into(&shaped, &options, &mut frame, (7, 3))
+            .unwrap();
+
+        let mut composited = blank.clone();
+        let mut frame = Frame::new(
+            &mut composited,
+            width,
+            height,
+            stride,
+            PixelFormat::Bgra8Premultiplied,
+        )
+        .unwrap();
+        o4e_core::frame::render_via_bitmap(&backend, &shaped, &options, &mut frame, (7, 3))
+            .unwrap();
+
+        let direct_bounds = red_bounds(&direct).expect("text drawn into the frame");
+        let composited_bounds = red_bounds(&composited).unwrap();
+        for (a, b) in [
+            (direct_bounds.0, composited_bounds.0),
+            (direct_bounds.1, composited_bounds.1),
+            (direct_bounds.2, composited_bounds.2),
+            (direct_bounds.3, composited_bounds.3),
+        ] {
+            assert!(
+                a.abs_diff(b) <= 1,
+                "{direct_bounds:?} vs {composited_bounds:?}"
+            );
+        }
+        // Row padding is left alone and the frame stays opaque.
+        assert!(direct
+            .chunks_exact(stride)
+            .all(|row| row[stride - 16..].iter().all(|&byte| byte == 255)));
+        assert!(direct[..stride * (height as usize - 1)]
+            .chunks_exact(stride)
+            .all(|row| row[..width as usize * 4]
+                .chunks_exact(4)
+                .all(|px| px[3] == 255)));
+
+        // Backgrounds take the bitmap path and cover the bitmap's area.
+        options.background = "#0000ff".to_string();
+        let mut backed = blank.clone();
+        let mut frame = Frame::new(
+            &mut backed,
+            width,
+            height,
+            stride,
+            PixelFormat::Bgra8Premultiplied,
+        )
+        .unwrap();
+        backend
+            .render_into(&shaped, &options, &mut frame, (7, 3))
+            .unwrap();
+        let pixel = 3 * stride + 7 * 4;
+        assert_eq!(&backed[pixel..pixel + 4], &[255, 0, 0, 255]);
+
+        // The output pixel format does not leak into the composite: no white box around
+        // Gray8 text, and A8 keeps its color
+        options.background = "transparent".to_string();
+        options.effects = vec![o4e_core::Effect::Blur { radius: 0.5 }];
+        for pixel_format in [PixelFormat::Gray8, PixelFormat::A8] {
+            options.pixel_format = pixel_format;
+            let mut dark = [0u8, 0, 0, 255].repeat((width * height) as usize);
+            let mut frame = Frame::new(
+                &mut dark,
+                width,
+                height,
+                width as usize * 4,
+                PixelFormat::Bgra8Premultiplied,
+            )
+            .unwrap();
+            backend
+                .render_into(&shaped, &options, &mut frame, (7, 3))
+                .unwrap();
+            assert!(dark.chunks_exact(4).all(|px| px[0] == 0 && px[1] == 0));
+            assert!(dark.chunks_exact(4).any(|px| px[2] > 200));
+        }
+    }
+
+    #[test]
+    fn test_png_metadata_describes_the_render() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("Hi", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let options = RenderOptions {
+            format: RenderFormat::Png,
+            png: Some(o4e_core::PngOptions {
+                metadata: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let png_bytes = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Png(bytes) => bytes,
+            other => panic!("expected PNG output, got {other:?}"),
+        };
+
+        let reader = png::Decoder::new(png_bytes.as_slice()).read_info().unwrap();
+        let info = reader.info();
+        // Black text is stored as gray with alpha
+        assert_eq!(info.color_type, png::ColorType::GrayscaleAlpha);
+        let text: HashMap<&str, &str> = info
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.as_str(), chunk.text.as_str()))
+            .collect();
+        assert_eq!(text.get("Title"), Some(&"Hi"));
+        assert_eq!(text.get("o4e:backend"), Some(&"HarfBuzz+ICU"));
+        assert_eq!(text.get("o4e:font-size"), Some(&"48"));
+        assert!(text["o4e:font-path"].ends_with("NotoSans-Regular.ttf"));
+    }
+
+    #[test]
+    fn test_pixel_formats_share_coverage_and_geometry() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("Hi", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let render = |pixel_format| {
+            let options = RenderOptions {
+                pixel_format,
+                ..Default::default()
+            };
+            match backend.render(&shaped, &options).unwrap() {
+                RenderOutput::Bitmap(bitmap) => bitmap,
+                _ => panic!("Expected bitmap output"),
+            }
+        };
+
+        let rgba = render(PixelFormat::Rgba8);
+        let mask = render(PixelFormat::A8);
+        assert_eq!(mask.format, PixelFormat::A8);
+        assert_eq!((mask.width, mask.height), (rgba.width, rgba.height));
+        assert_eq!(mask.stride, mask.width as usize);
+        let alpha: Vec<u8> = rgba.data.chunks_exact(4).map(|px| px[3]).collect();
+        assert_eq!(mask.data, alpha);
+
+        // Formats without alpha flatten onto white paper.
+        let gray = render(PixelFormat::Gray8);
+        assert_eq!(gray.data.len(), (gray.width * gray.height) as usize);
+        assert!(gray.data.contains(&255) && gray.data.iter().any(|&value| value < 64));
+
+        let bgra = render(PixelFormat::Bgra8Premultiplied);
+        assert_eq!(bgra.stride, bgra.width as usize * 4);
+        assert!(bgra.data.chunks_exact(4).all(|px| px[0] <= px[3]));
+    }
+
+    #[test]
+    fn test_deduplicated_svg_rasterizes_like_expanded_svg() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend
+            .segment("referee", &SegmentOptions::default())
+            .unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+
+        let rasterize = |options: &o4e_core::types::SvgOptions| {
+            let svg = o4e_render::SvgRenderer::new(options)
+                .render(&shaped, options)
+                .unwrap();
+            let tree = usvg::Tree::from_str(&svg, &usvg::Options::default()).expect("valid SVG");
+            let size = tree.size().to_int_size();
+            let mut pixmap = Pixmap::new(size.width(), size.height()).unwrap();
+            resvg::render(&tree, Transform::identity(), &mut pixmap.as_mut());
+            pixmap
+        };
+
+        let expanded = rasterize(&o4e_core::types::SvgOptions::default());
+        let deduplicated = rasterize(&o4e_core::types::SvgOptions {
+            deduplicate_glyphs: true,
+            ..Default::default()
+        });
+        assert_eq!(expanded.width(), deduplicated.width());
+        assert_eq!(expanded.height(), deduplicated.height());
+        assert!(expanded.data() == deduplicated.data());
+    }
+
+    fn svg_view_box(svg: &str) -> (f32, f32) {
+        let start = svg.find("viewBox=\"").unwrap() + 9;
+        let mut values = svg[start..].split_whitespace();
+        let x = values.next().unwrap().parse().unwrap();
+        let y = values.next().unwrap().parse().unwrap();
+        (x, y)
+    }
+
+    fn ink_bounds(alpha: &[u8], width: u32) -> Option<(u32, u32, u32, u32)> {
+        let mut bounds: Option<(u32, u32, u32, u32)> = None;
+        for (idx, value) in alpha.iter().enumerate() {
+            if *value <= 127 {
+                continue;
+            }
+            let (x, y) = (idx as u32 % width, idx as u32 / width);
+            bounds = Some(match bounds {
+                None => (x, y, x, y),
+                Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            });
+        }
+        bounds
+    }
+
     #[test]
     fn test_clear_cache_empties_internal_layers() {
         ensure_test_fonts();
@@ -874,4 +1968,409 @@ mod tests {
         assert_eq!(backend.ttf_cache.read().len(), 0);
         assert_eq!(backend.font_data_cache.read().len(), 0);
     }
+
+    #[test]
+    fn test_dpi_scales_raster_output_and_glyph_cache_keys() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("Hi", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let render = |dpi| {
+            let options = RenderOptions {
+                dpi,
+                padding: 4,
+                ..Default::default()
+            };
+            match backend.render(&shaped, &options).unwrap() {
+                RenderOutput::Bitmap(bitmap) => bitmap,
+                _ => panic!("Expected bitmap output"),
+            }
+        };
+
+        let single = render(72.0);
+        let cached = backend.cache.stats().glyph_count;
+        let double = render(o4e_core::units::dpi_for_scale(2.0));
+        assert!(double.width.abs_diff(single.width * 2) <= 2);
+        assert!(double.height.abs_diff(single.height * 2) <= 2);
+        // Each effective pixel size gets its own masks
+        assert_eq!(backend.cache.stats().glyph_count, cached * 2);
+
+        let ink =
+            |bitmap: &o4e_core::Bitmap| bitmap.data.chunks_exact(4).filter(|px| px[3] > 0).count();
+        let ratio = ink(&double) as f32 / ink(&single) as f32;
+        assert!((3.0..5.0).contains(&ratio), "ink ratio {ratio}");
+
+        assert!(backend
+            .render(
+                &shaped,
+                &RenderOptions {
+                    dpi: 0.0,
+                    ..Default::default()
+                }
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_canvas_fits_line_box_or_ink() {
+        let backend = HarfBuzzBackend::new();
+        let runs = backend.segment("gjy", &SegmentOptions::default()).unwrap();
+        let render = |size, sizing| {
+            let font = Font::from_path(fixture_font_path("NotoSans-Regular.ttf"), size);
+            let shaped = backend.shape(&runs[0], &font).unwrap();
+            let options = RenderOptions {
+                sizing,
+                padding: 0,
+                ..Default::default()
+            };
+            match backend.render(&shaped, &options).unwrap() {
+                RenderOutput::Bitmap(bitmap) => bitmap,
+                _ => panic!("Expected bitmap output"),
+            }
+        };
+        let alpha = |bitmap: &o4e_core::Bitmap, x: u32, y: u32| {
+            bitmap.data[(y * bitmap.width + x) as usize * 4 + 3]
+        };
+        let row_has_ink =
+            |bitmap: &o4e_core::Bitmap, y| (0..bitmap.width).any(|x| alpha(bitmap, x, y) > 0);
+        let column_has_ink =
+            |bitmap: &o4e_core::Bitmap, x| (0..bitmap.height).any(|y| alpha(bitmap, x, y) > 0);
+
+        // Noto Sans spans 1069 units above and 293 below the baseline per 1000
+        let large = render(200.0, SizingMode::Logical);
+        assert!(large.height.abs_diff(273) <= 1, "height {}", large.height);
+        assert!(!row_has_ink(&large, large.height - 1));
+        let small = render(8.0, SizingMode::Logical);
+        assert!(small.height <= 12, "height {}", small.height);
+
+        let ink = render(200.0, SizingMode::Ink);
+        assert!(ink.height < large.height);
+        assert!(row_has_ink(&ink, 0) && row_has_ink(&ink, ink.height - 1));
+        assert!(column_has_ink(&ink, 0) && column_has_ink(&ink, ink.width - 1));
+    }
+
+    #[test]
+    fn test_shaped_advances_match_font_metrics() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("Hi", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+
+        let entry = backend.get_or_create_ttf_face(&font).unwrap();
+        let face = entry.face();
+        let scale = font.size / face.units_per_em() as f32;
+        for glyph in &shaped.glyphs {
+            let units = face.glyph_hor_advance(GlyphId(glyph.id as u16)).unwrap();
+            assert!((glyph.advance - units as f32 * scale).abs() < 0.05);
+        }
+        let total: f32 = shaped.glyphs.iter().map(|glyph| glyph.advance).sum();
+        assert!((shaped.advance - total).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_decorations_are_drawn_and_skip_descenders() {
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let runs = backend.segment("xgx", &SegmentOptions::default()).unwrap();
+        let shaped = backend.shape(&runs[0], &font).unwrap();
+        let render = |decorations| {
+            let options = RenderOptions {
+                decorations,
+                padding: 0,
+                ..Default::default()
+            };
+            match backend.render(&shaped, &options).unwrap() {
+                RenderOutput::Bitmap(bitmap) => bitmap,
+                _ => panic!("Expected bitmap output"),
+            }
+        };
+
+        let plain = render(Vec::new());
+        let underlined = render(vec![o4e_core::Decoration::new(DecorationLine::Underline)]);
+        // The underline sits inside the line box
+        assert_eq!(
+            (plain.width, plain.height),
+            (underlined.width, underlined.height)
+        );
+        let added: Vec<u32> = (0..plain.data.len() / 4)
+            .filter(|&index| underlined.data[index * 4 + 3] > plain.data[index * 4 + 3])
+            .map(|index| index as u32 % plain.width)
+            .collect();
+        assert!(
+            added.iter().any(|&x| x <= 1),
+            "underline starts at the pen origin"
+        );
+        let g = &shaped.glyphs[1];
+        let g_center = (g.x + g.advance / 2.0).round() as u32;
+        assert!(
+            added.iter().all(|&x| x.abs_diff(g_center) > 2),
+            "underline skips the descender"
+        );
+
+        let struck = render(vec![o4e_core::Decoration {
+            color: Some("red".to_string()),
+            ..o4e_core::Decoration::new(DecorationLine::LineThrough)
+        }]);
+        assert!(struck.data.chunks_exact(4).any(|px| px == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_wrapped_paragraph_hyphenates_and_stacks_lines() {
+        use o4e_render::paragraph::{break_paragraph, ParagraphOptions};
+
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let max_width = font.size * 5.0;
+        let options = ParagraphOptions {
+            max_width: Some(max_width),
+            hyphenator: o4e_unicode::Hyphenator::for_language("en"),
+            ..Default::default()
+        };
+        let paragraph = break_paragraph(
+            &backend,
+            "Hyphenation keeps narrow columns even",
+            &font,
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        assert!(paragraph.lines.len() > 2);
+        assert!(paragraph
+            .lines
+            .iter()
+            .all(|line| line.shaped.advance <= max_width));
+
+        // The font has U+2010, so hyphenated lines end in it
+        let face_entry = backend.get_or_create_ttf_face(&font).unwrap();
+        let face = face_entry.face();
+        let hyphen = u32::from(face.glyph_index('\u{2010}').unwrap().0);
+        let hyphenated = paragraph
+            .lines
+            .iter()
+            .find(|line| line.hyphenated)
+            .expect("a hyphenated line");
+        assert_eq!(hyphenated.shaped.glyphs.last().unwrap().id, hyphen);
+
+        // One line box per line, a line height apart
+        let options = RenderOptions {
+            padding: 0,
+            ..Default::default()
+        };
+        let bitmap = match backend.render(&paragraph.to_shaped(), &options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            _ => panic!("Expected bitmap output"),
+        };
+        let scale = font.size / face.units_per_em() as f32;
+        let line_box = (face.ascender() - face.descender()) as f32 * scale;
+        let expected = line_box + (paragraph.lines.len() - 1) as f32 * paragraph.line_height;
+        assert!(
+            (bitmap.height as f32 - expected).abs() <= 1.0,
+            "{} vs {expected}",
+            bitmap.height
+        );
+        assert!(bitmap.width as f32 <= paragraph.width().ceil() + 1.0);
+    }
+
+    #[test]
+    fn test_truncated_rtl_text_shows_ellipsis_on_the_left() {
+        use o4e_render::truncate::{truncate, TruncateOptions};
+
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoNaskhArabic-Regular.ttf");
+        let text = "مرحبا بالعالم الجميل";
+        let options = TruncateOptions {
+            max_width: font.size * 3.0,
+            segment: SegmentOptions {
+                bidi_resolve: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let shaped = truncate(&backend, text, &font, &options, |_| {}).unwrap();
+        assert!(shaped.advance <= options.max_width);
+
+        // The logical start is kept and the ellipsis follows it, which in RTL is leftmost
+        let kept = shaped.text.trim_end_matches(['\u{2026}', '.']);
+        assert!(!kept.is_empty() && kept.len() < shaped.text.len());
+        assert!(text.starts_with(kept));
+        let leftmost = shaped
+            .glyphs
+            .iter()
+            .min_by(|a, b| a.x.total_cmp(&b.x))
+            .unwrap();
+        assert!(leftmost.cluster as usize >= kept.len());
+    }
+
+    #[test]
+    fn test_rich_text_renders_each_span_in_its_color() {
+        use o4e_render::rich::{render_rich, shape_rich, SpanStyle, TextSpan};
+        use o4e_render::tabs::TabOptions;
+
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let text = "red blue";
+        let span = |range, color: &str| {
+            TextSpan::new(
+                range,
+                SpanStyle {
+                    color: Some(color.to_string()),
+                    ..Default::default()
+                },
+            )
+        };
+        let spans = [span(0..3, "#ff0000"), span(4..8, "#0000ff")];
+        let layout = shape_rich(
+            &backend,
+            text,
+            &spans,
+            &font,
+            &SegmentOptions::default(),
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(layout.runs.len(), 3);
+
+        let options = RenderOptions {
+            format: o4e_core::types::RenderFormat::Raw,
+            ..Default::default()
+        };
+        let bitmap = match render_rich(&backend, &layout, &options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+        // Inked pixels left of the space are red, right of it blue
+        let split = (layout.runs[1].shaped.glyphs[0].x + options.padding as f32) as usize;
+        let (mut red, mut blue) = (0, 0);
+        for row in bitmap.data.chunks(bitmap.stride) {
+            for (x, pixel) in row[..bitmap.width as usize * 4].chunks(4).enumerate() {
+                if pixel[3] < 200 {
+                    continue;
+                }
+                assert_eq!(pixel[1], 0);
+                if x < split {
+                    assert!(pixel[0] > 0 && pixel[2] == 0, "{pixel:?} at {x}");
+                    red += 1;
+                } else {
+                    assert!(pixel[2] > 0 && pixel[0] == 0, "{pixel:?} at {x}");
+                    blue += 1;
+                }
+            }
+        }
+        assert!(red > 0 && blue > 0);
+
+        let options = RenderOptions {
+            format: o4e_core::types::RenderFormat::Svg,
+            ..Default::default()
+        };
+        let svg = match render_rich(&backend, &layout, &options).unwrap() {
+            RenderOutput::Svg(svg) => svg,
+            other => panic!("expected SVG output, got {other:?}"),
+        };
+        assert!(svg.contains(r##"fill="#ff0000""##) && svg.contains(r##"fill="#0000ff""##));
+        usvg::Tree::from_str(&svg, &usvg::Options::default()).expect("valid SVG");
+    }
+
+    #[test]
+    fn test_rich_text_follows_transforms_and_paths() {
+        use o4e_render::rich::{render_rich, shape_rich, SpanStyle, TextSpan};
+        use o4e_render::tabs::TabOptions;
+
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let spans = [TextSpan::new(
+            6..10,
+            SpanStyle {
+                color: Some("#0000ff".to_string()),
+                ..Default::default()
+            },
+        )];
+        let layout = shape_rich(
+            &backend,
+            "Rotate this",
+            &spans,
+            &font,
+            &SegmentOptions::default(),
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(layout.runs.len(), 3);
+        let coverage = |output: RenderOutput| -> u64 {
+            let RenderOutput::Bitmap(bitmap) = output else {
+                panic!("expected bitmap output, got {output:?}");
+            };
+            bitmap
+                .data
+                .chunks_exact(4)
+                .map(|pixel| u64::from(pixel[3]))
+                .sum()
+        };
+
+        // Every layer lands where one render of the whole text puts its glyphs, so no ink
+        // is clipped
+        for (transform, text_path) in [
+            (Some(Transform2D::rotate(90.0)), None),
+            (
+                Some(Transform2D::rotate(-30.0).then(Transform2D::scale(2.0, 1.0))),
+                None,
+            ),
+            (
+                None,
+                Some(o4e_core::TextPath::new("M0 0 Q150 -150 300 0 T600 0")),
+            ),
+        ] {
+            let options = RenderOptions {
+                format: RenderFormat::Raw,
+                transform,
+                text_path,
+                ..Default::default()
+            };
+            let rich = coverage(render_rich(&backend, &layout, &options).unwrap());
+            let plain = coverage(backend.render(&layout.to_shaped(), &options).unwrap());
+            let ratio = rich as f64 / plain as f64;
+            assert!(
+                (0.97..1.03).contains(&ratio),
+                "{options:?}: {rich} vs {plain}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_markup_tt_renders_in_a_monospace_font() {
+        use o4e_render::markup::parse_markup;
+        use o4e_render::rich::{render_rich, shape_rich};
+        use o4e_render::tabs::TabOptions;
+
+        let backend = HarfBuzzBackend::new();
+        let font = fixture_font("NotoSans-Regular.ttf");
+        let markup = parse_markup("<tt>iiii</tt> WWWW", &font).unwrap();
+        let layout = shape_rich(
+            &backend,
+            &markup.text,
+            &markup.spans,
+            &font,
+            &SegmentOptions::default(),
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        let options = RenderOptions {
+            format: o4e_core::types::RenderFormat::Raw,
+            ..Default::default()
+        };
+        let bitmap = match render_rich(&backend, &layout, &options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+        assert!(bitmap.data.chunks(4).any(|pixel| pixel[3] > 0));
+
+        // Where a monospace face is installed, "i" is as wide as any of its letters (0.6 em
+        // or so); otherwise the text keeps the base font
+        let monospace = Font::parse_css("16px monospace").unwrap();
+        let tt = &layout.runs[0].shaped;
+        if FontDatabase::global().resolve(&monospace).is_ok() {
+            assert!(tt.glyphs[0].advance > font.size * 0.5, "{tt:?}");
+        }
+    }
 }
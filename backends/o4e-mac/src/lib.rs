@@ -18,7 +18,7 @@ use core_graphics::{
     context::{CGContext, CGTextDrawingMode},
     data_provider::CGDataProvider,
     font::{CGFont, CGGlyph},
-    geometry::{CGPoint, CGRect, CGSize},
+    geometry::{CGPoint, CGSize},
 };
 use core_text::{
     font::{self, new_from_name, CTFont, CTFontRef},
@@ -32,9 +32,10 @@ use core_text::{
 };
 use lru::LruCache;
 use o4e_core::{
+    render_metadata,
     types::{AntialiasMode, FontSource, FontStyle, RenderFormat},
-    Backend, Bitmap, Font, FontCache, Glyph, O4eError, RenderOptions, RenderOutput, RenderSurface,
-    Result, SegmentOptions, ShapingResult, TextRun,
+    Backend, Font, FontCache, Glyph, O4eError, RenderOptions, RenderOutput, RenderSurface, Result,
+    SegmentOptions, ShapingResult, TextAnchor, TextRun,
 };
 use o4e_fontdb::FontDatabase;
 use o4e_unicode::TextSegmenter;
@@ -459,6 +460,7 @@ impl Backend for CoreTextBackend {
             bbox,
             font: Some(resolved_font.clone()),
             direction: run.direction,
+            lines: Vec::new(),
         };
         let result = Arc::new(result);
 
@@ -471,14 +473,30 @@ impl Backend for CoreTextBackend {
     }
 
     fn render(&self, shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput> {
-        // Diagnostics removed for simplicity
-        // Check if we have glyphs to render
+        // Vector output never needs the raster pass
+        if options.format == RenderFormat::Svg {
+            let svg_options = options.svg_options();
+            let renderer = o4e_render::SvgRenderer::new(&svg_options);
+            return Ok(RenderOutput::Svg(renderer.render(shaped, &svg_options)?));
+        }
+        if options.format == RenderFormat::Pdf {
+            let renderer = o4e_render::PdfRenderer::new();
+            return Ok(RenderOutput::Pdf(
+                renderer.render(shaped, &options.pdf_options())?,
+            ));
+        }
+
+        // Rasterize in device pixels; the metadata keeps the requested point size
+        let metadata = render_metadata(self.name(), shaped);
+        let (shaped, options) = o4e_core::units::to_device_pixels(shaped, options)?;
+        let (shaped, options) = (shaped.as_ref(), options.as_ref());
+
+        // Text without glyphs still gets the requested canvas, background and format
         if shaped.glyphs.is_empty() {
-            return Ok(RenderOutput::Bitmap(Bitmap {
-                width: 1,
-                height: 1,
-                data: vec![0, 0, 0, 0],
-            }));
+            let text_color = o4e_core::utils::parse_color(&options.color)?;
+            return RenderSurface::empty(options, text_color)?
+                .with_metadata(metadata)
+                .into_render_output(options.format);
         }
 
         // Get the font from ShapingResult
@@ -515,24 +533,7 @@ impl Backend for CoreTextBackend {
 
         Self::configure_antialias(&context, options.antialias);
 
-        let (text_r, text_g, text_b, text_a) =
-            o4e_core::utils::parse_color(&options.color).map_err(O4eError::render)?;
-
-        // Fill background if not transparent
-        if options.background != "transparent" {
-            let (bg_r, bg_g, bg_b, bg_a) =
-                o4e_core::utils::parse_color(&options.background).map_err(O4eError::render)?;
-            context.set_rgb_fill_color(
-                bg_r as f64 / 255.0,
-                bg_g as f64 / 255.0,
-                bg_b as f64 / 255.0,
-                bg_a as f64 / 255.0,
-            );
-            context.fill_rect(CGRect::new(
-                &CGPoint::new(0.0, 0.0),
-                &CGSize::new(width as f64, height as f64),
-            ));
-        }
+        let (text_r, text_g, text_b, text_a) = o4e_core::utils::parse_color(&options.color)?;
 
         // Set text color
         context.set_rgb_fill_color(
@@ -569,14 +570,21 @@ impl Backend for CoreTextBackend {
         ct_font.draw_glyphs(&glyph_ids, &glyph_positions, context.clone());
         context.restore();
 
-        if options.format == RenderFormat::Svg {
-            let svg_options = o4e_core::types::SvgOptions::default();
-            let renderer = o4e_render::SvgRenderer::new(&svg_options);
-            let svg = renderer.render(shaped, &svg_options);
-            return Ok(RenderOutput::Svg(svg));
+        let mut surface = RenderSurface::from_rgba(width as u32, height as u32, buffer, true)
+            .with_render_options(options, (text_r, text_g, text_b, text_a))?
+            .with_metadata(metadata);
+        if let Some(canvas) = options.canvas {
+            surface = surface.with_canvas(
+                canvas,
+                TextAnchor {
+                    origin_x: padding,
+                    baseline_y: baseline_y as f32,
+                    advance: shaped.advance,
+                    ascent: ct_font.ascent() as f32,
+                    descent: ct_font.descent() as f32,
+                },
+            );
         }
-
-        let surface = RenderSurface::from_rgba(width as u32, height as u32, buffer, true);
         surface.into_render_output(options.format)
     }
 
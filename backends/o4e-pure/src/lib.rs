@@ -10,9 +10,12 @@ use alloc::vec;
 use alloc::vec::Vec;
 
 use o4e_core::{
+    color::svg_paint_attributes,
+    render_metadata,
     types::{Direction, RenderFormat},
-    Backend, Bitmap, Font, Glyph, RenderOptions, RenderOutput, RenderSurface, Result,
-    SegmentOptions, ShapingResult, TextRun,
+    utils::parse_color,
+    Backend, Bitmap, Font, Glyph, O4eError, RenderOptions, RenderOutput, RenderSurface, Result,
+    SegmentOptions, ShapingResult, SvgOptions, TextAnchor, TextRun,
 };
 
 /// Pure Rust backend using rustybuzz for shaping and tiny-skia for rendering
@@ -102,18 +105,7 @@ impl PureRustBackend {
         let mut bitmap = vec![0u8; (width * height * 4) as usize];
 
         // Parse colors
-        let (text_r, text_g, text_b, text_a) = parse_color(&options.color);
-
-        // Fill background
-        if options.background != "transparent" {
-            let (bg_r, bg_g, bg_b, bg_a) = parse_color(&options.background);
-            for pixel in bitmap.chunks_exact_mut(4) {
-                pixel[0] = bg_r;
-                pixel[1] = bg_g;
-                pixel[2] = bg_b;
-                pixel[3] = bg_a;
-            }
-        }
+        let (text_r, text_g, text_b, text_a) = parse_color(&options.color)?;
 
         // In a real implementation, we'd render actual glyphs here
         // For now, just create placeholder rectangles for each glyph
@@ -142,11 +134,41 @@ impl PureRustBackend {
             }
         }
 
-        Ok(Bitmap {
-            width,
-            height,
-            data: bitmap,
-        })
+        Ok(Bitmap::rgba(width, height, bitmap))
+    }
+
+    /// Simple SVG generation - one box per glyph, honoring color, background, padding and scale
+    fn render_svg(&self, shaped: &ShapingResult, options: &SvgOptions) -> Result<String> {
+        let bbox = shaped.bbox;
+        let padding = options.padding.max(0.0);
+        let (x, y) = (bbox.x - padding, bbox.y - padding);
+        let (width, height) = (bbox.width + padding * 2.0, bbox.height + padding * 2.0);
+
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.2}" height="{:.2}" viewBox="{x:.2} {y:.2} {width:.2} {height:.2}">"#,
+            width * options.scale,
+            height * options.scale,
+        );
+        if let Some(background) = &options.background {
+            svg.push_str(&format!(
+                r#"<rect x="{x:.2}" y="{y:.2}" width="{width:.2}" height="{height:.2}"{}/>"#,
+                svg_paint_attributes("fill", background)?
+            ));
+        }
+
+        let fill = options.foreground_color.as_deref().unwrap_or("#000000");
+        svg.push_str(&format!("<g{}>", svg_paint_attributes("fill", fill)?));
+        for glyph in &shaped.glyphs {
+            svg.push_str(&format!(
+                r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}"/>"#,
+                glyph.x,
+                bbox.y - glyph.y,
+                glyph.advance * 0.8,
+                -bbox.y
+            ));
+        }
+        svg.push_str("</g></svg>");
+        Ok(svg)
     }
 }
 
@@ -197,32 +219,51 @@ impl Backend for PureRustBackend {
             bbox,
             font: Some(font.clone()),
             direction: run.direction,
+            lines: Vec::new(),
         })
     }
 
     fn render(&self, shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput> {
         // Diagnostics removed for simplicity
         match options.format {
-            RenderFormat::Raw | RenderFormat::Png => {
+            RenderFormat::Raw
+            | RenderFormat::Png
+            | RenderFormat::Webp
+            | RenderFormat::Jpeg
+            | RenderFormat::Pgm
+            | RenderFormat::Pbm
+            | RenderFormat::Bmp => {
+                let metadata = render_metadata(self.name(), shaped);
+                let (shaped, options) = o4e_core::units::to_device_pixels(shaped, options)?;
+                let (shaped, options) = (shaped.as_ref(), options.as_ref());
                 let bitmap = self.render_glyphs(shaped, options)?;
-                let surface =
-                    RenderSurface::from_rgba(bitmap.width, bitmap.height, bitmap.data, false);
-                surface.into_render_output(options.format)
-            }
-            RenderFormat::Svg => {
-                // Simple SVG generation
-                let mut svg = String::from(r#"<svg xmlns="http://www.w3.org/2000/svg">"#);
-
-                for glyph in &shaped.glyphs {
-                    svg.push_str(&format!(
-                        r#"<rect x="{}" y="10" width="8" height="12" fill="black"/>"#,
-                        glyph.x
-                    ));
+                let mut surface =
+                    RenderSurface::from_rgba(bitmap.width, bitmap.height, bitmap.data, false)
+                        .with_render_options(options, parse_color(&options.color)?)?
+                        .with_metadata(metadata);
+                if let Some(canvas) = options.canvas {
+                    // Placeholder boxes hang from the top padding; treat the bbox as the
+                    // ascent
+                    let padding = options.padding as f32;
+                    surface = surface.with_canvas(
+                        canvas,
+                        TextAnchor {
+                            origin_x: padding,
+                            baseline_y: padding + shaped.bbox.height,
+                            advance: shaped.advance,
+                            ascent: shaped.bbox.height,
+                            descent: 0.0,
+                        },
+                    );
                 }
-
-                svg.push_str("</svg>");
-                Ok(RenderOutput::Svg(svg))
+                surface.into_render_output(options.format)
             }
+            RenderFormat::Svg => Ok(RenderOutput::Svg(
+                self.render_svg(shaped, &options.svg_options())?,
+            )),
+            RenderFormat::Pdf => Err(O4eError::render(
+                "PDF output is not supported by the pure backend",
+            )),
         }
     }
 
@@ -287,21 +328,6 @@ fn is_rtl_script(script: Script) -> bool {
     matches!(script, Script::Arabic | Script::Hebrew)
 }
 
-fn parse_color(color: &str) -> (u8, u8, u8, u8) {
-    if color.starts_with('#') && color.len() == 7 {
-        if let (Ok(r), Ok(g), Ok(b)) = (
-            u8::from_str_radix(&color[1..3], 16),
-            u8::from_str_radix(&color[3..5], 16),
-            u8::from_str_radix(&color[5..7], 16),
-        ) {
-            return (r, g, b, 255);
-        }
-    }
-
-    // Default to black
-    (0, 0, 0, 255)
-}
-
 #[cfg(target_arch = "wasm32")]
 mod wasm {
     use super::*;
@@ -363,6 +389,77 @@ mod tests {
         assert_eq!(runs.len(), 2); // Latin and Cyrillic
     }
 
+    #[test]
+    fn test_svg_honors_color_background_and_padding() {
+        let backend = PureRustBackend::new();
+        let font = Font::new("test", 10.0);
+        let run = backend
+            .segment("ab", &SegmentOptions::default())
+            .unwrap()
+            .remove(0);
+        let shaped = backend.shape(&run, &font).unwrap();
+        let options = RenderOptions {
+            format: RenderFormat::Svg,
+            color: String::from("#ff000080"),
+            background: String::from("#ffffff"),
+            padding: 4,
+            dpi: 144.0,
+            ..RenderOptions::default()
+        };
+
+        let svg = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Svg(svg) => svg,
+            other => panic!("expected SVG output, got {other:?}"),
+        };
+        assert!(
+            svg.contains(r#"viewBox="-4.00 -12.00 20.00 18.00""#),
+            "{svg}"
+        );
+        assert!(svg.contains(r#"width="40.00" height="36.00""#), "{svg}");
+        assert!(svg.contains(r##"fill="#ffffff""##), "{svg}");
+        assert!(
+            svg.contains(r##"<g fill="#ff0000" fill-opacity="0.502">"##),
+            "{svg}"
+        );
+
+        let options = RenderOptions {
+            color: String::from(r#"red"/><script/>"#),
+            ..options
+        };
+        assert!(backend.render(&shaped, &options).is_err());
+    }
+
+    #[test]
+    fn test_raster_colors_use_css_syntax() {
+        let backend = PureRustBackend::new();
+        let font = Font::new("test", 10.0);
+        let run = backend
+            .segment("a", &SegmentOptions::default())
+            .unwrap()
+            .remove(0);
+        let shaped = backend.shape(&run, &font).unwrap();
+        let options = RenderOptions {
+            color: String::from("hsl(240 100% 50%)"),
+            background: String::from("currentColor"),
+            ..RenderOptions::default()
+        };
+
+        let bitmap = match backend.render(&shaped, &options).unwrap() {
+            RenderOutput::Bitmap(bitmap) => bitmap,
+            other => panic!("expected bitmap output, got {other:?}"),
+        };
+        assert!(bitmap.data.chunks_exact(4).all(|px| px == [0, 0, 255, 255]));
+
+        let invalid = RenderOptions {
+            color: String::from("#12345"),
+            ..RenderOptions::default()
+        };
+        match backend.render(&shaped, &invalid) {
+            Err(O4eError::InvalidColor(error)) => assert_eq!(error.input, "#12345"),
+            other => panic!("expected a color error, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_simple_shaping() {
         let backend = PureRustBackend::new();
@@ -5,9 +5,10 @@
 #![cfg(target_os = "windows")]
 
 use o4e_core::{
+    render_metadata,
     types::{AntialiasMode, Direction, RenderFormat},
-    Backend, Bitmap, Font, FontCache, Glyph, O4eError, RenderOptions, RenderOutput, RenderSurface,
-    Result, SegmentOptions, ShapingResult, TextRun,
+    Backend, Font, FontCache, Glyph, O4eError, RenderOptions, RenderOutput, RenderSurface, Result,
+    SegmentOptions, ShapingResult, TextAnchor, TextRun,
 };
 
 use windows::Win32::Graphics::DirectWrite::{
@@ -751,6 +752,7 @@ impl Backend for DirectWriteBackend {
                 },
                 font: Some(resolved_font.clone()),
                 direction: run.direction,
+                lines: Vec::new(),
             };
             return Ok(empty);
         }
@@ -876,6 +878,7 @@ impl Backend for DirectWriteBackend {
             bbox,
             font: Some(resolved_font.clone()),
             direction: run.direction,
+            lines: Vec::new(),
         };
 
         let shaped = Arc::new(shaped);
@@ -888,14 +891,30 @@ impl Backend for DirectWriteBackend {
     }
 
     fn render(&self, shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput> {
-        // Diagnostics removed for simplicity
-        // Check if we have glyphs to render
+        // Vector output never needs the raster pass
+        if options.format == RenderFormat::Svg {
+            let svg_options = options.svg_options();
+            let renderer = o4e_render::SvgRenderer::new(&svg_options);
+            return Ok(RenderOutput::Svg(renderer.render(shaped, &svg_options)?));
+        }
+        if options.format == RenderFormat::Pdf {
+            let renderer = o4e_render::PdfRenderer::new();
+            return Ok(RenderOutput::Pdf(
+                renderer.render(shaped, &options.pdf_options())?,
+            ));
+        }
+
+        // Rasterize in device pixels; the metadata keeps the requested point size
+        let metadata = render_metadata(self.name(), shaped);
+        let (shaped, options) = o4e_core::units::to_device_pixels(shaped, options)?;
+        let (shaped, options) = (shaped.as_ref(), options.as_ref());
+
+        // Text without glyphs still gets the requested canvas, background and format
         if shaped.glyphs.is_empty() {
-            return Ok(RenderOutput::Bitmap(Bitmap {
-                width: 1,
-                height: 1,
-                data: vec![0, 0, 0, 0],
-            }));
+            let text_color = o4e_core::utils::parse_color(&options.color)?;
+            return RenderSurface::empty(options, text_color)?
+                .with_metadata(metadata)
+                .into_render_output(options.format);
         }
 
         // Get the font from ShapingResult
@@ -936,28 +955,15 @@ impl Backend for DirectWriteBackend {
                 .CreateWicBitmapRenderTarget(&bitmap, &render_props)?;
             self.configure_antialias(&render_target, options.antialias)?;
 
-            let (text_r, text_g, text_b, text_a) =
-                o4e_core::utils::parse_color(&options.color).map_err(|e| O4eError::render(e))?;
+            let (text_r, text_g, text_b, text_a) = o4e_core::utils::parse_color(&options.color)?;
 
             render_target.BeginDraw();
-            if options.background != "transparent" {
-                let (bg_r, bg_g, bg_b, bg_a) = o4e_core::utils::parse_color(&options.background)
-                    .map_err(|e| O4eError::render(e))?;
-                let clear_color = D2D1_COLOR_F {
-                    r: bg_r as f32 / 255.0,
-                    g: bg_g as f32 / 255.0,
-                    b: bg_b as f32 / 255.0,
-                    a: bg_a as f32 / 255.0,
-                };
-                render_target.Clear(Some(&clear_color));
-            } else {
-                render_target.Clear(Some(&D2D1_COLOR_F {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 0.0,
-                }));
-            }
+            render_target.Clear(Some(&D2D1_COLOR_F {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.0,
+            }));
 
             let brush = render_target.CreateSolidColorBrush(
                 &D2D1_COLOR_F {
@@ -1015,13 +1021,6 @@ impl Backend for DirectWriteBackend {
                 .map_err(|e| O4eError::render(format!("DrawGlyphRun failed: {e}")))?;
             render_target.EndDraw(None, None)?;
 
-            if options.format == RenderFormat::Svg {
-                let svg_options = o4e_core::types::SvgOptions::default();
-                let renderer = o4e_render::SvgRenderer::new(&svg_options);
-                let svg = renderer.render(&shaped, &svg_options);
-                return Ok(RenderOutput::Svg(svg));
-            }
-
             let mut buffer = vec![0u8; (width * height * 4) as usize];
             let rect = WICRect {
                 X: 0,
@@ -1030,7 +1029,21 @@ impl Backend for DirectWriteBackend {
                 Height: height as i32,
             };
             bitmap.CopyPixels(&rect, width * 4, &mut buffer)?;
-            let surface = RenderSurface::from_bgra(width, height, buffer, true);
+            let mut surface = RenderSurface::from_bgra(width, height, buffer, true)
+                .with_render_options(options, (text_r, text_g, text_b, text_a))?
+                .with_metadata(metadata);
+            if let Some(canvas) = options.canvas {
+                surface = surface.with_canvas(
+                    canvas,
+                    TextAnchor {
+                        origin_x: padding,
+                        baseline_y: padding + ascent,
+                        advance: shaped.advance,
+                        ascent,
+                        descent,
+                    },
+                );
+            }
             surface.into_render_output(options.format)
         }
     }
@@ -6,7 +6,7 @@ use dashmap::DashMap;
 use fontdb::{Database, Family, Query, Source, Stretch, Style, Weight};
 use log::warn;
 use o4e_core::{
-    types::{Font, FontSource, FontStyle},
+    types::{Font, FontSource, FontStretch, FontStyle},
     O4eError, Result,
 };
 use once_cell::sync::OnceCell;
@@ -65,13 +65,18 @@ impl FontDatabase {
                 }
             }
 
-            FontDatabase {
-                db: RwLock::new(db),
-                cache: DashMap::new(),
-            }
+            FontDatabase::new(db)
         })
     }
 
+    fn new(mut db: Database) -> Self {
+        select_generic_families(&mut db);
+        FontDatabase {
+            db: RwLock::new(db),
+            cache: DashMap::new(),
+        }
+    }
+
     /// Resolve a [`Font`] into a concrete handle that backends can load.
     pub fn resolve(&self, font: &Font) -> Result<Arc<FontHandle>> {
         self.resolve_inner(Some(font), &font.source, &font.family)
@@ -106,12 +111,15 @@ impl FontDatabase {
         fallback_name: &str,
     ) -> Result<Arc<FontHandle>> {
         let db = self.db.read();
-        let families_vec: Vec<Family<'_>> = if name == fallback_name {
-            vec![Family::Name(name)]
+        let mut families_vec: Vec<Family<'_>> = if name == fallback_name {
+            vec![family(name)]
         } else {
-            vec![Family::Name(name), Family::Name(fallback_name)]
+            vec![family(name), family(fallback_name)]
         };
-        let (weight, style) = if let Some(font) = font {
+        if let Some(font) = font {
+            families_vec.extend(font.fallbacks.iter().map(|name| family(name)));
+        }
+        let (weight, style, stretch) = if let Some(font) = font {
             (
                 Weight(font.weight),
                 match font.style {
@@ -119,14 +127,25 @@ impl FontDatabase {
                     FontStyle::Italic => Style::Italic,
                     FontStyle::Oblique => Style::Oblique,
                 },
+                match font.stretch {
+                    FontStretch::UltraCondensed => Stretch::UltraCondensed,
+                    FontStretch::ExtraCondensed => Stretch::ExtraCondensed,
+                    FontStretch::Condensed => Stretch::Condensed,
+                    FontStretch::SemiCondensed => Stretch::SemiCondensed,
+                    FontStretch::Normal => Stretch::Normal,
+                    FontStretch::SemiExpanded => Stretch::SemiExpanded,
+                    FontStretch::Expanded => Stretch::Expanded,
+                    FontStretch::ExtraExpanded => Stretch::ExtraExpanded,
+                    FontStretch::UltraExpanded => Stretch::UltraExpanded,
+                },
             )
         } else {
-            (Weight::NORMAL, Style::Normal)
+            (Weight::NORMAL, Style::Normal, Stretch::Normal)
         };
         let query = Query {
             families: &families_vec,
             weight,
-            stretch: Stretch::Normal,
+            stretch,
             style,
         };
 
@@ -221,6 +240,72 @@ impl FontDatabase {
     }
 }
 
+/// A family name, with CSS generic names mapped to fontdb's generic families.
+fn family(name: &str) -> Family<'_> {
+    match name {
+        "serif" => Family::Serif,
+        "sans-serif" => Family::SansSerif,
+        "monospace" => Family::Monospace,
+        "cursive" => Family::Cursive,
+        "fantasy" => Family::Fantasy,
+        name => Family::Name(name),
+    }
+}
+
+/// Point fontdb's generic families at installed fonts: the first present candidate, or for
+/// `monospace` any monospaced face. fontdb's defaults (Times New Roman, Arial, Courier
+/// New...) are often missing outside Windows and macOS.
+fn select_generic_families(db: &mut Database) {
+    const SERIF: [&str; 5] = [
+        "Times New Roman",
+        "Times",
+        "DejaVu Serif",
+        "Liberation Serif",
+        "Noto Serif",
+    ];
+    const SANS_SERIF: [&str; 5] = [
+        "Arial",
+        "Helvetica",
+        "DejaVu Sans",
+        "Liberation Sans",
+        "Noto Sans",
+    ];
+    const MONOSPACE: [&str; 6] = [
+        "Courier New",
+        "Menlo",
+        "Consolas",
+        "DejaVu Sans Mono",
+        "Liberation Mono",
+        "Noto Sans Mono",
+    ];
+
+    let installed = |db: &Database, name: &str| {
+        db.faces()
+            .any(|face| face.families.iter().any(|(family, _)| family == name))
+    };
+    let pick = |db: &Database, candidates: &[&str]| {
+        candidates
+            .iter()
+            .find(|name| installed(db, name))
+            .map(|name| name.to_string())
+    };
+    if let Some(name) = pick(db, &SERIF) {
+        db.set_serif_family(name);
+    }
+    if let Some(name) = pick(db, &SANS_SERIF) {
+        db.set_sans_serif_family(name);
+    }
+    let monospace = pick(db, &MONOSPACE).or_else(|| {
+        db.faces()
+            .find(|face| face.monospaced)
+            .and_then(|face| face.families.first())
+            .map(|(name, _)| name.clone())
+    });
+    if let Some(name) = monospace {
+        db.set_monospace_family(name);
+    }
+}
+
 fn cache_key(face: &fontdb::FaceInfo) -> String {
     match &face.source {
         Source::File(path) => format!("file:{}#{}", path.display(), face.index),
@@ -302,3 +387,31 @@ const THAI_FALLBACKS: [&str; 2] = ["NotoSansThai-Regular", "Thonburi"];
 const CYRILLIC_FALLBACKS: [&str; 3] = ["NotoSans-Regular", "PTSans-Regular", "ArialUnicodeMS"];
 const GREEK_FALLBACKS: [&str; 2] = ["NotoSans-Regular", "ArialUnicodeMS"];
 const DEFAULT_FALLBACKS: [&str; 3] = ["NotoSans-Regular", "DejaVuSans", "ArialUnicodeMS"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_database() -> FontDatabase {
+        let mut db = Database::new();
+        let fonts = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../testdata/fonts");
+        load_font_data_from_path(&mut db, &fonts).unwrap();
+        FontDatabase::new(db)
+    }
+
+    #[test]
+    fn generic_families_resolve_as_the_primary_name() {
+        let fonts = fixture_database();
+        let font = Font::parse_css("16px sans-serif").unwrap();
+        assert_eq!(fonts.resolve(&font).unwrap().family, "Noto Sans");
+
+        // Also as the fallback name of a bare source
+        let source = FontSource::Family("Missing".to_string());
+        let handle = fonts.resolve_source(&source, "sans-serif").unwrap();
+        assert_eq!(handle.family, "Noto Sans");
+
+        assert!(fonts
+            .resolve(&Font::parse_css("16px Missing").unwrap())
+            .is_err());
+    }
+}
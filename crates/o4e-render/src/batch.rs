@@ -3,7 +3,9 @@
 //! Batch rendering implementation for parallel text processing.
 
 use hdrhistogram::Histogram;
-use o4e_core::{Backend, Font, RenderOptions, RenderOutput, Result, SegmentOptions, ShapingResult};
+use o4e_core::{
+    utils::combine_shaped_runs, Backend, Font, RenderOptions, RenderOutput, Result, SegmentOptions,
+};
 use parking_lot::Mutex;
 use rayon::iter::IndexedParallelIterator;
 use rayon::prelude::*;
@@ -13,6 +15,9 @@ use std::sync::{
 };
 use std::time::{Duration, Instant};
 
+use crate::rich::{render_rich, shape_rich, TextSpan};
+use crate::tabs::{shape_with_tabs, TabOptions};
+
 /// Item to be rendered in batch.
 #[derive(Clone)]
 pub struct BatchItem {
@@ -24,6 +29,11 @@ pub struct BatchItem {
     pub segment_options: SegmentOptions,
     /// Render options
     pub render_options: RenderOptions,
+    /// Attributed ranges of `text`; when any are given the item is laid out with
+    /// [`shape_rich`] and rendered with [`render_rich`]
+    pub spans: Vec<TextSpan>,
+    /// Tab stops for tabs in `text`
+    pub tabs: TabOptions,
 }
 
 /// Result from batch rendering.
@@ -98,18 +108,42 @@ impl BatchRenderer {
 
     /// Render a single item.
     fn render_single(&self, item: &BatchItem) -> Result<RenderOutput> {
+        if !item.spans.is_empty() {
+            let layout = shape_rich(
+                self.backend.as_ref(),
+                &item.text,
+                &item.spans,
+                &item.font,
+                &item.segment_options,
+                &item.tabs,
+                |_| {},
+            )?;
+            return render_rich(self.backend.as_ref(), &layout, &item.render_options);
+        }
+        if item.text.contains('\t') {
+            let shaped = shape_with_tabs(
+                self.backend.as_ref(),
+                &item.text,
+                &item.font,
+                &item.tabs,
+                &item.segment_options,
+                |_| {},
+            )?;
+            return self.backend.render(&shaped, &item.render_options);
+        }
+
         // 1. Segment text
         let runs = self.backend.segment(&item.text, &item.segment_options)?;
 
         // 2. Shape each run
         let mut shaped_results = Vec::new();
-        for run in runs {
-            let shaped = self.backend.shape(&run, &item.font)?;
+        for run in &runs {
+            let shaped = self.backend.shape(run, &item.font)?;
             shaped_results.push(shaped);
         }
 
         // 3. Combine shaped results
-        let combined = combine_shaped_results(shaped_results);
+        let combined = combine_shaped_runs(&runs, shaped_results);
 
         // 4. Render
         self.backend.render(&combined, &item.render_options)
@@ -252,64 +286,12 @@ pub struct ProgressUpdate {
     pub p99: Duration,
 }
 
-/// Combine multiple shaped results into one.
-fn combine_shaped_results(results: Vec<ShapingResult>) -> ShapingResult {
-    if results.is_empty() {
-        return ShapingResult {
-            text: String::new(),
-            glyphs: vec![],
-            advance: 0.0,
-            bbox: o4e_core::types::BoundingBox {
-                x: 0.0,
-                y: 0.0,
-                width: 0.0,
-                height: 0.0,
-            },
-            font: None,
-            direction: o4e_core::types::Direction::LeftToRight,
-        };
-    }
-
-    if results.len() == 1 {
-        return results.into_iter().next().unwrap();
-    }
-
-    let mut all_glyphs = Vec::new();
-    let mut total_advance = 0.0;
-    let mut x_offset = 0.0;
-    let mut combined_text = String::new();
-
-    for result in results {
-        if !result.text.is_empty() {
-            combined_text.push_str(&result.text);
-        }
-        // Offset glyphs by accumulated advance
-        for mut glyph in result.glyphs {
-            glyph.x += x_offset;
-            all_glyphs.push(glyph);
-        }
-        total_advance += result.advance;
-        x_offset += result.advance;
-    }
-
-    let bbox = o4e_core::utils::calculate_bbox(&all_glyphs);
-
-    ShapingResult {
-        text: combined_text,
-        glyphs: all_glyphs,
-        advance: total_advance,
-        bbox,
-        font: None,
-        direction: o4e_core::types::Direction::LeftToRight,
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use o4e_core::{
         types::{BoundingBox, Direction, Glyph, TextRun},
-        Result,
+        Result, ShapingResult,
     };
     use std::sync::atomic::Ordering;
 
@@ -359,6 +341,7 @@ mod tests {
                 bbox,
                 font: Some(font.clone()),
                 direction: run.direction,
+                lines: Vec::new(),
             })
         }
 
@@ -383,13 +366,15 @@ mod tests {
                 font: font.clone(),
                 segment_options: segment_options.clone(),
                 render_options: render_options.clone(),
+                spans: Vec::new(),
+                tabs: TabOptions::default(),
             })
             .collect()
     }
 
     #[test]
     fn test_combine_empty_results() {
-        let combined = combine_shaped_results(vec![]);
+        let combined = combine_shaped_runs(&[], vec![]);
         assert!(combined.glyphs.is_empty());
         assert_eq!(combined.advance, 0.0);
         assert!(combined.text.is_empty());
@@ -409,9 +394,18 @@ mod tests {
             },
             font: None,
             direction: o4e_core::types::Direction::LeftToRight,
+            lines: Vec::new(),
+        };
+        let run = TextRun {
+            text: "abc".into(),
+            range: (0, 3),
+            script: "Latn".into(),
+            language: "en".into(),
+            direction: Direction::LeftToRight,
+            font: None,
         };
 
-        let combined = combine_shaped_results(vec![result.clone()]);
+        let combined = combine_shaped_runs(&[run], vec![result.clone()]);
         assert_eq!(combined.advance, result.advance);
         assert_eq!(combined.text, "abc".to_string());
     }
@@ -475,7 +469,11 @@ mod tests {
         };
 
         let shaped = renderer.shape(&run, &font).unwrap();
-        let combined = super::combine_shaped_results(vec![shaped.clone(), shaped]);
+        let next = TextRun {
+            range: (3, 6),
+            ..run.clone()
+        };
+        let combined = combine_shaped_runs(&[run, next], vec![shaped.clone(), shaped]);
         let mut last_x = -f32::INFINITY;
         for glyph in combined.glyphs {
             assert!(
@@ -0,0 +1,589 @@
+// this_file: crates/o4e-render/src/decoration.rs
+
+//! Underline, overline and line-through geometry.
+//!
+//! Decorations are built as fillable outlines in output space (y down, pen origin on the
+//! baseline), so the raster and SVG renderers draw the same shapes. With glyph placements
+//! (transformed or path-following text) each glyph's share of a line is mapped with that
+//! glyph, so lines bend along a text path.
+
+use std::f64::consts::TAU;
+use std::ops::Range;
+
+use kurbo::{Affine, BezPath, Circle, PathEl, Point, Rect, Shape, StrokeOpts};
+use o4e_core::{Decoration, DecorationLine, DecorationStyle, ShapingResult};
+use ttf_parser::{Face, GlyphId};
+
+use crate::layout::line_baselines;
+use crate::outlines::glyph_bez_path;
+
+/// Maximum distance between flattened curves and the exact geometry, in output units.
+const TOLERANCE: f64 = 0.01;
+
+/// Thinnest line drawn, in output units; keeps the dash, dot and wave counts bounded.
+const MIN_THICKNESS: f64 = 0.1;
+
+/// Font decoration metrics at one size, in output units (y down from the baseline).
+///
+/// Positions are line centers at the font's thickness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecorationMetrics {
+    pub underline_position: f64,
+    pub underline_thickness: f64,
+    pub overline_position: f64,
+    pub strikeout_position: f64,
+    pub strikeout_thickness: f64,
+}
+
+impl DecorationMetrics {
+    /// Metrics of `face` at `scale` output units per font unit.
+    ///
+    /// The `post` underline and OS/2 strikeout positions give the top edge of their line.
+    /// Fonts without them get an underline a tenth of an em below the baseline, a strikeout
+    /// at half the x-height and lines a twentieth of an em thick.
+    pub fn from_face(face: &Face<'_>, scale: f32) -> Self {
+        let scale = f64::from(scale);
+        let em = f64::from(face.units_per_em());
+        let thickness = |metrics: Option<ttf_parser::LineMetrics>| {
+            metrics
+                .map(|metrics| f64::from(metrics.thickness))
+                .filter(|&thickness| thickness > 0.0)
+                .unwrap_or(em / 20.0)
+        };
+        let underline_thickness = thickness(face.underline_metrics());
+        let underline_top = face
+            .underline_metrics()
+            .map_or(-em / 10.0, |metrics| f64::from(metrics.position));
+        let strikeout_thickness = thickness(face.strikeout_metrics());
+        let strikeout_top = face
+            .strikeout_metrics()
+            .map(|metrics| f64::from(metrics.position))
+            .filter(|&position| position > 0.0)
+            .unwrap_or_else(|| {
+                let x_height = face.x_height().map_or(em / 2.0, f64::from);
+                (x_height + strikeout_thickness) / 2.0
+            });
+
+        Self {
+            underline_position: (underline_thickness / 2.0 - underline_top) * scale,
+            underline_thickness: underline_thickness * scale,
+            overline_position: (underline_thickness / 2.0 - f64::from(face.ascender())) * scale,
+            strikeout_position: (strikeout_thickness / 2.0 - strikeout_top) * scale,
+            strikeout_thickness: strikeout_thickness * scale,
+        }
+    }
+}
+
+/// Fillable outlines (nonzero winding) of `decorations`, one per decoration, in output space.
+///
+/// Lines run across the pen advance of the run; `scale` is output units per font unit.
+/// Skip-ink lines break where the glyph outlines of `face` cross them, with a gap of one line
+/// thickness on each side.
+pub fn decoration_paths(
+    face: &Face<'_>,
+    scale: f32,
+    shaped: &ShapingResult,
+    decorations: &[Decoration],
+    placements: Option<&[Option<Affine>]>,
+) -> Vec<BezPath> {
+    if decorations.is_empty() || shaped.glyphs.is_empty() {
+        return vec![BezPath::new(); decorations.len()];
+    }
+    let metrics = DecorationMetrics::from_face(face, scale);
+    let lines = lines(shaped);
+    // Glyph ink in pen space, only extracted when some line skips it
+    let ink: Vec<Option<BezPath>> = if decorations.iter().any(skips_ink) {
+        shaped
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                let path = glyph_bez_path(face, GlyphId(u16::try_from(glyph.id).ok()?), scale)?;
+                Some(Affine::translate((f64::from(glyph.x), -f64::from(glyph.y))) * path)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    decorations
+        .iter()
+        .map(|decoration| {
+            let mut path = BezPath::new();
+            for (baseline, range) in &lines {
+                let glyphs = &shaped.glyphs[range.clone()];
+                let start = glyphs
+                    .iter()
+                    .map(|glyph| f64::from(glyph.x))
+                    .fold(f64::INFINITY, f64::min);
+                let end = glyphs
+                    .iter()
+                    .map(|glyph| f64::from(glyph.x + glyph.advance))
+                    .fold(f64::NEG_INFINITY, f64::max);
+                // Pen space of this line's baseline
+                let lift = Affine::translate((0.0, -baseline));
+                let line = LineGeometry::new(decoration, &metrics, start);
+                let mut spans = vec![(start, end)];
+                if skips_ink(decoration) {
+                    let (top, bottom) = line.extent();
+                    let (top, bottom) = (top - baseline, bottom - baseline);
+                    for interval in ink[range.clone()].iter().flatten().filter_map(|path| {
+                        ink_interval(path, top, bottom)
+                            .map(|(x0, x1)| (x0 - line.thickness, x1 + line.thickness))
+                    }) {
+                        spans = subtract(spans, interval);
+                    }
+                }
+
+                match placements {
+                    None => {
+                        for (x0, x1) in spans {
+                            line.append(&mut path, x0, x1, lift);
+                        }
+                    }
+                    Some(placements) => {
+                        for (glyph, placement) in glyphs.iter().zip(&placements[range.clone()]) {
+                            let Some(placement) = placement else {
+                                continue;
+                            };
+                            // From pen space into the glyph's placed frame
+                            let (gx, gy) = (f64::from(glyph.x), f64::from(glyph.y));
+                            let transform = *placement * Affine::translate((-gx, gy)) * lift;
+                            let cell = (gx, gx + f64::from(glyph.advance));
+                            for &(x0, x1) in &spans {
+                                let (x0, x1) = (x0.max(cell.0), x1.min(cell.1));
+                                if x1 > x0 {
+                                    line.append(&mut path, x0, x1, transform);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            path
+        })
+        .collect()
+}
+
+/// Runs of glyphs on the same baseline (stacked paragraph lines), with that baseline's
+/// offset (y up).
+fn lines(shaped: &ShapingResult) -> Vec<(f64, Range<usize>)> {
+    let mut lines: Vec<(f64, Range<usize>)> = Vec::new();
+    for (index, baseline) in line_baselines(shaped).into_iter().enumerate() {
+        let baseline = f64::from(baseline);
+        match lines.last_mut() {
+            Some((current, range)) if *current == baseline => range.end = index + 1,
+            _ => lines.push((baseline, index..index + 1)),
+        }
+    }
+    lines
+}
+
+fn skips_ink(decoration: &Decoration) -> bool {
+    decoration.skip_ink && decoration.line != DecorationLine::LineThrough
+}
+
+/// One decoration's style and vertical placement.
+struct LineGeometry {
+    style: DecorationStyle,
+    /// Center of the (first) line
+    center: f64,
+    thickness: f64,
+    /// Offset of the second line of a double decoration
+    double_offset: f64,
+    /// Pen position the dash, dot and wave patterns are phased from
+    origin: f64,
+}
+
+impl LineGeometry {
+    fn new(decoration: &Decoration, metrics: &DecorationMetrics, origin: f64) -> Self {
+        let (center, font_thickness) = match decoration.line {
+            DecorationLine::Underline => (metrics.underline_position, metrics.underline_thickness),
+            DecorationLine::Overline => (metrics.overline_position, metrics.underline_thickness),
+            DecorationLine::LineThrough => {
+                (metrics.strikeout_position, metrics.strikeout_thickness)
+            }
+        };
+        let thickness = decoration
+            .thickness
+            .map(f64::from)
+            .filter(|&thickness| thickness > 0.0)
+            .unwrap_or(font_thickness)
+            .max(MIN_THICKNESS);
+        // The second line moves away from the text; a double line-through straddles the
+        // strikeout position
+        let (center, double_offset) = match (decoration.style, decoration.line) {
+            (DecorationStyle::Double, DecorationLine::Underline) => (center, 2.0 * thickness),
+            (DecorationStyle::Double, DecorationLine::Overline) => (center, -2.0 * thickness),
+            (DecorationStyle::Double, DecorationLine::LineThrough) => {
+                (center - thickness, 2.0 * thickness)
+            }
+            _ => (center, 0.0),
+        };
+        Self {
+            style: decoration.style,
+            center,
+            thickness,
+            double_offset,
+            origin,
+        }
+    }
+
+    /// Top and bottom of the area the line covers.
+    fn extent(&self) -> (f64, f64) {
+        let half = self.thickness / 2.0;
+        match self.style {
+            DecorationStyle::Double => {
+                let second = self.center + self.double_offset;
+                (
+                    self.center.min(second) - half,
+                    self.center.max(second) + half,
+                )
+            }
+            DecorationStyle::Wavy => (
+                self.center - self.thickness - half,
+                self.center + self.thickness + half,
+            ),
+            _ => (self.center - half, self.center + half),
+        }
+    }
+
+    /// Append the line from `x0` to `x1` (pen space), mapped by `transform`.
+    fn append(&self, path: &mut BezPath, x0: f64, x1: f64, transform: Affine) {
+        let t = self.thickness;
+        let half = t / 2.0;
+        let mut push = |shape: BezPath| path.extend(transform * shape);
+        let bar = |center: f64, x0: f64, x1: f64| {
+            Rect::new(x0, center - half, x1, center + half).to_path(TOLERANCE)
+        };
+        match self.style {
+            DecorationStyle::Solid => push(bar(self.center, x0, x1)),
+            DecorationStyle::Double => {
+                push(bar(self.center, x0, x1));
+                push(bar(self.center + self.double_offset, x0, x1));
+            }
+            DecorationStyle::Dashed => {
+                // Dashes of three thicknesses with gaps of two
+                let period = 5.0 * t;
+                let first = ((x0 - self.origin) / period).floor() as i64;
+                let last = ((x1 - self.origin) / period).ceil() as i64;
+                for index in first..=last {
+                    let dash_start = self.origin + index as f64 * period;
+                    let (a, b) = (dash_start.max(x0), (dash_start + 3.0 * t).min(x1));
+                    if b > a {
+                        push(bar(self.center, a, b));
+                    }
+                }
+            }
+            DecorationStyle::Dotted => {
+                // Whole dots only, one thickness apart
+                let period = 2.0 * t;
+                let first = ((x0 - self.origin) / period).ceil() as i64;
+                let last = ((x1 - self.origin - t) / period).floor() as i64;
+                for index in first..=last {
+                    let center =
+                        Point::new(self.origin + index as f64 * period + half, self.center);
+                    push(Circle::new(center, half).to_path(TOLERANCE));
+                }
+            }
+            DecorationStyle::Wavy => {
+                // A sine wave with an amplitude of one thickness and a period of four
+                let wavelength = 4.0 * t;
+                let steps = (((x1 - x0) / wavelength) * 16.0).ceil().max(1.0) as usize;
+                let mut wave = BezPath::new();
+                for step in 0..=steps {
+                    let x = x0 + (x1 - x0) * step as f64 / steps as f64;
+                    let y = self.center + t * (TAU * (x - self.origin) / wavelength).sin();
+                    if step == 0 {
+                        wave.move_to((x, y));
+                    } else {
+                        wave.line_to((x, y));
+                    }
+                }
+                push(kurbo::stroke(
+                    wave,
+                    &kurbo::Stroke::new(t),
+                    &StrokeOpts::default(),
+                    TOLERANCE,
+                ));
+            }
+        }
+    }
+}
+
+/// Horizontal extent of the parts of `path` between `top` and `bottom`.
+fn ink_interval(path: &BezPath, top: f64, bottom: f64) -> Option<(f64, f64)> {
+    let mut interval: Option<(f64, f64)> = None;
+    let mut include = |x: f64| {
+        interval = Some(interval.map_or((x, x), |(x0, x1)| (x0.min(x), x1.max(x))));
+    };
+    let mut start = Point::ZERO;
+    let mut last = Point::ZERO;
+    kurbo::flatten(path, TOLERANCE, |element| {
+        let (from, to) = match element {
+            PathEl::MoveTo(point) => {
+                start = point;
+                last = point;
+                return;
+            }
+            PathEl::LineTo(point) => (last, point),
+            PathEl::ClosePath => (last, start),
+            // Flattening only yields lines
+            PathEl::QuadTo(..) | PathEl::CurveTo(..) => return,
+        };
+        last = to;
+        // Clip the segment to the band
+        let (low, high) = (from.y.min(to.y), from.y.max(to.y));
+        if high < top || low > bottom {
+            return;
+        }
+        let at = |y: f64| {
+            if to.y == from.y {
+                from.x
+            } else {
+                from.x + (to.x - from.x) * (y - from.y) / (to.y - from.y)
+            }
+        };
+        include(at(low.max(top)));
+        include(at(high.min(bottom)));
+    });
+    interval
+}
+
+/// `spans` without `interval`.
+fn subtract(spans: Vec<(f64, f64)>, (cut0, cut1): (f64, f64)) -> Vec<(f64, f64)> {
+    let mut kept = Vec::with_capacity(spans.len() + 1);
+    for (x0, x1) in spans {
+        if cut1 <= x0 || cut0 >= x1 {
+            kept.push((x0, x1));
+            continue;
+        }
+        if cut0 > x0 {
+            kept.push((x0, cut0));
+        }
+        if cut1 < x1 {
+            kept.push((cut1, x1));
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::face_and_scale;
+    use crate::test_support::{fixture_font, nominal_shaped};
+    use o4e_core::ShapedLine;
+    use owned_ttf_parser::AsFaceRef;
+
+    fn decoration(line: DecorationLine, style: DecorationStyle) -> Decoration {
+        Decoration {
+            style,
+            ..Decoration::new(line)
+        }
+    }
+
+    #[test]
+    fn metrics_come_from_post_and_os2() {
+        let (face, _) = face_and_scale(&fixture_font(100.0)).unwrap();
+        let face = face.as_face_ref();
+        let metrics = DecorationMetrics::from_face(face, 0.1);
+        let underline = face.underline_metrics().unwrap();
+        let strikeout = face.strikeout_metrics().unwrap();
+        assert!(
+            metrics.underline_position > 0.0,
+            "underline below the baseline"
+        );
+        assert!((metrics.underline_thickness - f64::from(underline.thickness) * 0.1).abs() < 1e-6);
+        assert!(
+            (metrics.strikeout_position
+                - (f64::from(strikeout.thickness) / 2.0 - f64::from(strikeout.position)) * 0.1)
+                .abs()
+                < 1e-6
+        );
+        assert!(metrics.overline_position < metrics.strikeout_position);
+    }
+
+    #[test]
+    fn solid_lines_span_the_advance() {
+        let (face, _) = face_and_scale(&fixture_font(100.0)).unwrap();
+        let face = face.as_face_ref();
+        let shaped = nominal_shaped("Hi", &fixture_font(100.0));
+        let paths = decoration_paths(
+            face,
+            0.1,
+            &shaped,
+            &[
+                decoration(DecorationLine::Underline, DecorationStyle::Solid),
+                decoration(DecorationLine::LineThrough, DecorationStyle::Double),
+            ],
+            None,
+        );
+        let underline = paths[0].bounding_box();
+        assert!(underline.x0.abs() < 1e-9);
+        assert!((underline.x1 - f64::from(shaped.advance)).abs() < 1e-9);
+        assert!(underline.y0 > 0.0);
+
+        let strike = paths[1].bounding_box();
+        let metrics = DecorationMetrics::from_face(face, 0.1);
+        let thickness = metrics.strikeout_thickness;
+        assert!((strike.height() - 3.0 * thickness).abs() < 1e-6);
+        assert!((strike.center().y - metrics.strikeout_position).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stacked_lines_get_their_own_lines() {
+        let (face, _) = face_and_scale(&fixture_font(100.0)).unwrap();
+        let face = face.as_face_ref();
+        let mut shaped = nominal_shaped("HiHi", &fixture_font(100.0));
+        let first_line = shaped.glyphs[2].x;
+        for glyph in &mut shaped.glyphs[2..] {
+            glyph.x -= first_line;
+            glyph.y -= 120.0;
+        }
+        shaped.lines = vec![
+            ShapedLine {
+                first_glyph: 0,
+                baseline: 0.0,
+            },
+            ShapedLine {
+                first_glyph: 2,
+                baseline: -120.0,
+            },
+        ];
+        let underline = decoration(DecorationLine::Underline, DecorationStyle::Solid);
+        let path = &decoration_paths(face, 0.1, &shaped, &[underline], None)[0];
+        let metrics = DecorationMetrics::from_face(face, 0.1);
+        let bounds = path.bounding_box();
+        assert!((bounds.x1 - f64::from(first_line)).abs() < 1e-6);
+        let bottom = 120.0 + metrics.underline_position + metrics.underline_thickness / 2.0;
+        assert!((bounds.y1 - bottom).abs() < 1e-6, "{bounds:?}");
+        assert_eq!(
+            path.elements()
+                .iter()
+                .filter(|el| matches!(el, PathEl::MoveTo(_)))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn skip_ink_breaks_underlines_at_descenders() {
+        let (face, _) = face_and_scale(&fixture_font(100.0)).unwrap();
+        let face = face.as_face_ref();
+        let shaped = nominal_shaped("xgx", &fixture_font(100.0));
+        let [skipping, solid] = [true, false].map(|skip_ink| {
+            let decoration = Decoration {
+                skip_ink,
+                ..Decoration::new(DecorationLine::Underline)
+            };
+            decoration_paths(face, 0.1, &shaped, &[decoration], None).remove(0)
+        });
+        let pieces = |path: &BezPath| {
+            path.elements()
+                .iter()
+                .filter(|element| matches!(element, PathEl::MoveTo(_)))
+                .count()
+        };
+        assert_eq!(pieces(&solid), 1);
+        assert_eq!(pieces(&skipping), 2, "the g descender splits the line");
+        // Both halves still reach the ends of the run
+        assert_eq!(skipping.bounding_box(), solid.bounding_box());
+
+        // Line-throughs are never broken
+        let through = decoration_paths(
+            face,
+            0.1,
+            &shaped,
+            &[Decoration::new(DecorationLine::LineThrough)],
+            None,
+        );
+        assert_eq!(pieces(&through[0]), 1);
+    }
+
+    #[test]
+    fn patterned_styles_stay_inside_the_line() {
+        let (face, _) = face_and_scale(&fixture_font(100.0)).unwrap();
+        let face = face.as_face_ref();
+        let shaped = nominal_shaped("HHHH", &fixture_font(100.0));
+        let solid = decoration_paths(
+            face,
+            0.1,
+            &shaped,
+            &[decoration(
+                DecorationLine::Underline,
+                DecorationStyle::Solid,
+            )],
+            None,
+        )
+        .remove(0)
+        .bounding_box();
+        for style in [DecorationStyle::Dashed, DecorationStyle::Dotted] {
+            let path = decoration_paths(
+                face,
+                0.1,
+                &shaped,
+                &[decoration(DecorationLine::Underline, style)],
+                None,
+            )
+            .remove(0);
+            let bounds = path.bounding_box();
+            assert!(bounds.x0 >= solid.x0 - 1e-6 && bounds.x1 <= solid.x1 + 1e-6);
+            assert!((bounds.y0 - solid.y0).abs() < 1e-3 && (bounds.y1 - solid.y1).abs() < 1e-3);
+            assert!(
+                path.area().abs() < solid.area() * 0.75,
+                "{style:?} leaves gaps"
+            );
+        }
+        let wavy = decoration_paths(
+            face,
+            0.1,
+            &shaped,
+            &[decoration(DecorationLine::Underline, DecorationStyle::Wavy)],
+            None,
+        )
+        .remove(0)
+        .bounding_box();
+        assert!(wavy.height() > solid.height() * 2.5);
+    }
+
+    #[test]
+    fn hairline_patterns_are_clamped() {
+        let (face, _) = face_and_scale(&fixture_font(100.0)).unwrap();
+        let face = face.as_face_ref();
+        let shaped = nominal_shaped("HHHH", &fixture_font(100.0));
+        for style in [
+            DecorationStyle::Dashed,
+            DecorationStyle::Dotted,
+            DecorationStyle::Wavy,
+        ] {
+            let [hairline, thinnest] = [1e-6, MIN_THICKNESS as f32].map(|thickness| {
+                let decoration = Decoration {
+                    thickness: Some(thickness),
+                    ..decoration(DecorationLine::Underline, style)
+                };
+                decoration_paths(face, 0.1, &shaped, &[decoration], None).remove(0)
+            });
+            assert_eq!(hairline.elements().len(), thinnest.elements().len());
+            assert!(hairline.elements().len() < 100_000, "{style:?}");
+        }
+    }
+
+    #[test]
+    fn placed_lines_follow_their_glyphs() {
+        let (face, _) = face_and_scale(&fixture_font(100.0)).unwrap();
+        let face = face.as_face_ref();
+        let shaped = nominal_shaped("HH", &fixture_font(100.0));
+        let shift = Affine::translate((0.0, 500.0));
+        let placements: Vec<Option<Affine>> = shaped
+            .glyphs
+            .iter()
+            .map(|glyph| Some(shift * Affine::translate((f64::from(glyph.x), 0.0))))
+            .collect();
+        let decorations = [Decoration::new(DecorationLine::Underline)];
+        let straight = decoration_paths(face, 0.1, &shaped, &decorations, None).remove(0);
+        let placed =
+            decoration_paths(face, 0.1, &shaped, &decorations, Some(&placements)).remove(0);
+        let (a, b) = (straight.bounding_box(), placed.bounding_box());
+        assert!((b.y0 - a.y0 - 500.0).abs() < 1e-9 && (b.x1 - a.x1).abs() < 1e-9);
+    }
+}
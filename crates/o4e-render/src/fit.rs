@@ -0,0 +1,364 @@
+// this_file: crates/o4e-render/src/fit.rs
+
+//! Fitting text to a box: the largest font size between two bounds at which it fits.
+//!
+//! Shaping scales linearly with the font size, so the text is shaped once at the maximum
+//! size and each candidate size is measured by scaling those advances and the font's line
+//! metrics, without rasterizing. Only the chosen size is laid out again.
+
+use o4e_core::{Backend, Font, O4eError, Result, SegmentOptions, TextRun};
+use o4e_unicode::Hyphenator;
+use owned_ttf_parser::AsFaceRef;
+
+use crate::paragraph::{
+    break_paragraph, font_line_height, LineShaper, Measured, Paragraph, ParagraphOptions,
+};
+use crate::svg::face_and_scale;
+use crate::tabs::TabOptions;
+
+/// The box and the sizes to try.
+#[derive(Debug, Clone)]
+pub struct FitOptions {
+    /// Box width in points; `None` leaves the width free
+    pub width: Option<f32>,
+    /// Box height in points; `None` leaves the height free
+    pub height: Option<f32>,
+    /// Smallest font size to try, in points
+    pub min_size: f32,
+    /// Largest font size to try, in points
+    pub max_size: f32,
+    /// Wrap lines to the box width, instead of breaking only at hard line breaks
+    pub wrap: bool,
+    /// Most lines allowed
+    pub max_lines: Option<usize>,
+    /// Baseline-to-baseline distance as a multiple of the font size; `None` uses the font's
+    /// [`Font::line_height`](o4e_core::Font::line_height) or its ascender, descender and
+    /// line gap
+    pub line_spacing: Option<f32>,
+    /// Patterns for hyphenation when wrapping
+    pub hyphenator: Option<Hyphenator>,
+    /// Sizes are searched to within this many points
+    pub precision: f32,
+    /// Segmentation before shaping
+    pub segment: SegmentOptions,
+    /// Tab stops, in points at the fitted size
+    pub tabs: TabOptions,
+}
+
+impl Default for FitOptions {
+    fn default() -> Self {
+        Self {
+            width: None,
+            height: None,
+            min_size: 6.0,
+            max_size: 72.0,
+            wrap: false,
+            max_lines: None,
+            line_spacing: None,
+            hyphenator: None,
+            precision: 0.1,
+            segment: SegmentOptions::default(),
+            tabs: TabOptions::default(),
+        }
+    }
+}
+
+/// The size found by [`fit_text`] and the text laid out at it.
+#[derive(Debug, Clone)]
+pub struct Fit {
+    /// Font size in points
+    pub size: f32,
+    /// Whether the text fits at `size`; when even `min_size` overflows, `size` is `min_size`
+    pub fits: bool,
+    /// Advance of the widest line, in points
+    pub width: f32,
+    /// From the first line's ascender to the last line's descender, in points
+    pub height: f32,
+    /// The text at `size`, wrapped when [`FitOptions::wrap`] is set
+    pub paragraph: Paragraph,
+}
+
+/// Find the largest size between `options.min_size` and `options.max_size` at which `text`
+/// in `font` fits the box, and lay it out at that size.
+///
+/// The size of `font` itself is ignored. `adjust_run` sees every run before it is shaped,
+/// for script, language or direction overrides.
+pub fn fit_text(
+    backend: &dyn Backend,
+    text: &str,
+    font: &Font,
+    options: &FitOptions,
+    adjust_run: impl Fn(&mut TextRun),
+) -> Result<Fit> {
+    validate(options)?;
+
+    let reference = Font {
+        size: options.max_size,
+        ..font.clone()
+    };
+    let shaper = LineShaper {
+        backend,
+        font: &reference,
+        segment: &options.segment,
+        adjust_run: &adjust_run,
+    };
+    let hyphenator = options.hyphenator.as_ref().filter(|_| options.wrap);
+    let mut measured = Measured::new(&shaper, text, hyphenator, true, &options.tabs)?;
+    let line_box = face_and_scale(&reference)
+        .map(|(face, scale)| {
+            let face = face.as_face_ref();
+            (face.ascender() - face.descender()) as f32 * scale
+        })
+        .filter(|height| *height > 0.0)
+        .unwrap_or(reference.size * 1.2);
+    let line_height = match options.line_spacing {
+        Some(spacing) => spacing * reference.size,
+        None => font_line_height(&reference),
+    };
+
+    // Lines, width and height at `size`, scaled from the reference measurements
+    let mut extent = |size: f32| {
+        let scale = size / reference.size;
+        // Explicit stops stay put in points while the text scales
+        measured.scale_tabs(scale.recip());
+        let max_width = options.width.filter(|_| options.wrap).map(|w| w / scale);
+        let lines = measured.lines(max_width);
+        let width = lines
+            .iter()
+            .map(|&(start, end)| measured.width(start, end))
+            .fold(0.0, f32::max);
+        let height = match lines.len() {
+            0 => 0.0,
+            count => line_box + (count - 1) as f32 * line_height,
+        };
+        (lines.len(), width * scale, height * scale)
+    };
+    let within = |count: usize, width: f32, height: f32| {
+        options.width.is_none_or(|max| width <= max)
+            && options.height.is_none_or(|max| height <= max)
+            && options.max_lines.is_none_or(|max| count <= max)
+    };
+    let mut fits = |size: f32| {
+        let (count, width, height) = extent(size);
+        within(count, width, height)
+    };
+
+    let size = if fits(options.max_size) {
+        options.max_size
+    } else if !fits(options.min_size) {
+        options.min_size
+    } else {
+        let (mut low, mut high) = (options.min_size, options.max_size);
+        while high - low > options.precision {
+            let middle = (low + high) / 2.0;
+            if fits(middle) {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    };
+
+    let sized = Font {
+        size,
+        ..font.clone()
+    };
+    let paragraph = break_paragraph(
+        backend,
+        text,
+        &sized,
+        &ParagraphOptions {
+            max_width: options.width.filter(|_| options.wrap),
+            line_height: Some(line_height * size / reference.size),
+            hyphenator: hyphenator.cloned(),
+            segment: options.segment.clone(),
+            tabs: options.tabs.clone(),
+        },
+        adjust_run,
+    )?;
+    // Measured again from the lines as laid out, which may differ from the scaled estimate
+    let width = paragraph.width();
+    let height = match paragraph.lines.len() {
+        0 => 0.0,
+        count => line_box * size / reference.size + (count - 1) as f32 * paragraph.line_height,
+    };
+    let fits = within(paragraph.lines.len(), width, height);
+    Ok(Fit {
+        size,
+        fits,
+        width,
+        height,
+        paragraph,
+    })
+}
+
+fn validate(options: &FitOptions) -> Result<()> {
+    let invalid = |name: &str, value: f32| O4eError::InvalidParameter {
+        name: name.to_string(),
+        value: value.to_string(),
+    };
+    let positive = |value: f32| value.is_finite() && value > 0.0;
+    if !positive(options.min_size) {
+        return Err(invalid("min_size", options.min_size));
+    }
+    if !(positive(options.max_size) && options.max_size >= options.min_size) {
+        return Err(invalid("max_size", options.max_size));
+    }
+    if !positive(options.precision) {
+        return Err(invalid("precision", options.precision));
+    }
+    if let Some(spacing) = options.line_spacing.filter(|&spacing| !positive(spacing)) {
+        return Err(invalid("line_spacing", spacing));
+    }
+    for (name, value) in [("width", options.width), ("height", options.height)] {
+        if let Some(value) = value.filter(|&value| !positive(value)) {
+            return Err(invalid(name, value));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MonoBackend;
+
+    fn fit(text: &str, options: &FitOptions) -> Fit {
+        fit_text(&MonoBackend, text, &Font::new("Test", 1.0), options, |_| {}).unwrap()
+    }
+
+    #[test]
+    fn largest_size_fits_width_and_height() {
+        // Six one-em characters; without font data a line box is 1.2 em
+        let options = FitOptions {
+            width: Some(60.0),
+            ..FitOptions::default()
+        };
+        let result = fit("fit me", &options);
+        assert!(result.fits);
+        assert!(result.size <= 10.0 && result.size > 9.9, "{}", result.size);
+        assert!(result.width <= 60.0);
+        assert_eq!(result.paragraph.font.size, result.size);
+
+        let options = FitOptions {
+            width: Some(600.0),
+            height: Some(24.0),
+            ..FitOptions::default()
+        };
+        let result = fit("fit me", &options);
+        assert!(result.size <= 20.0 && result.size > 19.9, "{}", result.size);
+
+        // Everything fits at the maximum; nothing fits below the minimum
+        assert_eq!(fit("fit me", &FitOptions::default()).size, 72.0);
+        let options = FitOptions {
+            width: Some(5.0),
+            ..FitOptions::default()
+        };
+        let result = fit("fit me", &options);
+        assert!(!result.fits);
+        assert_eq!(result.size, 6.0);
+    }
+
+    #[test]
+    fn wrapping_trades_lines_for_size() {
+        let mut options = FitOptions {
+            width: Some(50.0),
+            height: Some(100.0),
+            wrap: true,
+            ..FitOptions::default()
+        };
+        // One word per line: four lines of 1.2 em in 100 points
+        let result = fit("aa bb cc dd", &options);
+        assert_eq!(result.paragraph.lines.len(), 4);
+        assert!(
+            result.size <= 100.0 / 4.8 && result.size > 20.7,
+            "{}",
+            result.size
+        );
+        assert!(result.height <= 100.0);
+
+        // Two lines need "aa bb" (five em) within 50 points
+        options.max_lines = Some(2);
+        let result = fit("aa bb cc dd", &options);
+        assert_eq!(result.paragraph.lines.len(), 2);
+        assert!(result.size <= 10.0 && result.size > 9.9, "{}", result.size);
+
+        options.min_size = 0.0;
+        assert!(fit_text(&MonoBackend, "x", &Font::new("Test", 1.0), &options, |_| {}).is_err());
+    }
+
+    #[test]
+    fn tab_stops_stay_in_points_at_every_size() {
+        // "a" then "b" at the stop 40 points in: 40 points plus one em wide at any size
+        let options = FitOptions {
+            width: Some(55.0),
+            tabs: TabOptions {
+                interval: Some(40.0),
+                ..TabOptions::default()
+            },
+            ..FitOptions::default()
+        };
+        let result = fit("a\tb", &options);
+        assert!(result.fits);
+        assert!(result.size <= 15.0 && result.size > 14.9, "{}", result.size);
+        assert!(
+            (result.width - 40.0 - result.size).abs() < 1e-3,
+            "{}",
+            result.width
+        );
+    }
+
+    /// [`MonoBackend`] with advances rounded to whole points, so they stop scaling linearly.
+    struct RoundingBackend;
+
+    impl Backend for RoundingBackend {
+        fn segment(&self, text: &str, options: &SegmentOptions) -> Result<Vec<TextRun>> {
+            MonoBackend.segment(text, options)
+        }
+
+        fn shape(&self, run: &TextRun, font: &Font) -> Result<o4e_core::ShapingResult> {
+            let rounded = Font {
+                size: font.size.round(),
+                ..font.clone()
+            };
+            MonoBackend.shape(run, &rounded)
+        }
+
+        fn render(
+            &self,
+            shaped: &o4e_core::ShapingResult,
+            options: &o4e_core::RenderOptions,
+        ) -> Result<o4e_core::RenderOutput> {
+            MonoBackend.render(shaped, options)
+        }
+
+        fn name(&self) -> &str {
+            "rounding"
+        }
+
+        fn clear_cache(&self) {}
+    }
+
+    #[test]
+    fn result_is_measured_from_the_final_lines() {
+        let options = FitOptions {
+            width: Some(57.0),
+            ..FitOptions::default()
+        };
+        let result = fit_text(
+            &RoundingBackend,
+            "fit me",
+            &Font::new("Test", 1.0),
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        // The estimate scales the 72 point advances; the final advances round down to 9
+        assert!(result.size < 9.5, "{}", result.size);
+        assert_eq!(result.width, result.paragraph.width());
+        assert_eq!(result.width, 54.0);
+        assert!(result.fits);
+        assert!((result.height - result.size * 1.2).abs() < 1e-4);
+    }
+}
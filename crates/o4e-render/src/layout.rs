@@ -0,0 +1,268 @@
+// this_file: crates/o4e-render/src/layout.rs
+
+//! Glyph placement for transformed and path-following text.
+//!
+//! Placements map glyph space (y down, origin on the baseline at the glyph's pen position, as
+//! returned by [`crate::outlines::glyph_bez_path`]) into output space.
+
+use kurbo::{
+    Affine, BezPath, ParamCurve, ParamCurveArclen, ParamCurveDeriv, PathSeg, Point, Rect, Vec2,
+};
+use o4e_core::{O4eError, Result, ShapingResult, TextPath, Transform2D};
+
+/// Arc length accuracy, in output units.
+const ARCLEN_ACCURACY: f64 = 1e-3;
+
+/// One transform per glyph; `None` for glyphs that are not drawn (past the end of a path).
+pub type GlyphPlacements = Vec<Option<Affine>>;
+
+/// Convert a core transform to a kurbo affine.
+pub fn to_affine(transform: &Transform2D) -> Affine {
+    Affine::new(transform.0.map(f64::from))
+}
+
+/// Parse the SVG path data of a text path.
+pub fn parse_text_path(text_path: &TextPath) -> Result<BezPath> {
+    BezPath::from_svg(&text_path.data)
+        .map_err(|err| O4eError::render(format!("Invalid text path {:?}: {err}", text_path.data)))
+}
+
+/// Place each glyph of `shaped` along `path` by arc length.
+///
+/// A glyph is centered at the arc length of its advance midpoint (`start_offset` plus its pen
+/// position plus half its advance) and rotated to the path tangent there, so it sits upright on
+/// the path's left-hand side when the path runs left to right. Glyphs whose midpoint falls
+/// outside the path are dropped.
+pub fn layout_on_path(
+    shaped: &ShapingResult,
+    path: &BezPath,
+    start_offset: f64,
+) -> GlyphPlacements {
+    let segments: Vec<(PathSeg, f64)> = path
+        .segments()
+        .map(|segment| (segment, segment.arclen(ARCLEN_ACCURACY)))
+        .collect();
+    let total: f64 = segments.iter().map(|(_, length)| length).sum();
+
+    shaped
+        .glyphs
+        .iter()
+        .map(|glyph| {
+            let half_advance = glyph.advance as f64 / 2.0;
+            let midpoint = start_offset + glyph.x as f64 + half_advance;
+            if !(0.0..=total).contains(&midpoint) {
+                return None;
+            }
+            let (point, tangent) = point_at(&segments, midpoint)?;
+            Some(
+                Affine::translate(point.to_vec2())
+                    * Affine::rotate(tangent.atan2())
+                    * Affine::translate((-half_advance, -(glyph.y as f64))),
+            )
+        })
+        .collect()
+}
+
+/// Glyph placements for a render, or `None` for plain axis-aligned text.
+///
+/// Without a path, glyphs keep their shaped positions; `transform` is applied last.
+pub fn glyph_placements(
+    shaped: &ShapingResult,
+    transform: Option<&Transform2D>,
+    text_path: Option<&TextPath>,
+) -> Result<Option<GlyphPlacements>> {
+    let transform = transform.filter(|transform| !transform.is_identity());
+    if transform.is_none() && text_path.is_none() {
+        return Ok(None);
+    }
+
+    let placements = match text_path {
+        Some(text_path) => layout_on_path(
+            shaped,
+            &parse_text_path(text_path)?,
+            text_path.start_offset as f64,
+        ),
+        None => shaped
+            .glyphs
+            .iter()
+            .map(|glyph| Some(Affine::translate((glyph.x as f64, -(glyph.y as f64)))))
+            .collect(),
+    };
+    let global = transform.map_or(Affine::IDENTITY, to_affine);
+    Ok(Some(
+        placements
+            .into_iter()
+            .map(|placement| placement.map(|affine| global * affine))
+            .collect(),
+    ))
+}
+
+/// Baseline offset (y up, like [`o4e_core::Glyph::y`]) of the line each glyph sits on.
+///
+/// Lines come from [`ShapingResult::lines`]; a result without them is one line at 0.
+pub fn line_baselines(shaped: &ShapingResult) -> Vec<f32> {
+    let mut baselines = vec![0.0; shaped.glyphs.len()];
+    for (index, line) in shaped.lines.iter().enumerate() {
+        let end = shaped.lines.get(index + 1).map_or(baselines.len(), |next| {
+            next.first_glyph.min(baselines.len())
+        });
+        baselines[line.first_glyph.min(end)..end].fill(line.baseline);
+    }
+    baselines
+}
+
+/// Union of the placed line cells: each glyph's advance horizontally, `top` to `bottom`
+/// (y down, relative to the baseline) vertically.
+pub fn placed_line_bounds(
+    shaped: &ShapingResult,
+    placements: &[Option<Affine>],
+    top: f64,
+    bottom: f64,
+) -> Option<Rect> {
+    shaped
+        .glyphs
+        .iter()
+        .zip(placements)
+        .filter_map(|(glyph, placement)| {
+            let cell = Rect::new(0.0, top, glyph.advance as f64, bottom);
+            placement.map(|affine| affine.transform_rect_bbox(cell))
+        })
+        .reduce(|a, b| a.union(b))
+}
+
+/// Point and tangent at `distance` along the measured segments.
+fn point_at(segments: &[(PathSeg, f64)], distance: f64) -> Option<(Point, Vec2)> {
+    let mut remaining = distance;
+    for (index, (segment, length)) in segments.iter().enumerate() {
+        if remaining > *length && index + 1 < segments.len() {
+            remaining -= length;
+            continue;
+        }
+        let t = segment.inv_arclen(remaining.min(*length), ARCLEN_ACCURACY);
+        let mut tangent = derivative(segment, t);
+        if tangent.hypot2() < 1e-18 {
+            // Degenerate control points; look at the neighbourhood instead.
+            tangent = segment.eval((t + 1e-3).min(1.0)) - segment.eval((t - 1e-3).max(0.0));
+        }
+        return Some((segment.eval(t), tangent));
+    }
+    None
+}
+
+fn derivative(segment: &PathSeg, t: f64) -> Vec2 {
+    match segment {
+        PathSeg::Line(line) => line.p1 - line.p0,
+        PathSeg::Quad(quad) => quad.deriv().eval(t).to_vec2(),
+        PathSeg::Cubic(cubic) => cubic.deriv().eval(t).to_vec2(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use o4e_core::types::{BoundingBox, Direction};
+    use o4e_core::Glyph;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn shaped(advances: &[f32]) -> ShapingResult {
+        let mut x = 0.0;
+        let glyphs = advances
+            .iter()
+            .enumerate()
+            .map(|(index, &advance)| {
+                let glyph = Glyph {
+                    id: index as u32 + 1,
+                    cluster: index as u32,
+                    x,
+                    y: 0.0,
+                    advance,
+                };
+                x += advance;
+                glyph
+            })
+            .collect();
+        ShapingResult {
+            text: "ab".to_string(),
+            glyphs,
+            advance: x,
+            bbox: BoundingBox {
+                x: 0.0,
+                y: 0.0,
+                width: x,
+                height: 10.0,
+            },
+            font: None,
+            direction: Direction::LeftToRight,
+            lines: Vec::new(),
+        }
+    }
+
+    fn assert_close(actual: Point, expected: (f64, f64)) {
+        assert!(
+            (actual.x - expected.0).abs() < 1e-3 && (actual.y - expected.1).abs() < 1e-3,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn glyphs_follow_path_by_arc_length() {
+        // Right 10 units, then down: the second glyph turns the corner.
+        let path = BezPath::from_svg("M0 0 L10 0 L10 20").unwrap();
+        let placements = layout_on_path(&shaped(&[8.0, 8.0, 30.0]), &path, 1.0);
+
+        let first = placements[0].unwrap();
+        assert_close(first * Point::ZERO, (1.0, 0.0));
+        assert_close(first * Point::new(4.0, 0.0), (5.0, 0.0));
+
+        // Midpoint at 13 lies 3 units down the vertical leg, rotated a quarter turn.
+        let second = placements[1].unwrap();
+        assert_close(second * Point::new(4.0, 0.0), (10.0, 3.0));
+        assert_close(second * Point::new(4.0, -1.0), (11.0, 3.0));
+        let angle = (second * Point::new(1.0, 0.0) - second * Point::ZERO).atan2();
+        assert!((angle - FRAC_PI_2).abs() < 1e-6);
+
+        // Midpoint at 32 is past the 30-unit path.
+        assert!(placements[2].is_none());
+    }
+
+    #[test]
+    fn curved_paths_rotate_each_glyph_to_the_tangent() {
+        let path = BezPath::from_svg("M0 0 Q50 -50 100 0").unwrap();
+        let placements = layout_on_path(&shaped(&[10.0; 9]), &path, 0.0);
+        let angles: Vec<f64> = placements
+            .iter()
+            .map(|placement| {
+                let placement = placement.unwrap();
+                (placement * Point::new(1.0, 0.0) - placement * Point::ZERO).atan2()
+            })
+            .collect();
+        assert!(angles[0] < -0.5, "{angles:?}");
+        assert!(
+            angles.windows(2).all(|pair| pair[1] > pair[0]),
+            "{angles:?}"
+        );
+        assert!(angles[8] > 0.0, "{angles:?}");
+    }
+
+    #[test]
+    fn transforms_apply_after_shaped_positions() {
+        let text = shaped(&[5.0, 5.0]);
+        assert!(glyph_placements(&text, Some(&Transform2D::IDENTITY), None)
+            .unwrap()
+            .is_none());
+
+        let rotate = Transform2D::translate(100.0, 0.0).then(Transform2D::rotate(90.0));
+        let placements = glyph_placements(&text, Some(&rotate), None)
+            .unwrap()
+            .unwrap();
+        assert_close(placements[1].unwrap() * Point::ZERO, (0.0, 105.0));
+        let (x, y) = rotate.apply(5.0, 0.0);
+        assert_close(Point::new(x as f64, y as f64), (0.0, 105.0));
+
+        let bounds = placed_line_bounds(&text, &placements, -8.0, 2.0).unwrap();
+        assert!((bounds.x0 + 2.0).abs() < 1e-4 && (bounds.x1 - 8.0).abs() < 1e-4);
+        assert!((bounds.y0 - 100.0).abs() < 1e-4 && (bounds.y1 - 110.0).abs() < 1e-4);
+
+        assert!(glyph_placements(&text, None, Some(&TextPath::new("not a path"))).is_err());
+    }
+}
@@ -3,11 +3,31 @@
 //! Rendering utilities for o4e text engine.
 
 pub mod batch;
+pub mod decoration;
+pub mod fit;
+pub mod layout;
+pub mod markup;
 pub mod outlines;
+pub mod paragraph;
+pub mod pdf;
 pub mod perf;
+pub mod rich;
 pub mod svg;
+pub mod tabs;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod truncate;
 
 pub use batch::{BatchItem, BatchRenderer, BatchResult};
-pub use outlines::{glyph_outline, GlyphOutline, OutlineCommand};
+pub use decoration::{decoration_paths, DecorationMetrics};
+pub use fit::{fit_text, Fit, FitOptions};
+pub use layout::{glyph_placements, layout_on_path, GlyphPlacements};
+pub use markup::{parse_markup, Markup};
+pub use outlines::{glyph_outline, glyph_stroke_path, GlyphOutline, OutlineCommand};
+pub use paragraph::{break_paragraph, Line, Paragraph, ParagraphOptions};
+pub use pdf::PdfRenderer;
 pub use perf::{BufferPool, MetricType, PerfMetrics, PerfScope, PerfStats};
+pub use rich::{render_rich, shape_rich, RichLayout, SpanStyle, StyledRun, TextSpan};
 pub use svg::SvgRenderer;
+pub use tabs::{shape_with_tabs, TabAlign, TabOptions, TabStop};
+pub use truncate::{truncate, TruncateMode, TruncateOptions};
@@ -0,0 +1,588 @@
+// this_file: crates/o4e-render/src/markup.rs
+
+//! A small Pango-like markup language for attributed text.
+//!
+//! `<b>`, `<i>`, `<s>`, `<u>`, `<sup>`, `<sub>`, `<big>`, `<small>` and `<tt>` are
+//! shorthands; `<span>` takes attributes:
+//!
+//! | Attribute | Value |
+//! |---|---|
+//! | `font`, `font_desc` | a font description: `"Noto Sans Condensed Bold 14"` |
+//! | `font_family`, `face` | family name |
+//! | `size`, `font_size` | points, optionally with a `pt` or `px` suffix |
+//! | `weight`, `font_weight` | 1-1000 or a name such as `bold` or `light` |
+//! | `style`, `font_style` | `normal`, `italic` or `oblique` |
+//! | `stretch`, `font_stretch` | `normal` or a width such as `condensed` or `semi-expanded` |
+//! | `color`, `foreground`, `fgcolor` | CSS color |
+//! | `features`, `font_features` | `"smcp"`, `"liga=0"`, `"-kern"`, comma or space separated |
+//! | `variations`, `font_variations` | `"wght=650,wdth=80"` |
+//! | `underline`, `overline`, `strikethrough` | `single`, `double`, `dotted`, `dashed`, `wavy` |
+//! | `underline_color`, `overline_color`, `strikethrough_color` | CSS color |
+//! | `rise`, `baseline_shift` | points the baseline is raised by (Pango's `rise` is in 1024ths) |
+//!
+//! Lines also take `true` (`single`), `error` (`wavy`) and `false`, which drops a line set
+//! earlier in the same tag but not one of an enclosing element.
+//!
+//! The five XML entities and numeric character references are decoded; `<markup>` is
+//! accepted as a root element. Errors carry the byte offset into the markup.
+
+use o4e_core::{
+    color::CssColor,
+    types::{FontStretch, FontStyle},
+    Decoration, DecorationLine, DecorationStyle, Font, FontDescription, O4eError, Result,
+};
+
+use crate::rich::{SpanStyle, TextSpan};
+
+/// Size factor of `<big>`; `<small>` divides by it.
+const BIG: f32 = 1.2;
+/// Size factor of `<sup>` and `<sub>`.
+const SCRIPT_SIZE: f32 = 0.7;
+/// Baseline shift of `<sup>`, in ems of the enclosing size.
+const SUPERSCRIPT_RISE: f32 = 0.35;
+/// Baseline shift of `<sub>`, in ems of the enclosing size.
+const SUBSCRIPT_DROP: f32 = 0.15;
+
+/// Markup split into plain text and spans over it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Markup {
+    /// The text with tags removed and entities decoded
+    pub text: String,
+    /// One span per non-empty element, outer elements first; ranges are byte offsets into
+    /// `text`
+    pub spans: Vec<TextSpan>,
+}
+
+/// Parse `markup` into text and spans for [`crate::rich::shape_rich`].
+///
+/// Relative sizes (`<big>`, `<small>`, `<sup>`, `<sub>`) resolve against the size of the
+/// enclosing element, starting at `font.size`.
+pub fn parse_markup(markup: &str, font: &Font) -> Result<Markup> {
+    let mut text = String::new();
+    let mut spans: Vec<TextSpan> = Vec::new();
+    // Open elements: name, offset of the start tag, index of their span and font size
+    let mut open: Vec<(&str, usize, Option<usize>, f32)> = Vec::new();
+    let mut pos = 0;
+    while pos < markup.len() {
+        let rest = &markup[pos..];
+        let Some(next) = rest.find(['<', '&']) else {
+            text.push_str(rest);
+            break;
+        };
+        text.push_str(&rest[..next]);
+        pos += next;
+
+        if markup[pos..].starts_with('&') {
+            let (decoded, end) = entity(markup, pos)?;
+            text.push(decoded);
+            pos = end;
+            continue;
+        }
+        if markup[pos..].starts_with("<!--") {
+            let end = markup[pos..]
+                .find("-->")
+                .ok_or_else(|| O4eError::markup(pos, "unterminated comment"))?;
+            pos += end + 3;
+            continue;
+        }
+
+        let tag = Tag::parse(markup, pos)?;
+        let size = open.last().map_or(font.size, |element| element.3);
+        if tag.closing {
+            match open.pop() {
+                Some((name, _, span, _)) if name == tag.name => {
+                    if let Some(index) = span {
+                        spans[index].range.end = text.len();
+                    }
+                }
+                Some((name, start, ..)) => {
+                    return Err(O4eError::markup(
+                        pos,
+                        format!(
+                            "</{}> does not close <{name}> opened at byte {start}",
+                            tag.name
+                        ),
+                    ))
+                }
+                None => {
+                    return Err(O4eError::markup(
+                        pos,
+                        format!("</{}> closes no element", tag.name),
+                    ))
+                }
+            }
+        } else {
+            let (style, inner_size) = tag.style(size)?;
+            let span = (style != SpanStyle::default() && !tag.empty).then(|| {
+                spans.push(TextSpan::new(text.len()..text.len(), style));
+                spans.len() - 1
+            });
+            if !tag.empty {
+                open.push((tag.name, pos, span, inner_size));
+            }
+        }
+        pos = tag.end;
+    }
+    if let Some((name, start, ..)) = open.pop() {
+        return Err(O4eError::markup(start, format!("<{name}> is never closed")));
+    }
+
+    spans.retain(|span| !span.range.is_empty());
+    Ok(Markup { text, spans })
+}
+
+/// Decode the entity or character reference at `start`; returns the character and the
+/// offset after the `;`.
+fn entity(markup: &str, start: usize) -> Result<(char, usize)> {
+    let rest = &markup[start + 1..];
+    let end = rest
+        .find(';')
+        .filter(|&end| end <= 10)
+        .ok_or_else(|| O4eError::markup(start, "unterminated entity; write & as &amp;"))?;
+    let name = &rest[..end];
+    let decoded = match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => match name.strip_prefix('#') {
+            Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok(),
+            Some(decimal) => decimal.parse().ok(),
+            None => None,
+        }
+        .and_then(char::from_u32),
+    };
+    decoded
+        .map(|decoded| (decoded, start + 1 + end + 1))
+        .ok_or_else(|| O4eError::markup(start, format!("unknown entity &{name};")))
+}
+
+/// One start, end or empty-element tag.
+struct Tag<'m> {
+    /// Offset of the `<`
+    start: usize,
+    name: &'m str,
+    closing: bool,
+    /// `<tag/>`
+    empty: bool,
+    attributes: Vec<Attribute<'m>>,
+    /// Offset after the `>`
+    end: usize,
+}
+
+/// `name="value"` in a start tag.
+struct Attribute<'m> {
+    name: &'m str,
+    /// Offset of the name
+    start: usize,
+    /// The value with entities decoded
+    value: String,
+    /// Offset of the value, after the quote
+    value_start: usize,
+}
+
+impl<'m> Tag<'m> {
+    fn parse(markup: &'m str, start: usize) -> Result<Self> {
+        let bytes = markup.as_bytes();
+        let unterminated = || O4eError::markup(start, "unterminated tag; write < as &lt;");
+        let name_at = |pos: usize| {
+            let len = markup[pos..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(markup.len() - pos);
+            &markup[pos..pos + len]
+        };
+        let skip_space = |mut pos: usize| {
+            while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+                pos += 1;
+            }
+            pos
+        };
+
+        let closing = bytes.get(start + 1) == Some(&b'/');
+        let mut pos = start + 1 + usize::from(closing);
+        let name = name_at(pos);
+        if name.is_empty() {
+            return Err(unterminated());
+        }
+        pos = skip_space(pos + name.len());
+
+        let mut attributes = Vec::new();
+        loop {
+            match bytes.get(pos) {
+                None => return Err(unterminated()),
+                Some(b'>') => {
+                    pos += 1;
+                    break;
+                }
+                Some(b'/') if !closing && bytes.get(pos + 1) == Some(&b'>') => {
+                    return Ok(Self {
+                        start,
+                        name,
+                        closing,
+                        empty: true,
+                        attributes,
+                        end: pos + 2,
+                    });
+                }
+                Some(_) if closing => {
+                    return Err(O4eError::markup(pos, "end tags take no attributes"))
+                }
+                Some(_) => {
+                    let attribute_start = pos;
+                    let attribute = name_at(pos);
+                    if attribute.is_empty() {
+                        return Err(O4eError::markup(pos, "expected an attribute name"));
+                    }
+                    pos = skip_space(pos + attribute.len());
+                    if bytes.get(pos) != Some(&b'=') {
+                        return Err(O4eError::markup(
+                            pos,
+                            format!("expected = after attribute {attribute}"),
+                        ));
+                    }
+                    pos = skip_space(pos + 1);
+                    let quote = match bytes.get(pos) {
+                        Some(&quote @ (b'"' | b'\'')) => quote as char,
+                        _ => {
+                            return Err(O4eError::markup(
+                                pos,
+                                format!("value of {attribute} must be quoted"),
+                            ))
+                        }
+                    };
+                    let value_start = pos + 1;
+                    let len = markup[value_start..].find(quote).ok_or_else(unterminated)?;
+                    let raw = &markup[value_start..value_start + len];
+                    let mut value = String::new();
+                    let mut at = 0;
+                    while let Some(amp) = raw[at..].find('&') {
+                        value.push_str(&raw[at..at + amp]);
+                        let (decoded, end) = entity(markup, value_start + at + amp)?;
+                        value.push(decoded);
+                        at = end - value_start;
+                    }
+                    value.push_str(&raw[at..]);
+                    attributes.push(Attribute {
+                        name: attribute,
+                        start: attribute_start,
+                        value,
+                        value_start,
+                    });
+                    pos = skip_space(value_start + len + 1);
+                }
+            }
+        }
+        Ok(Self {
+            start,
+            name,
+            closing,
+            empty: false,
+            attributes,
+            end: pos,
+        })
+    }
+
+    /// Style of the element and the font size inside it, given the enclosing size.
+    fn style(&self, size: f32) -> Result<(SpanStyle, f32)> {
+        let mut style = SpanStyle::default();
+        let scaled = |style: &mut SpanStyle, factor: f32| style.size = Some(size * factor);
+        let line = |line| Decoration::new(line);
+        match self.name {
+            "markup" | "span" => {}
+            "b" => style.weight = Some(700),
+            "i" => style.style = Some(FontStyle::Italic),
+            "s" => style.decorations.push(line(DecorationLine::LineThrough)),
+            "u" => style.decorations.push(line(DecorationLine::Underline)),
+            "tt" => style.family = Some("monospace".to_string()),
+            "big" => scaled(&mut style, BIG),
+            "small" => scaled(&mut style, 1.0 / BIG),
+            "sup" => {
+                scaled(&mut style, SCRIPT_SIZE);
+                style.baseline_shift = SUPERSCRIPT_RISE * size;
+            }
+            "sub" => {
+                scaled(&mut style, SCRIPT_SIZE);
+                style.baseline_shift = -SUBSCRIPT_DROP * size;
+            }
+            name => {
+                return Err(O4eError::markup(
+                    self.start,
+                    format!("unknown tag <{name}>"),
+                ))
+            }
+        }
+        if self.name != "span" {
+            if let Some(attribute) = self.attributes.first() {
+                return Err(O4eError::markup(
+                    attribute.start,
+                    format!("<{}> takes no attribute {}", self.name, attribute.name),
+                ));
+            }
+        }
+        for attribute in &self.attributes {
+            span_attribute(&mut style, attribute.name, &attribute.value).map_err(|reason| {
+                match reason {
+                    Some(reason) => O4eError::markup(attribute.value_start, reason),
+                    None => O4eError::markup(
+                        attribute.start,
+                        format!("unknown attribute {}", attribute.name),
+                    ),
+                }
+            })?;
+        }
+        let inner = style.size.unwrap_or(size);
+        Ok((style, inner))
+    }
+}
+
+/// Apply one `<span>` attribute. Errors are why the value was rejected, or `None` for an
+/// unknown attribute.
+fn span_attribute(
+    style: &mut SpanStyle,
+    attribute: &str,
+    value: &str,
+) -> std::result::Result<(), Option<String>> {
+    let invalid = || Some(format!("invalid {attribute} {value:?}"));
+    match attribute {
+        "font" | "font_desc" => {
+            let description =
+                FontDescription::parse(value).map_err(|error| Some(error.to_string()))?;
+            if let Some(family) = description.families.first() {
+                style.family = Some(family.clone());
+            }
+            style.weight = description.weight.or(style.weight);
+            style.style = description.style.or(style.style);
+            style.stretch = description.stretch.or(style.stretch);
+            style.size = description.size.or(style.size);
+            style.variations.extend(description.variations);
+        }
+        "font_family" | "face" => {
+            if value.trim().is_empty() {
+                return Err(invalid());
+            }
+            style.family = Some(value.trim().to_string());
+        }
+        "size" | "font_size" => style.size = Some(points(value).ok_or_else(invalid)?),
+        "weight" | "font_weight" => {
+            let weight = match value.trim().parse::<u16>() {
+                Ok(weight) => Some(weight).filter(|weight| (1..=1000).contains(weight)),
+                Err(_) if value.trim() == "normal" => Some(400),
+                Err(_) => keyword(value).and_then(|description| description.weight),
+            };
+            style.weight = Some(weight.ok_or_else(invalid)?);
+        }
+        "style" | "font_style" => {
+            let font_style = match value.trim() {
+                "normal" => Some(FontStyle::Normal),
+                _ => keyword(value).and_then(|description| description.style),
+            };
+            style.style = Some(font_style.ok_or_else(invalid)?);
+        }
+        "stretch" | "font_stretch" => {
+            let stretch = match value.trim() {
+                "normal" => Some(FontStretch::Normal),
+                _ => keyword(value).and_then(|description| description.stretch),
+            };
+            style.stretch = Some(stretch.ok_or_else(invalid)?);
+        }
+        "color" | "foreground" | "fgcolor" => {
+            CssColor::parse(value).map_err(|error| Some(error.to_string()))?;
+            style.color = Some(value.to_string());
+        }
+        "features" | "font_features" => {
+            for feature in value.split([',', ' ']).filter(|item| !item.is_empty()) {
+                let (tag, enabled) = if let Some(tag) = feature.strip_prefix('-') {
+                    (tag, false)
+                } else if let Some((tag, setting)) = feature.split_once('=') {
+                    let enabled = match setting.trim() {
+                        "1" | "on" | "true" => true,
+                        "0" | "off" | "false" => false,
+                        _ => return Err(invalid()),
+                    };
+                    (tag, enabled)
+                } else {
+                    (feature.strip_prefix('+').unwrap_or(feature), true)
+                };
+                let tag = tag.trim();
+                if tag.len() != 4 || !tag.is_ascii() {
+                    return Err(Some(format!("invalid feature tag {tag:?}")));
+                }
+                style.features.insert(tag.to_string(), enabled);
+            }
+        }
+        "variations" | "font_variations" => {
+            for variation in value.split([',', ' ']).filter(|item| !item.is_empty()) {
+                let (axis, setting) = variation.split_once('=').ok_or_else(invalid)?;
+                let setting: f32 = setting.trim().parse().map_err(|_| invalid())?;
+                let axis = axis.trim();
+                if axis.len() != 4 || !axis.is_ascii() || !setting.is_finite() {
+                    return Err(invalid());
+                }
+                style.variations.insert(axis.to_string(), setting);
+            }
+        }
+        "underline" | "overline" | "strikethrough" => {
+            let line = match attribute {
+                "underline" => DecorationLine::Underline,
+                "overline" => DecorationLine::Overline,
+                _ => DecorationLine::LineThrough,
+            };
+            let decoration_style = match value {
+                "false" => {
+                    style
+                        .decorations
+                        .retain(|decoration| decoration.line != line);
+                    return Ok(());
+                }
+                "single" | "true" | "low" | "solid" => DecorationStyle::Solid,
+                "double" => DecorationStyle::Double,
+                "dotted" => DecorationStyle::Dotted,
+                "dashed" => DecorationStyle::Dashed,
+                "wavy" | "error" => DecorationStyle::Wavy,
+                _ => return Err(invalid()),
+            };
+            decoration(style, line).style = decoration_style;
+        }
+        "underline_color" | "overline_color" | "strikethrough_color" => {
+            CssColor::parse(value).map_err(|error| Some(error.to_string()))?;
+            let line = match attribute {
+                "underline_color" => DecorationLine::Underline,
+                "overline_color" => DecorationLine::Overline,
+                _ => DecorationLine::LineThrough,
+            };
+            decoration(style, line).color = Some(value.to_string());
+        }
+        "rise" | "baseline_shift" => {
+            let shift = value
+                .trim()
+                .strip_suffix("pt")
+                .unwrap_or(value.trim())
+                .parse::<f32>()
+                .ok()
+                .filter(|shift| shift.is_finite())
+                .ok_or_else(invalid)?;
+            style.baseline_shift = shift;
+        }
+        _ => return Err(None),
+    }
+    Ok(())
+}
+
+/// The decoration of `style` drawing `line`, added when missing.
+fn decoration(style: &mut SpanStyle, line: DecorationLine) -> &mut Decoration {
+    match style
+        .decorations
+        .iter()
+        .position(|decoration| decoration.line == line)
+    {
+        Some(index) => &mut style.decorations[index],
+        None => {
+            style.decorations.push(Decoration::new(line));
+            style.decorations.last_mut().expect("just pushed")
+        }
+    }
+}
+
+/// A single Pango keyword such as `bold`, `italic` or `condensed`.
+fn keyword(value: &str) -> Option<FontDescription> {
+    FontDescription::parse(value)
+        .ok()
+        .filter(|description| description.families.is_empty() && description.size.is_none())
+}
+
+/// A size in points, with an optional `pt` or `px` suffix (the same at 72 DPI).
+fn points(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let number = value
+        .strip_suffix("pt")
+        .or_else(|| value.strip_suffix("px"))
+        .unwrap_or(value);
+    number
+        .parse::<f32>()
+        .ok()
+        .filter(|size| size.is_finite() && *size > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(markup: &str) -> Result<Markup> {
+        parse_markup(markup, &Font::new("Test", 10.0))
+    }
+
+    fn error_offset(markup: &str) -> usize {
+        match parse(markup) {
+            Err(O4eError::InvalidMarkup { offset, .. }) => offset,
+            other => panic!("expected a markup error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn tags_become_nested_spans() {
+        let markup = parse(
+            "a <b>bold <i>both</i></b> &amp; \
+             <span font=\"Noto Sans Bold Italic 14\" color=\"#c00\" \
+             features=\"smcp, -liga\">x</span><sup>2</sup><u></u>",
+        )
+        .unwrap();
+        assert_eq!(markup.text, "a bold both & x2");
+        let ranges: Vec<_> = markup.spans.iter().map(|span| span.range.clone()).collect();
+        assert_eq!(ranges, vec![2..11, 7..11, 14..15, 15..16]);
+        assert_eq!(markup.spans[0].style.weight, Some(700));
+        assert_eq!(markup.spans[1].style.style, Some(FontStyle::Italic));
+
+        let span = &markup.spans[2].style;
+        assert_eq!(span.family.as_deref(), Some("Noto Sans"));
+        assert_eq!(
+            (span.weight, span.style, span.size),
+            (Some(700), Some(FontStyle::Italic), Some(14.0))
+        );
+        assert_eq!(span.color.as_deref(), Some("#c00"));
+        assert_eq!(span.features.get("smcp"), Some(&true));
+        assert_eq!(span.features.get("liga"), Some(&false));
+
+        let sup = &markup.spans[3].style;
+        assert_eq!(sup.size, Some(7.0));
+        assert_eq!(sup.baseline_shift, 3.5);
+    }
+
+    #[test]
+    fn span_attributes_and_entities() {
+        let markup = parse(
+            "<span underline='double' underline_color='red' rise='2pt' weight='light' \
+             stretch='semi-condensed' variations='wght=650' size='12px'>&lt;&#x41;&#66;&gt;</span>",
+        )
+        .unwrap();
+        assert_eq!(markup.text, "<AB>");
+        let style = &markup.spans[0].style;
+        assert_eq!(style.decorations[0].style, DecorationStyle::Double);
+        assert_eq!(style.decorations[0].color.as_deref(), Some("red"));
+        assert_eq!(style.baseline_shift, 2.0);
+        assert_eq!(style.weight, Some(300));
+        assert_eq!(style.stretch, Some(FontStretch::SemiCondensed));
+        assert_eq!(style.variations.get("wght"), Some(&650.0));
+        assert_eq!(style.size, Some(12.0));
+
+        // Relative sizes nest
+        let markup = parse("<big><small>x</small></big>").unwrap();
+        assert!((markup.spans[1].style.size.unwrap() - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_byte() {
+        assert_eq!(error_offset("ab <b>c</i>"), 7);
+        assert_eq!(error_offset("ab <b>c"), 3);
+        assert_eq!(error_offset("x</b>"), 1);
+        assert_eq!(error_offset("<blink>x</blink>"), 0);
+        assert_eq!(error_offset("<span colour='red'>x</span>"), 6);
+        assert_eq!(error_offset("<span color='nope'>x</span>"), 13);
+        assert_eq!(error_offset("<span size=12>x</span>"), 11);
+        assert_eq!(error_offset("<b weight='bold'>x</b>"), 3);
+        assert_eq!(error_offset("fish & chips"), 5);
+        assert_eq!(error_offset("a &bogus; b"), 2);
+        assert_eq!(error_offset("1 < 2"), 2);
+        assert_eq!(error_offset("<span font='x'"), 0);
+    }
+}
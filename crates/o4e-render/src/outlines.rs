@@ -2,7 +2,8 @@
 
 //! Shared glyph outline recording utilities.
 
-use kurbo::{BezPath, Point};
+use kurbo::{BezPath, Cap, Join, Point, StrokeOpts};
+use o4e_core::types::{LineCap, LineJoin, Stroke};
 use owned_ttf_parser::{AsFaceRef, OwnedFace};
 use ttf_parser::{GlyphId, OutlineBuilder};
 
@@ -78,6 +79,41 @@ impl GlyphOutline {
 
         path
     }
+
+    /// Area covered by `stroke` around the scaled outline, as a path to fill (nonzero winding).
+    pub fn to_stroked_bez_path(&self, scale: f32, stroke: &Stroke) -> BezPath {
+        let path = self.to_bez_path(scale);
+        if path.elements().is_empty() || stroke.width <= 0.0 {
+            return BezPath::new();
+        }
+
+        kurbo::stroke(
+            path,
+            &kurbo_stroke(stroke),
+            &StrokeOpts::default(),
+            STROKE_TOLERANCE,
+        )
+    }
+}
+
+/// Maximum distance between a flattened stroke outline and the exact curve, in output units.
+const STROKE_TOLERANCE: f64 = 0.01;
+
+fn kurbo_stroke(stroke: &Stroke) -> kurbo::Stroke {
+    let join = match stroke.line_join {
+        LineJoin::Miter => Join::Miter,
+        LineJoin::Round => Join::Round,
+        LineJoin::Bevel => Join::Bevel,
+    };
+    let cap = match stroke.line_cap {
+        LineCap::Butt => Cap::Butt,
+        LineCap::Round => Cap::Round,
+        LineCap::Square => Cap::Square,
+    };
+    kurbo::Stroke::new(stroke.width as f64)
+        .with_join(join)
+        .with_caps(cap)
+        .with_miter_limit(stroke.miter_limit as f64)
 }
 
 fn scale_point(x: f32, y: f32, scale: f32) -> Point {
@@ -135,6 +171,20 @@ pub fn glyph_bez_path<S: OutlineSource>(
     glyph_outline(source, glyph_id).map(|outline| outline.to_bez_path(scale))
 }
 
+/// Convenience helper that records a glyph outline and returns its stroke as a fillable path.
+pub fn glyph_stroke_path<S: OutlineSource>(
+    source: &S,
+    glyph_id: GlyphId,
+    scale: f32,
+    stroke: &Stroke,
+) -> Option<BezPath> {
+    if scale <= 0.0 {
+        return None;
+    }
+    let path = glyph_outline(source, glyph_id)?.to_stroked_bez_path(scale, stroke);
+    (!path.elements().is_empty()).then_some(path)
+}
+
 #[derive(Default)]
 struct RecordingOutline {
     commands: Vec<OutlineCommand>,
@@ -231,6 +281,38 @@ mod tests {
         assert!(bounds.width() > 0.0 && bounds.height() > 0.0);
     }
 
+    #[test]
+    fn stroked_path_grows_by_half_the_width() {
+        let face = noto_face();
+        let glyph_id = face.as_face_ref().glyph_index('o').unwrap();
+        let scale = 0.1;
+        let fill = glyph_bez_path(face.as_face_ref(), glyph_id, scale)
+            .expect("path")
+            .bounding_box();
+        let stroke = Stroke {
+            width: 4.0,
+            line_join: LineJoin::Round,
+            ..Stroke::default()
+        };
+        let stroked = glyph_stroke_path(face.as_face_ref(), glyph_id, scale, &stroke)
+            .expect("stroke path")
+            .bounding_box();
+        for (outer, inner) in [
+            (fill.x0 - stroked.x0, 2.0),
+            (stroked.x1 - fill.x1, 2.0),
+            (fill.y0 - stroked.y0, 2.0),
+            (stroked.y1 - fill.y1, 2.0),
+        ] {
+            assert!((outer - inner).abs() < 0.1, "{fill:?} vs {stroked:?}");
+        }
+
+        let hairline = Stroke {
+            width: 0.0,
+            ..Stroke::default()
+        };
+        assert!(glyph_stroke_path(face.as_face_ref(), glyph_id, scale, &hairline).is_none());
+    }
+
     fn noto_face() -> OwnedFace {
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("../../testdata/fonts/NotoSans-Regular.ttf");
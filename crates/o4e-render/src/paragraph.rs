@@ -0,0 +1,638 @@
+// this_file: crates/o4e-render/src/paragraph.rs
+
+//! Paragraph layout: greedy line breaking to a maximum width, with hyphenation.
+//!
+//! Each line is segmented and shaped on its own, so bidi reordering and kerning follow the
+//! final lines. [`Paragraph::to_shaped`] stacks the lines into one [`ShapingResult`], every
+//! line one line height below the previous (glyph `y` offsets are y-up), which the renderers
+//! draw as a single block.
+
+use std::ops::Range;
+
+use o4e_core::{
+    utils::calculate_bbox, Backend, Font, O4eError, Result, SegmentOptions, ShapedLine,
+    ShapingResult, TextRun,
+};
+use o4e_unicode::{break_opportunities, BreakKind, BreakOpportunity, Hyphenator, SOFT_HYPHEN};
+
+use owned_ttf_parser::AsFaceRef;
+
+use crate::svg::{face_and_scale, font_identity};
+use crate::tabs::{shape_tabbed, TabLayout, TabOptions};
+
+/// U+2010 HYPHEN, shown at hyphenated line ends when the font has it.
+const HYPHEN: &str = "\u{2010}";
+/// U+002D HYPHEN-MINUS, the fallback.
+const HYPHEN_MINUS: &str = "-";
+
+/// How a paragraph is broken into lines.
+#[derive(Debug, Clone, Default)]
+pub struct ParagraphOptions {
+    /// Maximum line width in points; `None` only breaks at hard line breaks
+    pub max_width: Option<f32>,
+    /// Baseline-to-baseline distance in points; `None` uses [`font_line_height`]
+    pub line_height: Option<f32>,
+    /// Patterns for automatic hyphenation; soft hyphens (U+00AD) offer breaks either way
+    pub hyphenator: Option<Hyphenator>,
+    /// Segmentation of each line before shaping
+    pub segment: SegmentOptions,
+    /// Tab stops, measured from the start of each line
+    pub tabs: TabOptions,
+}
+
+/// One laid-out line.
+#[derive(Debug, Clone)]
+pub struct Line {
+    /// Byte range of the line's text in the paragraph, without trailing white space, line
+    /// breaks or a soft hyphen it broke at
+    pub range: Range<usize>,
+    /// Whether the line ends at a hyphenation point and carries a hyphen glyph
+    pub hyphenated: bool,
+    /// The line's glyphs, from x = 0 on its own baseline
+    pub shaped: ShapingResult,
+}
+
+/// Lines of a paragraph, in order.
+#[derive(Debug, Clone)]
+pub struct Paragraph {
+    pub lines: Vec<Line>,
+    /// Baseline-to-baseline distance in points
+    pub line_height: f32,
+    pub font: Font,
+}
+
+impl Paragraph {
+    /// Advance of the widest line.
+    pub fn width(&self) -> f32 {
+        self.lines
+            .iter()
+            .map(|line| line.shaped.advance)
+            .fold(0.0, f32::max)
+    }
+
+    /// Total height of the line boxes.
+    pub fn height(&self) -> f32 {
+        self.lines.len() as f32 * self.line_height
+    }
+
+    /// All lines as one shaping result, the first baseline at y = 0.
+    ///
+    /// Line texts are joined with "\n"; clusters are byte offsets into the joined text and
+    /// [`ShapingResult::lines`] records where each line starts. The result is set in the font
+    /// the backend shaped the lines in, which may be a fallback for `font`.
+    pub fn to_shaped(&self) -> ShapingResult {
+        let mut stacked = ShapingResult::empty(&self.font);
+        if let Some(font) = self
+            .lines
+            .iter()
+            .find(|line| !line.shaped.glyphs.is_empty())
+            .and_then(|line| line.shaped.font.clone())
+        {
+            stacked.font = Some(font);
+        }
+        let mut text = String::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            if index > 0 {
+                text.push('\n');
+            }
+            let offset = text.len() as u32;
+            let baseline = -(index as f32 * self.line_height);
+            stacked.lines.push(ShapedLine {
+                first_glyph: stacked.glyphs.len(),
+                baseline,
+            });
+            stacked
+                .glyphs
+                .extend(line.shaped.glyphs.iter().cloned().map(|mut glyph| {
+                    glyph.cluster += offset;
+                    glyph.y += baseline;
+                    glyph
+                }));
+            text.push_str(&line.shaped.text);
+        }
+        stacked.text = text;
+        stacked.advance = self.width();
+        stacked.bbox = calculate_bbox(&stacked.glyphs);
+        if let Some(line) = self.lines.first() {
+            stacked.direction = line.shaped.direction;
+        }
+        stacked
+    }
+}
+
+/// Break `text` into lines set in `font`.
+///
+/// Lines break greedily at the last opportunity that fits `max_width`; a word wider than a
+/// line overflows on a line of its own. Hyphenated lines end in U+2010, or U+002D when the
+/// font lacks it, and soft hyphens are never drawn. `adjust_run` sees every run before it is
+/// shaped, for script, language or direction overrides.
+///
+/// Fails when the backend sets parts of the text in different fonts, see [`join_font`].
+pub fn break_paragraph(
+    backend: &dyn Backend,
+    text: &str,
+    font: &Font,
+    options: &ParagraphOptions,
+    adjust_run: impl Fn(&mut TextRun),
+) -> Result<Paragraph> {
+    let shaper = LineShaper {
+        backend,
+        font,
+        segment: &options.segment,
+        adjust_run: &adjust_run,
+    };
+    let measured = Measured::new(
+        &shaper,
+        text,
+        options.hyphenator.as_ref(),
+        options.max_width.is_some(),
+        &options.tabs,
+    )?;
+    let lines = measured
+        .lines(options.max_width)
+        .into_iter()
+        .map(|(start, end)| shaper.line(text, start, end, measured.hyphen.0, &options.tabs))
+        .collect::<Result<Vec<_>>>()?;
+    let mut resolved = None;
+    for line in &lines {
+        join_font(&mut resolved, &line.shaped)?;
+    }
+
+    Ok(Paragraph {
+        lines,
+        line_height: options
+            .line_height
+            .unwrap_or_else(|| font_line_height(font)),
+        font: font.clone(),
+    })
+}
+
+/// Baseline-to-baseline distance of `font`: its [`Font::line_height`] when set, otherwise
+/// from the font's metrics, or 1.2 em without font data.
+pub fn font_line_height(font: &Font) -> f32 {
+    if let Some(multiple) = font.line_height {
+        return multiple * font.size;
+    }
+    face_and_scale(font)
+        .map(|(face, scale)| {
+            let face = face.as_face_ref();
+            (face.ascender() - face.descender() + face.line_gap()) as f32 * scale
+        })
+        .filter(|height| *height > 0.0)
+        .unwrap_or(font.size * 1.2)
+}
+
+/// Visible text of a line from `start` to `opportunity`, and whether it takes a hyphen.
+fn line_content(text: &str, start: usize, opportunity: BreakOpportunity) -> (Range<usize>, bool) {
+    let line = &text[start..opportunity.offset];
+    match opportunity.kind {
+        BreakKind::Hyphen => {
+            let line = line.strip_suffix(SOFT_HYPHEN).unwrap_or(line);
+            (start..start + line.len(), true)
+        }
+        BreakKind::Allowed | BreakKind::Mandatory => (start..start + line.trim_end().len(), false),
+    }
+}
+
+/// Break opportunities of a paragraph's text and the widths between them, from one shaping
+/// pass.
+pub(crate) struct Measured<'t> {
+    text: &'t str,
+    breaks: Vec<BreakOpportunity>,
+    /// Pen offsets per byte, see [`LineShaper::pen_offsets`]
+    prefix: Vec<f32>,
+    /// The hyphen string and its advance
+    pub(crate) hyphen: (&'static str, f32),
+    /// Tab stops, when the text has tabs to measure
+    tabs: Option<TabLayout>,
+}
+
+impl<'t> Measured<'t> {
+    /// Breaks of `text`, with widths only when `measure` is set (every line is zero wide
+    /// otherwise); tabs move to the stops of `tabs`.
+    pub(crate) fn new(
+        shaper: &LineShaper<'_>,
+        text: &'t str,
+        hyphenator: Option<&Hyphenator>,
+        measure: bool,
+        tabs: &TabOptions,
+    ) -> Result<Self> {
+        let breaks = break_opportunities(text, hyphenator);
+        let hyphen = if breaks
+            .iter()
+            .any(|opportunity| opportunity.kind == BreakKind::Hyphen)
+        {
+            shaper.first_available(&[HYPHEN, HYPHEN_MINUS])?
+        } else {
+            (HYPHEN_MINUS, 0.0)
+        };
+        let prefix = if measure {
+            shaper.pen_offsets(text)?
+        } else {
+            vec![0.0; text.len() + 1]
+        };
+        let tabs = if measure && text.contains('\t') {
+            Some(TabLayout::new(shaper, tabs)?.0)
+        } else {
+            None
+        };
+        Ok(Self {
+            text,
+            breaks,
+            prefix,
+            hyphen,
+            tabs,
+        })
+    }
+
+    /// Measure tabs for text laid out at `1 / scale` times the size it was shaped at; see
+    /// [`TabLayout::set_scale`].
+    pub(crate) fn scale_tabs(&mut self, scale: f32) {
+        if let Some(tabs) = &mut self.tabs {
+            tabs.set_scale(scale);
+        }
+    }
+
+    /// Advance of the line from `start` to `opportunity`, measured in the unbroken text with
+    /// its tabs at their stops.
+    pub(crate) fn width(&self, start: usize, opportunity: BreakOpportunity) -> f32 {
+        let (content, hyphenated) = line_content(self.text, start, opportunity);
+        let advance = |range: Range<usize>| self.prefix[range.end] - self.prefix[range.start];
+        let width = match &self.tabs {
+            Some(tabs) if self.text[content.clone()].contains('\t') => {
+                let mut pieces = self.text[content.clone()].split('\t').scan(
+                    content.start,
+                    |piece_start, piece| {
+                        let range = *piece_start..*piece_start + piece.len();
+                        *piece_start = range.end + 1;
+                        Some((piece, range))
+                    },
+                );
+                let mut pen = pieces.next().map_or(0.0, |(_, range)| advance(range));
+                for (piece, range) in pieces {
+                    let before_decimal = piece
+                        .find(tabs.decimal())
+                        .map(|decimal| advance(range.start..range.start + decimal));
+                    let width = advance(range);
+                    pen = tabs.place(pen, width, before_decimal) + width;
+                }
+                pen
+            }
+            _ => advance(content),
+        };
+        width + if hyphenated { self.hyphen.1 } else { 0.0 }
+    }
+
+    /// Start and end of every line when breaking greedily at the last opportunity that fits
+    /// `max_width`.
+    pub(crate) fn lines(&self, max_width: Option<f32>) -> Vec<(usize, BreakOpportunity)> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut fit: Option<BreakOpportunity> = None;
+        for &opportunity in &self.breaks {
+            let overflows = |start| {
+                max_width.is_some_and(|max_width| self.width(start, opportunity) > max_width)
+            };
+            if let Some(last) = fit.filter(|_| overflows(start)) {
+                lines.push((start, last));
+                start = last.offset;
+            }
+            if opportunity.kind == BreakKind::Mandatory || overflows(start) {
+                lines.push((start, opportunity));
+                start = opportunity.offset;
+                fit = None;
+            } else {
+                fit = Some(opportunity);
+            }
+        }
+        lines
+    }
+}
+
+/// Shapes pieces of one paragraph's text with the same font and run adjustments.
+pub(crate) struct LineShaper<'a> {
+    pub(crate) backend: &'a dyn Backend,
+    pub(crate) font: &'a Font,
+    pub(crate) segment: &'a SegmentOptions,
+    pub(crate) adjust_run: &'a dyn Fn(&mut TextRun),
+}
+
+impl LineShaper<'_> {
+    /// Shape `text` run by run, without soft hyphens; clusters are byte offsets into `text`.
+    ///
+    /// The result is set in the font the backend shaped the runs in; runs set in different
+    /// fonts fail, see [`join_font`].
+    pub(crate) fn shape(&self, text: &str) -> Result<ShapingResult> {
+        let mut line = ShapingResult::empty(self.font);
+        line.text = text.to_string();
+        let mut resolved = None;
+        for (index, mut run) in self
+            .backend
+            .segment(text, self.segment)?
+            .into_iter()
+            .enumerate()
+        {
+            (self.adjust_run)(&mut run);
+            let mut shaped = self.backend.shape(&run, self.font)?;
+            join_font(&mut resolved, &shaped)?;
+            if index == 0 {
+                line.direction = shaped.direction;
+            }
+            // Close the gap a drawn soft hyphen would leave
+            let pen = line.advance;
+            let mut removed = 0.0;
+            shaped.glyphs.retain_mut(|glyph| {
+                if is_soft_hyphen(&run.text, glyph.cluster as usize) {
+                    removed += glyph.advance;
+                    return false;
+                }
+                glyph.x += pen - removed;
+                glyph.cluster += run.range.0 as u32;
+                true
+            });
+            line.glyphs.append(&mut shaped.glyphs);
+            line.advance += shaped.advance - removed;
+        }
+        line.font = resolved.or(line.font);
+        line.bbox = calculate_bbox(&line.glyphs);
+        Ok(line)
+    }
+
+    /// Summed advances of `text` up to each byte offset (in logical order), from one shaping
+    /// pass; `offsets[end] - offsets[start]` measures `text[start..end]`.
+    pub(crate) fn pen_offsets(&self, text: &str) -> Result<Vec<f32>> {
+        let mut offsets = vec![0.0f32; text.len() + 1];
+        for mut run in self.backend.segment(text, self.segment)? {
+            (self.adjust_run)(&mut run);
+            let shaped = self.backend.shape(&run, self.font)?;
+            for glyph in shaped.glyphs {
+                let cluster = glyph.cluster as usize;
+                if !is_soft_hyphen(&run.text, cluster) {
+                    let start = (run.range.0 + cluster).min(text.len().saturating_sub(1));
+                    offsets[start + 1] += glyph.advance;
+                }
+            }
+        }
+        for offset in 1..offsets.len() {
+            offsets[offset] += offsets[offset - 1];
+        }
+        Ok(offsets)
+    }
+
+    /// The first of `candidates` the font has glyphs for (else the last), with its advance.
+    pub(crate) fn first_available(
+        &self,
+        candidates: &[&'static str],
+    ) -> Result<(&'static str, f32)> {
+        let mut shaped = None;
+        for candidate in candidates {
+            let result = self.shape(candidate)?;
+            if !result.glyphs.is_empty() && result.glyphs.iter().all(|glyph| glyph.id != 0) {
+                return Ok((candidate, result.advance));
+            }
+            shaped = Some((*candidate, result.advance));
+        }
+        Ok(shaped.unwrap_or(("", 0.0)))
+    }
+
+    fn line(
+        &self,
+        text: &str,
+        start: usize,
+        opportunity: BreakOpportunity,
+        hyphen: &str,
+        tabs: &TabOptions,
+    ) -> Result<Line> {
+        let (range, hyphenated) = line_content(text, start, opportunity);
+        let shaped = if hyphenated {
+            shape_tabbed(self, &format!("{}{hyphen}", &text[range.clone()]), tabs)?
+        } else {
+            shape_tabbed(self, &text[range.clone()], tabs)?
+        };
+        Ok(Line {
+            range,
+            hyphenated,
+            shaped,
+        })
+    }
+}
+
+/// Record the font `shaped` is set in as `resolved`, failing when `resolved` already holds
+/// another font.
+///
+/// Backends may shape runs in a fallback font, but a [`ShapingResult`] draws every glyph in
+/// its one font; text that needs several fonts is laid out with
+/// [`shape_rich`](crate::rich::shape_rich), which keeps them apart. Results without glyphs
+/// fit any font.
+pub(crate) fn join_font(resolved: &mut Option<Font>, shaped: &ShapingResult) -> Result<()> {
+    let Some(font) = shaped.font.as_ref().filter(|_| !shaped.glyphs.is_empty()) else {
+        return Ok(());
+    };
+    match resolved {
+        Some(first) if font_identity(first) != font_identity(font) => {
+            Err(O4eError::shaping(format!(
+                "text is set in both {} and {}; lay it out with shape_rich",
+                first.source.label(),
+                font.source.label()
+            )))
+        }
+        Some(_) => Ok(()),
+        None => {
+            *resolved = Some(font.clone());
+            Ok(())
+        }
+    }
+}
+
+fn is_soft_hyphen(text: &str, cluster: usize) -> bool {
+    text.get(cluster..)
+        .is_some_and(|rest| rest.starts_with(SOFT_HYPHEN))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{BidiBackend, FallbackBackend, MonoBackend};
+
+    fn lines(text: &str, options: &ParagraphOptions) -> Vec<String> {
+        let paragraph = break_paragraph(
+            &MonoBackend,
+            text,
+            &Font::new("Test", 10.0),
+            options,
+            |_| {},
+        )
+        .unwrap();
+        paragraph
+            .lines
+            .iter()
+            .map(|line| line.shaped.text.clone())
+            .collect()
+    }
+
+    fn wrap(max_width: f32) -> ParagraphOptions {
+        ParagraphOptions {
+            max_width: Some(max_width),
+            line_height: Some(12.0),
+            ..ParagraphOptions::default()
+        }
+    }
+
+    #[test]
+    fn lines_break_greedily_at_spaces_and_hard_breaks() {
+        assert_eq!(
+            lines("aa bb cc\ndd", &wrap(50.0)),
+            vec!["aa bb", "cc", "dd"]
+        );
+        // A word wider than the line overflows on its own line
+        assert_eq!(
+            lines("a verylongword b", &wrap(50.0)),
+            vec!["a", "verylongword", "b"]
+        );
+        assert_eq!(
+            lines("aa bb\n\ncc", &ParagraphOptions::default()),
+            vec!["aa bb", "", "cc"]
+        );
+    }
+
+    #[test]
+    fn hyphenation_fills_lines_and_falls_back_to_hyphen_minus() {
+        let options = ParagraphOptions {
+            hyphenator: Hyphenator::for_language("en"),
+            ..wrap(90.0)
+        };
+        assert_eq!(lines("a hyphenation", &options), vec!["a hyphen-", "ation"]);
+        assert_eq!(
+            lines("a hyphenation", &wrap(90.0)),
+            vec!["a", "hyphenation"]
+        );
+
+        // Soft hyphens break without patterns and vanish elsewhere
+        let text = "soft\u{AD}hyphen soft\u{AD}er";
+        let paragraph = break_paragraph(
+            &MonoBackend,
+            text,
+            &Font::new("Test", 10.0),
+            &wrap(70.0),
+            |_| {},
+        )
+        .unwrap();
+        let texts: Vec<&str> = paragraph
+            .lines
+            .iter()
+            .map(|line| line.shaped.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["soft-", "hyphen", "soft\u{AD}er"]);
+        assert!(paragraph.lines[0].hyphenated);
+        assert_eq!(&text[paragraph.lines[0].range.clone()], "soft");
+        let last = &paragraph.lines[2].shaped;
+        assert_eq!(last.glyphs.len(), 6);
+        assert_eq!(last.glyphs[4].x, 40.0);
+        assert_eq!(last.advance, 60.0);
+    }
+
+    #[test]
+    fn stacked_lines_drop_by_the_line_height() {
+        let paragraph = break_paragraph(
+            &MonoBackend,
+            "aa bbb",
+            &Font::new("Test", 10.0),
+            &wrap(30.0),
+            |run| run.language = "de".to_string(),
+        )
+        .unwrap();
+        assert_eq!(paragraph.width(), 30.0);
+        assert_eq!(paragraph.height(), 24.0);
+
+        let shaped = paragraph.to_shaped();
+        assert_eq!(shaped.text, "aa\nbbb");
+        assert_eq!(shaped.advance, 30.0);
+        let positions: Vec<(f32, f32)> = shaped
+            .glyphs
+            .iter()
+            .map(|glyph| (glyph.x, glyph.y))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (0.0, -12.0),
+                (10.0, -12.0),
+                (20.0, -12.0)
+            ]
+        );
+        // Clusters index the joined text, and every line keeps its own baseline
+        let clusters: Vec<u32> = shaped.glyphs.iter().map(|glyph| glyph.cluster).collect();
+        assert_eq!(clusters, vec![0, 1, 3, 4, 5]);
+        assert_eq!(
+            crate::layout::line_baselines(&shaped),
+            vec![0.0, 0.0, -12.0, -12.0, -12.0]
+        );
+        assert_eq!(font_line_height(&Font::new("Missing", 10.0)), 12.0);
+        let font = Font {
+            line_height: Some(1.5),
+            ..Font::new("Missing", 10.0)
+        };
+        assert_eq!(font_line_height(&font), 15.0);
+    }
+
+    #[test]
+    fn clusters_index_the_line_across_runs() {
+        let options = ParagraphOptions {
+            segment: SegmentOptions {
+                bidi_resolve: true,
+                script_itemize: true,
+                ..SegmentOptions::default()
+            },
+            ..ParagraphOptions::default()
+        };
+        // Right-to-left paragraph: runs come in visual order, the reverse of the text
+        let text = "אב ab גד";
+        let paragraph = break_paragraph(
+            &BidiBackend,
+            text,
+            &Font::new("Test", 10.0),
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        let shaped = &paragraph.lines[0].shaped;
+        assert_eq!(shaped.text, text);
+        let mut clusters: Vec<usize> = shaped
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.cluster as usize)
+            .collect();
+        clusters.sort_unstable();
+        let starts: Vec<usize> = text.char_indices().map(|(index, _)| index).collect();
+        assert_eq!(clusters, starts);
+    }
+
+    #[test]
+    fn lines_keep_the_font_the_backend_resolved() {
+        let options = ParagraphOptions {
+            segment: SegmentOptions {
+                bidi_resolve: true,
+                script_itemize: true,
+                ..SegmentOptions::default()
+            },
+            ..wrap(30.0)
+        };
+        let font = Font::new("Test", 10.0);
+        let paragraph =
+            break_paragraph(&FallbackBackend, "אב גד", &font, &options, |_| {}).unwrap();
+        assert_eq!(paragraph.lines.len(), 2);
+        let shaped = paragraph.to_shaped();
+        assert_eq!(shaped.font.unwrap().family, "Fallback");
+        assert_eq!(paragraph.font.family, "Test");
+
+        // One result cannot draw runs in two fonts
+        let err = break_paragraph(&FallbackBackend, "ab גד", &font, &options, |_| {})
+            .expect_err("a line mixing fonts fails");
+        assert!(err.to_string().contains("shape_rich"), "{err}");
+        let err = break_paragraph(&FallbackBackend, "ab\nגד", &font, &options, |_| {})
+            .expect_err("lines in different fonts fail");
+        assert!(err.to_string().contains("shape_rich"), "{err}");
+    }
+}
@@ -0,0 +1,980 @@
+// this_file: crates/o4e-render/src/pdf.rs
+
+//! PDF rendering implementation for o4e.
+//!
+//! Produces a single-page PDF in which every glyph is a filled vector path recorded through
+//! [`GlyphOutline`]. An invisible text layer with a subset TrueType font and a ToUnicode map
+//! keeps the text searchable and copyable.
+
+use crate::outlines::{glyph_outline, GlyphOutline, OutlineCommand};
+use crate::svg::{cluster_spans, face_and_scale, logical_bounds};
+use flate2::{write::ZlibEncoder, Compression};
+use kurbo::{Rect, Shape, Vec2};
+use o4e_core::{
+    color::Rgba,
+    types::{LineCap, LineJoin},
+    utils::{parse_color, parse_color_with_current},
+    Glyph, PdfOptions, Result, ShapingResult,
+};
+use owned_ttf_parser::AsFaceRef;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use ttf_parser::{GlyphId, Tag};
+
+/// PDF renderer for converting shaped text to a PDF document.
+///
+/// One unit of the shaped layout maps to one PDF point.
+#[derive(Debug, Default)]
+pub struct PdfRenderer;
+
+impl PdfRenderer {
+    /// Create a new PDF renderer.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Render shaped text to PDF bytes.
+    ///
+    /// The page covers the same area as SVG output: the union of the glyph ink bounds and the
+    /// font's ascender/descender line box, grown by `options.padding` on every side.
+    ///
+    /// Fails with [`O4eError::InvalidColor`](o4e_core::O4eError::InvalidColor) when a color
+    /// in `options` does not parse.
+    pub fn render(&self, shaped: &ShapingResult, options: &PdfOptions) -> Result<Vec<u8>> {
+        let face = shaped.font.as_ref().and_then(face_and_scale);
+
+        let outset = options.stroke.as_ref().map_or(0.0, |s| s.outset() as f64);
+        let mut bounds = logical_bounds(shaped);
+        let mut outlines: HashMap<u32, Option<GlyphOutline>> = HashMap::new();
+        if let Some((face, scale)) = &face {
+            for glyph in &shaped.glyphs {
+                let outline = outlines.entry(glyph.id).or_insert_with(|| {
+                    let id = GlyphId(u16::try_from(glyph.id).ok()?);
+                    glyph_outline(face.as_face_ref(), id)
+                });
+                if let Some(outline) = outline {
+                    let offset = Vec2::new(glyph.x as f64, -glyph.y as f64);
+                    let ink = outline.to_bez_path(*scale).bounding_box() + offset;
+                    bounds = bounds.union(ink.inflate(outset, outset));
+                }
+            }
+        }
+        let padding = options.padding.max(0.0) as f64;
+        let page = bounds.inflate(padding, padding);
+
+        let mut content = String::with_capacity(256 + shaped.glyphs.len() * 128);
+        let mut graphics_states = Vec::new();
+        // `currentColor` means the text color
+        let text_color = parse_color(&options.foreground_color)?;
+
+        if let Some(background) = options.background.as_deref() {
+            if let Some(fill) =
+                color_operators(background, text_color, false, &mut graphics_states)?
+            {
+                let _ = writeln!(
+                    content,
+                    "q {fill} 0 0 {} {} re f Q",
+                    num(page.width()),
+                    num(page.height())
+                );
+            }
+        }
+
+        if let Some((_, scale)) = &face {
+            let scale = *scale as f64;
+            // Stroke under the fill, see `Stroke`.
+            if let Some(stroke) = &options.stroke {
+                if let Some(color) =
+                    color_operators(&stroke.color, text_color, true, &mut graphics_states)?
+                {
+                    let _ = writeln!(
+                        content,
+                        "q {color} {} w {} j {} J {} M",
+                        num(stroke.width.max(0.0) as f64 / scale),
+                        match stroke.line_join {
+                            LineJoin::Miter => 0,
+                            LineJoin::Round => 1,
+                            LineJoin::Bevel => 2,
+                        },
+                        match stroke.line_cap {
+                            LineCap::Butt => 0,
+                            LineCap::Round => 1,
+                            LineCap::Square => 2,
+                        },
+                        num(stroke.miter_limit.max(1.0) as f64)
+                    );
+                    write_glyphs(&mut content, shaped, &outlines, page, scale, "S");
+                    content.push_str("Q\n");
+                }
+            }
+            if options.fill {
+                if let Some(color) = color_operators(
+                    &options.foreground_color,
+                    text_color,
+                    false,
+                    &mut graphics_states,
+                )? {
+                    let _ = writeln!(content, "q {color}");
+                    write_glyphs(&mut content, shaped, &outlines, page, scale, "f");
+                    content.push_str("Q\n");
+                }
+            }
+        }
+
+        let text_font = options
+            .embed_text
+            .then(|| TextLayer::new(shaped, page))
+            .flatten();
+        if let Some(layer) = &text_font {
+            layer.write_content(&mut content);
+        }
+
+        let mut writer = PdfWriter::new();
+        let catalog = writer.reserve();
+        let pages = writer.reserve();
+        let page_id = writer.reserve();
+        let contents = writer.reserve();
+
+        writer.object(catalog, &format!("<< /Type /Catalog /Pages {pages} 0 R >>"));
+        writer.object(
+            pages,
+            &format!("<< /Type /Pages /Kids [{page_id} 0 R] /Count 1 >>"),
+        );
+
+        let mut resources = String::new();
+        if !graphics_states.is_empty() {
+            resources.push_str(" /ExtGState <<");
+            for (index, (key, alpha)) in graphics_states.iter().enumerate() {
+                let _ = write!(resources, " /GS{index} << /{key} {} >>", num(*alpha));
+            }
+            resources.push_str(" >>");
+        }
+        if let Some(layer) = &text_font {
+            let font = layer.write_objects(&mut writer);
+            let _ = write!(resources, " /Font << /F0 {font} 0 R >>");
+        }
+
+        writer.object(
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent {pages} 0 R /MediaBox [0 0 {} {}] /Resources <<{resources} >> /Contents {contents} 0 R >>",
+                num(page.width()),
+                num(page.height())
+            ),
+        );
+        writer.stream(contents, "", content.as_bytes());
+
+        Ok(writer.finish(catalog))
+    }
+}
+
+/// Position of a glyph origin on the page (PDF space, y up from the bottom edge).
+fn page_origin(glyph: &Glyph, page: Rect) -> (f64, f64) {
+    (glyph.x as f64 - page.x0, page.y1 + glyph.y as f64)
+}
+
+/// Paint every glyph outline with `operator` (`f` to fill, `S` to stroke).
+fn write_glyphs(
+    content: &mut String,
+    shaped: &ShapingResult,
+    outlines: &HashMap<u32, Option<GlyphOutline>>,
+    page: Rect,
+    scale: f64,
+    operator: &str,
+) {
+    for glyph in &shaped.glyphs {
+        if let Some(Some(outline)) = outlines.get(&glyph.id) {
+            let (x, y) = page_origin(glyph, page);
+            let _ = write!(
+                content,
+                "q {s} 0 0 {s} {} {} cm ",
+                num(x),
+                num(y),
+                s = num(scale)
+            );
+            write_outline(content, outline);
+            let _ = writeln!(content, "{operator} Q");
+        }
+    }
+}
+
+/// Color operators for `color` (stroking or non-stroking), registering an alpha graphics
+/// state when needed.
+///
+/// Returns `None` for fully transparent colors, which paint nothing.
+fn color_operators(
+    color: &str,
+    current: Rgba,
+    stroking: bool,
+    graphics_states: &mut Vec<(&'static str, f64)>,
+) -> Result<Option<String>> {
+    let (r, g, b, a) = parse_color_with_current(color, current)?;
+    if a == 0 {
+        return Ok(None);
+    }
+
+    let mut ops = String::new();
+    if a < 255 {
+        let _ = write!(ops, "/GS{} gs ", graphics_states.len());
+        graphics_states.push((if stroking { "CA" } else { "ca" }, a as f64 / 255.0));
+    }
+    let _ = write!(
+        ops,
+        "{} {} {} {}",
+        num(r as f64 / 255.0),
+        num(g as f64 / 255.0),
+        num(b as f64 / 255.0),
+        if stroking { "RG" } else { "rg" }
+    );
+    Ok(Some(ops))
+}
+
+/// Append path construction operators for an outline in font units.
+///
+/// PDF has no quadratic segments, so TrueType curves are raised to cubics.
+fn write_outline(content: &mut String, outline: &GlyphOutline) {
+    let mut current = (0.0f32, 0.0f32);
+    for command in outline.commands() {
+        match *command {
+            OutlineCommand::MoveTo(x, y) => {
+                let _ = write!(content, "{} {} m ", num(x as f64), num(y as f64));
+                current = (x, y);
+            }
+            OutlineCommand::LineTo(x, y) => {
+                let _ = write!(content, "{} {} l ", num(x as f64), num(y as f64));
+                current = (x, y);
+            }
+            OutlineCommand::QuadTo {
+                ctrl_x,
+                ctrl_y,
+                x,
+                y,
+            } => {
+                let c1 = (
+                    current.0 + 2.0 / 3.0 * (ctrl_x - current.0),
+                    current.1 + 2.0 / 3.0 * (ctrl_y - current.1),
+                );
+                let c2 = (x + 2.0 / 3.0 * (ctrl_x - x), y + 2.0 / 3.0 * (ctrl_y - y));
+                write_curve(content, c1, c2, (x, y));
+                current = (x, y);
+            }
+            OutlineCommand::CurveTo {
+                ctrl1_x,
+                ctrl1_y,
+                ctrl2_x,
+                ctrl2_y,
+                x,
+                y,
+            } => {
+                write_curve(content, (ctrl1_x, ctrl1_y), (ctrl2_x, ctrl2_y), (x, y));
+                current = (x, y);
+            }
+            OutlineCommand::Close => content.push_str("h "),
+        }
+    }
+}
+
+fn write_curve(content: &mut String, c1: (f32, f32), c2: (f32, f32), end: (f32, f32)) {
+    let _ = write!(
+        content,
+        "{} {} {} {} {} {} c ",
+        num(c1.0 as f64),
+        num(c1.1 as f64),
+        num(c2.0 as f64),
+        num(c2.1 as f64),
+        num(end.0 as f64),
+        num(end.1 as f64)
+    );
+}
+
+/// Format a number for PDF content: at most three decimals, no trailing zeros.
+fn num(value: f64) -> String {
+    let mut text = format!("{value:.3}");
+    if text.contains('.') {
+        let trimmed = text.trim_end_matches('0').trim_end_matches('.').len();
+        text.truncate(trimmed);
+    }
+    if text == "-0" {
+        text.remove(0);
+    }
+    text
+}
+
+/// Invisible text drawn over the glyph paths so viewers can search and copy it.
+///
+/// Every cluster becomes one CID whose ToUnicode entry is the cluster's source text and whose
+/// width is the cluster's advance. CIDs map to the first glyph of their cluster, so the
+/// embedded font only needs those glyphs (and their composite components).
+struct TextLayer {
+    font_size: f32,
+    units_per_em: u16,
+    spans: Vec<TextSpan>,
+    font_file: Vec<u8>,
+    base_font: String,
+    bbox: [i16; 4],
+    ascent: i16,
+    descent: i16,
+}
+
+struct TextSpan {
+    text: String,
+    glyph_id: u16,
+    x: f64,
+    y: f64,
+    advance: f32,
+}
+
+impl TextLayer {
+    /// Build the text layer, or `None` when the font lacks TrueType outlines to embed.
+    fn new(shaped: &ShapingResult, page: Rect) -> Option<Self> {
+        let font = shaped.font.as_ref()?;
+        let (face, _) = face_and_scale(font)?;
+        let face = face.as_face_ref();
+
+        let mut spans = Vec::new();
+        for span in cluster_spans(shaped) {
+            let (_, y) = page_origin(span.glyph, page);
+            spans.push(TextSpan {
+                text: span.text.to_string(),
+                glyph_id: u16::try_from(span.glyph.id).ok()?,
+                x: span.x as f64 - page.x0,
+                y,
+                advance: span.advance,
+            });
+        }
+        if spans.is_empty() {
+            return None;
+        }
+
+        let used: BTreeSet<u16> = spans.iter().map(|span| span.glyph_id).collect();
+        let font_file = subset_truetype(face, &used)?;
+        let bbox = face.global_bounding_box();
+
+        Some(Self {
+            font_size: font.size,
+            units_per_em: face.units_per_em(),
+            spans,
+            base_font: format!("{}+{}", subset_tag(&used), postscript_name(face)),
+            font_file,
+            bbox: [bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max],
+            ascent: face.ascender(),
+            descent: face.descender(),
+        })
+    }
+
+    fn write_content(&self, content: &mut String) {
+        let _ = writeln!(content, "BT 3 Tr /F0 {} Tf", num(self.font_size as f64));
+        for (index, span) in self.spans.iter().enumerate() {
+            let _ = writeln!(
+                content,
+                "1 0 0 1 {} {} Tm <{:04X}> Tj",
+                num(span.x),
+                num(span.y),
+                index + 1
+            );
+        }
+        content.push_str("ET\n");
+    }
+
+    /// Write the font objects and return the id of the Type0 font dictionary.
+    fn write_objects(&self, writer: &mut PdfWriter) -> usize {
+        let type0 = writer.reserve();
+        let cid_font = writer.reserve();
+        let descriptor = writer.reserve();
+        let font_file = writer.reserve();
+        let cid_to_gid = writer.reserve();
+        let to_unicode = writer.reserve();
+
+        let em = |value: i16| num(value as f64 * 1000.0 / self.units_per_em as f64);
+
+        writer.object(
+            type0,
+            &format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{cid_font} 0 R] /ToUnicode {to_unicode} 0 R >>",
+                self.base_font
+            ),
+        );
+
+        let mut widths = String::new();
+        for span in &self.spans {
+            let width = if self.font_size > 0.0 {
+                span.advance as f64 * 1000.0 / self.font_size as f64
+            } else {
+                0.0
+            };
+            let _ = write!(widths, "{} ", num(width));
+        }
+        writer.object(
+            cid_font,
+            &format!(
+                "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {descriptor} 0 R /DW 0 /W [1 [{}]] /CIDToGIDMap {cid_to_gid} 0 R >>",
+                self.base_font,
+                widths.trim_end()
+            ),
+        );
+
+        writer.object(
+            descriptor,
+            &format!(
+                "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{} {} {} {}] /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /FontFile2 {font_file} 0 R >>",
+                self.base_font,
+                em(self.bbox[0]),
+                em(self.bbox[1]),
+                em(self.bbox[2]),
+                em(self.bbox[3]),
+                em(self.ascent),
+                em(self.descent),
+                em(self.ascent)
+            ),
+        );
+
+        writer.stream(
+            font_file,
+            &format!(" /Length1 {}", self.font_file.len()),
+            &self.font_file,
+        );
+
+        let mut map = vec![0u8, 0u8];
+        for span in &self.spans {
+            map.extend_from_slice(&span.glyph_id.to_be_bytes());
+        }
+        writer.stream(cid_to_gid, "", &map);
+
+        writer.stream(to_unicode, "", self.to_unicode_cmap().as_bytes());
+
+        type0
+    }
+
+    fn to_unicode_cmap(&self) -> String {
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+             1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+        );
+        let entries: Vec<(usize, &str)> = self
+            .spans
+            .iter()
+            .enumerate()
+            .filter(|(_, span)| !span.text.is_empty())
+            .map(|(index, span)| (index + 1, span.text.as_str()))
+            .collect();
+        // bfchar blocks hold at most 100 entries.
+        for block in entries.chunks(100) {
+            let _ = writeln!(cmap, "{} beginbfchar", block.len());
+            for (cid, text) in block {
+                let _ = write!(cmap, "<{cid:04X}> <");
+                for unit in text.encode_utf16() {
+                    let _ = write!(cmap, "{unit:04X}");
+                }
+                cmap.push_str(">\n");
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap
+    }
+}
+
+/// Six-letter subset prefix derived from the embedded glyph set.
+fn subset_tag(glyphs: &BTreeSet<u16>) -> String {
+    let mut hash: u32 = 2_166_136_261;
+    for glyph in glyphs {
+        for byte in glyph.to_be_bytes() {
+            hash = (hash ^ byte as u32).wrapping_mul(16_777_619);
+        }
+    }
+    (0..6)
+        .map(|i| (b'A' + ((hash >> (i * 5)) % 26) as u8) as char)
+        .collect()
+}
+
+/// PostScript name of the face, restricted to characters valid in a PDF name.
+fn postscript_name(face: &ttf_parser::Face<'_>) -> String {
+    let name: String = face
+        .names()
+        .into_iter()
+        .filter(|name| name.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+        .find_map(|name| name.to_string())
+        .unwrap_or_default()
+        .chars()
+        .filter(|ch| ch.is_ascii_alphanumeric() || *ch == '-' || *ch == '_')
+        .collect();
+    if name.is_empty() {
+        "Font".to_string()
+    } else {
+        name
+    }
+}
+
+/// Build a TrueType font program that keeps only `glyphs` (plus `.notdef` and composite
+/// components); every other `glyf` entry is emptied so glyph ids stay stable.
+///
+/// Returns `None` for fonts without `glyf` outlines.
+fn subset_truetype(face: &ttf_parser::Face<'_>, glyphs: &BTreeSet<u16>) -> Option<Vec<u8>> {
+    let raw = face.raw_face();
+    let table = |tag: &[u8; 4]| raw.table(Tag::from_bytes(tag));
+    let head = table(b"head")?;
+    let loca = table(b"loca")?;
+    let glyf = table(b"glyf")?;
+    let maxp = table(b"maxp")?;
+    if head.len() < 54 || maxp.len() < 6 {
+        return None;
+    }
+
+    let glyph_count = u16::from_be_bytes([maxp[4], maxp[5]]) as usize;
+    let long_offsets = i16::from_be_bytes([head[50], head[51]]) == 1;
+    let offset = |index: usize| -> Option<usize> {
+        if long_offsets {
+            let bytes = loca.get(index * 4..index * 4 + 4)?;
+            Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+        } else {
+            let bytes = loca.get(index * 2..index * 2 + 2)?;
+            Some(u16::from_be_bytes([bytes[0], bytes[1]]) as usize * 2)
+        }
+    };
+    let glyph_data = |index: usize| -> &[u8] {
+        match (offset(index), offset(index + 1)) {
+            (Some(start), Some(end)) if start <= end => glyf.get(start..end).unwrap_or_default(),
+            _ => &[],
+        }
+    };
+
+    // Close the glyph set over composite components.
+    let mut keep = BTreeSet::new();
+    let mut pending: Vec<u16> = glyphs.iter().copied().chain([0]).collect();
+    while let Some(glyph) = pending.pop() {
+        if (glyph as usize) >= glyph_count || !keep.insert(glyph) {
+            continue;
+        }
+        pending.extend(composite_components(glyph_data(glyph as usize)));
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((glyph_count + 1) * 4);
+    for index in 0..glyph_count {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if keep.contains(&(index as u16)) {
+            new_glyf.extend_from_slice(glyph_data(index));
+            while new_glyf.len() % 4 != 0 {
+                new_glyf.push(0);
+            }
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    let mut new_head = head.to_vec();
+    new_head[8..12].fill(0); // checkSumAdjustment
+    new_head[50..52].copy_from_slice(&1i16.to_be_bytes());
+
+    let mut tables: BTreeMap<[u8; 4], Vec<u8>> = BTreeMap::new();
+    tables.insert(*b"head", new_head);
+    tables.insert(*b"loca", new_loca);
+    tables.insert(*b"glyf", new_glyf);
+    for tag in [b"hhea", b"hmtx", b"maxp", b"cvt ", b"fpgm", b"prep"] {
+        if let Some(data) = table(tag) {
+            tables.insert(*tag, data.to_vec());
+        }
+    }
+
+    Some(write_sfnt(&tables))
+}
+
+/// Glyph ids referenced by a composite glyph (empty for simple glyphs).
+fn composite_components(data: &[u8]) -> Vec<u16> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let read = |pos: usize| {
+        data.get(pos..pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+    let mut components = Vec::new();
+    if data.len() < 10 || (read(0).unwrap_or(0) as i16) >= 0 {
+        return components;
+    }
+
+    let mut pos = 10;
+    while let (Some(flags), Some(glyph)) = (read(pos), read(pos + 2)) {
+        components.push(glyph);
+        pos += 4;
+        pos += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            4
+        } else {
+            2
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            pos += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            pos += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            pos += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    components
+}
+
+/// Serialize tables (sorted by tag) into an sfnt container.
+fn write_sfnt(tables: &BTreeMap<[u8; 4], Vec<u8>>) -> Vec<u8> {
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.max(1).leading_zeros() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    out.extend_from_slice(&count.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&(count * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, data) in tables {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&table_checksum(data).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().div_ceil(4) * 4;
+    }
+    for data in tables.values() {
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(4) * 4, 0);
+    }
+    out
+}
+
+fn table_checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Minimal PDF object writer tracking byte offsets for the cross-reference table.
+struct PdfWriter {
+    buf: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        Self {
+            buf: b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+            offsets: Vec::new(),
+        }
+    }
+
+    /// Allocate the next object id.
+    fn reserve(&mut self) -> usize {
+        self.offsets.push(0);
+        self.offsets.len()
+    }
+
+    fn object(&mut self, id: usize, body: &str) {
+        self.offsets[id - 1] = self.buf.len();
+        let _ = write!(self.buf, "{id} 0 obj\n{body}\nendobj\n");
+    }
+
+    /// Write a Flate-compressed stream; `extra` is appended to the stream dictionary.
+    fn stream(&mut self, id: usize, extra: &str, data: &[u8]) {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .expect("in-memory compression cannot fail");
+
+        self.offsets[id - 1] = self.buf.len();
+        let _ = write!(
+            self.buf,
+            "{id} 0 obj\n<< /Length {} /Filter /FlateDecode{extra} >>\nstream\n",
+            compressed.len()
+        );
+        self.buf.extend_from_slice(&compressed);
+        self.buf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        let xref = self.buf.len();
+        let _ = write!(
+            self.buf,
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.offsets.len() + 1
+        );
+        for offset in &self.offsets {
+            let _ = writeln!(self.buf, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            self.buf,
+            "trailer\n<< /Size {} /Root {root} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.offsets.len() + 1
+        );
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::SvgRenderer;
+    use crate::test_support::{fixture_font, nominal_shaped};
+    use flate2::read::ZlibDecoder;
+    use o4e_core::types::{Stroke, SvgOptions};
+    use std::io::Read;
+
+    fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+        haystack[from..]
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map(|i| from + i)
+    }
+
+    /// Dictionaries and decompressed data of every stream object, in file order (the page
+    /// content stream is written last).
+    fn streams(pdf: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut found = Vec::new();
+        let mut cursor = 0;
+        while let Some(start) = find(pdf, b">>\nstream\n", cursor) {
+            let dict_start = pdf[..start].windows(2).rposition(|w| w == b"<<").unwrap();
+            let dict = String::from_utf8_lossy(&pdf[dict_start..start + 2]).into_owned();
+            let length: usize = dict
+                .split("/Length ")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|n| n.parse().ok())
+                .expect("stream length");
+            let data_start = start + 10;
+            let data = &pdf[data_start..data_start + length];
+            let mut decoded = Vec::new();
+            ZlibDecoder::new(data).read_to_end(&mut decoded).unwrap();
+            found.push((dict, decoded));
+            cursor = data_start + length;
+        }
+        found
+    }
+
+    fn media_box(pdf: &[u8]) -> (f64, f64) {
+        let text = String::from_utf8_lossy(pdf);
+        let start = text.find("/MediaBox [0 0 ").expect("media box") + 15;
+        let end = start + text[start..].find(']').unwrap();
+        let values: Vec<f64> = text[start..end]
+            .split_whitespace()
+            .map(|v| v.parse().unwrap())
+            .collect();
+        (values[0], values[1])
+    }
+
+    #[test]
+    fn test_cross_reference_offsets_point_at_objects() {
+        let font = fixture_font(24.0);
+        let pdf = PdfRenderer::new()
+            .render(&nominal_shaped("Hi", &font), &PdfOptions::default())
+            .unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.7\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+
+        let startxref = pdf.windows(10).rposition(|w| w == b"startxref\n").unwrap();
+        let xref: usize = String::from_utf8_lossy(&pdf[startxref + 10..])
+            .lines()
+            .next()
+            .and_then(|n| n.parse().ok())
+            .expect("startxref");
+        let table = String::from_utf8_lossy(&pdf[xref..startxref]).into_owned();
+        assert!(table.starts_with("xref\n"));
+        let entries: Vec<&str> = table
+            .lines()
+            .skip(3)
+            .take_while(|line| !line.starts_with("trailer"))
+            .collect();
+        assert!(entries.len() >= 10, "text layer adds font objects");
+        for (id, line) in entries.iter().enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            let header = format!("{} 0 obj", id + 1);
+            assert!(
+                pdf[offset..].starts_with(header.as_bytes()),
+                "object {}",
+                id + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_page_matches_svg_view_box_and_paints_glyph_paths() {
+        let font = fixture_font(24.0);
+        let shaped = nominal_shaped("Ag", &font);
+        let options = PdfOptions {
+            foreground_color: "#33669980".to_string(),
+            background: Some("#ffffff".to_string()),
+            padding: 5.0,
+            embed_text: false,
+            ..PdfOptions::default()
+        };
+        let pdf = PdfRenderer::new().render(&shaped, &options).unwrap();
+
+        let svg = SvgRenderer::default()
+            .render(
+                &shaped,
+                &SvgOptions {
+                    padding: 5.0,
+                    ..SvgOptions::default()
+                },
+            )
+            .unwrap();
+        let start = svg.find("viewBox=\"").unwrap() + 9;
+        let end = start + svg[start..].find('"').unwrap();
+        let view_box: Vec<f64> = svg[start..end]
+            .split_whitespace()
+            .map(|v| v.parse().unwrap())
+            .collect();
+        let (width, height) = media_box(&pdf);
+        assert!(
+            (width - view_box[2]).abs() < 0.01,
+            "{width} vs {view_box:?}"
+        );
+        assert!(
+            (height - view_box[3]).abs() < 0.01,
+            "{height} vs {view_box:?}"
+        );
+
+        let text = String::from_utf8_lossy(&pdf).into_owned();
+        assert!(
+            text.contains("/ExtGState << /GS0 << /ca 0.502 >> >>"),
+            "{text}"
+        );
+        assert!(!text.contains("/Font"), "{text}");
+
+        let (_, content) = streams(&pdf).pop().unwrap();
+        let content = String::from_utf8_lossy(&content);
+        assert!(content.starts_with("q 1 1 1 rg 0 0 "), "{content}");
+        assert!(content.contains("q /GS0 gs 0.2 0.4 0.6 rg\n"), "{content}");
+        assert_eq!(content.matches(" cm ").count(), 2, "{content}");
+        assert!(content.contains(" c "), "TrueType curves become cubics");
+        assert!(!content.contains("BT"), "{content}");
+    }
+
+    #[test]
+    fn test_stroke_is_painted_under_the_fill() {
+        let font = fixture_font(24.0);
+        let shaped = nominal_shaped("o", &font);
+        let options = PdfOptions {
+            stroke: Some(Stroke {
+                width: 2.0,
+                color: "#ff000080".to_string(),
+                line_join: LineJoin::Round,
+                ..Stroke::default()
+            }),
+            embed_text: false,
+            ..PdfOptions::default()
+        };
+        let pdf = PdfRenderer::new().render(&shaped, &options).unwrap();
+        let text = String::from_utf8_lossy(&pdf).into_owned();
+        assert!(text.contains("/GS0 << /CA 0.502 >>"), "{text}");
+
+        let (_, content) = streams(&pdf).pop().unwrap();
+        let content = String::from_utf8_lossy(&content);
+        let stroke = content.find("q /GS0 gs 1 0 0 RG ").expect("stroke layer");
+        let fill = content.find("q 0 0 0 rg\n").expect("fill layer");
+        assert!(stroke < fill, "{content}");
+        assert!(content.contains(" w 1 j 0 J 4 M\n"), "{content}");
+        assert!(content.contains("S Q\n") && content.contains("f Q\n"));
+
+        let outlined = PdfRenderer::new()
+            .render(
+                &shaped,
+                &PdfOptions {
+                    fill: false,
+                    ..options
+                },
+            )
+            .unwrap();
+        let (_, content) = streams(&outlined).pop().unwrap();
+        assert!(!String::from_utf8_lossy(&content).contains("f Q"));
+    }
+
+    #[test]
+    fn test_invalid_colors_are_errors() {
+        let shaped = nominal_shaped("o", &fixture_font(24.0));
+        let foreground = PdfOptions {
+            foreground_color: "blurple".to_string(),
+            ..PdfOptions::default()
+        };
+        let background = PdfOptions {
+            background: Some("#12".to_string()),
+            ..PdfOptions::default()
+        };
+        for options in [foreground, background] {
+            assert!(matches!(
+                PdfRenderer::new().render(&shaped, &options),
+                Err(o4e_core::O4eError::InvalidColor(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_text_layer_embeds_subset_font_and_to_unicode() {
+        let font = fixture_font(24.0);
+        let shaped = nominal_shaped("Hé", &font);
+        let pdf = PdfRenderer::new()
+            .render(&shaped, &PdfOptions::default())
+            .unwrap();
+        let streams = streams(&pdf);
+
+        let content = String::from_utf8_lossy(&streams.last().unwrap().1).into_owned();
+        assert!(content.contains("BT 3 Tr /F0 24 Tf"), "{content}");
+        assert!(content.contains("<0001> Tj") && content.contains("<0002> Tj"));
+
+        let (dict, font_file) = streams
+            .iter()
+            .find(|(dict, _)| dict.contains("/Length1"))
+            .expect("embedded font program");
+        assert!(dict.contains(&format!("/Length1 {}", font_file.len())));
+        let subset = ttf_parser::Face::parse(font_file, 0).expect("subset parses");
+        let source = face_and_scale(&font).unwrap().0;
+        let source = source.as_face_ref();
+        let eacute = source.glyph_index('é').unwrap();
+        let unused = source.glyph_index('z').unwrap();
+        assert!(
+            crate::outlines::glyph_outline(&subset, eacute).is_some(),
+            "composite glyph keeps its components"
+        );
+        assert!(crate::outlines::glyph_outline(&subset, unused).is_none());
+
+        let cmap = streams
+            .iter()
+            .map(|(_, data)| String::from_utf8_lossy(data).into_owned())
+            .find(|data| data.contains("begincmap"))
+            .expect("ToUnicode CMap");
+        assert!(cmap.contains("<0001> <0048>\n<0002> <00E9>\n"), "{cmap}");
+
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Subtype /CIDFontType2"));
+        assert!(text.contains("+NotoSans-Regular"));
+    }
+
+    #[test]
+    fn test_text_layer_maps_every_run_of_combined_text() {
+        let font = fixture_font(24.0);
+        let shaped = o4e_core::utils::combine_shaped_results(vec![
+            nominal_shaped("Hé", &font),
+            nominal_shaped("zH", &font),
+        ]);
+        let pdf = PdfRenderer::new()
+            .render(&shaped, &PdfOptions::default())
+            .unwrap();
+        let cmap = streams(&pdf)
+            .iter()
+            .map(|(_, data)| String::from_utf8_lossy(data).into_owned())
+            .find(|data| data.contains("begincmap"))
+            .expect("ToUnicode CMap");
+        assert!(
+            cmap.contains("<0001> <0048>\n<0002> <00E9>\n<0003> <007A>\n<0004> <0048>\n"),
+            "{cmap}"
+        );
+    }
+}
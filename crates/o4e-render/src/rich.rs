@@ -0,0 +1,791 @@
+// this_file: crates/o4e-render/src/rich.rs
+
+//! Attributed (rich) text: one string with spans of font, color, decoration and baseline
+//! attributes, laid out on one line and rendered as one image or SVG.
+//!
+//! Span edges are passed to [`Backend::segment_with_boundaries`], so every run the
+//! segmenter produces (bidi, script and span boundaries together) carries one set of
+//! attributes. Runs are shaped with their span's font and placed one after the other in
+//! visual order. SVG output draws each styled run as its own group; bitmap formats render
+//! each run onto a canvas covering the whole text and composite the layers.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use kurbo::Rect;
+use o4e_core::{
+    render_metadata,
+    types::{
+        BaselinePosition, Canvas, Direction, FontSource, FontStretch, FontStyle, HorizontalAlign,
+        VerticalAlign,
+    },
+    units::{device_scale, to_device_pixels},
+    utils::{calculate_bbox, parse_color, parse_color_with_current},
+    Backend, Decoration, Font, Frame, O4eError, Paint, PixelFormat, RenderFormat, RenderOptions,
+    RenderOutput, RenderSurface, Result, SegmentOptions, ShapingResult, TextAnchor, TextRun,
+    Transform2D,
+};
+
+use crate::paragraph::LineShaper;
+use crate::svg::{font_identity, line_extents, SvgLayer, SvgRenderer};
+use crate::tabs::{tab_gap, TabLayout, TabOptions};
+
+/// Attributes of a span; unset fields keep the base font and render options.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpanStyle {
+    pub family: Option<String>,
+    /// Font size in points
+    pub size: Option<f32>,
+    pub weight: Option<u16>,
+    pub style: Option<FontStyle>,
+    pub stretch: Option<FontStretch>,
+    /// OpenType features switched on or off, on top of the font's
+    pub features: HashMap<String, bool>,
+    /// Variation axis values, on top of the font's
+    pub variations: HashMap<String, f32>,
+    /// Text color (CSS color)
+    pub color: Option<String>,
+    /// Lines drawn in addition to the render options' decorations; a span's line replaces
+    /// one of the same kind
+    pub decorations: Vec<Decoration>,
+    /// Points the baseline is raised by (negative lowers it); nested spans add up
+    pub baseline_shift: f32,
+}
+
+impl SpanStyle {
+    /// Apply the attributes `inner` sets on top of these, as for a span nested in this one.
+    pub fn merge(&mut self, inner: &SpanStyle) {
+        if inner.family.is_some() {
+            self.family.clone_from(&inner.family);
+        }
+        self.size = inner.size.or(self.size);
+        self.weight = inner.weight.or(self.weight);
+        self.style = inner.style.or(self.style);
+        self.stretch = inner.stretch.or(self.stretch);
+        self.features.extend(inner.features.clone());
+        self.variations.extend(inner.variations.clone());
+        if inner.color.is_some() {
+            self.color.clone_from(&inner.color);
+        }
+        self.decorations = merge_decorations(&self.decorations, &inner.decorations);
+        self.baseline_shift += inner.baseline_shift;
+    }
+
+    /// `font` with the font attributes of this style.
+    pub fn font(&self, font: &Font) -> Font {
+        let mut font = font.clone();
+        if let Some(family) = &self.family {
+            font.family = family.clone();
+            font.source = FontSource::Family(family.clone());
+        }
+        font.size = self.size.unwrap_or(font.size);
+        font.weight = self.weight.unwrap_or(font.weight);
+        font.style = self.style.unwrap_or(font.style);
+        font.stretch = self.stretch.unwrap_or(font.stretch);
+        font.features.tags.extend(self.features.clone());
+        font.variations.extend(self.variations.clone());
+        font
+    }
+}
+
+/// Attributes over a byte range of the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub range: Range<usize>,
+    pub style: SpanStyle,
+}
+
+impl TextSpan {
+    pub fn new(range: Range<usize>, style: SpanStyle) -> Self {
+        Self { range, style }
+    }
+}
+
+/// Glyphs of one font, color and set of decorations.
+#[derive(Debug, Clone)]
+pub struct StyledRun {
+    /// Glyphs positioned from the start of the line, baseline shift included; clusters are
+    /// byte offsets into `shaped.text`
+    pub shaped: ShapingResult,
+    /// Byte range of the run in the attributed text
+    pub range: Range<usize>,
+    /// Text color; the render options' color when unset
+    pub color: Option<String>,
+    /// Decorations on top of the render options' decorations
+    pub decorations: Vec<Decoration>,
+}
+
+/// Attributed text laid out on one line, in visual order.
+#[derive(Debug, Clone)]
+pub struct RichLayout {
+    pub runs: Vec<StyledRun>,
+    /// Total pen advance in points
+    pub advance: f32,
+}
+
+impl RichLayout {
+    /// All glyphs as one shaping result with the first run's font, for measuring and
+    /// metadata; rendering needs the runs.
+    pub fn to_shaped(&self) -> ShapingResult {
+        let mut text = String::new();
+        let mut glyphs = Vec::new();
+        for run in &self.runs {
+            let offset = text.len() as u32;
+            glyphs.extend(run.shaped.glyphs.iter().cloned().map(|mut glyph| {
+                glyph.cluster += offset;
+                glyph
+            }));
+            text.push_str(&run.shaped.text);
+        }
+        let first = self.runs.first().map(|run| &run.shaped);
+        ShapingResult {
+            text,
+            bbox: calculate_bbox(&glyphs),
+            glyphs,
+            advance: self.advance,
+            font: first.and_then(|shaped| shaped.font.clone()),
+            direction: first.map_or(Direction::LeftToRight, |shaped| shaped.direction),
+            lines: Vec::new(),
+        }
+    }
+}
+
+/// Segment and shape `text` with the attributes of `spans` over `font`.
+///
+/// Where spans overlap, later spans apply on top of earlier ones (see [`SpanStyle::merge`]).
+/// A span family the backend cannot find falls back to the base font's family. Span ranges
+/// must lie on character boundaries of `text`. Tabs move the runs after them to the stops of
+/// `tabs`, measured in the base font. `adjust_run` sees every run before it is shaped, for
+/// script, language or direction overrides.
+pub fn shape_rich(
+    backend: &dyn Backend,
+    text: &str,
+    spans: &[TextSpan],
+    font: &Font,
+    segment: &SegmentOptions,
+    tabs: &TabOptions,
+    adjust_run: impl Fn(&mut TextRun),
+) -> Result<RichLayout> {
+    for span in spans {
+        let Range { start, end } = span.range;
+        if start > end || !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            return Err(O4eError::InvalidParameter {
+                name: "span".to_string(),
+                value: format!("{start}..{end} in {} bytes of text", text.len()),
+            });
+        }
+    }
+    tabs.validate()?;
+    let tab_layout = if text.contains('\t') {
+        let shaper = LineShaper {
+            backend,
+            font,
+            segment,
+            adjust_run: &adjust_run,
+        };
+        Some(TabLayout::new(&shaper, tabs)?)
+    } else {
+        None
+    };
+    // Every tab is a run of its own
+    let boundaries: Vec<usize> = spans
+        .iter()
+        .flat_map(|span| [span.range.start, span.range.end])
+        .chain(
+            text.match_indices('\t')
+                .flat_map(|(offset, _)| [offset, offset + 1]),
+        )
+        .collect();
+
+    let mut pieces: Vec<Piece> = Vec::new();
+    for mut run in backend.segment_with_boundaries(text, segment, &boundaries)? {
+        adjust_run(&mut run);
+        let covering: Vec<usize> = spans
+            .iter()
+            .enumerate()
+            .filter(|(_, span)| span.range.contains(&run.range.0))
+            .map(|(index, _)| index)
+            .collect();
+        let mut style = SpanStyle::default();
+        for &index in &covering {
+            style.merge(&spans[index].style);
+        }
+        let run_font = style.font(font);
+        run.font = Some(run_font.clone());
+        let shaped = match (&tab_layout, run.text.as_str()) {
+            // Tabs are sized once the text after them is known
+            (Some((_, space_id)), "\t") => tab_gap(font, *space_id, 0.0),
+            // A family the backend cannot find keeps the base font's
+            _ => match backend.shape(&run, &run_font) {
+                Err(O4eError::FontNotFound { .. }) if style.family.is_some() => {
+                    let base_family = SpanStyle {
+                        family: None,
+                        ..style.clone()
+                    }
+                    .font(font);
+                    run.font = Some(base_family.clone());
+                    backend.shape(&run, &base_family)?
+                }
+                result => result?,
+            },
+        };
+        pieces.push(Piece {
+            run,
+            covering,
+            style,
+            shaped,
+        });
+    }
+
+    if let Some((layout, _)) = &tab_layout {
+        let mut pen = 0.0;
+        for index in 0..pieces.len() {
+            if pieces[index].is_tab() {
+                let following: Vec<&Piece> = pieces[index + 1..]
+                    .iter()
+                    .take_while(|piece| !piece.is_tab())
+                    .collect();
+                let advance = following.iter().map(|piece| piece.shaped.advance).sum();
+                let before_decimal = before_decimal(&following, layout.decimal());
+                let width = layout.place(pen, advance, before_decimal) - pen;
+                let gap = &mut pieces[index].shaped;
+                gap.glyphs[0].advance = width;
+                gap.advance = width;
+            }
+            pen += pieces[index].shaped.advance;
+        }
+    }
+
+    let mut runs: Vec<StyledRun> = Vec::new();
+    // Spans covering the last run, to merge runs with the same attributes and font; tabs
+    // keep runs of their own
+    let mut last_key: Option<(Vec<usize>, String)> = None;
+    let mut pen = 0.0;
+    for piece in pieces {
+        let is_tab = piece.is_tab();
+        let Piece {
+            run,
+            covering,
+            style,
+            mut shaped,
+        } = piece;
+        for glyph in &mut shaped.glyphs {
+            glyph.x += pen;
+            glyph.y += style.baseline_shift;
+        }
+        pen += shaped.advance;
+
+        let key = (!is_tab).then(|| {
+            (
+                covering,
+                shaped.font.as_ref().map(font_identity).unwrap_or_default(),
+            )
+        });
+        match runs.last_mut() {
+            Some(last) if key.is_some() && last_key == key && last.range.end == run.range.0 => {
+                let offset = last.shaped.text.len() as u32;
+                last.shaped
+                    .glyphs
+                    .extend(shaped.glyphs.into_iter().map(|mut glyph| {
+                        glyph.cluster += offset;
+                        glyph
+                    }));
+                last.shaped.text.push_str(&shaped.text);
+                last.shaped.advance += shaped.advance;
+                last.shaped.bbox = calculate_bbox(&last.shaped.glyphs);
+                last.range.end = run.range.1;
+            }
+            _ => {
+                shaped.bbox = calculate_bbox(&shaped.glyphs);
+                runs.push(StyledRun {
+                    shaped,
+                    range: run.range.0..run.range.1,
+                    color: style.color,
+                    decorations: style.decorations,
+                });
+            }
+        }
+        last_key = key;
+    }
+    Ok(RichLayout { runs, advance: pen })
+}
+
+/// A run shaped from x = 0, with the spans covering it and their merged style.
+struct Piece {
+    run: TextRun,
+    covering: Vec<usize>,
+    style: SpanStyle,
+    shaped: ShapingResult,
+}
+
+impl Piece {
+    fn is_tab(&self) -> bool {
+        self.run.text == "\t"
+    }
+}
+
+/// Advance of `pieces` up to the first `decimal` character, if one of them has it.
+fn before_decimal(pieces: &[&Piece], decimal: char) -> Option<f32> {
+    let mut pen = 0.0;
+    for piece in pieces {
+        if let Some(offset) = piece.run.text.find(decimal) {
+            let before: f32 = piece
+                .shaped
+                .glyphs
+                .iter()
+                .filter(|glyph| (glyph.cluster as usize) < offset)
+                .map(|glyph| glyph.advance)
+                .sum();
+            return Some(pen + before);
+        }
+        pen += piece.shaped.advance;
+    }
+    None
+}
+
+/// Render a laid-out attributed text as one image or SVG with `backend`.
+///
+/// The options apply to the whole text as they do for [`Backend::render`]; run colors and
+/// decorations take over from the text color, paint and decorations. PDF output is not
+/// supported. In bitmap formats, backgrounds other than a solid color are drawn under the
+/// first run, so effects see them as part of the text.
+pub fn render_rich(
+    backend: &dyn Backend,
+    layout: &RichLayout,
+    options: &RenderOptions,
+) -> Result<RenderOutput> {
+    let decorations: Vec<Vec<Decoration>> = layout
+        .runs
+        .iter()
+        .map(|run| merge_decorations(&options.decorations, &run.decorations))
+        .collect();
+    let layers: Vec<SvgLayer<'_>> = layout
+        .runs
+        .iter()
+        .zip(&decorations)
+        .map(|(run, decorations)| SvgLayer {
+            shaped: &run.shaped,
+            color: run.color.as_deref(),
+            decorations: Some(decorations),
+        })
+        .collect();
+    match options.format {
+        RenderFormat::Svg => {
+            let svg_options = options.svg_options();
+            let renderer = SvgRenderer::new(&svg_options);
+            return Ok(RenderOutput::Svg(
+                renderer.render_layers(&layers, &svg_options)?,
+            ));
+        }
+        RenderFormat::Pdf => {
+            return Err(O4eError::render(
+                "Attributed text renders to bitmap formats and SVG, not PDF",
+            ));
+        }
+        _ => {}
+    }
+
+    // Every layer is drawn on a canvas covering what the SVG viewBox would, in whole points
+    let svg_options = options.svg_options();
+    let area = SvgRenderer::new(&svg_options)
+        .layer_bounds(&layers, &svg_options)
+        .view_box;
+    let padding = if options.canvas.is_some() {
+        0.0
+    } else {
+        f64::from(options.padding)
+    };
+    let area = Rect::new(
+        (area.x0 - padding).floor(),
+        (area.y0 - padding).floor(),
+        (area.x1 + padding).ceil(),
+        (area.y1 + padding).ceil(),
+    );
+    let layer_canvas = Canvas {
+        width: area.width().max(1.0) as u32,
+        height: area.height().max(1.0) as u32,
+        baseline: BaselinePosition::Absolute(-area.y0 as f32),
+        horizontal_align: HorizontalAlign::Left,
+        vertical_align: VerticalAlign::Baseline,
+        overflow: Default::default(),
+    };
+    let scale = device_scale(options.dpi)?;
+    let width = (layer_canvas.width as f32 * scale).round().max(1.0) as u32;
+    let height = (layer_canvas.height as f32 * scale).round().max(1.0) as u32;
+    let mut data = vec![0u8; width as usize * height as usize * 4];
+    let mut frame = Frame::new(
+        &mut data,
+        width,
+        height,
+        width as usize * 4,
+        PixelFormat::Rgba8Premultiplied,
+    )?;
+
+    let background = options.resolved_background();
+    let solid_background = match &background {
+        Some(Paint::Solid(color)) => Some(parse_color_with_current(
+            color,
+            parse_color(&options.color)?,
+        )?),
+        _ => None,
+    };
+    // The layer canvas starts at the left edge of `area`, which is in output space. Text
+    // without a transform or path moves its glyphs there; otherwise the move follows the
+    // transform, so glyphs keep their places on the path
+    let transformed = options.text_path.is_some()
+        || options
+            .transform
+            .as_ref()
+            .is_some_and(|transform| !transform.is_identity());
+    let transform = transformed.then(|| {
+        options
+            .transform
+            .unwrap_or_default()
+            .then(Transform2D::translate(-area.x0 as f32, 0.0))
+    });
+    for (index, (run, decorations)) in layout.runs.iter().zip(decorations).enumerate() {
+        let mut shaped = run.shaped.clone();
+        if !transformed {
+            for glyph in &mut shaped.glyphs {
+                glyph.x -= area.x0 as f32;
+            }
+        }
+        let layer_options = RenderOptions {
+            transform,
+            format: RenderFormat::Raw,
+            color: run.color.clone().unwrap_or_else(|| options.color.clone()),
+            paint: if run.color.is_some() {
+                None
+            } else {
+                options.paint.clone()
+            },
+            decorations,
+            background: "transparent".to_string(),
+            background_paint: background
+                .clone()
+                .filter(|_| index == 0 && solid_background.is_none()),
+            effects: Vec::new(),
+            canvas: Some(layer_canvas),
+            pixel_format: PixelFormat::Rgba8Premultiplied,
+            ..options.clone()
+        };
+        match backend.render(&shaped, &layer_options)? {
+            RenderOutput::Bitmap(bitmap) => frame.draw_bitmap(0, 0, &bitmap),
+            _ => {
+                return Err(O4eError::render(format!(
+                    "{} did not return a bitmap to composite",
+                    backend.name()
+                )))
+            }
+        }
+    }
+
+    let shaped = layout.to_shaped();
+    let mut surface = RenderSurface::from_rgba(width, height, data, true)
+        .with_effects(options.effects.clone())
+        .with_pixel_format(options.pixel_format)
+        .with_jpeg_quality(options.jpeg_quality)
+        .with_png_options(options.png.clone().unwrap_or_default())
+        .with_metadata(render_metadata(backend.name(), &shaped));
+    if let Some((r, g, b, a)) = solid_background {
+        surface = surface.with_background([r, g, b, a]);
+    }
+    let (_, device_options) = to_device_pixels(&shaped, options)?;
+    if let Some(canvas) = device_options.canvas {
+        let (ascent, descent) = layout
+            .runs
+            .iter()
+            .map(|run| line_extents(&run.shaped))
+            .fold((0.0f32, 0.0f32), |(ascent, descent), (top, bottom)| {
+                (ascent.max(-top), descent.max(bottom))
+            });
+        surface = surface.with_canvas(
+            canvas,
+            TextAnchor {
+                origin_x: -area.x0 as f32 * scale,
+                baseline_y: -area.y0 as f32 * scale,
+                advance: layout.advance * scale,
+                ascent: ascent * scale,
+                descent: descent * scale,
+            },
+        );
+    }
+    surface.into_render_output(options.format)
+}
+
+/// `base` with the lines of `overrides` added, each replacing a line of the same kind.
+fn merge_decorations(base: &[Decoration], overrides: &[Decoration]) -> Vec<Decoration> {
+    let mut merged: Vec<Decoration> = base
+        .iter()
+        .filter(|decoration| overrides.iter().all(|other| other.line != decoration.line))
+        .cloned()
+        .collect();
+    merged.extend(overrides.iter().cloned());
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tabs::{TabAlign, TabStop};
+    use crate::test_support::{BidiBackend, MonoBackend};
+    use o4e_core::DecorationLine;
+
+    fn style(apply: impl FnOnce(&mut SpanStyle)) -> SpanStyle {
+        let mut style = SpanStyle::default();
+        apply(&mut style);
+        style
+    }
+
+    #[test]
+    fn spans_split_runs_and_set_fonts_colors_and_shifts() {
+        let spans = [
+            TextSpan::new(
+                2..6,
+                style(|style| {
+                    style.size = Some(20.0);
+                    style.color = Some("red".to_string());
+                }),
+            ),
+            // Nested inside the first span
+            TextSpan::new(
+                4..6,
+                style(|style| {
+                    style.weight = Some(700);
+                    style.baseline_shift = 5.0;
+                    style.decorations = vec![Decoration::new(DecorationLine::Underline)];
+                }),
+            ),
+        ];
+        let layout = shape_rich(
+            &MonoBackend,
+            "a bold c",
+            &spans,
+            &Font::new("Test", 10.0),
+            &SegmentOptions::default(),
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+
+        let texts: Vec<&str> = layout
+            .runs
+            .iter()
+            .map(|run| run.shaped.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["a ", "bo", "ld", " c"]);
+        let bold = &layout.runs[2];
+        let font = bold.shaped.font.as_ref().unwrap();
+        assert_eq!((font.size, font.weight), (20.0, 700));
+        assert_eq!(bold.color.as_deref(), Some("red"));
+        assert_eq!(bold.decorations.len(), 1);
+        // Pen positions continue across runs: "a " at 10, "bo" at 20
+        assert_eq!(bold.shaped.glyphs[0].x, 60.0);
+        assert_eq!(bold.shaped.glyphs[0].y, 5.0);
+        assert_eq!(layout.runs[3].shaped.glyphs[0].x, 100.0);
+        assert_eq!(layout.advance, 120.0);
+        assert!(layout.runs[3].color.is_none());
+
+        let shaped = layout.to_shaped();
+        assert_eq!(shaped.text, "a bold c");
+        assert_eq!(shaped.glyphs[4].cluster, 4);
+    }
+
+    #[test]
+    fn right_to_left_spans_are_placed_in_visual_order() {
+        // "مرحبا بالعالم" with the second word red: it is drawn first, at the left
+        let text = "مرحبا بالعالم";
+        let spans = [TextSpan::new(
+            11..text.len(),
+            style(|style| style.color = Some("red".to_string())),
+        )];
+        let segment = SegmentOptions {
+            bidi_resolve: true,
+            ..SegmentOptions::default()
+        };
+        let layout = shape_rich(
+            &BidiBackend,
+            text,
+            &spans,
+            &Font::new("Test", 10.0),
+            &segment,
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        let placed: Vec<(Range<usize>, f32)> = layout
+            .runs
+            .iter()
+            .map(|run| (run.range.clone(), run.shaped.glyphs[0].x))
+            .collect();
+        assert_eq!(placed, vec![(11..text.len(), 0.0), (0..11, 70.0)]);
+        assert_eq!(layout.runs[0].color.as_deref(), Some("red"));
+        assert_eq!(layout.advance, 130.0);
+    }
+
+    #[test]
+    fn unknown_span_families_keep_the_base_font() {
+        /// [`MonoBackend`] that only knows the "Test" family.
+        struct TestFamilyBackend;
+
+        impl Backend for TestFamilyBackend {
+            fn segment(&self, text: &str, options: &SegmentOptions) -> Result<Vec<TextRun>> {
+                MonoBackend.segment(text, options)
+            }
+
+            fn shape(&self, run: &TextRun, font: &Font) -> Result<ShapingResult> {
+                if font.family != "Test" {
+                    return Err(O4eError::FontNotFound {
+                        name: font.family.clone(),
+                    });
+                }
+                MonoBackend.shape(run, font)
+            }
+
+            fn render(
+                &self,
+                shaped: &ShapingResult,
+                options: &RenderOptions,
+            ) -> Result<RenderOutput> {
+                MonoBackend.render(shaped, options)
+            }
+
+            fn name(&self) -> &str {
+                "test-family"
+            }
+
+            fn clear_cache(&self) {}
+        }
+
+        let spans = [TextSpan::new(
+            0..4,
+            style(|style| {
+                style.family = Some("monospace".to_string());
+                style.weight = Some(700);
+            }),
+        )];
+        let layout = shape_rich(
+            &TestFamilyBackend,
+            "code x",
+            &spans,
+            &Font::new("Test", 10.0),
+            &SegmentOptions::default(),
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        let font = layout.runs[0].shaped.font.as_ref().unwrap();
+        assert_eq!((font.family.as_str(), font.weight), ("Test", 700));
+    }
+
+    #[test]
+    fn runs_with_the_same_attributes_merge_and_bad_spans_fail() {
+        let spans = [TextSpan::new(0..1, SpanStyle::default())];
+        let layout = shape_rich(
+            &MonoBackend,
+            "ab",
+            &spans,
+            &Font::new("Test", 10.0),
+            &SegmentOptions::default(),
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(layout.runs.len(), 2);
+
+        let layout = shape_rich(
+            &MonoBackend,
+            "ab",
+            &[],
+            &Font::new("Test", 10.0),
+            &SegmentOptions::default(),
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(layout.runs.len(), 1);
+
+        // Past the end, inside "é" and reversed
+        for (start, end) in [(0, 9), (1, 2), (2, 0)] {
+            let spans = [TextSpan::new(start..end, SpanStyle::default())];
+            assert!(shape_rich(
+                &MonoBackend,
+                "é",
+                &spans,
+                &Font::new("Test", 10.0),
+                &SegmentOptions::default(),
+                &TabOptions::default(),
+                |_| {}
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn tabs_move_styled_runs_to_their_stops() {
+        let spans = [TextSpan::new(
+            0..3,
+            style(|style| style.color = Some("#ff0000".to_string())),
+        )];
+        let tabs = TabOptions {
+            stops: vec![TabStop::new(80.0, TabAlign::Decimal)],
+            interval: Some(40.0),
+            ..TabOptions::default()
+        };
+        let layout = shape_rich(
+            &MonoBackend,
+            "ERR\t12.5\tdone",
+            &spans,
+            &Font::new("Test", 10.0),
+            &SegmentOptions::default(),
+            &tabs,
+            |_| {},
+        )
+        .unwrap();
+        let start = |c: char| {
+            let glyphs = layout.runs.iter().flat_map(|run| &run.shaped.glyphs);
+            glyphs.clone().find(|glyph| glyph.id == c as u32).unwrap().x
+        };
+        // "." on the decimal stop, then the next 40-point stop
+        assert_eq!((start('1'), start('.'), start('d')), (60.0, 80.0, 120.0));
+        assert_eq!(layout.advance, 160.0);
+        assert_eq!(layout.runs[0].color.as_deref(), Some("#ff0000"));
+        assert_eq!(layout.to_shaped().text, "ERR\t12.5\tdone");
+    }
+
+    #[test]
+    fn svg_draws_each_run_with_its_color() {
+        let spans = [TextSpan::new(
+            1..2,
+            style(|style| style.color = Some("#ff0000".to_string())),
+        )];
+        let layout = shape_rich(
+            &MonoBackend,
+            "abc",
+            &spans,
+            &Font::new("Test", 10.0),
+            &SegmentOptions::default(),
+            &TabOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        let options = RenderOptions {
+            format: RenderFormat::Svg,
+            color: "#0000ff".to_string(),
+            ..RenderOptions::default()
+        };
+        let svg = match render_rich(&MonoBackend, &layout, &options).unwrap() {
+            RenderOutput::Svg(svg) => svg,
+            _ => panic!("Expected SVG output"),
+        };
+        assert!(svg.contains(r##"<g id="text" fill="#0000ff">"##), "{svg}");
+        assert!(svg.contains(r##"<g id="text-1" fill="#ff0000">"##));
+        assert!(svg.contains(r##"<g id="text-2" fill="#0000ff">"##));
+
+        let options = RenderOptions {
+            format: RenderFormat::Pdf,
+            ..RenderOptions::default()
+        };
+        assert!(render_rich(&MonoBackend, &layout, &options).is_err());
+    }
+}
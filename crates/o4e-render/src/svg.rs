@@ -2,9 +2,18 @@
 
 //! SVG rendering implementation for o4e.
 
+use crate::decoration::decoration_paths;
+use crate::layout::{glyph_placements, line_baselines, placed_line_bounds, to_affine};
 use crate::outlines::glyph_bez_path as recorded_glyph_path;
-use kurbo::{BezPath, PathEl, Point};
-use o4e_core::{types::BoundingBox, Font, Glyph, ShapingResult, SvgOptions};
+use base64::Engine;
+use kurbo::{Affine, BezPath, PathEl, Point, Rect, Shape, Vec2};
+use o4e_core::{
+    color::{svg_color_attributes as color_attributes, svg_paint_attributes as paint_attributes},
+    types::{Decoration, DecorationLine, LineCap, LineJoin},
+    utils::quantize_size,
+    Font, Glyph, GradientStop, O4eError, Paint, ShapingResult, SizingMode, SpreadMode, Stroke,
+    SvgOptions, TextPath,
+};
 use o4e_fontdb::FontDatabase;
 use owned_ttf_parser::{AsFaceRef, OwnedFace};
 use parking_lot::RwLock;
@@ -15,6 +24,39 @@ use std::sync::{Arc, OnceLock};
 use thiserror::Error;
 use ttf_parser::{FaceParsingError, GlyphId};
 
+/// One styled run of attributed text for [`SvgRenderer::render_layers`].
+#[derive(Debug, Clone, Copy)]
+pub struct SvgLayer<'a> {
+    /// Glyphs positioned in the pen space shared by all layers
+    pub shaped: &'a ShapingResult,
+    /// Fill color instead of the options' paint
+    pub color: Option<&'a str>,
+    /// Decorations instead of the options' decorations
+    pub decorations: Option<&'a [Decoration]>,
+}
+
+/// What [`SvgRenderer::layer_bounds`] found for a set of layers.
+pub(crate) struct LayerBounds {
+    /// Union of the line boxes
+    pub(crate) text_box: Rect,
+    /// Area the viewBox covers before padding
+    pub(crate) view_box: Rect,
+    /// Whether any glyphs have their own placement matrix
+    placed: bool,
+    outlines: OutlineTable,
+    layers: Vec<LayerGlyphs>,
+    /// Decoration outlines of each layer
+    decorations: Vec<Vec<BezPath>>,
+}
+
+struct LayerGlyphs {
+    placements: Option<Vec<Option<Affine>>>,
+    /// Outline index of every glyph
+    outlines: Vec<Option<usize>>,
+    /// Index of the layer's first glyph among all layers
+    first_glyph: usize,
+}
+
 /// SVG renderer for converting shaped text to SVG format.
 pub struct SvgRenderer {
     precision: usize,
@@ -40,69 +82,633 @@ impl SvgRenderer {
     }
 
     /// Render shaped text to SVG string.
-    pub fn render(&self, shaped: &ShapingResult, options: &SvgOptions) -> String {
-        let mut svg = String::with_capacity(1024);
+    ///
+    /// The SVG user space has its origin on the baseline at the pen start, y pointing down.
+    /// The viewBox covers the union of the glyph ink bounds and the font's ascender/descender
+    /// line box (the ink alone for `SizingMode::Ink`) plus any decorations, grown by
+    /// `options.padding` on every side. With a transform or a text path each glyph gets its
+    /// own `matrix()` and the viewBox fits the placed glyphs and line cells.
+    ///
+    /// Fails with [`O4eError::InvalidColor`] when a color in `options` does not parse.
+    pub fn render(&self, shaped: &ShapingResult, options: &SvgOptions) -> Result<String, O4eError> {
+        self.render_layers(
+            &[SvgLayer {
+                shaped,
+                color: None,
+                decorations: None,
+            }],
+            options,
+        )
+    }
 
-        // Calculate bounding box
-        let bbox = calculate_svg_bbox(&shaped.glyphs, shaped.bbox);
+    /// Render several styled runs sharing one pen space (attributed text) as one SVG.
+    ///
+    /// Each layer is drawn like [`render`](Self::render) draws its text, in its own `<g>`
+    /// and with its own font, fill color and decorations; the viewBox covers all of them.
+    pub fn render_layers(
+        &self,
+        layers: &[SvgLayer<'_>],
+        options: &SvgOptions,
+    ) -> Result<String, O4eError> {
+        let mut svg = String::with_capacity(1024);
+        let bounds = self.layer_bounds(layers, options);
+        let padding = options.padding.max(0.0) as f64;
+        let view_box = bounds.view_box.inflate(padding, padding);
+        let text: String = layers
+            .iter()
+            .map(|layer| layer.shaped.text.as_str())
+            .collect();
 
         // Write SVG header
+        let scale = options.scale as f64;
+        let label = if options.accessible_text {
+            format!(r#" role="img" aria-label="{}""#, escape_xml(&text))
+        } else {
+            String::new()
+        };
         let _ = write!(
             &mut svg,
-            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.p$} {:.p$} {:.p$} {:.p$}">"#,
-            bbox.x,
-            bbox.y,
-            bbox.width,
-            bbox.height,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.p$}" height="{:.p$}" viewBox="{:.p$} {:.p$} {:.p$} {:.p$}"{}>"#,
+            view_box.width() * scale,
+            view_box.height() * scale,
+            view_box.x0,
+            view_box.y0,
+            view_box.width(),
+            view_box.height(),
+            label,
             p = self.precision
         );
 
         svg.push('\n');
 
-        // Start a group for the text
-        svg.push_str(r#"  <g id="text">"#);
-        svg.push('\n');
+        if options.accessible_text {
+            let _ = writeln!(&mut svg, "  <title>{}</title>", escape_xml(&text));
+        }
+
+        // Gradient and pattern paints become paint servers referenced by id
+        let mut defs = Vec::new();
+        let text_box = bounds.text_box;
+        let background = options
+            .background_paint
+            .clone()
+            .or_else(|| options.background.clone().map(Paint::Solid))
+            .map(|paint| {
+                self.paint_reference("fill", "background-paint", &paint, view_box, &mut defs)
+            })
+            .transpose()?;
+        let fill_attributes = if !options.fill {
+            r#" fill="none""#.to_string()
+        } else if let Some(paint) = &options.paint {
+            self.paint_reference("fill", "fill-paint", paint, text_box, &mut defs)?
+        } else {
+            options
+                .foreground_color
+                .as_deref()
+                .map(|color| paint_attributes("fill", color))
+                .transpose()?
+                .unwrap_or_default()
+        };
+        let stroke_attributes = match &options.stroke {
+            Some(stroke) => {
+                let paint = self.paint_reference(
+                    "stroke",
+                    "stroke-paint",
+                    &stroke.resolved_paint(),
+                    text_box,
+                    &mut defs,
+                )?;
+                self.stroke_attributes(stroke, &paint, options.fill)
+            }
+            None => String::new(),
+        };
+        // The selectable overlay follows the same path through `<textPath>`
+        let text_path = options
+            .text_path
+            .as_ref()
+            .filter(|_| options.selectable_text && bounds.placed);
+        if let Some(text_path) = text_path {
+            defs.push(format!(
+                r#"    <path id="text-path" d="{}" />"#,
+                escape_xml(&text_path.data)
+            ));
+        }
+        if options.deduplicate_glyphs {
+            for (index, data) in bounds.outlines.data.iter().enumerate() {
+                defs.push(format!(r#"    <path id="outline-{index}" d="{data}" />"#));
+            }
+        }
+
+        if !defs.is_empty() {
+            svg.push_str("  <defs>\n");
+            for def in &defs {
+                svg.push_str(def);
+                svg.push('\n');
+            }
+            svg.push_str("  </defs>\n");
+        }
+
+        if let Some(background) = background {
+            let _ = writeln!(
+                &mut svg,
+                r#"  <rect id="background" x="{:.p$}" y="{:.p$}" width="{:.p$}" height="{:.p$}"{} />"#,
+                view_box.x0,
+                view_box.y0,
+                view_box.width(),
+                view_box.height(),
+                background,
+                p = self.precision
+            );
+        }
+
+        // Underlines and overlines go under the glyphs, line-throughs over them
+        self.write_layer_decorations(&mut svg, options, layers, &bounds.decorations, false)?;
+
+        let mut glyph_index = 0;
+        for (layer_index, (layer, layer_bounds)) in layers.iter().zip(&bounds.layers).enumerate() {
+            let shaped = layer.shaped;
+            let fill = match layer.color {
+                Some(color) if options.fill => paint_attributes("fill", color)?,
+                _ => fill_attributes.clone(),
+            };
+            // Start a group for the text
+            let id = match layer_index {
+                0 => "text".to_string(),
+                index => format!("text-{index}"),
+            };
+            let _ = writeln!(&mut svg, r#"  <g id="{id}"{fill}{stroke_attributes}>"#);
 
-        // Render each glyph as a path (placeholder for now)
-        for (i, glyph) in shaped.glyphs.iter().enumerate() {
-            let mut emitted_path = false;
-            if options.include_paths {
-                if let Some(path_data) = self.glyph_path_data(glyph, shaped.font.as_ref()) {
-                    let _ = write!(
-                        &mut svg,
-                        r#"    <path id="glyph-{}" d="{}" transform="translate({:.p$}, {:.p$})" />"#,
-                        i,
-                        path_data,
-                        glyph.x,
-                        glyph.y,
-                        p = self.precision
-                    );
-                    svg.push('\n');
-                    emitted_path = true;
+            for (glyph, outline) in shaped.glyphs.iter().zip(&layer_bounds.outlines) {
+                let i = glyph_index;
+                glyph_index += 1;
+                let placement = match &layer_bounds.placements {
+                    Some(placements) => match placements[i - layer_bounds.first_glyph] {
+                        Some(placement) => Some(self.matrix(placement)),
+                        // Past the end of the text path
+                        None => continue,
+                    },
+                    None => None,
+                };
+                match outline {
+                    Some(index) if options.deduplicate_glyphs => {
+                        let position = match &placement {
+                            Some(matrix) => format!(r#" transform="{matrix}""#),
+                            None => format!(
+                                r#" x="{:.p$}" y="{:.p$}""#,
+                                glyph.x,
+                                baseline_offset(glyph),
+                                p = self.precision
+                            ),
+                        };
+                        let _ = write!(
+                            &mut svg,
+                            r##"    <use id="glyph-{}" href="#outline-{}"{} data-cluster="{}" />"##,
+                            i, index, position, glyph.cluster,
+                        );
+                    }
+                    Some(index) => {
+                        let transform = placement.unwrap_or_else(|| {
+                            format!(
+                                "translate({:.p$}, {:.p$})",
+                                glyph.x,
+                                baseline_offset(glyph),
+                                p = self.precision
+                            )
+                        });
+                        let _ = write!(
+                            &mut svg,
+                            r#"    <path id="glyph-{}" d="{}" transform="{}" data-cluster="{}" />"#,
+                            i, bounds.outlines.data[*index], transform, glyph.cluster,
+                        );
+                    }
+                    None => {
+                        // Simple rectangle placeholder when path extraction is not available
+                        let (x, y, transform) = match placement {
+                            Some(matrix) => (0.0, -0.5, format!(r#" transform="{matrix}""#)),
+                            None => (glyph.x, baseline_offset(glyph) - 0.5, String::new()),
+                        };
+                        let _ = write!(
+                            &mut svg,
+                            r#"    <rect x="{:.p$}" y="{:.p$}" width="{:.p$}" height="1"{} data-cluster="{}" />"#,
+                            x,
+                            y,
+                            glyph.advance,
+                            transform,
+                            glyph.cluster,
+                            p = self.precision
+                        );
+                    }
                 }
+                svg.push('\n');
             }
 
-            if !emitted_path {
-                // Simple rectangle placeholder when path extraction is not available
-                let _ = write!(
+            // Close group
+            svg.push_str("  </g>\n");
+        }
+        self.write_layer_decorations(&mut svg, options, layers, &bounds.decorations, true)?;
+
+        if options.selectable_text {
+            for (index, layer) in layers.iter().enumerate() {
+                self.write_selectable_text(
                     &mut svg,
-                    r#"    <rect x="{:.p$}" y="{:.p$}" width="{:.p$}" height="1" />"#,
-                    glyph.x,
-                    glyph.y - 0.5,
-                    glyph.advance,
-                    p = self.precision
+                    layer.shaped,
+                    options,
+                    text_path,
+                    view_box,
+                    index,
                 );
-                svg.push('\n');
             }
         }
 
-        // Close group
-        svg.push_str("  </g>\n");
-
         // Close SVG
         svg.push_str("</svg>");
 
-        svg
+        Ok(svg)
+    }
+
+    /// Outlines, decorations and the areas they cover for every layer.
+    pub(crate) fn layer_bounds(
+        &self,
+        layers: &[SvgLayer<'_>],
+        options: &SvgOptions,
+    ) -> LayerBounds {
+        let outset = options.stroke.as_ref().map_or(0.0, |s| s.outset() as f64);
+        let mut outlines = OutlineTable::new(self.precision);
+        let mut text_box: Option<Rect> = None;
+        let mut ink: Option<Rect> = None;
+        let mut add_ink = |rect: Rect| ink = Some(ink.map_or(rect, |ink| ink.union(rect)));
+        let mut placed = false;
+        let mut first_glyph = 0;
+        let mut per_layer = Vec::with_capacity(layers.len());
+        let mut decorations = Vec::with_capacity(layers.len());
+        for layer in layers {
+            let shaped = layer.shaped;
+            // Transformed or path-following text places every glyph with its own matrix
+            let placements = glyph_placements(
+                shaped,
+                options.transform.as_ref(),
+                options.text_path.as_ref(),
+            )
+            .unwrap_or_else(|err| {
+                log::warn!("{err}; rendering text on a straight baseline");
+                None
+            });
+            placed |= placements.is_some();
+            let (top, bottom) = line_extents(shaped);
+            let layer_box = placements
+                .as_ref()
+                .and_then(|placements| {
+                    placed_line_bounds(shaped, placements, f64::from(top), f64::from(bottom))
+                })
+                .unwrap_or_else(|| logical_bounds(shaped));
+            text_box = Some(text_box.map_or(layer_box, |b| b.union(layer_box)));
+
+            let mut glyph_outlines = Vec::with_capacity(shaped.glyphs.len());
+            for (i, glyph) in shaped.glyphs.iter().enumerate() {
+                let outline = if options.include_paths {
+                    outlines.resolve(glyph, shaped.font.as_ref(), |glyph, font| {
+                        self.glyph_path(glyph, font)
+                    })
+                } else {
+                    None
+                };
+                if let Some(index) = outline {
+                    let glyph_ink = outlines.bounds[index].inflate(outset, outset);
+                    match &placements {
+                        Some(placements) => {
+                            if let Some(placement) = placements[i] {
+                                add_ink(placement.transform_rect_bbox(glyph_ink));
+                            }
+                        }
+                        None => {
+                            let offset = Vec2::new(glyph.x as f64, baseline_offset(glyph) as f64);
+                            add_ink(glyph_ink + offset);
+                        }
+                    }
+                }
+                glyph_outlines.push(outline);
+            }
+            let layer_decorations = layer.decorations.unwrap_or(&options.decorations);
+            let paths = match shaped.font.as_ref().and_then(face_and_scale) {
+                Some((face, scale)) if !layer_decorations.is_empty() => decoration_paths(
+                    face.as_face_ref(),
+                    scale,
+                    shaped,
+                    layer_decorations,
+                    placements.as_deref(),
+                ),
+                _ => Vec::new(),
+            };
+            for path in paths.iter().filter(|path| !path.elements().is_empty()) {
+                add_ink(path.bounding_box());
+            }
+            decorations.push(paths);
+            per_layer.push(LayerGlyphs {
+                placements,
+                outlines: glyph_outlines,
+                first_glyph,
+            });
+            first_glyph += shaped.glyphs.len();
+        }
+
+        let text_box = text_box.unwrap_or(Rect::ZERO);
+        let view_box = match options.sizing {
+            SizingMode::Logical => ink.map_or(text_box, |ink| ink.union(text_box)),
+            SizingMode::Ink => ink.unwrap_or(Rect::ZERO),
+        };
+        LayerBounds {
+            text_box,
+            view_box,
+            placed,
+            outlines,
+            layers: per_layer,
+            decorations,
+        }
+    }
+
+    /// Every layer's decorations: line-throughs when `over`, the other lines otherwise.
+    fn write_layer_decorations(
+        &self,
+        svg: &mut String,
+        options: &SvgOptions,
+        layers: &[SvgLayer<'_>],
+        paths: &[Vec<BezPath>],
+        over: bool,
+    ) -> Result<(), O4eError> {
+        let mut first_index = 0;
+        for (layer, paths) in layers.iter().zip(paths) {
+            let decorations = layer.decorations.unwrap_or(&options.decorations);
+            let foreground = layer.color.or(options.foreground_color.as_deref());
+            self.write_decorations(svg, decorations, foreground, paths, first_index, over)?;
+            first_index += decorations.len();
+        }
+        Ok(())
+    }
+
+    /// One filled `<path>` per decoration: line-throughs when `over`, the other lines otherwise.
+    ///
+    /// Decorations without a color of their own use the foreground color.
+    fn write_decorations(
+        &self,
+        svg: &mut String,
+        decorations: &[Decoration],
+        foreground: Option<&str>,
+        paths: &[BezPath],
+        first_index: usize,
+        over: bool,
+    ) -> Result<(), O4eError> {
+        for (index, (decoration, path)) in decorations.iter().zip(paths).enumerate() {
+            if (decoration.line == DecorationLine::LineThrough) != over
+                || path.elements().is_empty()
+            {
+                continue;
+            }
+            let fill = decoration
+                .color
+                .as_deref()
+                .filter(|color| !color.eq_ignore_ascii_case("currentColor"))
+                .or(foreground)
+                .map(|color| paint_attributes("fill", color))
+                .transpose()?
+                .unwrap_or_default();
+            let _ = writeln!(
+                svg,
+                r#"  <path id="decoration-{}" d="{}"{fill} />"#,
+                first_index + index,
+                path_to_string(path, self.precision)
+            );
+        }
+        Ok(())
+    }
+
+    /// SVG `matrix()` for a glyph placement.
+    ///
+    /// The linear part keeps at least four decimals so rotated glyphs stay on their path.
+    fn matrix(&self, affine: Affine) -> String {
+        let linear = self.precision.max(4);
+        // Snap values that round to zero so rotations do not print `-0.0000`.
+        let snap = |coeff: f64, digits: usize| {
+            if coeff.abs() < 0.5 * 10f64.powi(-(digits as i32)) {
+                0.0
+            } else {
+                coeff
+            }
+        };
+        let [a, b, c, d, e, f] = affine.as_coeffs();
+        let (a, b, c, d) = (
+            snap(a, linear),
+            snap(b, linear),
+            snap(c, linear),
+            snap(d, linear),
+        );
+        let (e, f) = (snap(e, self.precision), snap(f, self.precision));
+        format!(
+            "matrix({:.l$} {:.l$} {:.l$} {:.l$} {:.p$} {:.p$})",
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            l = linear,
+            p = self.precision
+        )
+    }
+
+    /// Stroke presentation attributes; with a fill, the stroke is painted underneath it.
+    ///
+    /// `paint` holds the already resolved `stroke` (and opacity) attributes.
+    fn stroke_attributes(&self, stroke: &Stroke, paint: &str, filled: bool) -> String {
+        let mut attributes = paint.to_string();
+        let _ = write!(
+            attributes,
+            r#" stroke-width="{:.p$}" stroke-linejoin="{}" stroke-linecap="{}""#,
+            stroke.width,
+            match stroke.line_join {
+                LineJoin::Miter => "miter",
+                LineJoin::Round => "round",
+                LineJoin::Bevel => "bevel",
+            },
+            match stroke.line_cap {
+                LineCap::Butt => "butt",
+                LineCap::Round => "round",
+                LineCap::Square => "square",
+            },
+            p = self.precision
+        );
+        if stroke.line_join == LineJoin::Miter {
+            let _ = write!(attributes, r#" stroke-miterlimit="{}""#, stroke.miter_limit);
+        }
+        if filled {
+            attributes.push_str(r#" paint-order="stroke""#);
+        }
+        attributes
+    }
+
+    /// Attributes painting `property` with `paint`.
+    ///
+    /// Solid paints are written inline. Other paints are laid out over `area` and pushed to
+    /// `defs` as a paint server named `id`; a pattern whose image cannot be read paints nothing.
+    fn paint_reference(
+        &self,
+        property: &str,
+        id: &str,
+        paint: &Paint,
+        area: Rect,
+        defs: &mut Vec<String>,
+    ) -> Result<String, O4eError> {
+        if let Paint::Solid(color) = paint {
+            return Ok(paint_attributes(property, color)?);
+        }
+        Ok(match self.paint_server(id, paint, area)? {
+            Some(server) => {
+                defs.push(server);
+                format!(r##" {property}="url(#{id})""##)
+            }
+            None => format!(r#" {property}="none""#),
+        })
+    }
+
+    /// `<linearGradient>`, `<radialGradient>` or `<pattern>` element for a non-solid paint.
+    fn paint_server(
+        &self,
+        id: &str,
+        paint: &Paint,
+        area: Rect,
+    ) -> Result<Option<String>, O4eError> {
+        // Gradient geometry is in unit-box coordinates, mapped onto `area`.
+        let unit_box = format!(
+            "matrix({:.p$} 0 0 {:.p$} {:.p$} {:.p$})",
+            area.width(),
+            area.height(),
+            area.x0,
+            area.y0,
+            p = self.precision
+        );
+        let mut server = String::new();
+        match paint {
+            Paint::Solid(_) => return Ok(None),
+            Paint::LinearGradient {
+                start,
+                end,
+                stops,
+                spread,
+            } => {
+                let _ = writeln!(
+                    server,
+                    r#"    <linearGradient id="{id}" gradientUnits="userSpaceOnUse" gradientTransform="{unit_box}" x1="{}" y1="{}" x2="{}" y2="{}" spreadMethod="{}">"#,
+                    start[0],
+                    start[1],
+                    end[0],
+                    end[1],
+                    spread_method(*spread)
+                );
+                write_stops(&mut server, stops)?;
+                server.push_str("    </linearGradient>");
+            }
+            Paint::RadialGradient {
+                center,
+                radius,
+                stops,
+                spread,
+            } => {
+                let _ = writeln!(
+                    server,
+                    r#"    <radialGradient id="{id}" gradientUnits="userSpaceOnUse" gradientTransform="{unit_box}" cx="{}" cy="{}" r="{}" spreadMethod="{}">"#,
+                    center[0],
+                    center[1],
+                    radius,
+                    spread_method(*spread)
+                );
+                write_stops(&mut server, stops)?;
+                server.push_str("    </radialGradient>");
+            }
+            Paint::Pattern { png, scale } => {
+                let Some((width, height)) = png_size(png) else {
+                    return Ok(None);
+                };
+                let width = width as f64 * *scale as f64;
+                let height = height as f64 * *scale as f64;
+                let _ = write!(
+                    server,
+                    r#"    <pattern id="{id}" patternUnits="userSpaceOnUse" x="{:.p$}" y="{:.p$}" width="{:.p$}" height="{:.p$}"><image width="{:.p$}" height="{:.p$}" preserveAspectRatio="none" href="data:image/png;base64,{}" /></pattern>"#,
+                    area.x0,
+                    area.y0,
+                    width,
+                    height,
+                    width,
+                    height,
+                    base64::engine::general_purpose::STANDARD.encode(png),
+                    p = self.precision
+                );
+            }
+        }
+        Ok(Some(server))
+    }
+
+    /// Write an invisible `<text>` overlay with one `<tspan>` per cluster.
+    ///
+    /// Each span is stretched over the advance of its glyphs so browser selection highlights
+    /// line up with the rendered outlines.
+    ///
+    /// Transformed text carries the transform on the `<text>` element; text on a path wraps the
+    /// spans in a `<textPath>` referencing the `text-path` definition.
+    fn write_selectable_text(
+        &self,
+        svg: &mut String,
+        shaped: &ShapingResult,
+        options: &SvgOptions,
+        text_path: Option<&TextPath>,
+        view_box: Rect,
+        layer_index: usize,
+    ) {
+        let font_size = shaped
+            .font
+            .as_ref()
+            .map(|font| font.size as f64)
+            .unwrap_or_else(|| view_box.height());
+        let transform = options
+            .transform
+            .filter(|transform| !transform.is_identity())
+            .map(|transform| format!(r#" transform="{}""#, self.matrix(to_affine(&transform))))
+            .unwrap_or_default();
+        let id = match layer_index {
+            0 => "selectable-text".to_string(),
+            index => format!("selectable-text-{index}"),
+        };
+        let _ = writeln!(
+            svg,
+            r#"  <text id="{}" font-size="{:.p$}" fill-opacity="0" xml:space="preserve"{}{}>"#,
+            id,
+            font_size,
+            transform,
+            if options.accessible_text {
+                r#" aria-hidden="true""#
+            } else {
+                ""
+            },
+            p = self.precision
+        );
+        // Inside `<textPath>`, x is the distance along the path
+        let start = text_path.map_or(0.0, |text_path| text_path.start_offset);
+        if text_path.is_some() {
+            svg.push_str("    <textPath href=\"#text-path\">\n");
+        }
+        for span in cluster_spans(shaped) {
+            let _ = writeln!(
+                svg,
+                r#"    <tspan x="{:.p$}" y="{:.p$}" textLength="{:.p$}" lengthAdjust="spacingAndGlyphs" data-cluster="{}">{}</tspan>"#,
+                start + span.x,
+                0.0 - span.baseline,
+                span.advance,
+                span.cluster,
+                escape_xml(span.text),
+                p = self.precision
+            );
+        }
+        if text_path.is_some() {
+            svg.push_str("    </textPath>\n");
+        }
+        svg.push_str("  </text>\n");
     }
 
     /// Render a single glyph to SVG path string without font context (best effort).
@@ -116,6 +722,11 @@ impl SvgRenderer {
     }
 
     fn glyph_path_data(&self, glyph: &Glyph, font: Option<&Font>) -> Option<String> {
+        self.glyph_path(glyph, font)
+            .map(|path| path_to_string(&path, self.precision))
+    }
+
+    fn glyph_path(&self, glyph: &Glyph, font: Option<&Font>) -> Option<BezPath> {
         let outline = svg_outline(font, glyph)?;
         let processed = if self.simplify {
             simplify_path(outline, self.precision)
@@ -123,41 +734,229 @@ impl SvgRenderer {
             outline
         };
 
-        if processed.elements().is_empty() {
-            return None;
+        (!processed.elements().is_empty()).then_some(processed)
+    }
+}
+
+/// Distinct glyph outlines seen during a render, keyed by font identity and glyph id.
+///
+/// Each outline is extracted and serialized once; glyph occurrences refer to it by index.
+struct OutlineTable {
+    precision: usize,
+    indices: HashMap<OutlineKey, Option<usize>>,
+    data: Vec<String>,
+    bounds: Vec<Rect>,
+}
+
+#[derive(Hash, PartialEq, Eq)]
+struct OutlineKey {
+    font: Option<String>,
+    glyph_id: u32,
+}
+
+impl OutlineTable {
+    fn new(precision: usize) -> Self {
+        Self {
+            precision,
+            indices: HashMap::new(),
+            data: Vec::new(),
+            bounds: Vec::new(),
         }
+    }
 
-        Some(path_to_string(&processed, self.precision))
+    fn resolve(
+        &mut self,
+        glyph: &Glyph,
+        font: Option<&Font>,
+        extract: impl FnOnce(&Glyph, Option<&Font>) -> Option<BezPath>,
+    ) -> Option<usize> {
+        let key = OutlineKey {
+            font: font.map(font_identity),
+            glyph_id: glyph.id,
+        };
+        if let Some(index) = self.indices.get(&key) {
+            return *index;
+        }
+
+        let index = extract(glyph, font).map(|path| {
+            self.bounds.push(path.bounding_box());
+            self.data.push(path_to_string(&path, self.precision));
+            self.data.len() - 1
+        });
+        self.indices.insert(key, index);
+        index
     }
 }
 
-/// Calculate SVG bounding box from glyphs.
-fn calculate_svg_bbox(glyphs: &[Glyph], fallback: BoundingBox) -> BoundingBox {
-    if glyphs.is_empty() {
-        return fallback;
+/// Identity of the outlines a font produces: source, scaled size and variation coordinates.
+pub(crate) fn font_identity(font: &Font) -> String {
+    let mut variations: Vec<_> = font.variations.iter().collect();
+    variations.sort_by(|a, b| a.0.cmp(b.0));
+    let mut identity = format!("{}@{}", font.source.label(), quantize_size(font.size));
+    for (tag, value) in variations {
+        let _ = write!(identity, ";{tag}={value}");
+    }
+    identity
+}
+
+fn write_stops(server: &mut String, stops: &[GradientStop]) -> Result<(), O4eError> {
+    for stop in stops {
+        let color = color_attributes("stop-color", "stop-opacity", &stop.color)?;
+        let _ = writeln!(server, r#"      <stop offset="{}"{color} />"#, stop.offset);
+    }
+    Ok(())
+}
+
+fn spread_method(spread: SpreadMode) -> &'static str {
+    match spread {
+        SpreadMode::Pad => "pad",
+        SpreadMode::Repeat => "repeat",
+        SpreadMode::Reflect => "reflect",
+    }
+}
+
+/// Pixel size from a PNG header.
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if png.len() < 24 || !png.starts_with(SIGNATURE) || &png[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(png[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// A run of consecutive glyphs sharing a cluster, with the source text it maps to.
+pub(crate) struct ClusterSpan<'a> {
+    pub(crate) cluster: u32,
+    /// The span's first glyph
+    pub(crate) glyph: &'a Glyph,
+    pub(crate) text: &'a str,
+    pub(crate) x: f32,
+    pub(crate) advance: f32,
+    /// Baseline of the cluster's line (y up), see [`line_baselines`]
+    pub(crate) baseline: f32,
+}
+
+/// Group glyphs into clusters in visual order.
+///
+/// Clusters are byte offsets into `shaped.text`; each cluster owns the text up to the next
+/// larger cluster offset, so ligatures and reordered (RTL) runs still copy the full source.
+/// The line breaks joining stacked lines belong to no cluster.
+pub(crate) fn cluster_spans(shaped: &ShapingResult) -> Vec<ClusterSpan<'_>> {
+    let mut starts: Vec<usize> = shaped.glyphs.iter().map(|g| g.cluster as usize).collect();
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut spans: Vec<ClusterSpan<'_>> = Vec::new();
+    for (glyph, baseline) in shaped.glyphs.iter().zip(line_baselines(shaped)) {
+        if let Some(span) = spans.last_mut().filter(|s| s.cluster == glyph.cluster) {
+            span.x = span.x.min(glyph.x);
+            span.advance += glyph.advance;
+            continue;
+        }
+        let start = glyph.cluster as usize;
+        let end = starts
+            .iter()
+            .find(|&&next| next > start)
+            .copied()
+            .unwrap_or(shaped.text.len());
+        let mut text = shaped.text.get(start..end).unwrap_or_default();
+        if !shaped.lines.is_empty() {
+            text = text.trim_end_matches('\n');
+        }
+        spans.push(ClusterSpan {
+            cluster: glyph.cluster,
+            glyph,
+            text,
+            x: glyph.x,
+            advance: glyph.advance,
+            baseline,
+        });
+    }
+    spans
+}
+
+/// Escape text for use in SVG character data and attribute values.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Vertical translation of a glyph in SVG space.
+///
+/// `Glyph::y` is a y-up baseline offset while SVG is y-down; subtracting from zero keeps
+/// unshifted glyphs at `0` instead of printing `-0`.
+fn baseline_offset(glyph: &Glyph) -> f32 {
+    0.0 - glyph.y
+}
+
+/// Top and bottom (y down) of one line box: the font's ascender and descender.
+pub(crate) fn line_extents(shaped: &ShapingResult) -> (f32, f32) {
+    let fallback = shaped.bbox;
+    shaped
+        .font
+        .as_ref()
+        .and_then(vertical_extents)
+        .unwrap_or((fallback.y, fallback.y + fallback.height))
+}
+
+/// Line box of the shaped text: pen positions horizontally, from the ascender of the first
+/// line to the descender of the last vertically.
+///
+/// Falls back to the shaping bounding box when no font metrics are available.
+pub(crate) fn logical_bounds(shaped: &ShapingResult) -> Rect {
+    let fallback = shaped.bbox;
+    if shaped.glyphs.is_empty() {
+        return Rect::new(
+            fallback.x as f64,
+            fallback.y as f64,
+            (fallback.x + fallback.width) as f64,
+            (fallback.y + fallback.height) as f64,
+        );
     }
 
-    // For SVG, we need to include the full advance width
     let mut min_x = f32::MAX;
-    let mut min_y = f32::MAX;
     let mut max_x = f32::MIN;
-    let mut max_y = f32::MIN;
-
-    for glyph in glyphs {
+    for glyph in &shaped.glyphs {
         min_x = min_x.min(glyph.x);
         max_x = max_x.max(glyph.x + glyph.advance);
-
-        // Estimate glyph height (this is a simplification)
-        min_y = min_y.min(glyph.y - 1.0);
-        max_y = max_y.max(glyph.y + 0.5);
     }
 
-    BoundingBox {
-        x: min_x,
-        y: min_y,
-        width: max_x - min_x,
-        height: max_y - min_y,
-    }
+    let (top, bottom) = line_extents(shaped);
+    // Stacked lines extend the box from the first baseline to the last
+    let (low, high) = line_baselines(shaped)
+        .into_iter()
+        .fold((0.0f32, 0.0f32), |(low, high), baseline| {
+            (low.min(baseline), high.max(baseline))
+        });
+
+    Rect::new(
+        min_x as f64,
+        (top - high) as f64,
+        max_x as f64,
+        (bottom - low) as f64,
+    )
+}
+
+/// Ascender and descender of the font in y-down SVG units (top is negative).
+fn vertical_extents(font: &Font) -> Option<(f32, f32)> {
+    let (face, scale) = face_and_scale(font)?;
+    let face = face.as_face_ref();
+    Some((
+        -(face.ascender() as f32) * scale,
+        -(face.descender() as f32) * scale,
+    ))
 }
 
 fn svg_outline(font: Option<&Font>, glyph: &Glyph) -> Option<BezPath> {
@@ -167,7 +966,7 @@ fn svg_outline(font: Option<&Font>, glyph: &Glyph) -> Option<BezPath> {
     recorded_glyph_path(face.as_face_ref(), glyph_id, scale)
 }
 
-fn face_and_scale(font: &Font) -> Option<(Arc<OwnedFace>, f32)> {
+pub(crate) fn face_and_scale(font: &Font) -> Option<(Arc<OwnedFace>, f32)> {
     if font.size <= 0.0 {
         return None;
     }
@@ -322,7 +1121,9 @@ enum FontLoadError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use o4e_core::types::Direction;
+    use crate::test_support::{fixture_font, nominal_shaped};
+    use o4e_core::types::{BoundingBox, Direction};
+    use o4e_core::{Decoration, DecorationStyle, ShapedLine, Transform2D};
     use std::fs;
     use std::path::PathBuf;
 
@@ -354,6 +1155,7 @@ mod tests {
             },
             font: None,
             direction: Direction::LeftToRight,
+            lines: Vec::new(),
         }
     }
 
@@ -379,9 +1181,10 @@ mod tests {
             },
             font: None,
             direction: Direction::LeftToRight,
+            lines: Vec::new(),
         };
 
-        let svg = renderer.render(&shaped, &SvgOptions::default());
+        let svg = renderer.render(&shaped, &SvgOptions::default()).unwrap();
         assert!(svg.contains("<svg"));
         assert!(svg.contains("</svg>"));
     }
@@ -403,7 +1206,7 @@ mod tests {
     fn test_render_simple_text_produces_rectangles() {
         let renderer = SvgRenderer::default();
         let shaped = sample_shaping_result();
-        let svg = renderer.render(&shaped, &SvgOptions::default());
+        let svg = renderer.render(&shaped, &SvgOptions::default()).unwrap();
         assert!(
             svg.contains("<rect"),
             "SVG should contain fallback rectangles"
@@ -416,8 +1219,8 @@ mod tests {
         let mut shaped = sample_shaping_result();
         shaped.glyphs[0].x = -5.0;
         shaped.glyphs[1].x = 15.0;
-        let svg = renderer.render(&shaped, &SvgOptions::default());
-        let expected = format!("viewBox=\"{:.2} {:.2} {:.2} {:.2}\"", -5.0, -1.0, 32.0, 1.5);
+        let svg = renderer.render(&shaped, &SvgOptions::default()).unwrap();
+        let expected = format!("viewBox=\"{:.2} {:.2} {:.2} {:.2}\"", -5.0, -1.0, 32.0, 2.0);
         assert!(
             svg.contains(&expected),
             "ViewBox should match calculated bounding box ({expected}), got {svg}"
@@ -427,7 +1230,9 @@ mod tests {
     #[test]
     fn test_svg_output_is_well_formed() {
         let renderer = SvgRenderer::default();
-        let svg = renderer.render(&sample_shaping_result(), &SvgOptions::default());
+        let svg = renderer
+            .render(&sample_shaping_result(), &SvgOptions::default())
+            .unwrap();
         assert!(svg.starts_with("<svg "), "SVG should start with root tag");
         assert!(svg.contains("</g>"), "SVG should close group tag");
         assert!(
@@ -460,15 +1265,433 @@ mod tests {
             },
             font: Some(font),
             direction: Direction::LeftToRight,
+            lines: Vec::new(),
         };
 
-        let svg = renderer.render(&shaped, &SvgOptions::default());
+        let svg = renderer.render(&shaped, &SvgOptions::default()).unwrap();
         assert!(
             svg.contains("<path id=\"glyph-0\""),
             "Expected glyph path in SVG output: {svg}"
         );
     }
 
+    #[test]
+    fn test_viewbox_covers_ascender_and_descender() {
+        let renderer = SvgRenderer::default();
+        let font = fixture_font(100.0);
+        let shaped = nominal_shaped("Ag", &font);
+
+        let svg = renderer.render(&shaped, &SvgOptions::default()).unwrap();
+        let (x, y, width, height) = parse_view_box(&svg);
+        // Noto Sans: ascender 1069, descender -293 units per 1000.
+        assert!(y <= -106.8, "viewBox top should reach the ascender: {y}");
+        assert!(
+            y + height >= 29.2,
+            "viewBox should include descenders: {svg}"
+        );
+        assert!(x <= 0.0 && x + width >= shaped.advance as f64 - 0.01);
+    }
+
+    #[test]
+    fn test_viewbox_includes_padding() {
+        let renderer = SvgRenderer::default();
+        let font = fixture_font(100.0);
+        let shaped = nominal_shaped("A", &font);
+
+        let tight = parse_view_box(&renderer.render(&shaped, &SvgOptions::default()).unwrap());
+        let options = SvgOptions {
+            padding: 8.0,
+            ..SvgOptions::default()
+        };
+        let padded = parse_view_box(&renderer.render(&shaped, &options).unwrap());
+        assert!((padded.0 - (tight.0 - 8.0)).abs() < 0.02);
+        assert!((padded.1 - (tight.1 - 8.0)).abs() < 0.02);
+        assert!((padded.2 - (tight.2 + 16.0)).abs() < 0.02);
+        assert!((padded.3 - (tight.3 + 16.0)).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_ink_sizing_fits_the_glyphs() {
+        let renderer = SvgRenderer::default();
+        let font = fixture_font(100.0);
+        let shaped = nominal_shaped("a", &font);
+
+        let logical = parse_view_box(&renderer.render(&shaped, &SvgOptions::default()).unwrap());
+        let options = SvgOptions {
+            sizing: SizingMode::Ink,
+            ..SvgOptions::default()
+        };
+        let (x, y, width, height) = parse_view_box(&renderer.render(&shaped, &options).unwrap());
+        // The x-height is 536 units; no ascender or descender space is left
+        assert!((-60.0..-50.0).contains(&y), "ink top at the x-height: {y}");
+        assert!((y + height).abs() < 2.0, "ink bottom on the baseline");
+        assert!(x > 0.0 && width < shaped.advance as f64);
+        assert!(height < logical.3 / 2.0);
+    }
+
+    #[test]
+    fn test_glyph_outlines_are_upright_above_baseline() {
+        let renderer = SvgRenderer::default();
+        let font = fixture_font(100.0);
+        let shaped = nominal_shaped("A", &font);
+        let outline = renderer.glyph_path(&shaped.glyphs[0], Some(&font)).unwrap();
+        let bounds = outline.bounding_box();
+        assert!(bounds.y0 < -70.0, "cap height should extend upwards");
+        assert!(bounds.y1 <= 0.5, "'A' must sit on the baseline");
+    }
+
+    #[test]
+    fn test_positive_glyph_offset_moves_glyph_up() {
+        let renderer = SvgRenderer::default();
+        let mut shaped = sample_shaping_result();
+        shaped.glyphs[1].y = 4.0;
+        let svg = renderer.render(&shaped, &SvgOptions::default()).unwrap();
+        assert!(svg.contains(r#"<rect x="0.00" y="-0.50""#), "{svg}");
+        assert!(svg.contains(r#"<rect x="10.00" y="-4.50""#), "{svg}");
+    }
+
+    #[test]
+    fn test_deduplicated_output_defines_each_outline_once() {
+        let renderer = SvgRenderer::default();
+        let font = fixture_font(24.0);
+        let shaped = nominal_shaped("eee ee", &font);
+        let options = SvgOptions {
+            deduplicate_glyphs: true,
+            ..SvgOptions::default()
+        };
+
+        let svg = renderer.render(&shaped, &options).unwrap();
+        assert_eq!(svg.matches("<path ").count(), 1, "{svg}");
+        assert_eq!(svg.matches(r##"href="#outline-0""##).count(), 5, "{svg}");
+        // The space has no outline and keeps the rectangle placeholder.
+        assert_eq!(svg.matches("<rect ").count(), 1, "{svg}");
+
+        let expanded = renderer.render(&shaped, &SvgOptions::default()).unwrap();
+        assert!(svg.len() < expanded.len() / 2);
+        assert_eq!(
+            parse_view_box(&svg),
+            parse_view_box(&expanded),
+            "deduplication must not change geometry"
+        );
+    }
+
+    #[test]
+    fn test_deduplicated_outlines_honor_precision_and_simplify() {
+        let font = fixture_font(24.0);
+        let mut shaped = nominal_shaped("ee", &font);
+        // A second line on a baseline with a long float tail
+        shaped.lines = vec![
+            ShapedLine {
+                first_glyph: 0,
+                baseline: 0.0,
+            },
+            ShapedLine {
+                first_glyph: 1,
+                baseline: -28.123_456,
+            },
+        ];
+        let options = SvgOptions {
+            precision: 3,
+            simplify: false,
+            deduplicate_glyphs: true,
+            selectable_text: true,
+            ..SvgOptions::default()
+        };
+        let renderer = SvgRenderer::new(&options);
+
+        let svg = renderer.render(&shaped, &options).unwrap();
+        let expected = renderer
+            .render_glyph_with_font(&shaped.glyphs[0], &font)
+            .unwrap();
+        assert!(svg.contains(&format!(r#"d="{expected}""#)), "{svg}");
+        assert!(svg.contains(r#"x="13.536" y="0.000""#), "{svg}");
+        let spans: Vec<&str> = svg.lines().filter(|l| l.contains("<tspan")).collect();
+        assert!(spans[0].contains(r#"x="0.000" y="0.000" "#), "{svg}");
+        assert!(spans[1].contains(r#" y="28.123" "#), "{svg}");
+    }
+
+    #[test]
+    fn test_fill_background_and_scale_attributes() {
+        let renderer = SvgRenderer::default();
+        let options = SvgOptions {
+            foreground_color: Some("#336699cc".into()),
+            background: Some("#ffffff".into()),
+            scale: 2.0,
+            ..SvgOptions::default()
+        };
+
+        let svg = renderer.render(&sample_shaping_result(), &options).unwrap();
+        assert!(svg.contains(r#"width="44.00" height="4.00""#), "{svg}");
+        assert!(
+            svg.contains(
+                r##"<rect id="background" x="0.00" y="-1.00" width="22.00" height="2.00" fill="#ffffff" />"##
+            ),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r##"<g id="text" fill="#336699" fill-opacity="0.800">"##),
+            "{svg}"
+        );
+    }
+
+    #[test]
+    fn test_invalid_colors_are_errors() {
+        let options = SvgOptions {
+            foreground_color: Some("not a color".to_string()),
+            ..SvgOptions::default()
+        };
+        assert!(matches!(
+            SvgRenderer::default().render(&sample_shaping_result(), &options),
+            Err(O4eError::InvalidColor(_))
+        ));
+    }
+
+    #[test]
+    fn test_decorations_wrap_the_glyph_group() {
+        let renderer = SvgRenderer::default();
+        let font = fixture_font(100.0);
+        let shaped = nominal_shaped("gg", &font);
+        let options = SvgOptions {
+            foreground_color: Some("#336699".to_string()),
+            decorations: vec![
+                Decoration::new(DecorationLine::Underline),
+                Decoration {
+                    color: Some("red".to_string()),
+                    style: DecorationStyle::Wavy,
+                    ..Decoration::new(DecorationLine::LineThrough)
+                },
+            ],
+            ..SvgOptions::default()
+        };
+        let svg = renderer.render(&shaped, &options).unwrap();
+
+        let underline = svg.find(r#"<path id="decoration-0""#).expect("underline");
+        let group = svg.find(r#"<g id="text""#).unwrap();
+        let through = svg
+            .find(r#"<path id="decoration-1""#)
+            .expect("line-through");
+        assert!(underline < group && group < through);
+        assert!(svg[underline..group].contains(r##"fill="#336699""##));
+        assert!(svg[through..].contains(r#"fill="red""#));
+        // Skip-ink splits the underline around both descenders
+        let underline_data = &svg[underline..group];
+        assert_eq!(underline_data.matches('M').count(), 3, "{underline_data}");
+
+        let plain = parse_view_box(&renderer.render(&shaped, &SvgOptions::default()).unwrap());
+        let thick = SvgOptions {
+            decorations: vec![Decoration {
+                thickness: Some(40.0),
+                skip_ink: false,
+                ..Decoration::new(DecorationLine::Underline)
+            }],
+            ..SvgOptions::default()
+        };
+        let decorated = parse_view_box(&renderer.render(&shaped, &thick).unwrap());
+        assert!(
+            decorated.1 + decorated.3 > plain.1 + plain.3,
+            "viewBox grows to the line"
+        );
+    }
+
+    #[test]
+    fn test_stroke_attributes_and_outlined_text() {
+        let font = fixture_font(24.0);
+        let shaped = nominal_shaped("o", &font);
+        let stroke = Stroke {
+            width: 3.0,
+            color: "#ff000080".to_string(),
+            line_join: LineJoin::Round,
+            line_cap: LineCap::Square,
+            ..Stroke::default()
+        };
+        let plain = SvgRenderer::default()
+            .render(&shaped, &SvgOptions::default())
+            .unwrap();
+
+        let options = SvgOptions {
+            stroke: Some(stroke.clone()),
+            foreground_color: Some("#000000".to_string()),
+            ..SvgOptions::default()
+        };
+        let svg = SvgRenderer::default().render(&shaped, &options).unwrap();
+        assert!(
+            svg.contains(
+                r##"<g id="text" fill="#000000" stroke="#ff0000" stroke-opacity="0.502" stroke-width="3.00" stroke-linejoin="round" stroke-linecap="square" paint-order="stroke">"##
+            ),
+            "{svg}"
+        );
+        let (px, _, pw, _) = parse_view_box(&plain);
+        let (sx, _, sw, _) = parse_view_box(&svg);
+        assert!(sx < px, "stroke must widen the viewBox: {plain} vs {svg}");
+        assert!(sw > pw);
+
+        let outlined = SvgRenderer::default()
+            .render(
+                &shaped,
+                &SvgOptions {
+                    stroke: Some(Stroke {
+                        line_join: LineJoin::Miter,
+                        ..stroke
+                    }),
+                    fill: false,
+                    ..SvgOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(
+            outlined.contains(r##"<g id="text" fill="none" stroke="#ff0000""##),
+            "{outlined}"
+        );
+        assert!(outlined.contains(r#"stroke-miterlimit="4""#), "{outlined}");
+        assert!(!outlined.contains("paint-order"), "{outlined}");
+    }
+
+    #[test]
+    fn test_gradient_and_pattern_paints_become_defs() {
+        let font = fixture_font(24.0);
+        let shaped = nominal_shaped("o", &font);
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 3]);
+        let options = SvgOptions {
+            paint: Some(Paint::LinearGradient {
+                start: [0.0, 0.0],
+                end: [1.0, 0.0],
+                stops: vec![
+                    GradientStop::new(0.0, "#ff0000"),
+                    GradientStop::new(1.0, "#0000ff80"),
+                ],
+                spread: SpreadMode::Reflect,
+            }),
+            stroke: Some(Stroke {
+                paint: Some(Paint::RadialGradient {
+                    center: [0.5, 0.5],
+                    radius: 0.5,
+                    stops: vec![GradientStop::new(0.0, "#00ff00")],
+                    spread: SpreadMode::Pad,
+                }),
+                ..Stroke::default()
+            }),
+            background_paint: Some(Paint::Pattern { png, scale: 2.0 }),
+            ..SvgOptions::default()
+        };
+        let svg = SvgRenderer::default().render(&shaped, &options).unwrap();
+
+        assert_eq!(svg.matches("<defs>").count(), 1, "{svg}");
+        assert!(svg.contains(r#"<linearGradient id="fill-paint" gradientUnits="userSpaceOnUse""#));
+        assert!(svg.contains(r#"x1="0" y1="0" x2="1" y2="0" spreadMethod="reflect">"#));
+        assert!(svg.contains(r##"<stop offset="1" stop-color="#0000ff" stop-opacity="0.502" />"##));
+        assert!(
+            svg.contains(r#"<radialGradient id="stroke-paint""#),
+            "{svg}"
+        );
+        assert!(svg.contains(r#"<pattern id="background-paint" patternUnits="userSpaceOnUse""#));
+        assert!(svg.contains(r#"width="4.00" height="6.00" preserveAspectRatio="none" href="data:image/png;base64,iVBORw0KGgo"#));
+        assert!(
+            svg.contains(r##"<rect id="background""##)
+                && svg.contains(r##"fill="url(#background-paint)""##)
+        );
+        assert!(
+            svg.contains(r##"<g id="text" fill="url(#fill-paint)" stroke="url(#stroke-paint)""##),
+            "{svg}"
+        );
+
+        // The gradient spans the line box of the text.
+        let bounds = logical_bounds(&shaped);
+        assert!(svg.contains(&format!(
+            r#"gradientTransform="matrix({:.2} 0 0 {:.2} {:.2} {:.2})""#,
+            bounds.width(),
+            bounds.height(),
+            bounds.x0,
+            bounds.y0
+        )));
+    }
+
+    #[test]
+    fn test_transform_rotates_glyphs_and_viewbox() {
+        let font = fixture_font(24.0);
+        let shaped = nominal_shaped("Hello", &font);
+        let plain = SvgRenderer::default()
+            .render(&shaped, &SvgOptions::default())
+            .unwrap();
+        let options = SvgOptions {
+            transform: Some(Transform2D::rotate(90.0)),
+            selectable_text: true,
+            ..SvgOptions::default()
+        };
+        let rotated = SvgRenderer::default().render(&shaped, &options).unwrap();
+
+        let (_, _, plain_width, plain_height) = parse_view_box(&plain);
+        let (_, _, width, height) = parse_view_box(&rotated);
+        assert!(plain_width > plain_height);
+        assert!((width - plain_height).abs() < 0.05, "{plain} vs {rotated}");
+        assert!((height - plain_width).abs() < 0.05, "{plain} vs {rotated}");
+        assert!(
+            rotated.contains(r#"id="glyph-0" d=""#)
+                && rotated
+                    .contains(r#"transform="matrix(0.0000 1.0000 -1.0000 0.0000 0.00 0.00)""#),
+            "{rotated}"
+        );
+        assert!(
+            rotated.contains(r#"<text id="selectable-text" font-size="24.00" fill-opacity="0" xml:space="preserve" transform="matrix("#),
+            "{rotated}"
+        );
+    }
+
+    #[test]
+    fn test_text_path_places_glyphs_and_selectable_overlay() {
+        let font = fixture_font(24.0);
+        let shaped = nominal_shaped("Hello", &font);
+        let options = SvgOptions {
+            text_path: Some(TextPath {
+                data: "M0 0 Q60 -60 120 0".to_string(),
+                start_offset: 10.0,
+            }),
+            selectable_text: true,
+            deduplicate_glyphs: true,
+            ..SvgOptions::default()
+        };
+        let svg = SvgRenderer::default().render(&shaped, &options).unwrap();
+
+        assert!(svg.contains(r#"<path id="text-path" d="M0 0 Q60 -60 120 0" />"#));
+        assert_eq!(svg.matches(r#"<use id="glyph-"#).count(), 5, "{svg}");
+        assert_eq!(svg.matches(r#"transform="matrix("#).count(), 5, "{svg}");
+        assert!(svg.contains(r##"<textPath href="#text-path">"##), "{svg}");
+        assert!(svg.contains(r#"<tspan x="10.00" y="0.00""#), "{svg}");
+
+        // A path shorter than the text drops the glyphs that do not fit.
+        let short = SvgRenderer::default()
+            .render(
+                &shaped,
+                &SvgOptions {
+                    text_path: Some(TextPath::new("M0 0 H20")),
+                    ..SvgOptions::default()
+                },
+            )
+            .unwrap();
+        let drawn = short.matches(r#"<path id="glyph-"#).count();
+        assert!((1..5).contains(&drawn), "{short}");
+    }
+
+    #[test]
+    fn test_outline_identity_distinguishes_sizes() {
+        let (small, _) = noto_sans_font(12.0);
+        let (large, _) = noto_sans_font(24.0);
+        assert_ne!(font_identity(&small), font_identity(&large));
+        let mut varied = large.clone();
+        varied.variations.insert("wght".into(), 700.0);
+        assert_ne!(font_identity(&large), font_identity(&varied));
+    }
+
+    fn parse_view_box(svg: &str) -> (f64, f64, f64, f64) {
+        let start = svg.find("viewBox=\"").expect("viewBox present") + 9;
+        let end = start + svg[start..].find('"').unwrap();
+        let values: Vec<f64> = svg[start..end]
+            .split_whitespace()
+            .map(|v| v.parse().unwrap())
+            .collect();
+        (values[0], values[1], values[2], values[3])
+    }
+
     fn noto_sans_font(size: f32) -> (Font, PathBuf) {
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("../../testdata/fonts/NotoSans-Regular.ttf");
@@ -485,4 +1708,145 @@ mod tests {
             .expect("Glyph must exist")
             .0 as u32
     }
+
+    #[test]
+    fn test_accessible_text_adds_title_and_label() {
+        let mut shaped = sample_shaping_result();
+        shaped.text = "a&<b>".to_string();
+        let options = SvgOptions {
+            accessible_text: true,
+            ..SvgOptions::default()
+        };
+        let svg = SvgRenderer::default().render(&shaped, &options).unwrap();
+        assert!(
+            svg.contains(r#"role="img" aria-label="a&amp;&lt;b&gt;""#),
+            "{svg}"
+        );
+        assert!(svg.contains("<title>a&amp;&lt;b&gt;</title>"), "{svg}");
+
+        let plain = SvgRenderer::default()
+            .render(&shaped, &SvgOptions::default())
+            .unwrap();
+        assert!(!plain.contains("<title>"), "{plain}");
+        assert!(!plain.contains("aria-label"), "{plain}");
+    }
+
+    #[test]
+    fn test_glyphs_carry_cluster_attributes() {
+        let svg = SvgRenderer::default()
+            .render(&sample_shaping_result(), &SvgOptions::default())
+            .unwrap();
+        assert!(svg.contains(r#"height="1" data-cluster="0" />"#), "{svg}");
+        assert!(svg.contains(r#"height="1" data-cluster="1" />"#), "{svg}");
+    }
+
+    #[test]
+    fn test_selectable_text_spans_follow_clusters() {
+        // RTL run in visual order, with a two-glyph cluster covering a multi-byte character.
+        let mut shaped = sample_shaping_result();
+        shaped.text = "aé!".to_string();
+        shaped.direction = Direction::RightToLeft;
+        shaped.glyphs = vec![
+            Glyph {
+                id: 3,
+                cluster: 3,
+                x: 0.0,
+                y: 0.0,
+                advance: 4.0,
+            },
+            Glyph {
+                id: 2,
+                cluster: 1,
+                x: 4.0,
+                y: 0.0,
+                advance: 6.0,
+            },
+            Glyph {
+                id: 5,
+                cluster: 1,
+                x: 6.0,
+                y: 0.0,
+                advance: 0.0,
+            },
+            Glyph {
+                id: 1,
+                cluster: 0,
+                x: 10.0,
+                y: 0.0,
+                advance: 5.0,
+            },
+        ];
+        let options = SvgOptions {
+            selectable_text: true,
+            ..SvgOptions::default()
+        };
+        let svg = SvgRenderer::default().render(&shaped, &options).unwrap();
+        let spans: Vec<&str> = svg.lines().filter(|l| l.contains("<tspan")).collect();
+        assert_eq!(spans.len(), 3, "{svg}");
+        assert!(
+            spans[0].contains(r#"x="0.00" y="0.00" textLength="4.00""#)
+                && spans[0].ends_with(">!</tspan>"),
+            "{svg}"
+        );
+        assert!(
+            spans[1].contains(r#"x="4.00" y="0.00" textLength="6.00""#)
+                && spans[1].ends_with(">é</tspan>"),
+            "{svg}"
+        );
+        assert!(spans[2].ends_with(r#"data-cluster="0">a</tspan>"#), "{svg}");
+        assert!(
+            svg.contains(r#"fill-opacity="0" xml:space="preserve">"#),
+            "{svg}"
+        );
+    }
+
+    #[test]
+    fn test_selectable_text_covers_every_combined_run() {
+        let font = fixture_font(10.0);
+        let shaped = o4e_core::utils::combine_shaped_results(vec![
+            nominal_shaped("ab ", &font),
+            nominal_shaped("cd", &font),
+        ]);
+        let options = SvgOptions {
+            selectable_text: true,
+            ..SvgOptions::default()
+        };
+        let svg = SvgRenderer::default().render(&shaped, &options).unwrap();
+        let text: Vec<&str> = svg
+            .lines()
+            .filter(|l| l.contains("<tspan"))
+            .map(|span| &span[span.rfind("\">").unwrap() + 2..span.len() - "</tspan>".len()])
+            .collect();
+        assert_eq!(text, vec!["a", "b", " ", "c", "d"], "{svg}");
+        assert!(svg.contains(r#"data-cluster="4""#), "{svg}");
+    }
+
+    #[test]
+    fn test_selectable_text_follows_paragraph_lines() {
+        use crate::paragraph::{break_paragraph, ParagraphOptions};
+        use crate::test_support::MonoBackend;
+
+        let options = ParagraphOptions {
+            max_width: Some(30.0),
+            line_height: Some(12.0),
+            ..ParagraphOptions::default()
+        };
+        let paragraph =
+            break_paragraph(&MonoBackend, "ab cd", &fixture_font(10.0), &options, |_| {}).unwrap();
+        let options = SvgOptions {
+            selectable_text: true,
+            ..SvgOptions::default()
+        };
+        let svg = SvgRenderer::default()
+            .render(&paragraph.to_shaped(), &options)
+            .unwrap();
+        let spans: Vec<&str> = svg.lines().filter(|l| l.contains("<tspan")).collect();
+        let text: Vec<&str> = spans
+            .iter()
+            .map(|span| &span[span.rfind("\">").unwrap() + 2..span.len() - "</tspan>".len()])
+            .collect();
+        assert_eq!(text, vec!["a", "b", "c", "d"], "{svg}");
+        assert!(spans[1].contains(r#" y="0.00" "#), "{svg}");
+        assert!(spans[2].contains(r#"x="0.00" y="12.00" "#), "{svg}");
+    }
 }
@@ -0,0 +1,432 @@
+// this_file: crates/o4e-render/src/tabs.rs
+
+//! Tab stops: U+0009 moves the pen to the next stop instead of drawing the font's tab glyph.
+//!
+//! Text is split at tabs and every piece is shaped on its own, then placed against its stop;
+//! the tab itself becomes a space glyph as wide as the gap it leaves. Pieces follow each
+//! other in logical order, so stops are measured from the left edge whatever the direction.
+
+use o4e_core::{
+    utils::combine_shaped_results, Backend, Font, O4eError, Result, SegmentOptions, ShapingResult,
+    TextRun,
+};
+
+use crate::paragraph::{join_font, LineShaper};
+use crate::svg::font_identity;
+
+/// How the text after a tab lines up with its stop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TabAlign {
+    /// The text starts at the stop
+    #[default]
+    Left,
+    /// The text is centered on the stop
+    Center,
+    /// The text ends at the stop
+    Right,
+    /// The first [`TabOptions::decimal`] character sits at the stop; text without one ends
+    /// at the stop
+    Decimal,
+}
+
+/// An explicit tab stop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TabStop {
+    /// Distance from the start of the line, in points
+    pub position: f32,
+    pub align: TabAlign,
+}
+
+impl TabStop {
+    pub fn new(position: f32, align: TabAlign) -> Self {
+        Self { position, align }
+    }
+}
+
+/// Where tabs stop.
+#[derive(Debug, Clone)]
+pub struct TabOptions {
+    /// Explicit stops, in any order
+    pub stops: Vec<TabStop>,
+    /// Distance between the left-aligned stops past the explicit ones, in points; `None` is
+    /// eight spaces of the font
+    pub interval: Option<f32>,
+    /// Character decimal stops align on
+    pub decimal: char,
+}
+
+impl Default for TabOptions {
+    fn default() -> Self {
+        Self {
+            stops: Vec::new(),
+            interval: None,
+            decimal: '.',
+        }
+    }
+}
+
+impl TabOptions {
+    /// The first stop after `pen`: an explicit one, else the next multiple of `interval`.
+    pub fn next_stop(&self, pen: f32, interval: f32) -> TabStop {
+        self.stops
+            .iter()
+            .filter(|stop| stop.position > pen)
+            .min_by(|a, b| a.position.total_cmp(&b.position))
+            .copied()
+            .unwrap_or_else(|| {
+                TabStop::new(((pen / interval).floor() + 1.0) * interval, TabAlign::Left)
+            })
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        let invalid = |name: &str, value: f32| O4eError::InvalidParameter {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        if let Some(interval) = self
+            .interval
+            .filter(|&interval| !(interval.is_finite() && interval > 0.0))
+        {
+            return Err(invalid("tab_interval", interval));
+        }
+        if let Some(stop) = self
+            .stops
+            .iter()
+            .find(|stop| !(stop.position.is_finite() && stop.position >= 0.0))
+        {
+            return Err(invalid("tab_stop", stop.position));
+        }
+        Ok(())
+    }
+}
+
+/// Shape one line of `text` in `font`, moving the text after every tab to its stop.
+///
+/// Glyph positions in the result already include the tab gaps. `adjust_run` sees every run
+/// before it is shaped, for script, language or direction overrides.
+pub fn shape_with_tabs(
+    backend: &dyn Backend,
+    text: &str,
+    font: &Font,
+    options: &TabOptions,
+    segment: &SegmentOptions,
+    adjust_run: impl Fn(&mut TextRun),
+) -> Result<ShapingResult> {
+    let shaper = LineShaper {
+        backend,
+        font,
+        segment,
+        adjust_run: &adjust_run,
+    };
+    shape_tabbed(&shaper, text, options)
+}
+
+/// [`shape_with_tabs`] with a paragraph's shaper.
+pub(crate) fn shape_tabbed(
+    shaper: &LineShaper<'_>,
+    text: &str,
+    options: &TabOptions,
+) -> Result<ShapingResult> {
+    options.validate()?;
+    if !text.contains('\t') {
+        return shaper.shape(text);
+    }
+
+    // Pieces and gaps are shaped on their own; combining them offsets each one's clusters
+    // by the text before it, so clusters index the whole line
+    let mut resolved = None;
+    let mut pieces = Vec::new();
+    for piece in text.split('\t') {
+        let shaped = shaper.shape(piece)?;
+        join_font(&mut resolved, &shaped)?;
+        let before_decimal = match piece.find(options.decimal) {
+            Some(decimal) => Some(shaper.shape(&piece[..decimal])?.advance),
+            None => None,
+        };
+        pieces.push((shaped, before_decimal));
+    }
+
+    // Gaps are spaces of the font the pieces are set in, which may be a fallback
+    let (stops, mut space_id) = TabLayout::new(shaper, options)?;
+    if let Some(font) = resolved
+        .as_ref()
+        .filter(|font| font_identity(font) != font_identity(shaper.font))
+    {
+        let fallback = LineShaper { font, ..*shaper };
+        if let Some(space) = fallback.shape(" ")?.glyphs.first() {
+            space_id = space.id;
+        }
+    }
+
+    let mut pen = 0.0;
+    let mut results = Vec::new();
+    for (index, (shaped, before_decimal)) in pieces.into_iter().enumerate() {
+        if index > 0 {
+            let start = stops.place(pen, shaped.advance, before_decimal);
+            results.push(tab_gap(shaper.font, space_id, start - pen));
+            pen = start;
+        }
+        pen += shaped.advance;
+        results.push(shaped);
+    }
+    let mut line = combine_shaped_results(results);
+    line.font = resolved.or(line.font);
+    Ok(line)
+}
+
+/// Tab stops resolved for one font: where the text after a tab starts.
+#[derive(Debug, Clone)]
+pub(crate) struct TabLayout {
+    options: TabOptions,
+    /// Distance between the stops past the explicit ones, in layout units
+    interval: f32,
+    /// Layout units per point of the stop positions
+    scale: f32,
+}
+
+impl TabLayout {
+    /// The stops of `options` in the shaper's font, and the font's space glyph.
+    pub(crate) fn new(shaper: &LineShaper<'_>, options: &TabOptions) -> Result<(Self, u32)> {
+        options.validate()?;
+        let space = shaper.shape(" ")?;
+        let space_id = space.glyphs.first().map_or(0, |glyph| glyph.id);
+        let interval = options
+            .interval
+            .or_else(|| Some(space.advance * 8.0).filter(|interval| *interval > 0.0))
+            .unwrap_or(shaper.font.size * 4.0);
+        let layout = Self {
+            options: options.clone(),
+            interval,
+            scale: 1.0,
+        };
+        Ok((layout, space_id))
+    }
+
+    /// Measure in units `scale` times the points of the stops, as for text shaped at
+    /// `scale` times the size it is laid out at; stops from the font's space scale with it.
+    pub(crate) fn set_scale(&mut self, scale: f32) {
+        if let Some(interval) = self.options.interval {
+            self.interval = interval * scale;
+        }
+        self.scale = scale;
+    }
+
+    /// Character decimal stops align on.
+    pub(crate) fn decimal(&self) -> char {
+        self.options.decimal
+    }
+
+    /// Start of text `advance` wide following a tab at `pen`; `before_decimal` is the advance
+    /// up to its first decimal character, if it has one.
+    pub(crate) fn place(&self, pen: f32, advance: f32, before_decimal: Option<f32>) -> f32 {
+        let mut stop = self
+            .options
+            .next_stop(pen / self.scale, self.interval / self.scale);
+        stop.position *= self.scale;
+        let before_stop = match stop.align {
+            TabAlign::Left => 0.0,
+            TabAlign::Center => advance / 2.0,
+            TabAlign::Right => advance,
+            TabAlign::Decimal => before_decimal.unwrap_or(advance),
+        };
+        // Text too wide for its stop starts right at the pen
+        (stop.position - before_stop).max(pen)
+    }
+}
+
+/// A tab drawn as one space glyph `width` wide, cluster 0 of its "\t" text.
+pub(crate) fn tab_gap(font: &Font, space_id: u32, width: f32) -> ShapingResult {
+    let mut gap = ShapingResult::empty(font);
+    gap.text = "\t".to_string();
+    gap.glyphs = vec![o4e_core::Glyph {
+        id: space_id,
+        cluster: 0,
+        x: 0.0,
+        y: 0.0,
+        advance: width,
+    }];
+    gap.advance = width;
+    gap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MonoBackend;
+
+    fn positions(text: &str, options: &TabOptions) -> Vec<(char, f32)> {
+        let shaped = shape_with_tabs(
+            &MonoBackend,
+            text,
+            &Font::new("Test", 10.0),
+            options,
+            &SegmentOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(shaped.text, text);
+        shaped
+            .glyphs
+            .iter()
+            .map(|glyph| (char::from_u32(glyph.id).unwrap(), glyph.x))
+            .collect()
+    }
+
+    #[test]
+    fn tabs_default_to_every_eight_spaces() {
+        // Spaces are one em (10 points), so stops fall every 80 points
+        let placed = positions("ab\tc\t\td", &TabOptions::default());
+        assert_eq!(
+            placed,
+            vec![
+                ('a', 0.0),
+                ('b', 10.0),
+                (' ', 20.0),
+                ('c', 80.0),
+                (' ', 90.0),
+                (' ', 160.0),
+                ('d', 240.0)
+            ]
+        );
+
+        // Clusters index the whole line, the tabs included
+        let shaped = shape_with_tabs(
+            &MonoBackend,
+            "ab\tc\t\td",
+            &Font::new("Test", 10.0),
+            &TabOptions::default(),
+            &SegmentOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        let clusters: Vec<u32> = shaped.glyphs.iter().map(|glyph| glyph.cluster).collect();
+        assert_eq!(clusters, vec![0, 1, 2, 3, 4, 5, 6]);
+
+        // A pen already on a stop moves on to the next one
+        let options = TabOptions {
+            interval: Some(30.0),
+            ..TabOptions::default()
+        };
+        assert_eq!(positions("abc\td", &options)[4], ('d', 60.0));
+
+        let options = TabOptions {
+            interval: Some(0.0),
+            ..TabOptions::default()
+        };
+        assert!(shape_with_tabs(
+            &MonoBackend,
+            "a\tb",
+            &Font::new("Test", 10.0),
+            &options,
+            &SegmentOptions::default(),
+            |_| {},
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn explicit_stops_align_the_following_text() {
+        let options = TabOptions {
+            stops: vec![
+                TabStop::new(200.0, TabAlign::Decimal),
+                TabStop::new(50.0, TabAlign::Left),
+                TabStop::new(100.0, TabAlign::Right),
+                TabStop::new(150.0, TabAlign::Center),
+            ],
+            interval: Some(100.0),
+            ..TabOptions::default()
+        };
+        let starts: Vec<f32> = positions("a\tb\tcd\tef\t1.5\tx", &options)
+            .into_iter()
+            .filter(|(c, _)| "abce1x".contains(*c))
+            .map(|(_, x)| x)
+            .collect();
+        // Left at 50, right ending at 100, centered on 150, decimal point at 200, then the
+        // interval past the last stop
+        assert_eq!(starts, vec![0.0, 50.0, 80.0, 140.0, 190.0, 300.0]);
+
+        // Text too wide for a stop follows the previous text directly
+        let options = TabOptions {
+            stops: vec![TabStop::new(30.0, TabAlign::Right)],
+            ..TabOptions::default()
+        };
+        assert_eq!(positions("ab\twide", &options)[3], ('w', 20.0));
+    }
+
+    #[test]
+    fn paragraph_lines_restart_tab_stops() {
+        use crate::paragraph::{break_paragraph, ParagraphOptions};
+
+        let options = ParagraphOptions {
+            tabs: TabOptions {
+                interval: Some(40.0),
+                ..TabOptions::default()
+            },
+            ..ParagraphOptions::default()
+        };
+        let paragraph = break_paragraph(
+            &MonoBackend,
+            "abcde\tf\ng\th",
+            &Font::new("Test", 10.0),
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        let last_x = |line: usize| paragraph.lines[line].shaped.glyphs.last().unwrap().x;
+        assert_eq!(last_x(0), 80.0);
+        assert_eq!(last_x(1), 40.0);
+        assert_eq!(paragraph.lines[1].shaped.text, "g\th");
+    }
+
+    #[test]
+    fn tabbed_lines_fit_max_width() {
+        use crate::paragraph::{break_paragraph, ParagraphOptions};
+
+        // The font's tab is one em, but the stop moves "b" to 40
+        let options = ParagraphOptions {
+            max_width: Some(60.0),
+            tabs: TabOptions {
+                interval: Some(40.0),
+                ..TabOptions::default()
+            },
+            ..ParagraphOptions::default()
+        };
+        let paragraph = break_paragraph(
+            &MonoBackend,
+            "a\tb c\tde f",
+            &Font::new("Test", 10.0),
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        let texts: Vec<&str> = paragraph
+            .lines
+            .iter()
+            .map(|line| line.shaped.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["a\tb", "c\tde", "f"]);
+        for line in &paragraph.lines {
+            assert!(line.shaped.advance <= 60.0, "{:?}", line.shaped.text);
+        }
+    }
+
+    #[test]
+    fn tabbed_lines_keep_a_fallback_font() {
+        use crate::test_support::FallbackBackend;
+
+        let shape = |text: &str| {
+            shape_with_tabs(
+                &FallbackBackend,
+                text,
+                &Font::new("Test", 10.0),
+                &TabOptions::default(),
+                &SegmentOptions::default(),
+                |_| {},
+            )
+        };
+        let shaped = shape("אב\tגד").unwrap();
+        assert_eq!(shaped.font.unwrap().family, "Fallback");
+        let err = shape("ab\tגד").expect_err("pieces in different fonts fail");
+        assert!(err.to_string().contains("shape_rich"), "{err}");
+    }
+}
@@ -0,0 +1,186 @@
+// this_file: crates/o4e-render/src/test_support.rs
+
+//! Backends, fonts and shaping results shared by the crate's tests.
+
+use std::path::PathBuf;
+
+use o4e_core::{
+    types::{BoundingBox, Direction, FontSource, Glyph, RenderOptions, RenderOutput},
+    Backend, Font, Result, SegmentOptions, ShapingResult, TextRun,
+};
+use owned_ttf_parser::AsFaceRef;
+
+/// Every character is one em wide; the font has no U+2010.
+pub(crate) struct MonoBackend;
+
+impl Backend for MonoBackend {
+    fn segment(&self, text: &str, _options: &SegmentOptions) -> Result<Vec<TextRun>> {
+        Ok(vec![TextRun {
+            text: text.to_string(),
+            range: (0, text.len()),
+            script: "Latin".to_string(),
+            language: "en".to_string(),
+            direction: Direction::LeftToRight,
+            font: None,
+        }])
+    }
+
+    fn shape(&self, run: &TextRun, font: &Font) -> Result<ShapingResult> {
+        let glyphs: Vec<Glyph> = run
+            .text
+            .char_indices()
+            .enumerate()
+            .map(|(index, (cluster, c))| Glyph {
+                id: if c == '\u{2010}' { 0 } else { c as u32 },
+                cluster: cluster as u32,
+                x: index as f32 * font.size,
+                y: 0.0,
+                advance: font.size,
+            })
+            .collect();
+        let mut shaped = ShapingResult::empty(font);
+        shaped.text = run.text.clone();
+        shaped.advance = glyphs.len() as f32 * font.size;
+        shaped.glyphs = glyphs;
+        Ok(shaped)
+    }
+
+    fn render(&self, _shaped: &ShapingResult, _options: &RenderOptions) -> Result<RenderOutput> {
+        Ok(RenderOutput::Raw(Vec::new()))
+    }
+
+    fn name(&self) -> &str {
+        "mono"
+    }
+
+    fn clear_cache(&self) {}
+}
+
+/// [`MonoBackend`] shaping after real bidi segmentation, with right-to-left runs in visual
+/// order as HarfBuzz returns them.
+pub(crate) struct BidiBackend;
+
+impl Backend for BidiBackend {
+    fn segment(&self, text: &str, options: &SegmentOptions) -> Result<Vec<TextRun>> {
+        self.segment_with_boundaries(text, options, &[])
+    }
+
+    fn segment_with_boundaries(
+        &self,
+        text: &str,
+        options: &SegmentOptions,
+        boundaries: &[usize],
+    ) -> Result<Vec<TextRun>> {
+        o4e_unicode::TextSegmenter::new().segment_with_boundaries(text, options, boundaries)
+    }
+
+    fn shape(&self, run: &TextRun, font: &Font) -> Result<ShapingResult> {
+        let mut shaped = MonoBackend.shape(run, font)?;
+        if run.direction == Direction::RightToLeft {
+            shaped.glyphs.reverse();
+            let mut x = 0.0;
+            for glyph in &mut shaped.glyphs {
+                glyph.x = x;
+                x += glyph.advance;
+            }
+        }
+        Ok(shaped)
+    }
+
+    fn render(&self, shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput> {
+        MonoBackend.render(shaped, options)
+    }
+
+    fn name(&self) -> &str {
+        "bidi"
+    }
+
+    fn clear_cache(&self) {}
+}
+
+/// [`BidiBackend`] setting runs outside ASCII in a "Fallback" family, as backends do when
+/// the requested font lacks a script.
+pub(crate) struct FallbackBackend;
+
+impl Backend for FallbackBackend {
+    fn segment(&self, text: &str, options: &SegmentOptions) -> Result<Vec<TextRun>> {
+        BidiBackend.segment(text, options)
+    }
+
+    fn segment_with_boundaries(
+        &self,
+        text: &str,
+        options: &SegmentOptions,
+        boundaries: &[usize],
+    ) -> Result<Vec<TextRun>> {
+        BidiBackend.segment_with_boundaries(text, options, boundaries)
+    }
+
+    fn shape(&self, run: &TextRun, font: &Font) -> Result<ShapingResult> {
+        if run.text.is_ascii() {
+            return MonoBackend.shape(run, font);
+        }
+        let fallback = Font {
+            source: FontSource::Family("Fallback".to_string()),
+            ..Font::new("Fallback", font.size)
+        };
+        MonoBackend.shape(run, &fallback)
+    }
+
+    fn render(&self, shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput> {
+        MonoBackend.render(shaped, options)
+    }
+
+    fn name(&self) -> &str {
+        "fallback"
+    }
+
+    fn clear_cache(&self) {}
+}
+
+/// Noto Sans from `testdata/fonts` at `size` points.
+pub(crate) fn fixture_font(size: f32) -> Font {
+    let path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../testdata/fonts/NotoSans-Regular.ttf");
+    let mut font = Font::new("Noto Sans", size);
+    font.source = FontSource::Path(path.to_string_lossy().into_owned());
+    font
+}
+
+/// `text` set in `font` with its nominal glyphs and advances, one glyph per character and
+/// no kerning, for tests that need real outlines without a shaping backend.
+pub(crate) fn nominal_shaped(text: &str, font: &Font) -> ShapingResult {
+    let (face, scale) = crate::svg::face_and_scale(font).expect("fixture font loads");
+    let face = face.as_face_ref();
+    let mut x = 0.0;
+    let glyphs = text
+        .char_indices()
+        .map(|(cluster, c)| {
+            let id = face.glyph_index(c).expect("fixture font covers the text");
+            let advance = f32::from(face.glyph_hor_advance(id).unwrap_or(0)) * scale;
+            let glyph = Glyph {
+                id: u32::from(id.0),
+                cluster: cluster as u32,
+                x,
+                y: 0.0,
+                advance,
+            };
+            x += advance;
+            glyph
+        })
+        .collect();
+    ShapingResult {
+        text: text.to_string(),
+        glyphs,
+        advance: x,
+        bbox: BoundingBox {
+            x: 0.0,
+            y: 0.0,
+            width: x,
+            height: font.size,
+        },
+        font: Some(font.clone()),
+        direction: Direction::LeftToRight,
+        lines: Vec::new(),
+    }
+}
@@ -0,0 +1,331 @@
+// this_file: crates/o4e-render/src/truncate.rs
+
+//! Truncation with an ellipsis to a maximum width.
+//!
+//! Text is cut at grapheme cluster boundaries in logical order and reshaped together with
+//! the ellipsis, so bidi reordering places the ellipsis: an RTL string loses its logical
+//! end, which is its left side.
+
+use std::ops::Range;
+
+use o4e_core::{Backend, Font, Result, SegmentOptions, ShapingResult, TextRun};
+use o4e_unicode::grapheme_boundaries;
+
+use crate::paragraph::LineShaper;
+use crate::tabs::{shape_tabbed, TabOptions};
+
+/// U+2026 HORIZONTAL ELLIPSIS, shown when the font has it.
+const ELLIPSIS: &str = "\u{2026}";
+/// Three full stops, the fallback.
+const THREE_DOTS: &str = "...";
+
+/// Which part of the text an ellipsis replaces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncateMode {
+    /// "…ong file name"
+    Start,
+    /// "very l…e name"
+    Middle,
+    /// "very long fil…"
+    #[default]
+    End,
+}
+
+/// How text is truncated.
+#[derive(Debug, Clone, Default)]
+pub struct TruncateOptions {
+    /// Maximum advance in points
+    pub max_width: f32,
+    pub mode: TruncateMode,
+    /// Replacement for the removed text; `None` uses U+2026, or "..." when the font lacks it
+    pub ellipsis: Option<String>,
+    /// Segmentation before shaping
+    pub segment: SegmentOptions,
+    /// Tab stops of the line, ellipsis included
+    pub tabs: TabOptions,
+}
+
+/// Shape `text` in `font`, replacing part of it with an ellipsis when it is wider than
+/// `options.max_width`.
+///
+/// Text that fits comes back whole. Otherwise as many grapheme clusters as fit next to the
+/// ellipsis are kept, without white space beside the ellipsis; when none fit, the result
+/// is the ellipsis alone. `adjust_run` sees every run before it is shaped, for script,
+/// language or direction overrides.
+pub fn truncate(
+    backend: &dyn Backend,
+    text: &str,
+    font: &Font,
+    options: &TruncateOptions,
+    adjust_run: impl Fn(&mut TextRun),
+) -> Result<ShapingResult> {
+    let shaper = LineShaper {
+        backend,
+        font,
+        segment: &options.segment,
+        adjust_run: &adjust_run,
+    };
+    let whole = shape_tabbed(&shaper, text, &options.tabs)?;
+    if whole.advance <= options.max_width {
+        return Ok(whole);
+    }
+
+    let (ellipsis, ellipsis_width) = match &options.ellipsis {
+        Some(ellipsis) => (ellipsis.as_str(), shaper.shape(ellipsis)?.advance),
+        None => shaper.first_available(&[ELLIPSIS, THREE_DOTS])?,
+    };
+    let cut = Cut {
+        offsets: shaper.pen_offsets(text)?,
+        boundaries: grapheme_boundaries(text),
+    };
+
+    let mut budget = options.max_width - ellipsis_width;
+    loop {
+        let (head, tail) = cut.keep(options.mode, budget);
+        let shaped = shape_tabbed(
+            &shaper,
+            &format!(
+                "{}{ellipsis}{}",
+                text[head.clone()].trim_end(),
+                text[tail.clone()].trim_start()
+            ),
+            &options.tabs,
+        )?;
+        let kept = cut.width(&head) + cut.width(&tail);
+        if shaped.advance <= options.max_width || kept <= 0.0 {
+            return Ok(shaped);
+        }
+        // Shaping the cut text came out wider than measured (kerning, contextual forms, tab
+        // stops); keep strictly less, even when the overshoot is below the precision of `kept`
+        budget = (kept - (shaped.advance - options.max_width)).min(kept.next_down());
+    }
+}
+
+/// Grapheme-safe cuts of one text.
+struct Cut {
+    /// Summed advances up to each byte offset
+    offsets: Vec<f32>,
+    boundaries: Vec<usize>,
+}
+
+impl Cut {
+    fn width(&self, range: &Range<usize>) -> f32 {
+        self.offsets[range.end] - self.offsets[range.start]
+    }
+
+    /// Ranges kept before and after the ellipsis, together at most `budget` wide.
+    fn keep(&self, mode: TruncateMode, budget: f32) -> (Range<usize>, Range<usize>) {
+        let len = self.offsets.len() - 1;
+        // Longest prefix and suffix within a width
+        let head = |budget: f32| {
+            let end = self
+                .boundaries
+                .iter()
+                .copied()
+                .take_while(|&end| self.width(&(0..end)) <= budget)
+                .last()
+                .unwrap_or(0);
+            0..end
+        };
+        let tail = |budget: f32, from: usize| {
+            let start = self
+                .boundaries
+                .iter()
+                .copied()
+                .find(|&start| start >= from && self.width(&(start..len)) <= budget)
+                .unwrap_or(len);
+            start..len
+        };
+        match mode {
+            TruncateMode::End => (head(budget), len..len),
+            TruncateMode::Start => (0..0, tail(budget, 0)),
+            TruncateMode::Middle => {
+                let head = head(budget / 2.0);
+                let tail = tail(budget - self.width(&head), head.end);
+                (head, tail)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{BidiBackend, MonoBackend};
+    use o4e_core::types::{RenderOptions, RenderOutput};
+
+    fn truncated(text: &str, max_width: f32, mode: TruncateMode) -> String {
+        let options = TruncateOptions {
+            max_width,
+            mode,
+            ..TruncateOptions::default()
+        };
+        truncate(
+            &MonoBackend,
+            text,
+            &Font::new("Test", 10.0),
+            &options,
+            |_| {},
+        )
+        .unwrap()
+        .text
+    }
+
+    #[test]
+    fn ellipsis_replaces_the_end_start_or_middle() {
+        let name = "very long file name";
+        assert_eq!(truncated(name, 500.0, TruncateMode::End), name);
+        assert_eq!(truncated(name, 100.0, TruncateMode::End), "very long…");
+        assert_eq!(truncated(name, 100.0, TruncateMode::Start), "…file name");
+        assert_eq!(truncated(name, 90.0, TruncateMode::Start), "…ile name");
+        assert_eq!(truncated(name, 90.0, TruncateMode::Middle), "very…name");
+        assert_eq!(truncated(name, 5.0, TruncateMode::End), "…");
+    }
+
+    #[test]
+    fn cuts_keep_grapheme_clusters_whole() {
+        // "e" + combining acute is one cluster of two characters
+        let text = "cafe\u{301}s and more";
+        assert_eq!(truncated(text, 60.0, TruncateMode::End), "cafe\u{301}…");
+        assert_eq!(truncated(text, 55.0, TruncateMode::End), "caf…");
+
+        let options = TruncateOptions {
+            max_width: 70.0,
+            ellipsis: Some("~".to_string()),
+            ..TruncateOptions::default()
+        };
+        let shaped = truncate(
+            &MonoBackend,
+            text,
+            &Font::new("Test", 10.0),
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(shaped.text, "cafe\u{301}s~");
+        assert!(shaped.advance <= 70.0);
+    }
+
+    #[test]
+    fn tabs_move_to_their_stops() {
+        let options = TruncateOptions {
+            max_width: 80.0,
+            tabs: TabOptions {
+                interval: Some(40.0),
+                ..TabOptions::default()
+            },
+            ..TruncateOptions::default()
+        };
+        let shaped = truncate(
+            &MonoBackend,
+            "ab\tcdefgh",
+            &Font::new("Test", 10.0),
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(shaped.text, "ab\tcde…");
+        assert_eq!(shaped.advance, 80.0);
+        let c = shaped.glyphs.iter().find(|glyph| glyph.id == 'c' as u32);
+        assert_eq!(c.unwrap().x, 40.0);
+    }
+
+    #[test]
+    fn right_to_left_text_loses_its_left_side() {
+        let options = |max_width, mode| TruncateOptions {
+            max_width,
+            mode,
+            segment: SegmentOptions {
+                bidi_resolve: true,
+                ..SegmentOptions::default()
+            },
+            ..TruncateOptions::default()
+        };
+        let font = Font::new("Test", 10.0);
+        let ellipsis_x = |shaped: &ShapingResult| {
+            let ellipsis = shaped.glyphs.iter().find(|glyph| glyph.id == 0x2026);
+            ellipsis.unwrap().x
+        };
+        let xs = |shaped: &ShapingResult| {
+            shaped
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.x)
+                .collect::<Vec<_>>()
+        };
+
+        let end = truncate(
+            &BidiBackend,
+            "שלום עולם",
+            &font,
+            &options(60.0, TruncateMode::End),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(end.text, "שלום…");
+        assert_eq!(
+            ellipsis_x(&end),
+            xs(&end).into_iter().fold(f32::MAX, f32::min)
+        );
+
+        let start = truncate(
+            &BidiBackend,
+            "שלום עולם",
+            &font,
+            &options(60.0, TruncateMode::Start),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(start.text, "…עולם");
+        assert_eq!(
+            ellipsis_x(&start),
+            xs(&start).into_iter().fold(f32::MIN, f32::max)
+        );
+    }
+
+    /// [`MonoBackend`] setting text with an ellipsis a hair wider than its glyphs, like a kern
+    /// pair would.
+    struct KernedBackend;
+
+    impl Backend for KernedBackend {
+        fn segment(&self, text: &str, options: &SegmentOptions) -> Result<Vec<TextRun>> {
+            MonoBackend.segment(text, options)
+        }
+
+        fn shape(&self, run: &TextRun, font: &Font) -> Result<ShapingResult> {
+            let mut shaped = MonoBackend.shape(run, font)?;
+            if run.text.contains(ELLIPSIS) && run.text != ELLIPSIS {
+                shaped.advance += 2e-5;
+            }
+            Ok(shaped)
+        }
+
+        fn render(&self, shaped: &ShapingResult, options: &RenderOptions) -> Result<RenderOutput> {
+            MonoBackend.render(shaped, options)
+        }
+
+        fn name(&self) -> &str {
+            "kerned"
+        }
+
+        fn clear_cache(&self) {}
+    }
+
+    #[test]
+    fn the_smallest_overshoot_drops_one_cluster() {
+        let options = TruncateOptions {
+            max_width: 150.0,
+            ..TruncateOptions::default()
+        };
+        let shaped = truncate(
+            &KernedBackend,
+            "abcdefghijklmnopqrst",
+            &Font::new("Test", 10.0),
+            &options,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(shaped.text, "abcdefghijklm…");
+        assert!(shaped.advance <= 150.0);
+    }
+}
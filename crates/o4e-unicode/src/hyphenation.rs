@@ -0,0 +1,312 @@
+// this_file: crates/o4e-unicode/src/hyphenation.rs
+
+//! Liang hyphenation: where a word may break across lines with a hyphen.
+//!
+//! Patterns for common languages are embedded (compiled from the TeX hyphenation patterns);
+//! others load from TeX pattern files at runtime.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use o4e_core::{O4eError, Result};
+
+/// U+00AD SOFT HYPHEN: an invisible break opportunity that shows a hyphen when taken.
+pub const SOFT_HYPHEN: char = '\u{AD}';
+
+/// Finds hyphenation points in words of one language.
+#[derive(Debug, Clone)]
+pub struct Hyphenator {
+    patterns: PatternSource,
+    left_min: usize,
+    right_min: usize,
+}
+
+#[derive(Debug, Clone)]
+enum PatternSource {
+    Embedded(hypher::Lang),
+    Loaded(Arc<Patterns>),
+}
+
+impl Hyphenator {
+    /// Embedded patterns for a BCP-47 language tag (`"en"`, `"de-CH"`, `"nb_NO"`), if any.
+    pub fn for_language(language: &str) -> Option<Self> {
+        let primary = language.split(['-', '_']).next()?.to_ascii_lowercase();
+        let code = match primary.as_str() {
+            "nb" | "nn" => "no",
+            code => code,
+        };
+        let code: [u8; 2] = code.as_bytes().try_into().ok()?;
+        let lang = hypher::Lang::from_iso(code)?;
+        let (left_min, right_min) = lang.bounds();
+        Some(Self {
+            patterns: PatternSource::Embedded(lang),
+            left_min,
+            right_min,
+        })
+    }
+
+    /// Patterns from the source of a TeX pattern file.
+    ///
+    /// Reads the `\patterns{…}` and `\hyphenation{…}` (exceptions such as `ta-ble`) groups,
+    /// or treats the whole source as a pattern list when it has neither. `%` starts a comment.
+    pub fn from_patterns(source: &str) -> Result<Self> {
+        Ok(Self {
+            patterns: PatternSource::Loaded(Arc::new(Patterns::parse(source)?)),
+            left_min: 2,
+            right_min: 3,
+        })
+    }
+
+    /// Patterns from a TeX pattern file; see [`Hyphenator::from_patterns`].
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_patterns(&std::fs::read_to_string(path)?)
+    }
+
+    /// Keep at least `left_min` characters before and `right_min` after each hyphen.
+    pub fn with_bounds(mut self, left_min: usize, right_min: usize) -> Self {
+        self.left_min = left_min.max(1);
+        self.right_min = right_min.max(1);
+        self
+    }
+
+    /// The minimum number of characters before and after a hyphen.
+    pub fn bounds(&self) -> (usize, usize) {
+        (self.left_min, self.right_min)
+    }
+
+    /// Byte offsets in `word` where it may be hyphenated, in increasing order.
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        match &self.patterns {
+            PatternSource::Embedded(lang) => {
+                let mut offset = 0;
+                let mut points: Vec<usize> =
+                    hypher::hyphenate_bounded(word, *lang, self.left_min, self.right_min)
+                        .map(|syllable| {
+                            offset += syllable.len();
+                            offset
+                        })
+                        .collect();
+                points.pop();
+                points
+            }
+            PatternSource::Loaded(patterns) => {
+                let starts: Vec<usize> = word.char_indices().map(|(index, _)| index).collect();
+                let count = starts.len();
+                if count < self.left_min + self.right_min {
+                    return Vec::new();
+                }
+                patterns
+                    .break_positions(word)
+                    .into_iter()
+                    .filter(|&position| {
+                        position >= self.left_min && position + self.right_min <= count
+                    })
+                    .map(|position| starts[position])
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Liang patterns and exceptions loaded at runtime.
+#[derive(Debug, Default)]
+struct Patterns {
+    /// Letters of a pattern to the levels before, between and after them
+    levels: HashMap<String, Vec<u8>>,
+    /// Longest pattern, in characters
+    max_len: usize,
+    /// Lowercase words to the character positions they break before
+    exceptions: HashMap<String, Vec<usize>>,
+}
+
+impl Patterns {
+    fn parse(source: &str) -> Result<Self> {
+        let source: String = source
+            .lines()
+            .map(|line| line.split('%').next().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let pattern_groups = tex_groups(&source, "patterns");
+        let exception_groups = tex_groups(&source, "hyphenation");
+        let pattern_groups = if pattern_groups.is_empty() && exception_groups.is_empty() {
+            vec![source.as_str()]
+        } else {
+            pattern_groups
+        };
+
+        let mut patterns = Self::default();
+        for token in pattern_groups
+            .iter()
+            .flat_map(|group| group.split_whitespace())
+        {
+            let mut letters = String::new();
+            let mut levels = vec![0u8];
+            for c in token.chars() {
+                match c.to_digit(10) {
+                    Some(level) => *levels.last_mut().unwrap() = level as u8,
+                    None => {
+                        letters.push(lowercase(c));
+                        levels.push(0);
+                    }
+                }
+            }
+            if letters.is_empty() {
+                return Err(O4eError::InvalidParameter {
+                    name: "hyphenation_pattern".to_string(),
+                    value: token.to_string(),
+                });
+            }
+            patterns.max_len = patterns.max_len.max(levels.len() - 1);
+            patterns.levels.insert(letters, levels);
+        }
+        for token in exception_groups
+            .iter()
+            .flat_map(|group| group.split_whitespace())
+        {
+            let mut word = String::new();
+            let mut positions = Vec::new();
+            for c in token.chars() {
+                if c == '-' {
+                    positions.push(word.chars().count());
+                } else {
+                    word.push(lowercase(c));
+                }
+            }
+            patterns.exceptions.insert(word, positions);
+        }
+        Ok(patterns)
+    }
+
+    /// Character positions in `word` that the patterns break before, ignoring bounds.
+    fn break_positions(&self, word: &str) -> Vec<usize> {
+        let lower: String = word.chars().map(lowercase).collect();
+        if let Some(positions) = self.exceptions.get(&lower) {
+            return positions.clone();
+        }
+
+        let dotted: Vec<char> = std::iter::once('.')
+            .chain(lower.chars())
+            .chain(std::iter::once('.'))
+            .collect();
+        // Level between dotted[i - 1] and dotted[i]
+        let mut levels = vec![0u8; dotted.len() + 1];
+        for start in 0..dotted.len() {
+            let mut key = String::new();
+            for (length, &c) in dotted[start..].iter().take(self.max_len).enumerate() {
+                key.push(c);
+                if let Some(pattern) = self.levels.get(&key) {
+                    debug_assert_eq!(pattern.len(), length + 2);
+                    for (offset, &level) in pattern.iter().enumerate() {
+                        let slot = &mut levels[start + offset];
+                        *slot = (*slot).max(level);
+                    }
+                }
+            }
+        }
+        // Breaking before word character i sits between dotted[i] and dotted[i + 1]
+        (1..dotted.len() - 2)
+            .filter(|&position| levels[position + 1] % 2 == 1)
+            .collect()
+    }
+}
+
+/// Contents of every `\name{…}` group in a TeX source.
+fn tex_groups<'a>(source: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("\\{name}{{");
+    let mut groups = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find(&open) {
+        let body = &rest[start + open.len()..];
+        let end = body.find('}').unwrap_or(body.len());
+        groups.push(&body[..end]);
+        rest = &body[end..];
+    }
+    groups
+}
+
+/// Single-character lowercase mapping, so positions in the word stay aligned.
+fn lowercase(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(lower), None) => lower,
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(hyphenator: &Hyphenator, word: &str) -> String {
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for point in hyphenator.hyphenate(word) {
+            parts.push(&word[start..point]);
+            start = point;
+        }
+        parts.push(&word[start..]);
+        parts.join("-")
+    }
+
+    #[test]
+    fn embedded_patterns_follow_the_language_tag() {
+        let english = Hyphenator::for_language("en-US").unwrap();
+        assert_eq!(split(&english, "hyphenation"), "hy-phen-ation");
+        assert_eq!(split(&english, "Extensive"), "Ex-ten-sive");
+        assert!(english.hyphenate("cat").is_empty());
+
+        let german = Hyphenator::for_language("de_CH").unwrap();
+        assert!(!german.hyphenate("Silbentrennung").is_empty());
+        assert!(Hyphenator::for_language("nb").is_some());
+        assert!(Hyphenator::for_language("zz").is_none());
+        assert!(Hyphenator::for_language("").is_none());
+    }
+
+    #[test]
+    fn tex_patterns_load_with_exceptions_and_bounds() {
+        // From Liang's thesis: "hy-phen-ation" with the minimal pattern set
+        let source = r"
+            % comment \patterns{ignored}
+            \patterns{
+            hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n
+            }
+            \hyphenation{ ta-ble über-all }
+        ";
+        let hyphenator = Hyphenator::from_patterns(source).unwrap();
+        assert_eq!(split(&hyphenator, "hyphenation"), "hy-phen-ation");
+        assert_eq!(split(&hyphenator, "Table"), "Ta-ble");
+        assert_eq!(split(&hyphenator, "Überall"), "Über-all");
+
+        let hyphenator = hyphenator.with_bounds(3, 3);
+        assert_eq!(split(&hyphenator, "hyphenation"), "hyphen-ation");
+        assert_eq!(hyphenator.bounds(), (3, 3));
+
+        let bare = Hyphenator::from_patterns("hy3ph 1na n2at").unwrap();
+        assert_eq!(split(&bare, "hyphenation"), "hy-phe-nation");
+        assert!(matches!(
+            Hyphenator::from_patterns(r"\patterns{ 1 }"),
+            Err(O4eError::InvalidParameter { value, .. }) if value == "1"
+        ));
+    }
+
+    #[test]
+    fn pattern_files_load_from_disk() {
+        // One file per test run, so concurrent runs do not share it
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        let path =
+            std::env::temp_dir().join(format!("o4e-hyph-test-{}-{nanos}.tex", std::process::id()));
+        std::fs::write(
+            &path,
+            "\\patterns{ hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n }",
+        )
+        .unwrap();
+        let hyphenator = Hyphenator::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(split(&hyphenator, "hyphenation"), "hy-phen-ation");
+        assert!(Hyphenator::from_file(&path).is_err());
+    }
+}
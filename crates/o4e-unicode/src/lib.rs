@@ -2,6 +2,12 @@
 
 //! Unicode-aware text segmentation utilities shared across backends.
 
+pub mod hyphenation;
+pub mod linebreak;
+
+pub use hyphenation::{Hyphenator, SOFT_HYPHEN};
+pub use linebreak::{break_opportunities, BreakKind, BreakOpportunity};
+
 use icu_properties::{
     maps::{self, CodePointMapDataBorrowed},
     names::PropertyEnumToValueNameLinearMapperBorrowed,
@@ -14,6 +20,11 @@ use o4e_core::{
 };
 use unicode_bidi::BidiInfo;
 
+/// Byte offsets of the extended grapheme cluster boundaries in `text`, from 0 to its length.
+pub fn grapheme_boundaries(text: &str) -> Vec<usize> {
+    GraphemeClusterSegmenter::new().segment_str(text).collect()
+}
+
 /// Unicode-aware segmenter that powers all o4e backends.
 pub struct TextSegmenter {
     script_map: CodePointMapDataBorrowed<'static, Script>,
@@ -31,12 +42,24 @@ impl TextSegmenter {
 
     /// Segment text into runs that respect grapheme clusters, bidi, script, and optional word chunks.
     pub fn segment(&self, text: &str, options: &SegmentOptions) -> Result<Vec<TextRun>> {
+        self.segment_with_boundaries(text, options, &[])
+    }
+
+    /// [`segment`](Self::segment), also ending runs at the byte offsets in `boundaries`
+    /// (such as attribute span edges), so no run crosses one.
+    ///
+    /// Offsets inside a grapheme cluster end the run after that cluster.
+    pub fn segment_with_boundaries(
+        &self,
+        text: &str,
+        options: &SegmentOptions,
+        boundaries: &[usize],
+    ) -> Result<Vec<TextRun>> {
         if text.is_empty() {
             return Ok(Vec::new());
         }
 
-        let grapheme_boundaries: Vec<usize> =
-            GraphemeClusterSegmenter::new().segment_str(text).collect();
+        let grapheme_boundaries = grapheme_boundaries(text);
         if grapheme_boundaries.len() < 2 {
             return Ok(vec![self.build_run(
                 text,
@@ -53,7 +76,15 @@ impl TextSegmenter {
             .map(|pair| (pair[0], pair[1]))
             .collect();
 
-        let line_breaks = Self::hard_line_breaks(text);
+        let mut line_breaks = Self::hard_line_breaks(text);
+        line_breaks.extend(boundaries.iter().copied().map(|offset| {
+            // The end of the cluster holding `offset`
+            grapheme_boundaries[grapheme_boundaries
+                .partition_point(|&end| end < offset)
+                .min(grapheme_boundaries.len() - 1)]
+        }));
+        line_breaks.sort_unstable();
+        line_breaks.dedup();
         let word_breaks: Vec<usize> = if options.font_fallback {
             WordSegmenter::new_auto().segment_str(text).collect()
         } else {
@@ -65,6 +96,7 @@ impl TextSegmenter {
         let mut runs = Vec::with_capacity(slices.len());
 
         for slice in slices {
+            let first = runs.len();
             self.collect_runs_in_slice(
                 text,
                 slice,
@@ -79,6 +111,10 @@ impl TextSegmenter {
                 &language,
                 &mut runs,
             );
+            // Slices are in visual order; so are the runs within a right-to-left one
+            if slice.direction == Direction::RightToLeft {
+                runs[first..].reverse();
+            }
         }
 
         if runs.is_empty() {
@@ -119,8 +155,9 @@ impl TextSegmenter {
                 if run.start >= run.end {
                     continue;
                 }
-                let absolute_start = line.start + run.start;
-                let absolute_end = line.start + run.end;
+                // Runs and levels index the whole text, not the paragraph
+                let absolute_start = run.start;
+                let absolute_end = run.end;
                 let level = levels.get(run.start).copied().unwrap_or(paragraph.level);
                 let direction = if level.is_rtl() {
                     Direction::RightToLeft
@@ -374,6 +411,17 @@ mod tests {
         assert_eq!(runs[1].text, "Line2");
     }
 
+    #[test]
+    fn segment_bidi_paragraphs_after_the_first() {
+        let mut options = SegmentOptions::default();
+        options.bidi_resolve = true;
+        let runs = segment("ab\nגד", options);
+        let texts: Vec<&str> = runs.iter().map(|run| run.text.as_str()).collect();
+        assert_eq!(texts, vec!["ab\n", "גד"]);
+        assert_eq!(runs[1].range, (3, 7));
+        assert_eq!(runs[1].direction, Direction::RightToLeft);
+    }
+
     #[test]
     fn segment_splits_on_word_boundaries_for_fallback() {
         let mut options = SegmentOptions::default();
@@ -382,6 +430,38 @@ mod tests {
         assert!(runs.len() >= 2);
     }
 
+    #[test]
+    fn segment_ends_runs_at_extra_boundaries() {
+        let segmenter = TextSegmenter::new();
+        let options = SegmentOptions::default();
+        let runs = segmenter
+            .segment_with_boundaries("Hello bold world", &options, &[6, 10])
+            .unwrap();
+        let texts: Vec<&str> = runs.iter().map(|run| run.text.as_str()).collect();
+        assert_eq!(texts, vec!["Hello ", "bold", " world"]);
+        assert_eq!(runs[1].range, (6, 10));
+
+        // A boundary inside "e" + combining acute waits for the cluster to end
+        let runs = segmenter
+            .segment_with_boundaries("cafe\u{301}s", &options, &[4])
+            .unwrap();
+        assert_eq!(runs[0].text, "cafe\u{301}");
+
+        // Pieces of a right-to-left slice come out in visual order, last word first
+        let options = SegmentOptions {
+            bidi_resolve: true,
+            ..SegmentOptions::default()
+        };
+        let runs = segmenter
+            .segment_with_boundaries("שלום עולם", &options, &[9])
+            .unwrap();
+        let texts: Vec<&str> = runs.iter().map(|run| run.text.as_str()).collect();
+        assert_eq!(texts, vec!["עולם", "שלום "]);
+        assert!(runs
+            .iter()
+            .all(|run| run.direction == Direction::RightToLeft));
+    }
+
     #[test]
     fn segment_itemizes_cjk_and_latin() {
         let mut options = SegmentOptions::default();
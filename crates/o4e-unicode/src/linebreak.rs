@@ -0,0 +1,137 @@
+// this_file: crates/o4e-unicode/src/linebreak.rs
+
+//! Line break opportunities (UAX #14) with hyphenation points.
+
+use icu_segmenter::{LineSegmenter, WordSegmenter};
+
+use crate::hyphenation::{Hyphenator, SOFT_HYPHEN};
+
+/// How a line ending at a [`BreakOpportunity`] is finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakKind {
+    /// An optional break, such as after a space
+    Allowed,
+    /// A hard line break, or the end of the text
+    Mandatory,
+    /// An optional break inside a word (a hyphenation point or after a soft hyphen); the
+    /// line ends with a hyphen
+    Hyphen,
+}
+
+/// A position where a line may end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakOpportunity {
+    /// Byte offset the next line starts at
+    pub offset: usize,
+    pub kind: BreakKind,
+}
+
+/// Line break opportunities in `text`, in increasing order and ending with the end of the
+/// text.
+///
+/// Soft hyphens always offer a [`BreakKind::Hyphen`] break; words containing one are only
+/// broken there. Other words get the hyphenation points of `hyphenator`.
+pub fn break_opportunities(text: &str, hyphenator: Option<&Hyphenator>) -> Vec<BreakOpportunity> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    let mut breaks: Vec<BreakOpportunity> = LineSegmenter::new_auto()
+        .segment_str(text)
+        .filter(|&offset| offset > 0)
+        .map(|offset| {
+            let before = text[..offset].chars().next_back();
+            let kind = if offset == text.len() || before.is_some_and(is_hard_break) {
+                BreakKind::Mandatory
+            } else if before == Some(SOFT_HYPHEN) {
+                BreakKind::Hyphen
+            } else {
+                BreakKind::Allowed
+            };
+            BreakOpportunity { offset, kind }
+        })
+        .collect();
+
+    if let Some(hyphenator) = hyphenator {
+        let segmenter = WordSegmenter::new_auto();
+        let mut words = segmenter.segment_str(text);
+        let mut start = words.next().unwrap_or_default();
+        let mut points = Vec::new();
+        while let Some(end) = words.next() {
+            let word = &text[start..end];
+            if words.is_word_like()
+                && !word.contains(SOFT_HYPHEN)
+                && word.chars().all(char::is_alphabetic)
+            {
+                points.extend(hyphenator.hyphenate(word).into_iter().map(|point| {
+                    BreakOpportunity {
+                        offset: start + point,
+                        kind: BreakKind::Hyphen,
+                    }
+                }));
+            }
+            start = end;
+        }
+        breaks.extend(points);
+        // A word break already at a hyphenation point wins
+        breaks
+            .sort_by_key(|opportunity| (opportunity.offset, opportunity.kind == BreakKind::Hyphen));
+        breaks.dedup_by_key(|opportunity| opportunity.offset);
+    }
+    breaks
+}
+
+fn is_hard_break(c: char) -> bool {
+    matches!(
+        c,
+        '\n' | '\r' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(text: &str, hyphenator: Option<&Hyphenator>) -> Vec<(usize, BreakKind)> {
+        break_opportunities(text, hyphenator)
+            .into_iter()
+            .map(|opportunity| (opportunity.offset, opportunity.kind))
+            .collect()
+    }
+
+    #[test]
+    fn words_break_after_spaces_and_hard_breaks() {
+        assert_eq!(
+            describe("one two\nthree", None),
+            vec![
+                (4, BreakKind::Allowed),
+                (8, BreakKind::Mandatory),
+                (13, BreakKind::Mandatory),
+            ]
+        );
+        assert!(describe("", None).is_empty());
+    }
+
+    #[test]
+    fn hyphenation_and_soft_hyphens_add_breaks_inside_words() {
+        let english = Hyphenator::for_language("en").unwrap();
+        assert_eq!(
+            describe("a hyphenation", Some(&english)),
+            vec![
+                (2, BreakKind::Allowed),
+                (4, BreakKind::Hyphen),
+                (8, BreakKind::Hyphen),
+                (13, BreakKind::Mandatory),
+            ]
+        );
+
+        // Soft hyphens break without a hyphenator and replace its points in their word
+        let text = "hyphen\u{AD}ation";
+        let soft = vec![(8, BreakKind::Hyphen), (text.len(), BreakKind::Mandatory)];
+        assert_eq!(describe(text, None), soft);
+        assert_eq!(describe(text, Some(&english)), soft);
+
+        // Numbers and mixed words are left alone
+        assert_eq!(describe("1234567890", Some(&english)).len(), 1);
+    }
+}
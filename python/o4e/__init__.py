@@ -6,7 +6,7 @@ High-performance, multi-backend text rendering for Python.
 
 Features:
 - Multiple backends: CoreText (macOS), DirectWrite (Windows), ICU+HarfBuzz (cross-platform)
-- Output formats: PNG, SVG, raw bitmap data
+- Output formats: PNG, WebP, JPEG, PGM/PBM, BMP, SVG, PDF, raw bitmap data
 - Full Unicode support with complex script shaping
 - Batch processing with parallelization
 - PIL/Pillow and NumPy integration
@@ -24,6 +24,7 @@ try:
     _Font = _native.Font
     get_version = _native.get_version
     ShapingResult = _native.ShapingResult
+    FitResult = _native.FitResult
     Glyph = _native.Glyph
 except ImportError:
     # Try direct import
@@ -33,12 +34,14 @@ except ImportError:
         _Font = _native.Font
         get_version = _native.get_version
         ShapingResult = _native.ShapingResult
+        FitResult = _native.FitResult
         Glyph = _native.Glyph
     except ImportError:
         # Fallback for development/testing without compiled module
         _TextRenderer = None
         _Font = None
         ShapingResult = None
+        FitResult = None
         Glyph = None
         def get_version():
             return "0.1.0-dev"
@@ -62,7 +65,7 @@ __version__ = get_version()
 __all__ = [
     "TextRenderer", "Font", "render", "render_to_file", "shape_text",
     "list_backends", "get_default_backend", "RenderFormat", "Direction",
-    "ShapingResult", "Glyph", "Bitmap", "__version__"
+    "ShapingResult", "FitResult", "Glyph", "Bitmap", "__version__"
 ]
 
 
@@ -70,7 +73,13 @@ class RenderFormat(Enum):
     """Supported output formats for rendering."""
     RAW = "raw"      # Raw RGBA bitmap data
     PNG = "png"      # PNG image bytes
+    WEBP = "webp"    # Lossless WebP image bytes
+    JPEG = "jpeg"    # JPEG image bytes (needs a background)
+    PGM = "pgm"      # Binary PGM (P5) grayscale image bytes
+    PBM = "pbm"      # Binary PBM (P4) 1-bit image bytes
+    BMP = "bmp"      # BMP image bytes
     SVG = "svg"      # SVG XML string
+    PDF = "pdf"      # PDF document bytes
 
 
 class Direction(Enum):
@@ -90,13 +99,23 @@ class Bitmap:
             data: Raw pixel data
             width: Width in pixels
             height: Height in pixels
-            format: Pixel format ("rgba", "bgra", "rgb", "gray")
+            format: Pixel format ("rgba", "bgra", "rgba_premultiplied",
+                "bgra_premultiplied", "rgb", "gray" or "a8" for a coverage mask)
         """
         self.data = data
         self.width = width
         self.height = height
         self.format = format
 
+    @property
+    def channels(self) -> int:
+        """Bytes per pixel."""
+        if self.format == "rgb":
+            return 3
+        if self.format in ("gray", "a8"):
+            return 1
+        return 4
+
     def to_numpy(self) -> 'np.ndarray':
         """Convert to numpy array.
 
@@ -106,9 +125,8 @@ class Bitmap:
         if not HAS_NUMPY:
             raise ImportError("NumPy is required for this operation. Install with: pip install numpy")
 
-        channels = 4 if self.format in ("rgba", "bgra") else 3 if self.format == "rgb" else 1
         arr = np.frombuffer(self.data, dtype=np.uint8)
-        return arr.reshape((self.height, self.width, channels))
+        return arr.reshape((self.height, self.width, self.channels))
 
     def to_pil(self) -> 'PILImage.Image':
         """Convert to PIL/Pillow Image.
@@ -119,8 +137,19 @@ class Bitmap:
         if not HAS_PIL:
             raise ImportError("PIL/Pillow is required for this operation. Install with: pip install Pillow")
 
-        mode = "RGBA" if self.format == "rgba" else "RGB" if self.format == "rgb" else "L"
-        return PILImage.frombytes(mode, (self.width, self.height), self.data)
+        # (image mode, raw decoder mode); masks and luminance both map to "L"
+        modes = {
+            "rgba": ("RGBA", "RGBA"),
+            "bgra": ("RGBA", "BGRA"),
+            "rgba_premultiplied": ("RGBA", "RGBa"),
+            "bgra_premultiplied": ("RGBA", "BGRa"),
+            "rgb": ("RGB", "RGB"),
+        }
+        mode, raw_mode = modes.get(self.format, ("L", "L"))
+        size = (self.width, self.height)
+        if raw_mode == mode:
+            return PILImage.frombytes(mode, size, self.data)
+        return PILImage.frombytes(mode, size, self.data, "raw", raw_mode)
 
     def save(self, path: Union[str, Path], format: Optional[str] = None):
         """Save bitmap to file.
@@ -207,6 +236,36 @@ class Font:
         obj._init_from_native(native, ("bytes", name, payload), variations, features)
         return obj
 
+    @classmethod
+    def parse(cls, description: str) -> 'Font':
+        """Parse a Pango-style description: families, then stretch, weight and style
+        words, then the size, e.g. "Noto Sans Condensed Bold Italic 14px" or
+        "Noto Sans, DejaVu Sans 12/1.4 @wght=650".
+
+        Raises ValueError when the description lacks a family or size.
+        """
+        if _Font is None:
+            raise ImportError("o4e native module not available")
+        return cls._from_parsed(_Font.parse(description))
+
+    @classmethod
+    def parse_css(cls, shorthand: str) -> 'Font':
+        """Parse the CSS ``font`` shorthand, e.g. 'italic 700 16px/1.4 "Noto Serif", serif'.
+
+        Raises ValueError when the shorthand is malformed.
+        """
+        if _Font is None:
+            raise ImportError("o4e native module not available")
+        return cls._from_parsed(_Font.parse_css(shorthand))
+
+    @classmethod
+    def _from_parsed(cls, native: '_native.Font') -> 'Font':
+        obj = cls.__new__(cls)
+        obj._init_from_native(
+            native, ("family", native.family), native.variations, native.features
+        )
+        return obj
+
     def _init_from_native(
         self,
         native_font: '_native.Font',
@@ -226,7 +285,7 @@ class Font:
 
     @property
     def size(self) -> float:
-        """Font size in pixels."""
+        """Font size in points (pixels at the default 72 DPI)."""
         return self._font.size
 
     @property
@@ -239,6 +298,25 @@ class Font:
         """Font style (normal, italic, oblique)."""
         return self._font.style
 
+    @property
+    def stretch(self) -> str:
+        """Font width as a CSS keyword (normal, condensed, semi-expanded, ...)."""
+        return self._font.stretch
+
+    @property
+    def fallbacks(self) -> List[str]:
+        """Families tried in order when the family is not installed."""
+        return self._font.fallbacks
+
+    @property
+    def line_height(self) -> Optional[float]:
+        """Line spacing as a multiple of the size; None uses the font's metrics."""
+        return self._font.line_height
+
+    def to_css(self) -> str:
+        """The CSS ``font`` shorthand for this font."""
+        return self._font.to_css()
+
     def with_size(self, size: float) -> 'Font':
         """Create a copy with different size."""
         return self._clone(size=size)
@@ -248,21 +326,15 @@ class Font:
         return self._clone(weight=weight)
 
     def _clone(self, size: Optional[float] = None, weight: Optional[int] = None) -> 'Font':
-        kind = self._source[0]
-        args = {
-            "size": size if size is not None else self.size,
-            "weight": weight if weight is not None else self.weight,
-            "style": self.style,
-            "variations": self.variations.copy(),
-            "features": self.features.copy(),
-        }
-        if kind == "family":
-            return Font(self._source[1], **args)
-        if kind == "path":
-            return Font.from_path(self._source[1], **args)
-        if kind == "bytes":
-            return Font.from_bytes(self._source[1], self._source[2], **args)
-        raise ValueError(f"Unsupported font source {self._source}")
+        obj = Font.__new__(Font)
+        obj._init_from_native(
+            self._font.replace(size, weight), self._source, self.variations, self.features
+        )
+        return obj
+
+    def __str__(self) -> str:
+        """The Pango-style description, which ``Font.parse`` reads back."""
+        return str(self._font)
 
     def __repr__(self) -> str:
         return (
@@ -340,17 +412,110 @@ class TextRenderer:
         Args:
             text: Text to render
             font: Font specification or font family name
-            format: Output format (RenderFormat.RAW/PNG/SVG or "raw"/"png"/"svg")
-            color: Text color (hex, e.g., "#FF0000" for red)
-            background: Background color (hex or "transparent")
-            padding: Padding around text in pixels
+            format: Output format (a RenderFormat or "raw"/"png"/"webp"/"jpeg"/"pgm"/"pbm"/
+                "bmp"/"svg"/"pdf")
+            color: Text color as a CSS color (e.g. "#FF0000", "rgb(255 0 0)", "red")
+            background: Background CSS color ("transparent" for none; "currentColor" is
+                the text color)
+            padding: Padding around text in points
             direction: Text direction for bidirectional text
-            **options: Additional rendering options
+            **options: Additional rendering options, by feature:
+
+                Units
+                    - ``dpi`` (default 72) or ``scale`` (device scale factor, 2 for
+                      @2x): raster pixels per point. Sizes, padding, strokes, effects
+                      and canvas geometry are in points, so HiDPI renders keep the
+                      same layout. SVG keeps a viewBox in points and scales its size;
+                      PDF is always in points.
+
+                Strokes and sizing
+                    - ``stroke_width``, ``stroke_color``, ``stroke_join``,
+                      ``stroke_cap``, ``stroke_miter_limit``: stroke around the glyphs.
+                    - ``fill`` (default True): False draws outlined text.
+                    - ``sizing``: fits text-sized output to the font's line box grown
+                      to the ink ("logical", default) or to the glyph ink alone
+                      ("ink").
+
+                Decorations
+                    - ``underline``, ``overline``, ``line_through``: True or a style
+                      ("solid"/"double"/"dotted"/"dashed"/"wavy").
+                    - ``decoration_color``: line color, default the text color.
+                    - ``decoration_thickness``: points, default from the font.
+                    - ``decoration_skip_ink`` (default True): breaks under- and
+                      overlines around descenders.
+
+                Fixed canvas (bitmap formats)
+                    - ``canvas_width``, ``canvas_height``: canvas size in points.
+                    - ``baseline`` (points) or ``baseline_ratio`` (default 0.75):
+                      baseline position from the top edge.
+                    - ``align``: "left", "center" or "right".
+                    - ``vertical_align``: "baseline", "top", "middle" or "bottom".
+                    - ``overflow``: "clip" or "error".
+
+                Output encoding
+                    - ``pixel_format``: the RAW layout, "rgba" (default), "bgra",
+                      "rgba_premultiplied", "bgra_premultiplied", "rgb", "gray" or
+                      "a8". Image formats keep the channels they can store; PGM and
+                      PBM store "a8" coverage, or luminance otherwise.
+                    - ``jpeg_quality`` (1-100, default 90): JPEG output, which also
+                      needs a background.
+                    - ``png_compression``: "fast", "default" or "best".
+                    - ``png_filter``: "adaptive", "none", "sub", "up", "average" or
+                      "paeth".
+                    - ``png_reduce_colors`` (default True): grayscale or palette PNGs
+                      for single-color text.
+                    - ``png_srgb`` (default True): mark PNGs as sRGB.
+                    - ``png_metadata``: record text, font and backend in PNGs.
+
+                Paragraphs
+                    - ``max_width``: points; wraps the text into lines, which also
+                      break at hard line breaks.
+                    - ``line_height``: points between baselines, default from the
+                      font.
+                    - ``hyphenate`` (True for the ``language`` option, or a language
+                      tag) or ``hyphenation_patterns`` (a TeX pattern file): adds
+                      hyphenation points and needs ``max_width``. Soft hyphens
+                      (U+00AD) may always break.
+
+                Truncation
+                    - ``truncate``: "start", "middle" or "end"; keeps one line
+                      instead of wrapping, replacing text beyond ``max_width`` at
+                      whole grapheme clusters.
+                    - ``ellipsis``: the replacement, default U+2026, or "..." when
+                      the font lacks it.
+
+                Styled spans (every format but PDF, not with ``max_width``)
+                    - ``spans``: list of dicts with ``start`` and ``end`` (character
+                      indices) and any of ``family``, ``size``, ``weight``,
+                      ``style``, ``color``, ``features``, ``variations``,
+                      ``baseline_shift`` (points, raising the text) and the
+                      decoration options. Later spans override earlier ones where
+                      they overlap.
+                    - ``markup=True``: reads the text as Pango-like markup, and
+                      ``spans`` then index the text without tags. Supports ``<b>``,
+                      ``<i>``, ``<s>``, ``<u>``, ``<sup>``, ``<sub>``, ``<big>``,
+                      ``<small>``, ``<tt>`` and ``<span>`` with ``font`` ("Noto Sans
+                      Bold 14"), ``size``, ``weight``, ``style``, ``color``,
+                      ``features`` ("smcp,-liga"), ``variations``,
+                      ``underline``/``overline``/``strikethrough`` and ``rise``
+                      (points). Write ``&amp;`` and ``&lt;`` for literal ``&`` and
+                      ``<``. Malformed markup raises ValueError naming the byte
+                      offset.
+
+                Tabs
+                    - ``tab_interval``: points between stops, default eight spaces.
+                    - ``tab_stops``: explicit stops before the interval ones, as
+                      positions in points or ``(position, align)`` pairs with "left",
+                      "center", "right" or "decimal".
+                    - ``tab_decimal`` (default "."): the character decimal stops line
+                      up.
+                    Stops restart on every line of a paragraph and apply to
+                    truncated text, spans and markup as well.
 
         Returns:
             Rendered output:
             - Bitmap object for RAW format
-            - bytes for PNG format
+            - bytes for PNG, WebP, JPEG, PGM, PBM, BMP and PDF formats
             - str (XML) for SVG format
 
         Examples:
@@ -386,11 +551,75 @@ class TextRenderer:
 
         # Convert raw data to Bitmap object
         if format == "raw" and isinstance(result, tuple):
-            data, width, height = result
-            return Bitmap(data, width, height)
+            return Bitmap(*result)
 
         return result
 
+    def render_into(
+        self,
+        text: str,
+        font: Union[Font, str],
+        buffer: Any,
+        x: int = 0,
+        y: int = 0,
+        *,
+        width: Optional[int] = None,
+        height: Optional[int] = None,
+        stride: Optional[int] = None,
+        pixel_format: str = "rgba",
+        color: str = "#000000",
+        background: str = "transparent",
+        padding: int = 0,
+        direction: Union[str, Direction] = Direction.AUTO,
+        **options: Any
+    ) -> None:
+        """Composite text onto an existing image buffer in place.
+
+        Args:
+            text: Text to render
+            font: Font specification or font family name
+            buffer: Writable buffer: a bytearray, memoryview or uint8 NumPy array of
+                shape (height, width, 4); rows of array views may be padded
+            x, y: Position of the top-left corner of the bitmap ``render`` would return
+            width, height, stride: Frame geometry; inferred from array buffers
+            pixel_format: "rgba", "bgra", "rgba_premultiplied", "bgra_premultiplied",
+                "rgb", "gray" or "a8"
+            color: Text color as a CSS color
+            background: Background CSS color painted under the text's bitmap area
+            padding: Padding around text in points
+            direction: Text direction for bidirectional text
+            **options: Additional rendering options, as for ``render``
+
+        Examples:
+            >>> frame = np.zeros((720, 1280, 4), dtype=np.uint8)
+            >>> renderer.render_into("Live", Font("Arial", 48), frame, 40, 600,
+            ...                      pixel_format="bgra_premultiplied", color="white")
+        """
+        if isinstance(font, str):
+            font = Font(font)
+        if isinstance(direction, Direction):
+            direction = direction.value
+
+        render_options = {
+            "color": color,
+            "background": background,
+            "padding": padding,
+            "direction": direction,
+            **options
+        }
+        self._renderer.render_into(
+            text,
+            font._font,
+            buffer,
+            width=width,
+            height=height,
+            stride=stride,
+            pixel_format=pixel_format,
+            x=x,
+            y=y,
+            render_options=render_options,
+        )
+
     def shape(
         self,
         text: str,
@@ -437,6 +666,63 @@ class TextRenderer:
             shape_options=shape_options
         )
 
+    def fit(
+        self,
+        text: str,
+        font: Union[Font, str],
+        width: Optional[float] = None,
+        height: Optional[float] = None,
+        min_size: float = 6.0,
+        max_size: float = 72.0,
+        wrap: bool = False,
+        max_lines: Optional[int] = None,
+        **options: Any
+    ) -> 'FitResult':
+        """Find the largest font size at which text fits a box, without rendering.
+
+        The text is shaped once and measured at each candidate size by scaling, so this
+        is much cheaper than searching with ``render``.
+
+        Args:
+            text: Text to fit
+            font: Font specification (its size is ignored)
+            width: Box width in points, or None for no limit
+            height: Box height in points (first ascender to last descender), or None
+            min_size: Smallest font size to try, in points
+            max_size: Largest font size to try, in points
+            wrap: Wrap lines to ``width`` instead of only breaking at hard line breaks
+            max_lines: Most lines allowed
+            **options: ``line_spacing`` (baseline distance as a multiple of the size),
+                ``precision`` (points, default 0.1), ``hyphenate``,
+                ``hyphenation_patterns``, the tab stops ``tab_interval``, ``tab_stops``
+                and ``tab_decimal`` (in points at the fitted size) and the shaping
+                options of ``shape``
+
+        Returns:
+            FitResult with ``size``, ``fits`` (False when even ``min_size`` overflows,
+            with ``size`` set to it), ``width``, ``height`` and the ``lines`` of text
+
+        Examples:
+            >>> result = renderer.fit("Sale!", Font("Arial"), width=120, height=40)
+            >>> renderer.render("Sale!", Font("Arial", result.size), format="png")
+        """
+        if isinstance(font, str):
+            font = Font(font)
+
+        fit_options = {
+            "width": width,
+            "height": height,
+            "min_size": min_size,
+            "max_size": max_size,
+            "wrap": wrap,
+            "max_lines": max_lines,
+            **options
+        }
+        if "direction" in fit_options and isinstance(fit_options["direction"], Direction):
+            fit_options["direction"] = fit_options["direction"].value
+
+        return self._renderer.fit(text, font._font, fit_options=fit_options)
+
     def render_batch(
         self,
         items: List[Dict[str, Any]],
@@ -520,13 +806,18 @@ class TextRenderer:
 
         # Auto-detect format from extension
         if format is None:
-            ext = path.suffix.lower()
-            if ext == ".png":
-                format = RenderFormat.PNG
-            elif ext == ".svg":
-                format = RenderFormat.SVG
-            else:
-                format = RenderFormat.RAW
+            extensions = {
+                ".png": RenderFormat.PNG,
+                ".webp": RenderFormat.WEBP,
+                ".jpg": RenderFormat.JPEG,
+                ".jpeg": RenderFormat.JPEG,
+                ".pgm": RenderFormat.PGM,
+                ".pbm": RenderFormat.PBM,
+                ".bmp": RenderFormat.BMP,
+                ".svg": RenderFormat.SVG,
+                ".pdf": RenderFormat.PDF,
+            }
+            format = extensions.get(path.suffix.lower(), RenderFormat.RAW)
 
         result = self.render(text, font, format=format, **options)
 
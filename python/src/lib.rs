@@ -8,13 +8,24 @@ use std::sync::Arc;
 
 use o4e_core::{
     types::{
-        AntialiasMode, BoundingBox, Direction, Features, FontSource, FontStyle, HintingMode,
-        RenderFormat,
+        AntialiasMode, BaselinePosition, Canvas, CanvasOverflow, Decoration, DecorationLine,
+        DecorationStyle, Direction, Features, FontSource, FontStretch, FontStyle, HintingMode,
+        HorizontalAlign, LineCap, LineJoin, PngCompression, PngFilter, PngOptions, RenderFormat,
+        SizingMode, Stroke, VerticalAlign,
     },
-    utils::combine_shaped_results,
-    Backend, Font as CoreFont, Glyph as CoreGlyph, RenderOptions as CoreRenderOptions,
-    RenderOutput, SegmentOptions, ShapingResult as CoreShapingResult, TextRun,
+    utils::{combine_shaped_runs, parse_color},
+    Backend, Font as CoreFont, Frame, Glyph as CoreGlyph, PixelFormat,
+    RenderOptions as CoreRenderOptions, RenderOutput, SegmentOptions,
+    ShapingResult as CoreShapingResult, TextRun,
 };
+use o4e_render::fit::{fit_text, Fit, FitOptions};
+use o4e_render::markup::parse_markup;
+use o4e_render::paragraph::{break_paragraph, ParagraphOptions};
+use o4e_render::rich::{render_rich, shape_rich, SpanStyle, TextSpan};
+use o4e_render::tabs::{shape_with_tabs, TabAlign, TabOptions, TabStop};
+use o4e_render::truncate::{truncate, TruncateMode, TruncateOptions};
+use o4e_unicode::Hyphenator;
+use pyo3::buffer::PyBuffer;
 use pyo3::types::PyType;
 use pyo3::PyAny;
 use pyo3::{
@@ -48,8 +59,15 @@ struct Font {
     weight: u16,
     #[pyo3(get)]
     style: String,
+    #[pyo3(get)]
+    fallbacks: Vec<String>,
+    #[pyo3(get)]
+    line_height: Option<f32>,
+    stretch: FontStretch,
     source: FontSource,
+    #[pyo3(get)]
     variations: HashMap<String, f32>,
+    #[pyo3(get)]
     features: HashMap<String, bool>,
 }
 
@@ -66,6 +84,9 @@ impl Font {
             size: self.size,
             weight: self.weight,
             style: font_style,
+            stretch: self.stretch,
+            fallbacks: self.fallbacks.clone(),
+            line_height: self.line_height,
             variations: self.variations.clone(),
             features: Features {
                 tags: self.features.clone(),
@@ -73,6 +94,26 @@ impl Font {
             source: self.source.clone(),
         }
     }
+
+    fn from_core(font: CoreFont) -> Self {
+        let style = match font.style {
+            FontStyle::Normal => "normal",
+            FontStyle::Italic => "italic",
+            FontStyle::Oblique => "oblique",
+        };
+        Self {
+            family: font.family,
+            size: font.size,
+            weight: font.weight,
+            style: style.to_string(),
+            fallbacks: font.fallbacks,
+            line_height: font.line_height,
+            stretch: font.stretch,
+            source: font.source,
+            variations: font.variations,
+            features: font.features.tags,
+        }
+    }
 }
 
 #[pymethods]
@@ -92,6 +133,9 @@ impl Font {
             size: size.unwrap_or(16.0),
             weight: weight.unwrap_or(400),
             style: style.unwrap_or_else(|| "normal".to_string()),
+            fallbacks: Vec::new(),
+            line_height: None,
+            stretch: FontStretch::Normal,
             source: FontSource::Family(family),
             variations: variations.unwrap_or_default(),
             features: features.unwrap_or_default(),
@@ -114,6 +158,9 @@ impl Font {
             size: size.unwrap_or(16.0),
             weight: weight.unwrap_or(400),
             style: style.unwrap_or_else(|| "normal".to_string()),
+            fallbacks: Vec::new(),
+            line_height: None,
+            stretch: FontStretch::Normal,
             source: FontSource::Path(path),
             variations: variations.unwrap_or_default(),
             features: features.unwrap_or_default(),
@@ -140,6 +187,9 @@ impl Font {
             size: size.unwrap_or(16.0),
             weight: weight.unwrap_or(400),
             style: style.unwrap_or_else(|| "normal".to_string()),
+            fallbacks: Vec::new(),
+            line_height: None,
+            stretch: FontStretch::Normal,
             source: FontSource::Bytes {
                 name,
                 data: Arc::from(bytes.into_boxed_slice()),
@@ -148,6 +198,46 @@ impl Font {
             features: features.unwrap_or_default(),
         })
     }
+
+    /// Parse a Pango-style description such as "Noto Sans Condensed Bold Italic 14px".
+    #[classmethod]
+    fn parse(_cls: &Bound<'_, PyType>, description: &str) -> PyResult<Self> {
+        CoreFont::parse(description)
+            .map(Self::from_core)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Parse the CSS `font` shorthand, such as `italic 700 16px/1.4 "Noto Serif", serif`.
+    #[classmethod]
+    fn parse_css(_cls: &Bound<'_, PyType>, shorthand: &str) -> PyResult<Self> {
+        CoreFont::parse_css(shorthand)
+            .map(Self::from_core)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Width as a CSS keyword ("normal", "condensed", ...).
+    #[getter]
+    fn stretch(&self) -> &'static str {
+        self.stretch.css_name()
+    }
+
+    /// A copy with another size or weight.
+    #[pyo3(signature = (size=None, weight=None))]
+    fn replace(&self, size: Option<f32>, weight: Option<u16>) -> Self {
+        Self {
+            size: size.unwrap_or(self.size),
+            weight: weight.unwrap_or(self.weight),
+            ..self.clone()
+        }
+    }
+
+    fn to_css(&self) -> String {
+        self.to_core_font().to_css()
+    }
+
+    fn __str__(&self) -> String {
+        self.to_core_font().to_string()
+    }
 }
 
 /// Glyph information returned to Python.
@@ -206,6 +296,39 @@ impl ShapingResult {
     }
 }
 
+/// Font size found by `TextRenderer.fit`, returned to Python.
+#[pyclass]
+#[derive(Clone)]
+struct FitResult {
+    #[pyo3(get)]
+    size: f32,
+    #[pyo3(get)]
+    fits: bool,
+    #[pyo3(get)]
+    width: f32,
+    #[pyo3(get)]
+    height: f32,
+    #[pyo3(get)]
+    lines: Vec<String>,
+}
+
+impl FitResult {
+    fn from_core(fit: &Fit) -> Self {
+        Self {
+            size: fit.size,
+            fits: fit.fits,
+            width: fit.width,
+            height: fit.height,
+            lines: fit
+                .paragraph
+                .lines
+                .iter()
+                .map(|line| line.shaped.text.clone())
+                .collect(),
+        }
+    }
+}
+
 /// Runtime overrides applied to shaped runs.
 #[derive(Default, Clone)]
 struct ShapeOverrides {
@@ -232,6 +355,16 @@ struct RenderConfig {
     render: CoreRenderOptions,
     segment: SegmentOptions,
     overrides: ShapeOverrides,
+    /// Line breaking, when the text is laid out as a wrapped paragraph
+    paragraph: Option<ParagraphOptions>,
+    /// Ellipsis truncation to a single line, instead of wrapping
+    truncate: Option<TruncateOptions>,
+    /// Attributed ranges, in characters until [`byte_spans`] converts them
+    spans: Vec<TextSpan>,
+    /// The text is markup, parsed into text and spans before layout
+    markup: bool,
+    /// Tab stops, for single lines and paragraphs
+    tabs: TabOptions,
 }
 
 /// Main Python-facing renderer class.
@@ -305,6 +438,60 @@ impl TextRenderer {
         self.render_internal(py, text, font, render_format, render_options_ref)
     }
 
+    /// Composite rendered text onto a writable buffer (bytearray, memoryview, numpy array).
+    ///
+    /// `width`, `height` and `stride` default to the shape of a `(height, width, channels)`
+    /// array; `(x, y)` is where the top-left corner of the bitmap `render` would return
+    /// lands.
+    #[pyo3(signature = (
+        text, font, buffer, width=None, height=None, stride=None, pixel_format=None, x=0, y=0,
+        render_options=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn render_into<'py>(
+        &self,
+        text: &str,
+        font: &Font,
+        buffer: &Bound<'py, PyAny>,
+        width: Option<u32>,
+        height: Option<u32>,
+        stride: Option<usize>,
+        pixel_format: Option<&str>,
+        x: i32,
+        y: i32,
+        render_options: Option<Bound<'py, PyDict>>,
+    ) -> PyResult<()> {
+        let format = parse_pixel_format(pixel_format)?;
+        let buffer = PyBuffer::<u8>::get_bound(buffer)?;
+        if buffer.readonly() {
+            return Err(PyValueError::new_err("buffer must be writable"));
+        }
+        let layout = buffer_layout(&buffer, format)?;
+        let (width, height) =
+            match (width.or(layout.width), height.or(layout.height)) {
+                (Some(width), Some(height)) => (width, height),
+                _ => return Err(PyValueError::new_err(
+                    "width and height are required unless buffer is a (height, width, ...) array",
+                )),
+            };
+        let stride = stride
+            .or(layout.stride)
+            .unwrap_or(width as usize * format.bytes_per_pixel());
+
+        let (shaped, config) =
+            self.shape_for_render(text, font, RenderFormat::Raw, render_options.as_ref())?;
+
+        // SAFETY: the buffer is writable, spans `layout.len` bytes from `buf_ptr`, and stays
+        // exported (and the GIL held) until `buffer` drops at the end of this call.
+        let data =
+            unsafe { std::slice::from_raw_parts_mut(buffer.buf_ptr() as *mut u8, layout.len) };
+        let mut frame = Frame::new(data, width, height, stride, format)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        self.backend
+            .render_into(&shaped, &config.render, &mut frame, (x, y))
+            .map_err(|e| runtime_err("Render error", e))
+    }
+
     /// Shape text without rendering.
     #[pyo3(signature = (text, font, shape_options=None))]
     fn shape<'py>(
@@ -331,6 +518,28 @@ impl TextRenderer {
         Py::new(py, ShapingResult::from_core(&shaped))
     }
 
+    /// Find the largest font size at which text fits a box, measuring without rendering.
+    #[pyo3(signature = (text, font, fit_options=None))]
+    fn fit(
+        &self,
+        py: Python<'_>,
+        text: &str,
+        font: &Font,
+        fit_options: Option<Bound<'_, PyDict>>,
+    ) -> PyResult<Py<FitResult>> {
+        let (segment, overrides) = build_shape_config(fit_options.as_ref())?;
+        let options = parse_fit(fit_options.as_ref(), &segment)?;
+        let fit = fit_text(
+            self.backend.as_ref(),
+            text,
+            &font.to_core_font(),
+            &options,
+            |run| overrides.apply(run),
+        )
+        .map_err(|e| runtime_err("Layout error", e))?;
+        Py::new(py, FitResult::from_core(&fit))
+    }
+
     /// Render a batch of items (currently sequential).
     #[pyo3(signature = (items, format=None, max_workers=None))]
     fn render_batch<'py>(
@@ -422,25 +631,100 @@ impl TextRenderer {
         format: RenderFormat,
         render_options: Option<&Bound<'py, PyDict>>,
     ) -> PyResult<PyObject> {
+        let config = build_render_config(render_options, format)?;
         let core_font = font.to_core_font();
+        let markup = if config.markup {
+            Some(parse_markup(text, &core_font).map_err(|e| PyValueError::new_err(e.to_string()))?)
+        } else {
+            None
+        };
+        let (text, mut spans) = match &markup {
+            Some(markup) => (markup.text.as_str(), markup.spans.clone()),
+            None => (text, Vec::new()),
+        };
+        spans.extend(byte_spans(text, &config.spans)?);
+        let output = if spans.is_empty() {
+            let shaped = self.shape_with_config(text, font, &config)?;
+            self.backend.render(&shaped, &config.render)
+        } else {
+            let layout = shape_rich(
+                self.backend.as_ref(),
+                text,
+                &spans,
+                &core_font,
+                &config.segment,
+                &config.tabs,
+                |run| config.overrides.apply(run),
+            )
+            .map_err(|e| runtime_err("Layout error", e))?;
+            render_rich(self.backend.as_ref(), &layout, &config.render)
+        }
+        .map_err(|e| runtime_err("Render error", e))?;
+
+        self.output_to_py(py, output)
+    }
+
+    fn shape_for_render(
+        &self,
+        text: &str,
+        font: &Font,
+        format: RenderFormat,
+        render_options: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<(CoreShapingResult, RenderConfig)> {
         let config = build_render_config(render_options, format)?;
+        if !config.spans.is_empty() || config.markup {
+            return Err(PyValueError::new_err(
+                "spans and markup are only supported by render",
+            ));
+        }
+        let shaped = self.shape_with_config(text, font, &config)?;
+        Ok((shaped, config))
+    }
+
+    fn shape_with_config(
+        &self,
+        text: &str,
+        font: &Font,
+        config: &RenderConfig,
+    ) -> PyResult<CoreShapingResult> {
+        let core_font = font.to_core_font();
+        if let Some(options) = &config.truncate {
+            return truncate(self.backend.as_ref(), text, &core_font, options, |run| {
+                config.overrides.apply(run)
+            })
+            .map_err(|e| runtime_err("Layout error", e));
+        }
+        if let Some(paragraph) = &config.paragraph {
+            return Ok(break_paragraph(
+                self.backend.as_ref(),
+                text,
+                &core_font,
+                paragraph,
+                |run| config.overrides.apply(run),
+            )
+            .map_err(|e| runtime_err("Layout error", e))?
+            .to_shaped());
+        }
+        if text.contains('\t') {
+            return shape_with_tabs(
+                self.backend.as_ref(),
+                text,
+                &core_font,
+                &config.tabs,
+                &config.segment,
+                |run| config.overrides.apply(run),
+            )
+            .map_err(|e| runtime_err("Layout error", e));
+        }
         let mut runs = self
             .backend
             .segment(text, &config.segment)
             .map_err(|e| runtime_err("Segmentation error", e))?;
 
-        let shaped = if runs.is_empty() {
-            empty_shaping_result(&core_font)
-        } else {
-            self.shape_runs(&mut runs, &core_font, &config.overrides)?
-        };
-
-        let output = self
-            .backend
-            .render(&shaped, &config.render)
-            .map_err(|e| runtime_err("Render error", e))?;
-
-        self.output_to_py(py, output)
+        if runs.is_empty() {
+            return Ok(empty_shaping_result(&core_font));
+        }
+        self.shape_runs(&mut runs, &core_font, &config.overrides)
     }
 
     fn shape_runs(
@@ -450,7 +734,7 @@ impl TextRenderer {
         overrides: &ShapeOverrides,
     ) -> PyResult<CoreShapingResult> {
         let mut shaped_segments = Vec::with_capacity(runs.len());
-        for run in runs {
+        for run in runs.iter_mut() {
             overrides.apply(run);
             let shaped = self
                 .backend
@@ -458,26 +742,41 @@ impl TextRenderer {
                 .map_err(|e| runtime_err("Shaping error", e))?;
             shaped_segments.push(shaped);
         }
-        Ok(combine_shaped_results(shaped_segments))
+        Ok(combine_shaped_runs(runs, shaped_segments))
     }
 
     fn output_to_py<'py>(&self, py: Python<'py>, output: RenderOutput) -> PyResult<PyObject> {
         Ok(match output {
             RenderOutput::Bitmap(bitmap) => {
-                let bytes = PyBytes::new_bound(py, &bitmap.data);
+                // Rows are handed to Python tightly packed
+                let row_bytes = bitmap.width as usize * bitmap.format.bytes_per_pixel();
+                let bytes = if bitmap.stride == row_bytes {
+                    PyBytes::new_bound(py, &bitmap.data)
+                } else {
+                    let rows: Vec<u8> = (0..bitmap.height)
+                        .flat_map(|y| bitmap.row(y).iter().copied())
+                        .collect();
+                    PyBytes::new_bound(py, &rows)
+                };
                 let tuple = PyTuple::new_bound(
                     py,
                     &[
                         bytes.into_any().into_py(py),
                         bitmap.width.into_py(py),
                         bitmap.height.into_py(py),
+                        pixel_format_name(bitmap.format).into_py(py),
                     ],
                 );
                 tuple.into_any().into_py(py)
             }
-            RenderOutput::Png(data) | RenderOutput::Raw(data) => {
-                PyBytes::new_bound(py, &data).into_any().into_py(py)
-            }
+            RenderOutput::Png(data)
+            | RenderOutput::Webp(data)
+            | RenderOutput::Jpeg(data)
+            | RenderOutput::Pgm(data)
+            | RenderOutput::Pbm(data)
+            | RenderOutput::Bmp(data)
+            | RenderOutput::Raw(data)
+            | RenderOutput::Pdf(data) => PyBytes::new_bound(py, &data).into_any().into_py(py),
             RenderOutput::Svg(svg) => PyString::new_bound(py, &svg).into_any().into_py(py),
         })
     }
@@ -490,6 +789,7 @@ fn o4e(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<Font>()?;
     m.add_class::<Glyph>()?;
     m.add_class::<ShapingResult>()?;
+    m.add_class::<FitResult>()?;
     m.add_function(wrap_pyfunction!(get_version, m)?)?;
     Ok(())
 }
@@ -508,13 +808,18 @@ fn build_render_config(
     render.format = format;
     let mut segment = SegmentOptions::default();
     let mut overrides = ShapeOverrides::default();
+    let mut paragraph = None;
+    let mut truncate = None;
+    let mut spans = Vec::new();
+    let mut markup = false;
+    let mut tabs = TabOptions::default();
 
     if let Some(opts) = options {
         if let Some(color) = opts.get_item("color")? {
-            render.color = color.extract::<String>()?;
+            render.color = parse_color_option(&color)?;
         }
         if let Some(background) = opts.get_item("background")? {
-            render.background = background.extract::<String>()?;
+            render.background = parse_color_option(&background)?;
         }
         if let Some(padding) = opts.get_item("padding")? {
             let value: i32 = padding.extract()?;
@@ -526,12 +831,43 @@ fn build_render_config(
         if let Some(dpi) = opts.get_item("dpi")? {
             render.dpi = dpi.extract::<f32>()?;
         }
+        if let Some(scale) = opts.get_item("scale")? {
+            if opts.contains("dpi")? {
+                return Err(PyValueError::new_err("pass either dpi or scale, not both"));
+            }
+            render.dpi = o4e_core::units::dpi_for_scale(scale.extract::<f32>()?);
+        }
+        if !(render.dpi.is_finite() && render.dpi > 0.0) {
+            return Err(PyValueError::new_err("dpi and scale must be > 0"));
+        }
         if let Some(aa) = opts.get_item("antialias")? {
             render.antialias = parse_antialias(&aa)?;
         }
         if let Some(hinting) = opts.get_item("hinting")? {
             render.hinting = parse_hinting(&hinting)?;
         }
+        if let Some(sizing) = opts.get_item("sizing")? {
+            render.sizing = parse_sizing(&sizing)?;
+        }
+        render.stroke = parse_stroke(opts)?;
+        render.decorations = parse_decorations(opts)?;
+        render.canvas = parse_canvas(opts)?;
+        render.png = parse_png_options(opts)?;
+        if let Some(format) = opts.get_item("pixel_format")? {
+            render.pixel_format = parse_pixel_format(Some(&format.extract::<String>()?))?;
+        }
+        if let Some(quality) = opts.get_item("jpeg_quality")? {
+            let value: u8 = quality.extract()?;
+            if !(1..=100).contains(&value) {
+                return Err(PyValueError::new_err(
+                    "jpeg_quality must be between 1 and 100",
+                ));
+            }
+            render.jpeg_quality = value;
+        }
+        if let Some(fill) = opts.get_item("fill")? {
+            render.fill = fill.extract::<bool>()?;
+        }
         if let Some(direction) = opts.get_item("direction")? {
             overrides.direction = Some(parse_direction(&direction)?);
         }
@@ -552,12 +888,31 @@ fn build_render_config(
         if let Some(value) = opts.get_item("bidi_resolve")? {
             segment.bidi_resolve = value.extract::<bool>()?;
         }
+        tabs = parse_tabs(opts)?;
+        truncate = parse_truncate(opts, &segment, &tabs)?;
+        if truncate.is_none() {
+            paragraph = parse_paragraph(opts, &segment, &tabs)?;
+        }
+        spans = parse_spans(opts)?;
+        if let Some(value) = opts.get_item("markup")? {
+            markup = value.extract::<bool>()?;
+        }
+        if (markup || !spans.is_empty()) && (truncate.is_some() || paragraph.is_some()) {
+            return Err(PyValueError::new_err(
+                "spans and markup cannot be combined with max_width or truncate",
+            ));
+        }
     }
 
     Ok(RenderConfig {
         render,
         segment,
         overrides,
+        paragraph,
+        truncate,
+        spans,
+        markup,
+        tabs,
     })
 }
 
@@ -571,7 +926,13 @@ fn build_shape_config(
 fn parse_render_format(value: Option<&str>) -> PyResult<RenderFormat> {
     Ok(match value.map(|s| s.to_lowercase()) {
         Some(ref s) if s == "png" => RenderFormat::Png,
+        Some(ref s) if s == "webp" => RenderFormat::Webp,
+        Some(ref s) if s == "jpeg" || s == "jpg" => RenderFormat::Jpeg,
+        Some(ref s) if s == "pgm" => RenderFormat::Pgm,
+        Some(ref s) if s == "pbm" => RenderFormat::Pbm,
+        Some(ref s) if s == "bmp" => RenderFormat::Bmp,
         Some(ref s) if s == "svg" => RenderFormat::Svg,
+        Some(ref s) if s == "pdf" => RenderFormat::Pdf,
         Some(ref s) if s == "raw" => RenderFormat::Raw,
         None => RenderFormat::Raw,
         Some(other) => {
@@ -582,6 +943,81 @@ fn parse_render_format(value: Option<&str>) -> PyResult<RenderFormat> {
     })
 }
 
+fn parse_pixel_format(value: Option<&str>) -> PyResult<PixelFormat> {
+    Ok(match value.map(|s| s.to_lowercase()).as_deref() {
+        None | Some("rgba") => PixelFormat::Rgba8,
+        Some("bgra") => PixelFormat::Bgra8,
+        Some("rgba_premultiplied") => PixelFormat::Rgba8Premultiplied,
+        Some("bgra_premultiplied") => PixelFormat::Bgra8Premultiplied,
+        Some("rgb") => PixelFormat::Rgb8,
+        Some("gray") => PixelFormat::Gray8,
+        Some("a8") => PixelFormat::A8,
+        Some(other) => {
+            return Err(PyValueError::new_err(format!(
+                "Unsupported pixel format: {other}"
+            )))
+        }
+    })
+}
+
+/// Python name of a pixel format, as accepted by `parse_pixel_format`.
+fn pixel_format_name(format: PixelFormat) -> &'static str {
+    match format {
+        PixelFormat::Rgba8 => "rgba",
+        PixelFormat::Bgra8 => "bgra",
+        PixelFormat::Rgba8Premultiplied => "rgba_premultiplied",
+        PixelFormat::Bgra8Premultiplied => "bgra_premultiplied",
+        PixelFormat::Rgb8 => "rgb",
+        PixelFormat::Gray8 => "gray",
+        PixelFormat::A8 => "a8",
+    }
+}
+
+/// Byte span of a writable buffer, plus the frame geometry its shape implies.
+struct BufferLayout {
+    len: usize,
+    width: Option<u32>,
+    height: Option<u32>,
+    stride: Option<usize>,
+}
+
+/// Accept C-contiguous buffers of any shape, and `(height, width, channels)` arrays (or
+/// `(height, width)` for one-channel formats) whose rows are contiguous but may be padded,
+/// e.g. a cropped numpy view of a larger frame.
+fn buffer_layout(buffer: &PyBuffer<u8>, format: PixelFormat) -> PyResult<BufferLayout> {
+    let (shape, strides) = (buffer.shape(), buffer.strides());
+    let channels = format.bytes_per_pixel();
+    let pixels_contiguous = match shape.len() {
+        3 => shape[2] == channels && strides[2] == 1 && strides[1] == channels as isize,
+        2 => channels == 1 && strides[1] == 1,
+        _ => false,
+    };
+    if pixels_contiguous && strides[0] >= (shape[1] * channels) as isize {
+        let row = strides[0] as usize;
+        let len = match shape[0] {
+            0 => 0,
+            rows => row * (rows - 1) + shape[1] * channels,
+        };
+        return Ok(BufferLayout {
+            len,
+            width: Some(shape[1] as u32),
+            height: Some(shape[0] as u32),
+            stride: Some(row),
+        });
+    }
+    if buffer.is_c_contiguous() {
+        return Ok(BufferLayout {
+            len: buffer.len_bytes(),
+            width: None,
+            height: None,
+            stride: None,
+        });
+    }
+    Err(PyValueError::new_err(
+        "buffer must be C-contiguous or a (height, width, channels) array with contiguous rows",
+    ))
+}
+
 fn parse_direction(value: &Bound<'_, PyAny>) -> PyResult<Direction> {
     let direction = value.extract::<String>()?.to_lowercase();
     match direction.as_str() {
@@ -613,6 +1049,555 @@ fn parse_antialias(value: &Bound<'_, PyAny>) -> PyResult<AntialiasMode> {
     }
 }
 
+/// CSS color string, rejected up front with the parser's message instead of at render time.
+fn parse_color_option(value: &Bound<'_, PyAny>) -> PyResult<String> {
+    let color = value.extract::<String>()?;
+    parse_color(&color).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(color)
+}
+
+/// PNG encoder options from the `png_*` keys; `None` unless one is given.
+fn parse_png_options(opts: &Bound<'_, PyDict>) -> PyResult<Option<PngOptions>> {
+    let keys = [
+        "png_compression",
+        "png_filter",
+        "png_reduce_colors",
+        "png_srgb",
+        "png_metadata",
+    ];
+    let mut given = false;
+    for key in keys {
+        given |= opts.get_item(key)?.is_some();
+    }
+    if !given {
+        return Ok(None);
+    }
+
+    let mut png = PngOptions::default();
+    if let Some(compression) = opts.get_item("png_compression")? {
+        png.compression = match compression.extract::<String>()?.to_lowercase().as_str() {
+            "fast" => PngCompression::Fast,
+            "default" => PngCompression::Default,
+            "best" => PngCompression::Best,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid PNG compression: {other}"
+                )))
+            }
+        };
+    }
+    if let Some(filter) = opts.get_item("png_filter")? {
+        png.filter = match filter.extract::<String>()?.to_lowercase().as_str() {
+            "adaptive" => PngFilter::Adaptive,
+            "none" => PngFilter::None,
+            "sub" => PngFilter::Sub,
+            "up" => PngFilter::Up,
+            "average" => PngFilter::Average,
+            "paeth" => PngFilter::Paeth,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid PNG filter: {other}"
+                )))
+            }
+        };
+    }
+    if let Some(reduce) = opts.get_item("png_reduce_colors")? {
+        png.reduce_colors = reduce.extract::<bool>()?;
+    }
+    if let Some(srgb) = opts.get_item("png_srgb")? {
+        png.srgb = srgb.extract::<bool>()?;
+    }
+    if let Some(metadata) = opts.get_item("png_metadata")? {
+        png.metadata = metadata.extract::<bool>()?;
+    }
+    Ok(Some(png))
+}
+
+/// `underline`, `overline` and `line_through` take `True` or a style name; the
+/// `decoration_*` options apply to all of them.
+fn parse_decorations(opts: &Bound<'_, PyDict>) -> PyResult<Vec<Decoration>> {
+    let color = opts
+        .get_item("decoration_color")?
+        .map(|color| parse_color_option(&color))
+        .transpose()?;
+    let thickness = match opts.get_item("decoration_thickness")? {
+        Some(value) => {
+            let thickness = value.extract::<f32>()?;
+            if thickness.is_nan() || thickness <= 0.0 {
+                return Err(PyValueError::new_err("decoration_thickness must be > 0"));
+            }
+            Some(thickness)
+        }
+        None => None,
+    };
+    let skip_ink = match opts.get_item("decoration_skip_ink")? {
+        Some(value) => value.extract::<bool>()?,
+        None => true,
+    };
+
+    let mut decorations = Vec::new();
+    for (key, line) in [
+        ("underline", DecorationLine::Underline),
+        ("overline", DecorationLine::Overline),
+        ("line_through", DecorationLine::LineThrough),
+    ] {
+        let Some(value) = opts.get_item(key)? else {
+            continue;
+        };
+        let style = if let Ok(enabled) = value.extract::<bool>() {
+            if !enabled {
+                continue;
+            }
+            DecorationStyle::Solid
+        } else {
+            match value.extract::<String>()?.to_lowercase().as_str() {
+                "solid" => DecorationStyle::Solid,
+                "double" => DecorationStyle::Double,
+                "dotted" => DecorationStyle::Dotted,
+                "dashed" => DecorationStyle::Dashed,
+                "wavy" => DecorationStyle::Wavy,
+                other => {
+                    return Err(PyValueError::new_err(format!(
+                        "Invalid {key} style: {other}"
+                    )))
+                }
+            }
+        };
+        decorations.push(Decoration {
+            style,
+            color: color.clone(),
+            thickness,
+            skip_ink,
+            ..Decoration::new(line)
+        });
+    }
+    Ok(decorations)
+}
+
+/// Paragraph layout from `max_width` and `line_height`; `None` keeps a single line.
+fn parse_paragraph(
+    opts: &Bound<'_, PyDict>,
+    segment: &SegmentOptions,
+    tabs: &TabOptions,
+) -> PyResult<Option<ParagraphOptions>> {
+    let positive = |key: &str| -> PyResult<Option<f32>> {
+        match opts.get_item(key)? {
+            Some(value) => {
+                let value: f32 = value.extract()?;
+                if !(value.is_finite() && value > 0.0) {
+                    return Err(PyValueError::new_err(format!("{key} must be > 0")));
+                }
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    };
+    let max_width = positive("max_width")?;
+    let line_height = positive("line_height")?;
+
+    let hyphenator = parse_hyphenator(opts, segment)?;
+    if hyphenator.is_some() && max_width.is_none() {
+        let key = if opts.contains("hyphenation_patterns")? {
+            "hyphenation_patterns"
+        } else {
+            "hyphenate"
+        };
+        return Err(PyValueError::new_err(format!("{key} requires max_width")));
+    }
+
+    if max_width.is_none() && line_height.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(ParagraphOptions {
+        max_width,
+        line_height,
+        hyphenator,
+        segment: segment.clone(),
+        tabs: tabs.clone(),
+    }))
+}
+
+/// Hyphenation from `hyphenate`: `True` (patterns for the `language` option, default
+/// English) or a language tag; `hyphenation_patterns` loads a TeX pattern file instead.
+fn parse_hyphenator(
+    opts: &Bound<'_, PyDict>,
+    segment: &SegmentOptions,
+) -> PyResult<Option<Hyphenator>> {
+    if let Some(path) = opts.get_item("hyphenation_patterns")? {
+        let path = path.extract::<String>()?;
+        return Hyphenator::from_file(&path)
+            .map(Some)
+            .map_err(|err| PyValueError::new_err(err.to_string()));
+    }
+    let Some(value) = opts.get_item("hyphenate")? else {
+        return Ok(None);
+    };
+    let language = if let Ok(enabled) = value.extract::<bool>() {
+        if !enabled {
+            return Ok(None);
+        }
+        segment.language.clone().unwrap_or_else(|| "en".to_string())
+    } else {
+        value.extract::<String>()?
+    };
+    Hyphenator::for_language(&language)
+        .map(Some)
+        .ok_or_else(|| {
+            PyValueError::new_err(format!("No hyphenation patterns for language: {language}"))
+        })
+}
+
+/// Fit options: the box (`width`, `height`), `min_size` and `max_size`, `wrap`,
+/// `max_lines`, `line_spacing` (a multiple of the size), `precision`, hyphenation and tab
+/// stops.
+fn parse_fit(opts: Option<&Bound<'_, PyDict>>, segment: &SegmentOptions) -> PyResult<FitOptions> {
+    let mut fit = FitOptions {
+        segment: segment.clone(),
+        ..FitOptions::default()
+    };
+    let Some(opts) = opts else {
+        return Ok(fit);
+    };
+    let positive = |key: &str| -> PyResult<Option<f32>> {
+        match opts.get_item(key)? {
+            Some(value) if !value.is_none() => {
+                let value: f32 = value.extract()?;
+                if !(value.is_finite() && value > 0.0) {
+                    return Err(PyValueError::new_err(format!("{key} must be > 0")));
+                }
+                Ok(Some(value))
+            }
+            _ => Ok(None),
+        }
+    };
+    fit.width = positive("width")?;
+    fit.height = positive("height")?;
+    fit.min_size = positive("min_size")?.unwrap_or(fit.min_size);
+    fit.max_size = positive("max_size")?.unwrap_or(fit.max_size);
+    if fit.max_size < fit.min_size {
+        return Err(PyValueError::new_err("max_size must be >= min_size"));
+    }
+    fit.line_spacing = positive("line_spacing")?;
+    fit.precision = positive("precision")?.unwrap_or(fit.precision);
+    if let Some(wrap) = opts.get_item("wrap")? {
+        fit.wrap = wrap.extract::<bool>()?;
+    }
+    if let Some(max_lines) = opts.get_item("max_lines")?.filter(|value| !value.is_none()) {
+        let max_lines: usize = max_lines.extract()?;
+        if max_lines == 0 {
+            return Err(PyValueError::new_err("max_lines must be >= 1"));
+        }
+        fit.max_lines = Some(max_lines);
+    }
+    fit.hyphenator = parse_hyphenator(opts, segment)?;
+    fit.tabs = parse_tabs(opts)?;
+    Ok(fit)
+}
+
+/// Truncation from `truncate` ("start"/"middle"/"end"), which needs `max_width`, and an
+/// optional `ellipsis` string.
+fn parse_truncate(
+    opts: &Bound<'_, PyDict>,
+    segment: &SegmentOptions,
+    tabs: &TabOptions,
+) -> PyResult<Option<TruncateOptions>> {
+    let Some(mode) = opts.get_item("truncate")? else {
+        return Ok(None);
+    };
+    let mode = match mode.extract::<String>()?.to_lowercase().as_str() {
+        "start" => TruncateMode::Start,
+        "middle" => TruncateMode::Middle,
+        "end" => TruncateMode::End,
+        other => {
+            return Err(PyValueError::new_err(format!(
+                "Invalid truncate mode: {other}"
+            )))
+        }
+    };
+    let max_width: f32 = opts
+        .get_item("max_width")?
+        .ok_or_else(|| PyValueError::new_err("truncate requires max_width"))?
+        .extract()?;
+    if !(max_width.is_finite() && max_width > 0.0) {
+        return Err(PyValueError::new_err("max_width must be > 0"));
+    }
+    let ellipsis = match opts.get_item("ellipsis")? {
+        Some(value) => Some(value.extract::<String>()?),
+        None => None,
+    };
+    Ok(Some(TruncateOptions {
+        max_width,
+        mode,
+        ellipsis,
+        segment: segment.clone(),
+        tabs: tabs.clone(),
+    }))
+}
+
+/// Tab stops from `tab_stops`, a list of positions in points or `(position, align)` pairs
+/// with align "left", "center", "right" or "decimal"; `tab_interval` spaces the stops past
+/// them and `tab_decimal` is the character decimal stops align on.
+fn parse_tabs(opts: &Bound<'_, PyDict>) -> PyResult<TabOptions> {
+    let mut tabs = TabOptions::default();
+    if let Some(interval) = opts.get_item("tab_interval")? {
+        let interval: f32 = interval.extract()?;
+        if !(interval.is_finite() && interval > 0.0) {
+            return Err(PyValueError::new_err("tab_interval must be > 0"));
+        }
+        tabs.interval = Some(interval);
+    }
+    if let Some(decimal) = opts.get_item("tab_decimal")? {
+        tabs.decimal = decimal.extract()?;
+    }
+    let Some(stops) = opts.get_item("tab_stops")? else {
+        return Ok(tabs);
+    };
+    for stop in stops.iter()? {
+        let stop = stop?;
+        let (position, align) = match stop.extract::<f32>() {
+            Ok(position) => (position, TabAlign::Left),
+            Err(_) => {
+                let (position, align) = stop.extract::<(f32, String)>()?;
+                let align = match align.to_lowercase().as_str() {
+                    "left" => TabAlign::Left,
+                    "center" => TabAlign::Center,
+                    "right" => TabAlign::Right,
+                    "decimal" => TabAlign::Decimal,
+                    other => {
+                        return Err(PyValueError::new_err(format!(
+                            "Invalid tab alignment: {other}"
+                        )))
+                    }
+                };
+                (position, align)
+            }
+        };
+        if !(position.is_finite() && position >= 0.0) {
+            return Err(PyValueError::new_err("tab stop positions must be >= 0"));
+        }
+        tabs.stops.push(TabStop::new(position, align));
+    }
+    Ok(tabs)
+}
+
+/// Attributed ranges from `spans`, a list of dicts with `start` and `end` character indices
+/// and any of `family`, `size`, `weight`, `style`, `color`, `features`, `variations`,
+/// `baseline_shift` and the decoration keys.
+fn parse_spans(opts: &Bound<'_, PyDict>) -> PyResult<Vec<TextSpan>> {
+    let Some(value) = opts.get_item("spans")? else {
+        return Ok(Vec::new());
+    };
+    let mut spans = Vec::new();
+    for item in value.iter()? {
+        let item = item?;
+        let span = item
+            .downcast::<PyDict>()
+            .map_err(|_| PyValueError::new_err("each span must be a dict"))?;
+        let index = |key: &str| -> PyResult<usize> {
+            span.get_item(key)?
+                .ok_or_else(|| PyValueError::new_err(format!("span requires {key}")))?
+                .extract()
+        };
+        let (start, end) = (index("start")?, index("end")?);
+        if start > end {
+            return Err(PyValueError::new_err(format!(
+                "span start {start} is after its end {end}"
+            )));
+        }
+        let mut style = SpanStyle::default();
+        if let Some(family) = span.get_item("family")? {
+            style.family = Some(family.extract()?);
+        }
+        if let Some(size) = span.get_item("size")? {
+            let size: f32 = size.extract()?;
+            if !(size.is_finite() && size > 0.0) {
+                return Err(PyValueError::new_err("span size must be > 0"));
+            }
+            style.size = Some(size);
+        }
+        if let Some(weight) = span.get_item("weight")? {
+            style.weight = Some(weight.extract()?);
+        }
+        if let Some(font_style) = span.get_item("style")? {
+            style.style = Some(
+                match font_style.extract::<String>()?.to_lowercase().as_str() {
+                    "normal" => FontStyle::Normal,
+                    "italic" => FontStyle::Italic,
+                    "oblique" => FontStyle::Oblique,
+                    other => {
+                        return Err(PyValueError::new_err(format!(
+                            "Invalid span style: {other}"
+                        )))
+                    }
+                },
+            );
+        }
+        if let Some(color) = span.get_item("color")? {
+            style.color = Some(parse_color_option(&color)?);
+        }
+        if let Some(features) = span.get_item("features")? {
+            style.features = features.extract()?;
+        }
+        if let Some(variations) = span.get_item("variations")? {
+            style.variations = variations.extract()?;
+        }
+        if let Some(shift) = span.get_item("baseline_shift")? {
+            style.baseline_shift = shift.extract()?;
+        }
+        style.decorations = parse_decorations(span)?;
+        spans.push(TextSpan::new(start..end, style));
+    }
+    Ok(spans)
+}
+
+/// `spans` with character ranges turned into byte ranges of `text`.
+fn byte_spans(text: &str, spans: &[TextSpan]) -> PyResult<Vec<TextSpan>> {
+    let offsets: Vec<usize> = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain([text.len()])
+        .collect();
+    let byte = |index: usize| {
+        offsets.get(index).copied().ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "span index {index} is past the end of the text ({} characters)",
+                offsets.len() - 1
+            ))
+        })
+    };
+    spans
+        .iter()
+        .map(|span| {
+            Ok(TextSpan::new(
+                byte(span.range.start)?..byte(span.range.end)?,
+                span.style.clone(),
+            ))
+        })
+        .collect()
+}
+
+/// Stroke from the `stroke_*` keys; `None` unless `stroke_width` or `stroke_color` is given.
+fn parse_stroke(opts: &Bound<'_, PyDict>) -> PyResult<Option<Stroke>> {
+    let width = opts.get_item("stroke_width")?;
+    let color = opts.get_item("stroke_color")?;
+    if width.is_none() && color.is_none() {
+        return Ok(None);
+    }
+
+    let mut stroke = Stroke::default();
+    if let Some(width) = width {
+        stroke.width = width.extract::<f32>()?;
+        if stroke.width.is_nan() || stroke.width < 0.0 {
+            return Err(PyValueError::new_err("stroke_width must be >= 0"));
+        }
+    }
+    if let Some(color) = color {
+        stroke.color = parse_color_option(&color)?;
+    }
+    if let Some(join) = opts.get_item("stroke_join")? {
+        stroke.line_join = match join.extract::<String>()?.to_lowercase().as_str() {
+            "miter" => LineJoin::Miter,
+            "round" => LineJoin::Round,
+            "bevel" => LineJoin::Bevel,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid stroke join: {other}"
+                )))
+            }
+        };
+    }
+    if let Some(cap) = opts.get_item("stroke_cap")? {
+        stroke.line_cap = match cap.extract::<String>()?.to_lowercase().as_str() {
+            "butt" => LineCap::Butt,
+            "round" => LineCap::Round,
+            "square" => LineCap::Square,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid stroke cap: {other}"
+                )))
+            }
+        };
+    }
+    if let Some(limit) = opts.get_item("stroke_miter_limit")? {
+        stroke.miter_limit = limit.extract::<f32>()?;
+    }
+    Ok(Some(stroke))
+}
+
+/// Fixed canvas from the `canvas_*` keys; `None` unless `canvas_width` or `canvas_height` is
+/// given.
+fn parse_canvas(opts: &Bound<'_, PyDict>) -> PyResult<Option<Canvas>> {
+    let width = opts.get_item("canvas_width")?;
+    let height = opts.get_item("canvas_height")?;
+    let (width, height) = match (width, height) {
+        (None, None) => return Ok(None),
+        (Some(width), Some(height)) => (width.extract::<u32>()?, height.extract::<u32>()?),
+        _ => {
+            return Err(PyValueError::new_err(
+                "canvas_width and canvas_height must be given together",
+            ))
+        }
+    };
+    if width == 0 || height == 0 {
+        return Err(PyValueError::new_err("canvas size must be > 0"));
+    }
+
+    let mut canvas = Canvas::new(width, height);
+    match (opts.get_item("baseline")?, opts.get_item("baseline_ratio")?) {
+        (Some(_), Some(_)) => {
+            return Err(PyValueError::new_err(
+                "baseline and baseline_ratio are mutually exclusive",
+            ))
+        }
+        (Some(baseline), None) => {
+            canvas.baseline = BaselinePosition::Absolute(baseline.extract::<f32>()?)
+        }
+        (None, Some(ratio)) => {
+            canvas.baseline = BaselinePosition::Fraction(ratio.extract::<f32>()?)
+        }
+        (None, None) => {}
+    }
+    if let Some(align) = opts.get_item("align")? {
+        canvas.horizontal_align = match align.extract::<String>()?.to_lowercase().as_str() {
+            "left" => HorizontalAlign::Left,
+            "center" => HorizontalAlign::Center,
+            "right" => HorizontalAlign::Right,
+            other => return Err(PyValueError::new_err(format!("Invalid align: {other}"))),
+        };
+    }
+    if let Some(align) = opts.get_item("vertical_align")? {
+        canvas.vertical_align = match align.extract::<String>()?.to_lowercase().as_str() {
+            "baseline" => VerticalAlign::Baseline,
+            "top" => VerticalAlign::Top,
+            "middle" => VerticalAlign::Middle,
+            "bottom" => VerticalAlign::Bottom,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid vertical_align: {other}"
+                )))
+            }
+        };
+    }
+    if let Some(overflow) = opts.get_item("overflow")? {
+        canvas.overflow = match overflow.extract::<String>()?.to_lowercase().as_str() {
+            "clip" => CanvasOverflow::Clip,
+            "error" => CanvasOverflow::Error,
+            other => return Err(PyValueError::new_err(format!("Invalid overflow: {other}"))),
+        };
+    }
+    Ok(Some(canvas))
+}
+
+fn parse_sizing(value: &Bound<'_, PyAny>) -> PyResult<SizingMode> {
+    match value.extract::<String>()?.to_lowercase().as_str() {
+        "logical" => Ok(SizingMode::Logical),
+        "ink" => Ok(SizingMode::Ink),
+        other => Err(PyValueError::new_err(format!(
+            "Invalid sizing mode: {other}"
+        ))),
+    }
+}
+
 fn parse_hinting(value: &Bound<'_, PyAny>) -> PyResult<HintingMode> {
     if let Ok(enabled) = value.extract::<bool>() {
         return Ok(if enabled {
@@ -633,19 +1618,9 @@ fn parse_hinting(value: &Bound<'_, PyAny>) -> PyResult<HintingMode> {
 }
 
 fn empty_shaping_result(font: &CoreFont) -> CoreShapingResult {
-    CoreShapingResult {
-        text: String::new(),
-        glyphs: Vec::new(),
-        advance: 0.0,
-        bbox: BoundingBox {
-            x: 0.0,
-            y: 0.0,
-            width: 0.0,
-            height: font.size.max(1.0),
-        },
-        font: Some(font.clone()),
-        direction: Direction::LeftToRight,
-    }
+    let mut shaped = CoreShapingResult::empty(font);
+    shaped.bbox.height = font.size.max(1.0);
+    shaped
 }
 
 fn runtime_err<E: Display>(msg: &str, err: E) -> PyErr {
@@ -132,6 +132,32 @@ class TestBitmap:
         data_rgb = b'\x00' * (10 * 10 * 3)
         bitmap_rgb = o4e.Bitmap(data_rgb, 10, 10, "rgb")
         assert bitmap_rgb.format == "rgb"
+        assert bitmap_rgb.channels == 3
+
+        bitmap_mask = o4e.Bitmap(b'\x00' * 100, 10, 10, "a8")
+        assert bitmap_mask.channels == 1
+
+    @patch('o4e.HAS_PIL', True)
+    @patch('o4e.PILImage')
+    def test_bitmap_to_pil_decodes_bgra_and_masks(self, mock_pil):
+        """Test PIL conversion of non-RGBA pixel formats."""
+        data = b'\x00' * (2 * 2 * 4)
+        o4e.Bitmap(data, 2, 2, "bgra_premultiplied").to_pil()
+        mock_pil.frombytes.assert_called_with("RGBA", (2, 2), data, "raw", "BGRa")
+
+        mask = b'\x00' * 4
+        o4e.Bitmap(mask, 2, 2, "a8").to_pil()
+        mock_pil.frombytes.assert_called_with("L", (2, 2), mask)
+
+    def test_render_raw_keeps_native_pixel_format(self):
+        """Test that RAW renders report the native pixel format."""
+        renderer = o4e.TextRenderer()
+        renderer._renderer.render.return_value = (b'\x00' * 4, 2, 2, "a8")
+
+        result = renderer.render("Hi", "Arial", pixel_format="a8")
+        assert isinstance(result, o4e.Bitmap)
+        assert result.format == "a8"
+        assert renderer._renderer.render.call_args[1]["render_options"]["pixel_format"] == "a8"
 
     @patch('o4e.HAS_NUMPY', True)
     @patch('o4e.np')
@@ -267,6 +293,27 @@ class TestTextRenderer:
         assert call_args[1]["render_options"]["background"] == "#FFFFFF"
         assert call_args[1]["render_options"]["padding"] == 10
 
+    def test_render_into_forwards_buffer_and_geometry(self):
+        """Test compositing onto a caller-provided buffer."""
+        renderer = o4e.TextRenderer()
+        frame = bytearray(8 * 4 * 4)
+
+        result = renderer.render_into(
+            "Hi", "Arial", frame, 2, 1,
+            width=8, height=4, pixel_format="bgra_premultiplied", color="white"
+        )
+
+        assert result is None
+        call_args = renderer._renderer.render_into.call_args
+        assert call_args[0][2] is frame
+        assert call_args[1]["width"] == 8
+        assert call_args[1]["height"] == 4
+        assert call_args[1]["stride"] is None
+        assert call_args[1]["pixel_format"] == "bgra_premultiplied"
+        assert (call_args[1]["x"], call_args[1]["y"]) == (2, 1)
+        assert call_args[1]["render_options"]["color"] == "white"
+        assert call_args[1]["render_options"]["background"] == "transparent"
+
     def test_shape_text(self):
         """Test text shaping."""
         renderer = o4e.TextRenderer()
@@ -318,6 +365,21 @@ class TestTextRenderer:
             os.unlink(f.name)
             assert data == b'PNG DATA'
 
+    def test_render_to_file_detects_image_formats(self):
+        """Test that file extensions pick the matching encoder."""
+        renderer = o4e.TextRenderer()
+        renderer._renderer.render.return_value = b'IMAGE DATA'
+
+        for suffix, name in [(".webp", "webp"), (".jpg", "jpeg"), (".pgm", "pgm"),
+                             (".pbm", "pbm"), (".bmp", "bmp")]:
+            with tempfile.NamedTemporaryFile(suffix=suffix, delete=False) as f:
+                f.close()
+                renderer.render_to_file("Test", "Arial", f.name, jpeg_quality=75)
+                os.unlink(f.name)
+            args = renderer._renderer.render.call_args
+            assert args[0][2] == name
+            assert args[1]["render_options"]["jpeg_quality"] == 75
+
     @patch('o4e.HAS_NUMPY', True)
     def test_render_to_numpy(self):
         """Test rendering to numpy array."""
@@ -470,6 +532,9 @@ class TestEnums:
         assert o4e.RenderFormat.RAW.value == "raw"
         assert o4e.RenderFormat.PNG.value == "png"
         assert o4e.RenderFormat.SVG.value == "svg"
+        assert o4e.RenderFormat.WEBP.value == "webp"
+        assert o4e.RenderFormat.JPEG.value == "jpeg"
+        assert o4e.RenderFormat.BMP.value == "bmp"
 
     def test_direction_enum(self):
         """Test Direction enum."""